@@ -66,6 +66,7 @@ gameinput-greet = Greet
 gameinput-map-locationmarkerbutton = Set a waypoint in the Map
 gameinput-spectatespeedboost = Spectate speed boost
 gameinput-spectateviewpoint = Spectate viewpoint
+gameinput-spectatenextplayer = Spectate next player
 gameinput-mutemaster = Mute master volume
 gameinput-muteinactivemaster = Mute master volume (inactive window)
 gameinput-mutemusic = Mute music volume
@@ -42,5 +42,6 @@ hud-use = Use
 hud-mine = Mine
 hud-talk = Talk
 hud-trade = Trade
+hud-vault = Vault
 hud-mount = Mount
 hud-sit = Sit
\ No newline at end of file
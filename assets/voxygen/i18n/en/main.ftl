@@ -39,11 +39,13 @@ main-login-failed_auth_server_url_invalid = Failed to connect to auth server
 main-login-insecure_auth_scheme = The auth Scheme HTTP is NOT supported. It's insecure! For development purposes, HTTP is allowed for 'localhost' or debug builds
 main-login-server_full = Server is full
 main-login-untrusted_auth_server = Auth server not trusted
+main-login-server_key_not_trusted = Server key not trusted
 main-login-outdated_client_or_server = ServerWentMad: Probably versions are incompatible, check for updates.
 main-login-timeout = Timeout: Server did not respond in time. (Overloaded or network issues).
 main-login-server_shut_down = Server shut down
 main-login-network_error = Network error
 main-login-network_wrong_version = Mismatched server and client version, please update your game client.
+main-login-server_key_mismatch = The key of the server changed! Someone might be intercepting your connection, or the server got a new key. Expected { $expected }, got { $actual }. If the server announced a new key, remove the old one from your settings.
 main-login-failed_sending_request = Request to Auth server failed
 main-login-invalid_character = The selected character is invalid
 main-login-client_crashed = Client crashed
@@ -57,6 +59,13 @@ main-login-client_init_failed = Client failed to initialize: { $init_fail_reason
 main-login-username_bad_characters = Username contains invalid characters! (Only alphanumeric, '_' and '-' are allowed)
 main-login-username_too_long = Username is too long! Max length is: { $max_len }
 main-servers-select_server = Select a server
+main-servers-querying = Asking the server for its status...
+main-servers-unreachable = The server didn't answer
+main-servers-status = { $players }/{ $max_players } players · { $ping } ms · { $battle_mode }
+main-servers-pvp = PvP
+main-servers-pve = PvE
+main-servers-battle_mode_choosable = { $default } by default, choosable
+main-servers-different_version = Different version
 main-servers-singleplayer_error = Failed to connect to internal server: { $sp_error }
 main-servers-network_error = Server network/socket error: { $raw_error }
 main-servers-participant_error = Participant disconnect/protocol error: { $raw_error }
@@ -64,6 +73,7 @@ main-servers-stream_error = Client connection/compression/(de)serialization erro
 main-servers-database_error = Server database error: { $raw_error }
 main-servers-persistence_error = Server persistence error (Probably Asset/Character Data related): { $raw_error }
 main-servers-other_error = Server general error: { $raw_error }
+main-replay-failed = Could not play back the replay: { $reason }
 main-credits = Credits
 main-credits-created_by = created by
 main-credits-music = Music
@@ -14,7 +14,18 @@ pub enum ConnectionArgs {
         hostname: String,
         prefer_ipv6: bool,
     },
+    ///hostname: (hostname|ip):[<port>]
+    /// Tcp with encryption, the server has to own the `pinned` key. Without
+    /// one, connecting fails with the key of the server so it can be pinned,
+    /// see [`network::ConnectAddr::EncryptedTcp`].
+    EncryptedTcp {
+        hostname: String,
+        prefer_ipv6: bool,
+        pinned: Option<network::PublicKey>,
+    },
     Mpsc(u64),
+    /// Mpsc over a simulated link, to test bad network conditions
+    Sim(u64, network::LinkConditions),
 }
 
 impl ConnectionArgs {
@@ -1,6 +1,10 @@
+mod relay;
+
 use common::comp;
 use hashbrown::HashSet;
+use network::LinkConditions;
 use std::{
+    net::{Ipv4Addr, SocketAddr},
     sync::{
         atomic::{AtomicU32, Ordering},
         Arc,
@@ -25,6 +29,37 @@ struct Opt {
     /// Whether the clients should move
     #[structopt(short, long)]
     movement: bool,
+    /// Simulated delay of the link to the server, in milliseconds
+    #[structopt(long, default_value = "0")]
+    delay: u64,
+    /// Simulated random additional delay, up to this many milliseconds
+    #[structopt(long, default_value = "0")]
+    jitter: u64,
+    /// Simulated chance to lose messages which aren't guaranteed to be
+    /// delivered, from 0.0 to 1.0
+    #[structopt(long, default_value = "0")]
+    loss: f64,
+    /// Simulated throughput of the link to the server, in bytes per second
+    #[structopt(long)]
+    throughput: Option<u64>,
+}
+
+impl Opt {
+    /// The conditions of the simulated link of a client, if any were
+    /// specified
+    fn link_conditions(&self, index: u32) -> Option<LinkConditions> {
+        let conditions = LinkConditions {
+            delay: Duration::from_millis(self.delay),
+            jitter: Duration::from_millis(self.jitter),
+            loss: self.loss,
+            throughput: self.throughput,
+            ..Default::default()
+        };
+        (conditions != LinkConditions::default()).then_some(LinkConditions {
+            seed: index as u64,
+            ..conditions
+        })
+    }
 }
 
 fn main() {
@@ -49,6 +84,12 @@ fn main() {
     let finished_init = Arc::new(AtomicU32::new(0));
     let runtime = Arc::new(Runtime::new().unwrap());
 
+    // The clients connect to the relay instead, which connects them to the server
+    if opt.link_conditions(0).is_some() {
+        relay::spawn(&runtime, SocketAddr::from((Ipv4Addr::LOCALHOST, 14004)))
+            .expect("Failed to start the relay to the server");
+    }
+
     // TODO: calculate and log the required chunks per second to maintain the
     // selected scenario with full vd loaded
 
@@ -102,9 +143,12 @@ fn run_client(
 ) -> Result<(), veloren_client::Error> {
     let mut client = loop {
         // Connect to localhost
-        let addr = ConnectionArgs::Tcp {
-            prefer_ipv6: false,
-            hostname: "localhost".into(),
+        let addr = match opt.link_conditions(index) {
+            Some(conditions) => ConnectionArgs::Sim(relay::RELAY_MPSC_ADDR, conditions),
+            None => ConnectionArgs::Tcp {
+                prefer_ipv6: false,
+                hostname: "localhost".into(),
+            },
         };
         let runtime_clone = Arc::clone(&runtime);
         // NOTE: use a no-auth server
@@ -0,0 +1,67 @@
+//! Relays the swarm clients to the server, so they can connect over a
+//! simulated link with bad network conditions.
+//!
+//! The clients connect to the relay with [`ConnectionArgs::Sim`], and the
+//! relay connects each of them to the server over Tcp. Every stream the server
+//! opens is opened with the same arguments towards the client, and the
+//! messages are forwarded in both directions.
+//!
+//! [`ConnectionArgs::Sim`]: veloren_client::addr::ConnectionArgs::Sim
+use network::{ConnectAddr, ListenAddr, Network, NetworkError, Participant, Pid, Stream};
+use std::{net::SocketAddr, sync::Arc};
+use tokio::runtime::Runtime;
+use tracing::{info, warn};
+
+/// The Mpsc address the relay listens on
+pub const RELAY_MPSC_ADDR: u64 = 14_104;
+
+/// Start relaying the clients which connect to [`RELAY_MPSC_ADDR`] to the
+/// server at `server`
+pub fn spawn(runtime: &Arc<Runtime>, server: SocketAddr) -> Result<(), NetworkError> {
+    let mut listener = Network::new(Pid::new(), runtime);
+    let connector = Network::new(Pid::new(), runtime);
+    runtime.block_on(listener.listen(ListenAddr::Mpsc(RELAY_MPSC_ADDR)))?;
+    runtime.spawn(async move {
+        while let Ok(client) = listener.connected().await {
+            match connector.connect(ConnectAddr::Tcp(server)).await {
+                Ok(server) => {
+                    tokio::spawn(relay(client, server));
+                },
+                Err(e) => warn!(?e, "The relay failed to connect to the server"),
+            }
+        }
+        info!("The relay stopped listening");
+    });
+    Ok(())
+}
+
+async fn relay(client: Participant, mut server: Participant) {
+    while let Ok(server_stream) = server.opened().await {
+        let client_stream = match client
+            .open(
+                server_stream.prio(),
+                server_stream.promises(),
+                server_stream.guaranteed_bandwidth(),
+            )
+            .await
+        {
+            Ok(stream) => stream,
+            Err(_) => break,
+        };
+        tokio::spawn(forward(server_stream, client_stream));
+    }
+}
+
+/// Forward the messages of two streams to each other, until one of them is
+/// closed
+async fn forward(mut a: Stream, mut b: Stream) {
+    loop {
+        let result = tokio::select! {
+            msg = a.recv_raw() => msg.and_then(|msg| b.send_raw(&msg)),
+            msg = b.recv_raw() => msg.and_then(|msg| a.send_raw(&msg)),
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use authc::AuthClientError;
-pub use network::{InitProtocolError, NetworkConnectError, NetworkError};
+pub use network::{
+    EncryptionError, InitProtocolError, NetworkConnectError, NetworkError, PublicKey,
+};
 use network::{ParticipantError, StreamError};
 use specs::error::Error as SpecsError;
 
@@ -4,6 +4,8 @@
 
 pub mod addr;
 pub mod error;
+pub mod query;
+pub mod replay;
 
 // Reexports
 pub use crate::error::Error;
@@ -15,10 +17,14 @@ pub use specs::{
     Builder, DispatcherBuilder, Entity as EcsEntity, ReadStorage, World, WorldExt,
 };
 
-use crate::addr::ConnectionArgs;
+use crate::{
+    addr::ConnectionArgs,
+    replay::{ReplayError, ReplayHeader, ReplayRecorder, ReplayStream},
+};
 use byteorder::{ByteOrder, LittleEndian};
 use common::{
     character::{CharacterId, CharacterItem},
+    cmd::PluginChatCommand,
     comp::{
         self,
         chat::KillSource,
@@ -47,6 +53,7 @@ use common::{
     },
     trade::{PendingTrade, SitePrices, TradeAction, TradeId, TradeResult},
     uid::{Uid, UidAllocator},
+    util::Dir,
     vol::RectVolSize,
     weather::{Weather, WeatherGrid},
 };
@@ -57,10 +64,10 @@ use common_net::{
         self,
         world_msg::{EconomyInfo, PoiInfo, SiteId, SiteInfo},
         ChatTypeContext, ClientGeneral, ClientMsg, ClientRegister, ClientType, DisconnectReason,
-        InviteAnswer, Notification, PingMsg, PlayerInfo, PlayerListUpdate, PresenceKind,
-        RegisterError, ServerGeneral, ServerInit, ServerRegisterAnswer,
+        EcsCompPacket, InviteAnswer, Notification, PingMsg, PlayerInfo, PlayerListUpdate,
+        PresenceKind, RegisterError, ResumeToken, ServerGeneral, ServerInit, ServerRegisterAnswer,
     },
-    sync::WorldSyncExt,
+    sync::{CompSyncPackage, DeltaDecoder, WorldSyncExt},
 };
 use common_state::State;
 use common_systems::add_local_systems;
@@ -74,17 +81,21 @@ use specs::Component;
 use std::{
     collections::{BTreeMap, VecDeque},
     mem,
-    sync::Arc,
+    path::Path,
+    sync::{mpsc, Arc},
     time::{Duration, Instant},
 };
-use tokio::runtime::Runtime;
-use tracing::{debug, error, trace, warn};
+use tokio::{runtime::Runtime, time::error::Elapsed};
+use tracing::{debug, error, info, trace, warn};
 use vek::*;
 
 pub const MAX_SELECTABLE_VIEW_DISTANCE: u32 = 65;
 
 const PING_ROLLING_AVERAGE_SECS: usize = 10;
 
+/// The connection of `Client::reconnect`, or why it failed
+type Reconnection = Result<Result<(Network, Participant, [Stream; 6], ServerInit), Error>, Elapsed>;
+
 #[derive(Debug)]
 pub enum Event {
     Chat(comp::ChatMsg),
@@ -112,6 +123,9 @@ pub enum Event {
     MapMarker(comp::MapMarkerUpdate),
     StartSpectate(Vec3<f32>),
     SpectatePosition(Vec3<f32>),
+    /// The server confirmed which entity the spectator follows, `None` if it
+    /// doesn't follow one anymore
+    Spectating(Option<Uid>),
 }
 
 pub struct WorldData {
@@ -212,6 +226,8 @@ pub struct Client {
     world_data: WorldData,
     weather: WeatherLerp,
     player_list: HashMap<Uid, PlayerInfo>,
+    /// The chat commands registered by the plugins of the server
+    plugin_commands: Vec<PluginChatCommand>,
     character_list: CharacterList,
     sites: HashMap<SiteId, SiteInfoRich>,
     pois: Vec<PoiInfo>,
@@ -233,7 +249,14 @@ pub struct Client {
     pending_invites: HashSet<Uid>,
     // The pending trade the client is involved in, and it's id
     pending_trade: Option<(TradeId, PendingTrade, Option<SitePrices>)>,
-
+    inventory_delta: DeltaDecoder<comp::Inventory>,
+    comp_sync_delta: DeltaDecoder<CompSyncPackage<EcsCompPacket>>,
+
+    addr: ConnectionArgs,
+    /// Resumes the session when the connection is lost, see `Client::resume`
+    resume_token: ResumeToken,
+    /// The error which lost the connection, and the pending reconnect
+    resuming: Option<(Error, mpsc::Receiver<Reconnection>)>,
     network: Option<Network>,
     participant: Option<Participant>,
     general_stream: Stream,
@@ -263,6 +286,15 @@ pub struct Client {
 
     pending_chunks: HashMap<Vec2<i32>, Instant>,
     target_time_of_day: Option<TimeOfDay>,
+
+    /// The entity followed as spectator, see [`Client::spectate_entity`]
+    spectating: Option<Uid>,
+    spectated_look_dir: Option<Dir>,
+
+    /// Records the messages of the server, see [`Client::new_recording`]
+    replay: Option<ReplayRecorder>,
+    /// The messages of the server kept for tests, see [`Client::keep_received`]
+    received: Option<Vec<ServerGeneral>>,
 }
 
 /// Holds data related to the current players characters, as well as some
@@ -283,29 +315,53 @@ impl Client {
         password: &str,
         auth_trusted: impl FnMut(&str) -> bool,
     ) -> Result<Self, Error> {
-        let network = Network::new(Pid::new(), &runtime);
+        Self::new_inner(
+            addr,
+            runtime,
+            mismatched_server_info,
+            username,
+            password,
+            auth_trusted,
+            None,
+        )
+        .await
+    }
 
-        let mut participant = match addr {
-            ConnectionArgs::Tcp {
-                hostname,
-                prefer_ipv6,
-            } => addr::try_connect(&network, &hostname, prefer_ipv6, ConnectAddr::Tcp).await?,
-            ConnectionArgs::Quic {
-                hostname,
-                prefer_ipv6,
-            } => {
-                warn!(
-                    "QUIC is enabled. This is experimental and you won't be able to connect to \
-                     TCP servers unless deactivated"
-                );
-                let config = quinn::ClientConfig::with_native_roots();
-                addr::try_connect(&network, &hostname, prefer_ipv6, |a| {
-                    ConnectAddr::Quic(a, config.clone(), hostname.clone())
-                })
-                .await?
-            },
-            ConnectionArgs::Mpsc(id) => network.connect(ConnectAddr::Mpsc(id)).await?,
-        };
+    /// Like [`Client::new`], but records the session into a replay file at
+    /// `replay_path`, see [`replay`]. The client works normally if the file
+    /// can't be written.
+    pub async fn new_recording(
+        addr: ConnectionArgs,
+        runtime: Arc<Runtime>,
+        mismatched_server_info: &mut Option<ServerInfo>,
+        username: &str,
+        password: &str,
+        auth_trusted: impl FnMut(&str) -> bool,
+        replay_path: &Path,
+    ) -> Result<Self, Error> {
+        Self::new_inner(
+            addr,
+            runtime,
+            mismatched_server_info,
+            username,
+            password,
+            auth_trusted,
+            Some(replay_path),
+        )
+        .await
+    }
+
+    async fn new_inner(
+        addr: ConnectionArgs,
+        runtime: Arc<Runtime>,
+        mismatched_server_info: &mut Option<ServerInfo>,
+        username: &str,
+        password: &str,
+        auth_trusted: impl FnMut(&str) -> bool,
+        replay_path: Option<&Path>,
+    ) -> Result<Self, Error> {
+        let network = Network::new(Pid::new(), &runtime);
+        let mut participant = Self::connect(&network, addr.clone()).await?;
 
         let stream = participant.opened().await?;
         let ping_stream = participant.opened().await?;
@@ -344,6 +400,26 @@ impl Client {
 
         // Wait for initial sync
         let mut ping_interval = tokio::time::interval(Duration::from_secs(1));
+        let init = loop {
+            tokio::select! {
+                // Spawn in a blocking thread (leaving the network thread free).  This is mostly
+                // useful for bots.
+                res = register_stream.recv() => break res?,
+                _ = ping_interval.tick() => ping_stream.send(PingMsg::Ping)?,
+            }
+        };
+        let replay = replay_path.and_then(|path| {
+            let header = ReplayHeader {
+                git_hash: common::util::GIT_HASH.to_string(),
+                git_date: common::util::GIT_DATE.to_string(),
+                server_info: server_info.clone(),
+            };
+            bincode::serialize(&init)
+                .map_err(ReplayError::from)
+                .and_then(|init| ReplayRecorder::create(path, &header, &init))
+                .map_err(|e| warn!(?e, ?path, "Failed to start recording a replay"))
+                .ok()
+        });
         let ServerInit::GameSync {
             entity_package,
             time_of_day,
@@ -354,13 +430,12 @@ impl Client {
             component_recipe_book,
             material_stats,
             ability_map,
-        } = loop {
-            tokio::select! {
-                // Spawn in a blocking thread (leaving the network thread free).  This is mostly
-                // useful for bots.
-                res = register_stream.recv() => break res?,
-                _ = ping_interval.tick() => ping_stream.send(PingMsg::Ping)?,
-            }
+            resume_token,
+        } = init
+        else {
+            return Err(Error::Other(
+                "Server sent an unexpected initial sync".into(),
+            ));
         };
 
         // Spawn in a blocking thread (leaving the network thread free).  This is mostly
@@ -687,6 +762,7 @@ impl Client {
             },
             weather: WeatherLerp::default(),
             player_list: HashMap::new(),
+            plugin_commands: Vec::new(),
             character_list: CharacterList::default(),
             sites: sites
                 .iter()
@@ -714,7 +790,12 @@ impl Client {
             group_members: HashMap::new(),
             pending_invites: HashSet::new(),
             pending_trade: None,
+            inventory_delta: DeltaDecoder::default(),
+            comp_sync_delta: DeltaDecoder::default(),
 
+            addr,
+            resume_token,
+            resuming: None,
             network: Some(network),
             participant: Some(participant),
             general_stream: stream,
@@ -743,6 +824,49 @@ impl Client {
 
             pending_chunks: HashMap::new(),
             target_time_of_day: None,
+
+            spectating: None,
+            spectated_look_dir: None,
+
+            replay,
+            received: None,
+        })
+    }
+
+    async fn connect(network: &Network, addr: ConnectionArgs) -> Result<Participant, Error> {
+        Ok(match addr {
+            ConnectionArgs::Tcp {
+                hostname,
+                prefer_ipv6,
+            } => addr::try_connect(network, &hostname, prefer_ipv6, ConnectAddr::Tcp).await?,
+            ConnectionArgs::EncryptedTcp {
+                hostname,
+                prefer_ipv6,
+                pinned,
+            } => {
+                addr::try_connect(network, &hostname, prefer_ipv6, |a| {
+                    ConnectAddr::EncryptedTcp(a, pinned)
+                })
+                .await?
+            },
+            ConnectionArgs::Quic {
+                hostname,
+                prefer_ipv6,
+            } => {
+                warn!(
+                    "QUIC is enabled. This is experimental and you won't be able to connect to \
+                     TCP servers unless deactivated"
+                );
+                let config = quinn::ClientConfig::with_native_roots();
+                addr::try_connect(network, &hostname, prefer_ipv6, |a| {
+                    ConnectAddr::Quic(a, config.clone(), hostname.clone())
+                })
+                .await?
+            },
+            ConnectionArgs::Mpsc(id) => network.connect(ConnectAddr::Mpsc(id)).await?,
+            ConnectionArgs::Sim(id, conditions) => {
+                network.connect(ConnectAddr::Sim(id, conditions)).await?
+            },
         })
     }
 
@@ -787,15 +911,13 @@ impl Client {
 
         debug!("Registering client...");
 
-        register_stream.send(ClientRegister { token_or_username })?;
+        register_stream.send(ClientRegister {
+            token_or_username,
+            resume_token: None,
+        })?;
 
         match register_stream.recv::<ServerRegisterAnswer>().await? {
-            Err(RegisterError::AuthError(err)) => Err(Error::AuthErr(err)),
-            Err(RegisterError::InvalidCharacter) => Err(Error::InvalidCharacter),
-            Err(RegisterError::NotOnWhitelist) => Err(Error::NotOnWhitelist),
-            Err(RegisterError::Kicked(err)) => Err(Error::Kicked(err)),
-            Err(RegisterError::Banned(reason)) => Err(Error::Banned(reason)),
-            Err(RegisterError::TooManyPlayers) => Err(Error::TooManyPlayers),
+            Err(e) => Err(Self::register_error(e)),
             Ok(()) => {
                 debug!("Client registered successfully.");
                 Ok(())
@@ -803,6 +925,148 @@ impl Client {
         }
     }
 
+    fn register_error(error: RegisterError) -> Error {
+        match error {
+            RegisterError::AuthError(err) => Error::AuthErr(err),
+            RegisterError::InvalidCharacter => Error::InvalidCharacter,
+            RegisterError::NotOnWhitelist => Error::NotOnWhitelist,
+            RegisterError::Kicked(err) => Error::Kicked(err),
+            RegisterError::Banned(reason) => Error::Banned(reason),
+            RegisterError::TooManyPlayers => Error::TooManyPlayers,
+            // The server already gave up on us
+            RegisterError::SessionExpired => Error::ServerTimeout,
+        }
+    }
+
+    /// Reconnect after the connection to the server was lost, and take over
+    /// the entity this client left behind. The server keeps it for the client
+    /// timeout after noticing the loss.
+    async fn reconnect(
+        addr: ConnectionArgs,
+        runtime: &Runtime,
+        resume_token: ResumeToken,
+    ) -> Result<(Network, Participant, [Stream; 6], ServerInit), Error> {
+        let network = Network::new(Pid::new(), runtime);
+        let mut participant = Self::connect(&network, addr).await?;
+        // In the order the server opens them
+        let mut streams = [
+            participant.opened().await?,
+            participant.opened().await?,
+            participant.opened().await?,
+            participant.opened().await?,
+            participant.opened().await?,
+            participant.opened().await?,
+        ];
+
+        let register_stream = &mut streams[2];
+        register_stream.send(ClientType::Game)?;
+        let _: ServerInfo = register_stream.recv().await?;
+        register_stream.send(ClientRegister {
+            token_or_username: String::new(),
+            resume_token: Some(resume_token),
+        })?;
+        register_stream
+            .recv::<ServerRegisterAnswer>()
+            .await?
+            .map_err(Self::register_error)?;
+        let init = register_stream.recv().await?;
+        Ok((network, participant, streams, init))
+    }
+
+    /// Start resuming the session after the connection failed with `error`,
+    /// which is returned again if that's not possible. The reconnect runs in
+    /// the background, see `Client::poll_resume`.
+    fn resume(&mut self, error: Error) -> Result<(), Error> {
+        if self.presence.is_none()
+            || !matches!(
+                error,
+                Error::NetworkErr(_)
+                    | Error::ParticipantErr(_)
+                    | Error::StreamErr(_)
+                    | Error::ServerTimeout
+            )
+        {
+            return Err(error);
+        }
+        info!(
+            ?error,
+            "Lost the connection to the server, resuming the session"
+        );
+        let (tx, rx) = mpsc::channel();
+        let addr = self.addr.clone();
+        let runtime = Arc::clone(&self.runtime);
+        let resume_token = self.resume_token;
+        let client_timeout = self.client_timeout;
+        self.runtime.spawn(async move {
+            let reconnect = Self::reconnect(addr, &runtime, resume_token);
+            let _ = tx.send(tokio::time::timeout(client_timeout, reconnect).await);
+        });
+        self.resuming = Some((error, rx));
+        Ok(())
+    }
+
+    /// Finish resuming the session once the reconnect is done, returns
+    /// `Ok(false)` while it's still running. Fails with the error which lost
+    /// the connection if the session couldn't be resumed.
+    fn poll_resume(&mut self) -> Result<bool, Error> {
+        let Some((error, rx)) = self.resuming.take() else {
+            return Ok(true);
+        };
+        let (network, participant, streams, init) = match rx.try_recv() {
+            Ok(Ok(Ok(res))) => res,
+            Ok(Ok(Err(e))) => {
+                warn!(?e, "Failed to resume the session");
+                return Err(error);
+            },
+            Ok(Err(_)) => {
+                warn!("Timed out resuming the session");
+                return Err(error);
+            },
+            Err(mpsc::TryRecvError::Empty) => {
+                self.resuming = Some((error, rx));
+                return Ok(false);
+            },
+            Err(mpsc::TryRecvError::Disconnected) => return Err(error),
+        };
+        let ServerInit::Resume {
+            entity_package,
+            time_of_day,
+        } = init
+        else {
+            warn!("Server didn't resume the session");
+            return Err(error);
+        };
+
+        let [general, ping, register, character_screen, in_game, terrain] = streams;
+        let old = (
+            self.participant.replace(participant),
+            mem::replace(&mut self.general_stream, general),
+            mem::replace(&mut self.ping_stream, ping),
+            mem::replace(&mut self.register_stream, register),
+            mem::replace(&mut self.character_screen_stream, character_screen),
+            mem::replace(&mut self.in_game_stream, in_game),
+            mem::replace(&mut self.terrain_stream, terrain),
+            self.network.replace(network),
+        );
+        // Shutting down the broken connection might take a while
+        self.runtime.spawn_blocking(move || drop(old));
+
+        // The server sends everything around us again, start from scratch
+        self.clean_state();
+        self.clear_terrain();
+        self.inventory_delta.reset();
+        self.comp_sync_delta.reset();
+        let entity = self.state.ecs_mut().apply_entity_package(entity_package);
+        *self.state.ecs_mut().write_resource() = time_of_day;
+        *self.state.ecs_mut().write_resource() = PlayerEntity(Some(entity));
+        self.last_server_pong = self.state.get_time();
+        info!("Resumed the session");
+        Ok(true)
+    }
+
+    /// Whether the client lost its connection and is resuming its session
+    pub fn is_resuming(&self) -> bool { self.resuming.is_some() }
+
     fn send_msg_err<S>(&mut self, msg: S) -> Result<(), network::StreamError>
     where
         S: Into<ClientMsg>,
@@ -853,7 +1117,10 @@ impl Client {
                     | ClientGeneral::RequestPlayerPhysics { .. }
                     | ClientGeneral::RequestLossyTerrainCompression { .. }
                     | ClientGeneral::UpdateMapMarker(_)
-                    | ClientGeneral::SpectatePosition(_) => {
+                    | ClientGeneral::SpectatePosition(_)
+                    | ClientGeneral::SpectateEntity(_)
+                    | ClientGeneral::InventoryAck(_)
+                    | ClientGeneral::CompSyncAck(_) => {
                         #[cfg(feature = "tracy")]
                         {
                             ingame = 1.0;
@@ -1017,6 +1284,8 @@ impl Client {
 
     pub fn swap_slots(&mut self, a: Slot, b: Slot) {
         match (a, b) {
+            // The vault is accessed through a vault sprite, see [`Client::swap_vault_slots`]
+            (Slot::Vault(_), _) | (_, Slot::Vault(_)) => {},
             (Slot::Equip(equip), slot) | (slot, Slot::Equip(equip)) => self.control_action(
                 ControlAction::InventoryAction(InventoryAction::Swap(equip, slot)),
             ),
@@ -1028,6 +1297,18 @@ impl Client {
         }
     }
 
+    /// Moves an item into, out of or within the vault, which is accessed
+    /// through the vault sprite at `vault_pos`
+    pub fn swap_vault_slots(&mut self, from: Slot, to: Slot, vault_pos: Vec3<i32>) {
+        self.send_msg(ClientGeneral::ControlEvent(ControlEvent::InventoryEvent(
+            InventoryEvent::VaultSwap {
+                from,
+                to,
+                vault_pos,
+            },
+        )))
+    }
+
     pub fn drop_slot(&mut self, slot: Slot) {
         match slot {
             Slot::Equip(equip) => {
@@ -1036,6 +1317,8 @@ impl Client {
             Slot::Inventory(inv) => self.send_msg(ClientGeneral::ControlEvent(
                 ControlEvent::InventoryEvent(InventoryEvent::Drop(inv)),
             )),
+            // Items have to be taken out of the vault before they can be dropped
+            Slot::Vault(_) => {},
         }
     }
 
@@ -1063,6 +1346,8 @@ impl Client {
 
     pub fn split_swap_slots(&mut self, a: Slot, b: Slot) {
         match (a, b) {
+            // The vault is accessed through a vault sprite, see [`Client::split_swap_vault_slots`]
+            (Slot::Vault(_), _) | (_, Slot::Vault(_)) => {},
             (Slot::Equip(equip), slot) | (slot, Slot::Equip(equip)) => self.control_action(
                 ControlAction::InventoryAction(InventoryAction::Swap(equip, slot)),
             ),
@@ -1074,6 +1359,16 @@ impl Client {
         }
     }
 
+    pub fn split_swap_vault_slots(&mut self, from: Slot, to: Slot, vault_pos: Vec3<i32>) {
+        self.send_msg(ClientGeneral::ControlEvent(ControlEvent::InventoryEvent(
+            InventoryEvent::VaultSplitSwap {
+                from,
+                to,
+                vault_pos,
+            },
+        )))
+    }
+
     pub fn split_drop_slot(&mut self, slot: Slot) {
         match slot {
             Slot::Equip(equip) => {
@@ -1082,6 +1377,8 @@ impl Client {
             Slot::Inventory(inv) => self.send_msg(ClientGeneral::ControlEvent(
                 ControlEvent::InventoryEvent(InventoryEvent::SplitDrop(inv)),
             )),
+            // Items have to be taken out of the vault before they can be dropped
+            Slot::Vault(_) => {},
         }
     }
 
@@ -1113,6 +1410,10 @@ impl Client {
 
     pub fn player_list(&self) -> &HashMap<Uid, PlayerInfo> { &self.player_list }
 
+    /// The chat commands registered by the plugins of the server, the client
+    /// runs them by sending them to the server like the built-in commands
+    pub fn plugin_commands(&self) -> &[PluginChatCommand] { &self.plugin_commands }
+
     pub fn character_list(&self) -> &CharacterList { &self.character_list }
 
     pub fn server_info(&self) -> &ServerInfo { &self.server_info }
@@ -1426,6 +1727,22 @@ impl Client {
         write
     }
 
+    /// Follow an entity as spectator, or stop following it with `None`. The
+    /// server answers with [`Event::Spectating`], with `None` if it doesn't
+    /// allow it.
+    pub fn spectate_entity(&mut self, target: Option<Uid>) {
+        self.spectating = target;
+        self.spectated_look_dir = None;
+        self.send_msg(ClientGeneral::SpectateEntity(target));
+    }
+
+    /// The entity followed as spectator
+    pub fn spectating(&self) -> Option<Uid> { self.spectating }
+
+    /// Where the entity followed as spectator is looking, if the server sent
+    /// it yet
+    pub fn spectated_look_dir(&self) -> Option<Dir> { self.spectated_look_dir }
+
     /// Checks whether a player can swap their weapon+ability `Loadout` settings
     /// and sends the `ControlAction` event that signals to do the swap.
     pub fn swap_loadout(&mut self) { self.control_action(ControlAction::SwapEquippedWeapons) }
@@ -1680,6 +1997,11 @@ impl Client {
         add_foreign_systems: impl Fn(&mut DispatcherBuilder),
     ) -> Result<Vec<Event>, Error> {
         span!(_guard, "tick", "Client::tick");
+        // Nothing can be sent or received until the session is resumed
+        if !self.poll_resume()? {
+            return Ok(Vec::new());
+        }
+
         // This tick function is the centre of the Veloren universe. Most client-side
         // things are managed from here, and as such it's important that it
         // stays organised. Please consult the core developers before making
@@ -1758,7 +2080,10 @@ impl Client {
         }
 
         // Handle new messages from the server.
-        frontend_events.append(&mut self.handle_new_messages()?);
+        match self.handle_new_messages() {
+            Ok(mut events) => frontend_events.append(&mut events),
+            Err(e) => self.resume(e)?,
+        }
 
         // 3) Update client local data
         // Check if the invite has timed out and remove if so
@@ -1793,6 +2118,24 @@ impl Client {
             .ecs()
             .fetch::<EventBus<common::event::ServerEvent>>()
             .recv_all();
+        #[cfg(feature = "plugins")]
+        {
+            let actions = self
+                .state
+                .ecs()
+                .fetch::<common_state::plugin::PluginMgr>()
+                .take_actions();
+            let uid = self.uid();
+            for (plugin, action) in actions {
+                match plugin_action_event(action, uid) {
+                    Ok(event) => frontend_events.push(event),
+                    Err(action) => warn!(
+                        ?action,
+                        "Plugin '{}' emitted an action which is only applied by the server", plugin
+                    ),
+                }
+            }
+        }
 
         // 5) Terrain
         self.tick_terrain()?;
@@ -1803,6 +2146,18 @@ impl Client {
             self.last_server_ping = self.state.get_time();
         }
 
+        // Keep a spectator with the entity it follows, so the world around it is
+        // loaded
+        if let Some(target_pos) = self
+            .spectating
+            .and_then(|uid| self.state.ecs().entity_from_uid(uid.0))
+            .and_then(|target| self.state.read_component_copied::<comp::Pos>(target))
+        {
+            let entity = self.entity();
+            self.state
+                .write_component_ignore_entity_dead(entity, target_pos);
+        }
+
         // 6) Update the server about the player's physics attributes.
         if self.presence.is_some() {
             if let (Some(pos), Some(vel), Some(ori)) = (
@@ -1818,6 +2173,24 @@ impl Client {
                 })?;
             }
         }
+        // Record the physics of the player, which the server only sends to others
+        let entity = self.entity();
+        if let (Some(replay), Some(uid)) = (
+            &mut self.replay,
+            self.state.read_component_copied::<Uid>(entity),
+        ) {
+            if let Err(e) = replay.record_own_entity(
+                uid,
+                self.state.read_component_copied(entity),
+                self.state.read_component_copied(entity),
+                self.state.read_component_copied(entity),
+                self.state.read_component_cloned(entity),
+                self.force_update_counter,
+            ) {
+                warn!(?e, "Failed to record the replay, stopping the recording");
+                self.replay = None;
+            }
+        }
 
         /*
         // Output debug metrics
@@ -2149,6 +2522,18 @@ impl Client {
                     .ecs_mut()
                     .apply_comp_sync_package(comp_sync_package);
             },
+            ServerGeneral::CompSyncDelta(packet, force_counter) => {
+                // Acknowledge every package, so the server can use it as baseline
+                let (comp_sync_package, ack) = self.comp_sync_delta.decode(packet);
+                self.send_msg(ClientGeneral::CompSyncAck(ack));
+                // Otherwise the server sends all the components again
+                if let Some(comp_sync_package) = comp_sync_package {
+                    self.force_update_counter = force_counter;
+                    self.state
+                        .ecs_mut()
+                        .apply_comp_sync_package(comp_sync_package);
+                }
+            },
             ServerGeneral::CreateEntity(entity_package) => {
                 self.state.ecs_mut().apply_entity_package(entity_package);
             },
@@ -2162,6 +2547,9 @@ impl Client {
             ServerGeneral::Notification(n) => {
                 frontend_events.push(Event::Notification(n));
             },
+            ServerGeneral::PluginCommands(commands) => {
+                self.plugin_commands = commands;
+            },
             _ => unreachable!("Not a general msg"),
         }
         Ok(())
@@ -2286,13 +2674,20 @@ impl Client {
             // Cleanup for when the client goes back to the `presence = None`
             ServerGeneral::ExitInGameSuccess => {
                 self.presence = None;
+                self.spectating = None;
+                self.spectated_look_dir = None;
                 self.clean_state();
             },
             ServerGeneral::InventoryUpdate(inventory, event) => {
-                match event {
-                    InventoryUpdateEvent::BlockCollectFailed { .. } => {},
-                    InventoryUpdateEvent::EntityCollectFailed { .. } => {},
-                    _ => {
+                // Acknowledge every update, so the server can use it as baseline
+                let (inventory, ack) = self.inventory_delta.decode(inventory);
+                self.send_msg(ClientGeneral::InventoryAck(ack));
+                match (inventory, &event) {
+                    // The server sends the whole inventory again
+                    (None, _) => {},
+                    (_, InventoryUpdateEvent::BlockCollectFailed { .. }) => {},
+                    (_, InventoryUpdateEvent::EntityCollectFailed { .. }) => {},
+                    (Some(inventory), _) => {
                         // Push the updated inventory component to the client
                         // FIXME: Figure out whether this error can happen under normal gameplay,
                         // if not find a better way to handle it, if so maybe consider kicking the
@@ -2360,6 +2755,14 @@ impl Client {
             ServerGeneral::SpectatePosition(pos) => {
                 frontend_events.push(Event::SpectatePosition(pos));
             },
+            ServerGeneral::Spectating(target) => {
+                self.spectating = target;
+                self.spectated_look_dir = None;
+                frontend_events.push(Event::Spectating(target));
+            },
+            ServerGeneral::SpectatedLookDir(look_dir) => {
+                self.spectated_look_dir = Some(look_dir);
+            },
             _ => unreachable!("Not a in_game message"),
         }
         Ok(())
@@ -2403,6 +2806,10 @@ impl Client {
             },
             ServerGeneral::CharacterActionError(error) => {
                 warn!("CharacterActionError: {:?}.", error);
+                // The server didn't let the client spectate
+                if self.presence == Some(PresenceKind::Spectator) {
+                    self.presence = None;
+                }
                 events.push(Event::CharacterError(error));
             },
             ServerGeneral::CharacterDataLoadResult(Ok(metadata)) => {
@@ -2453,6 +2860,31 @@ impl Client {
         Ok(())
     }
 
+    /// Keep the messages received from the server from now on, for tests
+    /// which check what the server sent, see [`Client::take_received`]
+    pub fn keep_received(&mut self) { self.received.get_or_insert_with(Vec::new); }
+
+    /// The messages received from the server since the last call, empty
+    /// unless [`Client::keep_received`] was called
+    pub fn take_received(&mut self) -> Vec<ServerGeneral> {
+        self.received
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn record_received(&mut self, stream: ReplayStream, msg: &ServerGeneral) {
+        if let Some(replay) = &mut self.replay {
+            if let Err(e) = replay.record(stream, msg) {
+                warn!(?e, "Failed to record the replay, stopping the recording");
+                self.replay = None;
+            }
+        }
+        if let Some(received) = &mut self.received {
+            received.push(msg.clone());
+        }
+    }
+
     fn handle_messages(&mut self, frontend_events: &mut Vec<Event>) -> Result<u64, Error> {
         let mut cnt = 0;
         #[cfg(feature = "tracy")]
@@ -2462,6 +2894,7 @@ impl Client {
 
             while let Some(msg) = self.general_stream.try_recv()? {
                 cnt += 1;
+                self.record_received(ReplayStream::General, &msg);
                 self.handle_server_msg(frontend_events, msg)?;
             }
             while let Some(msg) = self.ping_stream.try_recv()? {
@@ -2470,6 +2903,7 @@ impl Client {
             }
             while let Some(msg) = self.character_screen_stream.try_recv()? {
                 cnt += 1;
+                self.record_received(ReplayStream::CharacterScreen, &msg);
                 self.handle_server_character_screen_msg(frontend_events, msg)?;
             }
             while let Some(msg) = self.in_game_stream.try_recv()? {
@@ -2478,6 +2912,7 @@ impl Client {
                 {
                     ingame_cnt += 1;
                 }
+                self.record_received(ReplayStream::InGame, &msg);
                 self.handle_server_in_game_msg(frontend_events, msg)?;
             }
             while let Some(msg) = self.terrain_stream.try_recv()? {
@@ -2488,6 +2923,7 @@ impl Client {
                         terrain_cnt += chunk.as_ref().map(|x| x.approx_len()).unwrap_or(0);
                     }
                 }
+                self.record_received(ReplayStream::Terrain, &msg);
                 self.handle_server_terrain_msg(msg)?;
             }
 
@@ -2812,6 +3248,30 @@ impl Drop for Client {
         });
         //explicitly drop the network here while the runtime is still existing
         drop(self.network.take());
+
+        if let Some(replay) = &mut self.replay {
+            if let Err(e) = replay.flush() {
+                warn!(?e, "Failed to write the end of the replay");
+            }
+        }
+    }
+}
+
+/// The frontend event for a plugin action which can be applied by the client,
+/// actions which modify the world are returned as they are only applied by the
+/// server
+#[cfg(feature = "plugins")]
+fn plugin_action_event(
+    action: common_state::plugin::Action,
+    own_uid: Option<Uid>,
+) -> Result<Event, common_state::plugin::Action> {
+    use common_state::plugin::Action;
+    match action {
+        Action::BroadcastMessage(msg) => Ok(Event::Chat(comp::ChatType::Meta.chat_msg(msg))),
+        Action::PlayerSendMessage(uid, msg) if Some(uid) == own_uid => {
+            Ok(Event::Chat(comp::ChatType::Meta.chat_msg(msg)))
+        },
+        action => Err(action),
     }
 }
 
@@ -2820,6 +3280,36 @@ mod tests {
     use super::*;
     use client_i18n::LocalizationHandle;
 
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn plugin_messages_are_shown_in_the_chat() {
+        use common_state::plugin::Action;
+
+        let own_uid = Some(Uid(1));
+        let is_chat = |result: Result<Event, Action>, text: &str| match result {
+            Ok(Event::Chat(msg)) => msg.message == text,
+            _ => false,
+        };
+        assert!(is_chat(
+            plugin_action_event(Action::BroadcastMessage("hello".to_owned()), own_uid),
+            "hello"
+        ));
+        assert!(is_chat(
+            plugin_action_event(Action::PlayerSendMessage(Uid(1), "hi".to_owned()), own_uid),
+            "hi"
+        ));
+        // Messages to other players and actions modifying the world are left to the
+        // server
+        assert!(matches!(
+            plugin_action_event(Action::PlayerSendMessage(Uid(2), "hi".to_owned()), own_uid),
+            Err(Action::PlayerSendMessage(Uid(2), _))
+        ));
+        assert!(matches!(
+            plugin_action_event(Action::KillEntity(Uid(1)), own_uid),
+            Err(Action::KillEntity(Uid(1)))
+        ));
+    }
+
     #[test]
     /// THIS TEST VERIFIES THE CONSTANT API.
     /// CHANGING IT WILL BREAK 3rd PARTY APPLICATIONS (please extend) which
@@ -0,0 +1,93 @@
+//! Status query of servers for the server list, see
+//! [`common_net::msg::query`].
+
+use crate::addr::resolve;
+use common_net::msg::query::{
+    decode_query, encode_query, QueryRequest, QueryResponse, MAX_QUERY_SIZE,
+};
+pub use common_net::msg::{query::DEFAULT_QUERY_PORT, ServerStatus};
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::{Duration, Instant},
+};
+use tokio::{net::UdpSocket, time::timeout};
+
+/// How long to wait for each answer of the server
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug)]
+pub enum QueryError {
+    HostnameLookupFailed(std::io::Error),
+    Io(std::io::Error),
+    /// The server didn't answer, it might be offline or not answer status
+    /// queries
+    Timeout,
+    UnexpectedAnswer,
+}
+
+impl From<std::io::Error> for QueryError {
+    fn from(err: std::io::Error) -> Self { Self::Io(err) }
+}
+
+/// Ask the server for its status without connecting to it. The address is
+/// the one of the game server, (hostname|ip):[<port>] like in
+/// [`crate::addr::ConnectionArgs`], the status is queried on its host at
+/// `query_port` instead. Also returns the round trip time of the status
+/// request as ping.
+pub async fn query_status(
+    address: &str,
+    query_port: u16,
+    prefer_ipv6: bool,
+) -> Result<(ServerStatus, Duration), QueryError> {
+    let mut result = Err(QueryError::Timeout);
+    for mut addr in resolve(address, prefer_ipv6)
+        .await
+        .map_err(QueryError::HostnameLookupFailed)?
+    {
+        addr.set_port(query_port);
+        result = query_addr(addr).await;
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+async fn query_addr(addr: SocketAddr) -> Result<(ServerStatus, Duration), QueryError> {
+    let local_addr = if addr.is_ipv6() {
+        SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0))
+    } else {
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))
+    };
+    let socket = UdpSocket::bind(local_addr).await?;
+    socket.connect(addr).await?;
+
+    let token = match request(&socket, QueryRequest::Token).await? {
+        (QueryResponse::Token { token }, _) => token,
+        _ => return Err(QueryError::UnexpectedAnswer),
+    };
+    match request(&socket, QueryRequest::Status { token }).await? {
+        (QueryResponse::Status(status), ping) => Ok((status, ping)),
+        _ => Err(QueryError::UnexpectedAnswer),
+    }
+}
+
+async fn request(
+    socket: &UdpSocket,
+    request: QueryRequest,
+) -> Result<(QueryResponse, Duration), QueryError> {
+    let sent = Instant::now();
+    socket.send(&encode_query(&request)).await?;
+    let mut buf = [0; MAX_QUERY_SIZE];
+    timeout(QUERY_TIMEOUT, async {
+        loop {
+            let len = socket.recv(&mut buf).await?;
+            // Datagrams which aren't query answers are ignored
+            if let Some(response) = decode_query(&buf[..len]) {
+                return Ok((response, sent.elapsed()));
+            }
+        }
+    })
+    .await
+    .map_err(|_| QueryError::Timeout)?
+}
@@ -0,0 +1,426 @@
+//! Recording of the messages the client gets from the server into replay
+//! files, for bug reports, tournaments or videos. They are played back by
+//! [`ReplayServer`], which a client connects to like to any server.
+//!
+//! A replay file starts with the [`REPLAY_MAGIC`] and the [`REPLAY_VERSION`],
+//! followed by the bincode encoded [`ReplayHeader`], the [`ServerInit`] the
+//! session started with and a [`ReplayFrame`] for each message.
+
+mod playback;
+
+pub use playback::ReplayServer;
+
+use common::{
+    comp::{self, CharacterState},
+    uid::Uid,
+};
+use common_net::{
+    msg::{EcsCompPacket, ServerGeneral, ServerInfo, ServerInit},
+    sync::{CompPacket, CompSyncPackage},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::Path,
+    time::Instant,
+};
+use tracing::info;
+
+/// Version of the replay format, replays of other versions can't be played
+/// back
+pub const REPLAY_VERSION: u32 = 1;
+const REPLAY_MAGIC: [u8; 8] = *b"VELOREPL";
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Bincode(bincode::Error),
+    Network(network::NetworkError),
+    NotAReplay,
+    UnsupportedVersion(u32),
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self { Self::Io(err) }
+}
+
+impl From<bincode::Error> for ReplayError {
+    fn from(err: bincode::Error) -> Self { Self::Bincode(err) }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Bincode(e) => write!(f, "Invalid replay: {}", e),
+            Self::Network(e) => write!(f, "Failed to serve the replay: {}", e),
+            Self::NotAReplay => write!(f, "Not a replay file"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "The replay has version {} of the format, only version {} is supported",
+                version, REPLAY_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    /// Version of the game which recorded the replay
+    pub git_hash: String,
+    pub git_date: String,
+    pub server_info: ServerInfo,
+}
+
+/// The stream a message was received on, the client handles the messages of
+/// each stream differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplayStream {
+    General,
+    CharacterScreen,
+    InGame,
+    Terrain,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayFrame {
+    /// Seconds since the recording started
+    pub time: f64,
+    pub stream: ReplayStream,
+    pub msg: ServerGeneral,
+}
+
+/// Serialized like [`ReplayFrame`], to record messages without cloning them
+#[derive(Serialize)]
+struct ReplayFrameRef<'a> {
+    time: f64,
+    stream: ReplayStream,
+    msg: &'a ServerGeneral,
+}
+
+/// Which components of the client's own entity were recorded last time,
+/// see [`ReplayRecorder::record_own_entity`]
+#[derive(Default)]
+struct OwnComps {
+    pos: bool,
+    vel: bool,
+    ori: bool,
+    character_state: bool,
+}
+
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+    start: Instant,
+    own_comps: OwnComps,
+}
+
+impl ReplayRecorder {
+    /// `init` is the bincode encoded [`ServerInit`] the session started with
+    pub(crate) fn create(
+        path: &Path,
+        header: &ReplayHeader,
+        init: &[u8],
+    ) -> Result<Self, ReplayError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&REPLAY_MAGIC)?;
+        writer.write_all(&REPLAY_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, header)?;
+        writer.write_all(init)?;
+        info!(?path, "Recording a replay");
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+            own_comps: OwnComps::default(),
+        })
+    }
+
+    pub(crate) fn record(
+        &mut self,
+        stream: ReplayStream,
+        msg: &ServerGeneral,
+    ) -> Result<(), ReplayError> {
+        let frame = ReplayFrameRef {
+            time: self.start.elapsed().as_secs_f64(),
+            stream,
+            msg,
+        };
+        bincode::serialize_into(&mut self.writer, &frame)?;
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<(), ReplayError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// The server doesn't send the physics of the client's own entity to it,
+    /// so they are recorded like it would send them to other clients
+    pub(crate) fn record_own_entity(
+        &mut self,
+        uid: Uid,
+        pos: Option<comp::Pos>,
+        vel: Option<comp::Vel>,
+        ori: Option<comp::Ori>,
+        character_state: Option<CharacterState>,
+        force_counter: u64,
+    ) -> Result<(), ReplayError> {
+        let mut package = CompSyncPackage::new();
+        sync_own_comp(&mut package, uid, &mut self.own_comps.pos, pos);
+        sync_own_comp(&mut package, uid, &mut self.own_comps.vel, vel);
+        sync_own_comp(&mut package, uid, &mut self.own_comps.ori, ori);
+        sync_own_comp(
+            &mut package,
+            uid,
+            &mut self.own_comps.character_state,
+            character_state,
+        );
+        if package.is_empty() {
+            return Ok(());
+        }
+        self.record(
+            ReplayStream::General,
+            &ServerGeneral::CompSync(package, force_counter),
+        )
+    }
+}
+
+fn sync_own_comp<C>(
+    package: &mut CompSyncPackage<EcsCompPacket>,
+    uid: Uid,
+    recorded: &mut bool,
+    comp: Option<C>,
+) where
+    EcsCompPacket: From<C>,
+    <EcsCompPacket as CompPacket>::Phantom: From<PhantomData<C>>,
+{
+    let present = comp.is_some();
+    match (comp, *recorded) {
+        (Some(comp), false) => package.comp_inserted(uid, comp),
+        (Some(comp), true) => package.comp_modified(uid, comp),
+        (None, true) => package.comp_removed::<C>(uid),
+        (None, false) => {},
+    }
+    *recorded = present;
+}
+
+pub struct ReplayReader {
+    reader: BufReader<File>,
+    /// Where the first frame starts in the file
+    frames_start: u64,
+}
+
+impl ReplayReader {
+    pub fn open(path: &Path) -> Result<(Self, ReplayHeader, ServerInit), ReplayError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; REPLAY_MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != REPLAY_MAGIC {
+            return Err(ReplayError::NotAReplay);
+        }
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let header = bincode::deserialize_from(&mut reader)?;
+        let init = bincode::deserialize_from(&mut reader)?;
+        let frames_start = reader.stream_position()?;
+        Ok((
+            Self {
+                reader,
+                frames_start,
+            },
+            header,
+            init,
+        ))
+    }
+
+    /// Returns `None` at the end of the replay. Replays which end in the
+    /// middle of a frame, because the game didn't stop recording properly,
+    /// end with the last whole frame.
+    pub fn next_frame(&mut self) -> Result<Option<ReplayFrame>, ReplayError> {
+        match bincode::deserialize_from(&mut self.reader) {
+            Ok(frame) => Ok(Some(frame)),
+            Err(e) => match *e {
+                bincode::ErrorKind::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+                e => Err(ReplayError::Bincode(Box::new(e))),
+            },
+        }
+    }
+
+    /// Start over with the first frame
+    pub fn rewind(&mut self) -> Result<(), ReplayError> {
+        self.reader.seek(SeekFrom::Start(self.frames_start))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::resources::TimeOfDay;
+    use common_net::{
+        msg::ecs_packet::EcsCompPhantom,
+        sync::{CompUpdateKind, EntityPackage},
+    };
+    use std::path::PathBuf;
+    use vek::Vec3;
+
+    fn replay_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "veloren-replay-{}-{}.vreplay",
+            name,
+            std::process::id()
+        ))
+    }
+
+    fn record(path: &Path) {
+        let header = ReplayHeader {
+            git_hash: "0123abcd".into(),
+            git_date: "2022-11-28".into(),
+            server_info: ServerInfo {
+                name: "Server".into(),
+                description: "Description".into(),
+                git_hash: "0123abcd".into(),
+                git_date: "2022-11-28".into(),
+                auth_provider: None,
+            },
+        };
+        let init = bincode::serialize(&ServerInit::Resume {
+            entity_package: EntityPackage {
+                uid: 7,
+                comps: Vec::new(),
+            },
+            time_of_day: TimeOfDay(100.0),
+        })
+        .unwrap();
+        let mut recorder = ReplayRecorder::create(path, &header, &init).unwrap();
+        recorder
+            .record(
+                ReplayStream::CharacterScreen,
+                &ServerGeneral::CharacterSuccess,
+            )
+            .unwrap();
+        recorder
+            .record(ReplayStream::InGame, &ServerGeneral::SetViewDistance(10))
+            .unwrap();
+        let pos = Some(comp::Pos(Vec3::new(1.0, 2.0, 3.0)));
+        recorder
+            .record_own_entity(Uid(7), pos, None, None, None, 1)
+            .unwrap();
+        recorder
+            .record_own_entity(Uid(7), pos, None, None, None, 1)
+            .unwrap();
+        recorder
+            .record_own_entity(Uid(7), None, None, None, None, 1)
+            .unwrap();
+        // Nothing to record without any of the components
+        recorder
+            .record_own_entity(Uid(7), None, None, None, None, 1)
+            .unwrap();
+        recorder.flush().unwrap();
+    }
+
+    fn own_pos_update(frame: ReplayFrame) -> CompUpdateKind<EcsCompPacket> {
+        assert_eq!(frame.stream, ReplayStream::General);
+        match frame.msg {
+            ServerGeneral::CompSync(mut package, 1) => {
+                assert_eq!(package.comp_updates.len(), 1);
+                let (uid, update) = package.comp_updates.remove(0);
+                assert_eq!(uid, 7);
+                update
+            },
+            msg => panic!("Unexpected message {:?}", msg),
+        }
+    }
+
+    #[test]
+    fn record_and_read() {
+        let path = replay_path("record-and-read");
+        record(&path);
+
+        let (mut reader, header, init) = ReplayReader::open(&path).unwrap();
+        assert_eq!(header.git_hash, "0123abcd");
+        assert_eq!(header.server_info.name, "Server");
+        assert!(matches!(
+            init,
+            ServerInit::Resume { entity_package, .. } if entity_package.uid == 7
+        ));
+
+        for _ in 0..2 {
+            let frame = reader.next_frame().unwrap().unwrap();
+            assert_eq!(frame.stream, ReplayStream::CharacterScreen);
+            assert!(matches!(frame.msg, ServerGeneral::CharacterSuccess));
+            let frame = reader.next_frame().unwrap().unwrap();
+            assert_eq!(frame.stream, ReplayStream::InGame);
+            assert!(matches!(frame.msg, ServerGeneral::SetViewDistance(10)));
+            // The own position is inserted, modified and removed
+            assert!(matches!(
+                own_pos_update(reader.next_frame().unwrap().unwrap()),
+                CompUpdateKind::Inserted(EcsCompPacket::Pos(_))
+            ));
+            assert!(matches!(
+                own_pos_update(reader.next_frame().unwrap().unwrap()),
+                CompUpdateKind::Modified(EcsCompPacket::Pos(_))
+            ));
+            assert!(matches!(
+                own_pos_update(reader.next_frame().unwrap().unwrap()),
+                CompUpdateKind::Removed(EcsCompPhantom::Pos(_))
+            ));
+            assert!(reader.next_frame().unwrap().is_none());
+            reader.rewind().unwrap();
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn truncated_frame() {
+        let path = replay_path("truncated-frame");
+        record(&path);
+        let file = fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(file.metadata().unwrap().len() - 2).unwrap();
+
+        // The replay ends with the last whole frame
+        let (mut reader, _, _) = ReplayReader::open(&path).unwrap();
+        let mut frames = 0;
+        while reader.next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!(frames, 4);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn not_a_replay() {
+        let path = replay_path("not-a-replay");
+        record(&path);
+        let mut replay = fs::read(&path).unwrap();
+
+        replay[8..12].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+        fs::write(&path, &replay).unwrap();
+        assert!(matches!(
+            ReplayReader::open(&path),
+            Err(ReplayError::UnsupportedVersion(version)) if version == REPLAY_VERSION + 1
+        ));
+
+        replay[0] = b'X';
+        fs::write(&path, &replay).unwrap();
+        assert!(matches!(
+            ReplayReader::open(&path),
+            Err(ReplayError::NotAReplay)
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,400 @@
+use super::{ReplayError, ReplayFrame, ReplayReader, ReplayStream};
+use crate::addr::ConnectionArgs;
+use common::uid::Uid;
+use common_net::{
+    msg::{
+        ClientGeneral, ClientRegister, ClientType, EcsCompPacket, PingMsg, ResumeToken,
+        ServerGeneral, ServerInfo, ServerInit, ServerRegisterAnswer,
+    },
+    sync::{CompUpdateKind, EntityPackage},
+};
+use hashbrown::{HashMap, HashSet};
+use network::{ListenAddr, Network, Participant, Pid, Promises, Stream};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::runtime::Runtime;
+use tracing::{debug, info, warn};
+use vek::*;
+
+/// Mpsc address the replay is served on, apart from the one of the
+/// singleplayer server
+const REPLAY_MPSC_ADDR: u64 = 14005;
+/// How often messages of the client are answered and due frames are sent
+const TICK: Duration = Duration::from_millis(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_REPLAY_SPEED: f64 = 16.0;
+
+struct Playback {
+    paused: bool,
+    speed: f64,
+    /// Seconds since the start of the replay
+    position: f64,
+    duration: f64,
+    seek: Option<f64>,
+    stop: bool,
+}
+
+/// Plays back a replay by acting as the server for a client, which connects
+/// to it with [`ReplayServer::connection_args`] and then has to spectate.
+/// Messages of the client are answered as far as needed for that, the
+/// character who recorded the replay is just another entity to the client.
+pub struct ReplayServer {
+    playback: Arc<Mutex<Playback>>,
+}
+
+impl ReplayServer {
+    pub fn start(path: &Path, runtime: &Arc<Runtime>) -> Result<Self, ReplayError> {
+        let (mut reader, header, init) = ReplayReader::open(path)?;
+        let ServerInit::GameSync {
+            entity_package: player,
+            time_of_day,
+            max_group_size,
+            client_timeout,
+            world_map,
+            recipe_book,
+            component_recipe_book,
+            material_stats,
+            ability_map,
+            resume_token: _,
+        } = init
+        else {
+            return Err(ReplayError::NotAReplay);
+        };
+        // The client spectates as an entity of its own
+        let init = ServerInit::GameSync {
+            entity_package: EntityPackage {
+                uid: u64::MAX,
+                comps: Vec::new(),
+            },
+            time_of_day,
+            max_group_size,
+            client_timeout,
+            world_map,
+            recipe_book,
+            component_recipe_book,
+            material_stats,
+            ability_map,
+            resume_token: ResumeToken(0),
+        };
+        let (duration, start) = scan(&mut reader, &player)?;
+        info!(?path, ?duration, "Playing back a replay");
+
+        let network = Network::new(Pid::new(), runtime);
+        runtime
+            .block_on(network.listen(ListenAddr::Mpsc(REPLAY_MPSC_ADDR)))
+            .map_err(ReplayError::Network)?;
+
+        let playback = Arc::new(Mutex::new(Playback {
+            paused: false,
+            speed: 1.0,
+            position: 0.0,
+            duration,
+            seek: None,
+            stop: false,
+        }));
+        let server = Server {
+            reader,
+            player,
+            start,
+            uids: HashSet::new(),
+            chunks: HashMap::new(),
+            lod_zones: HashMap::new(),
+        };
+        let server_info = ServerInfo {
+            auth_provider: None,
+            ..header.server_info
+        };
+        let runtime = Arc::clone(runtime);
+        let playback2 = Arc::clone(&playback);
+        thread::Builder::new()
+            .name("replay".to_owned())
+            .spawn(move || {
+                if let Err(e) = server.serve(network, &runtime, server_info, init, &playback2) {
+                    warn!(?e, "Stopped playing back the replay");
+                }
+            })?;
+        Ok(Self { playback })
+    }
+
+    pub fn connection_args(&self) -> ConnectionArgs { ConnectionArgs::Mpsc(REPLAY_MPSC_ADDR) }
+
+    pub fn paused(&self) -> bool { self.playback.lock().unwrap().paused }
+
+    pub fn set_paused(&self, paused: bool) { self.playback.lock().unwrap().paused = paused; }
+
+    pub fn speed(&self) -> f64 { self.playback.lock().unwrap().speed }
+
+    /// Clamped to [`MAX_REPLAY_SPEED`]
+    pub fn set_speed(&self, speed: f64) {
+        self.playback.lock().unwrap().speed = speed.clamp(0.0, MAX_REPLAY_SPEED);
+    }
+
+    /// Jump to `position` seconds from the start. The client sees the entities
+    /// it knows about deleted and created again when seeking backwards.
+    pub fn seek(&self, position: f64) {
+        let mut playback = self.playback.lock().unwrap();
+        playback.seek = Some(position.clamp(0.0, playback.duration));
+    }
+
+    /// Seconds since the start and the length of the replay
+    pub fn progress(&self) -> (f64, f64) {
+        let playback = self.playback.lock().unwrap();
+        (playback.position, playback.duration)
+    }
+}
+
+impl Drop for ReplayServer {
+    fn drop(&mut self) { self.playback.lock().unwrap().stop = true; }
+}
+
+/// Returns the length of the replay and the first position of the player
+fn scan(
+    reader: &mut ReplayReader,
+    player: &EntityPackage<EcsCompPacket>,
+) -> Result<(f64, Vec3<f32>), ReplayError> {
+    let mut start = player.comps.iter().find_map(|comp| match comp {
+        EcsCompPacket::Pos(pos) => Some(pos.0),
+        _ => None,
+    });
+    let mut duration = 0.0;
+    while let Some(frame) = reader.next_frame()? {
+        duration = frame.time;
+        if let (None, ServerGeneral::CompSync(package, _)) = (start, &frame.msg) {
+            start = package
+                .comp_updates
+                .iter()
+                .find_map(|(uid, update)| match update {
+                    CompUpdateKind::Inserted(EcsCompPacket::Pos(pos))
+                    | CompUpdateKind::Modified(EcsCompPacket::Pos(pos))
+                        if *uid == player.uid =>
+                    {
+                        Some(pos.0)
+                    },
+                    _ => None,
+                });
+        }
+    }
+    reader.rewind()?;
+    Ok((duration, start.unwrap_or_default()))
+}
+
+/// Whether a recorded message is sent to the client. Messages which are only
+/// meant for the player who recorded it are left out, the client would apply
+/// them to itself.
+fn replayed(msg: &ServerGeneral) -> bool {
+    !matches!(
+        msg,
+        ServerGeneral::SetPlayerEntity(_)
+            | ServerGeneral::Knockback(_)
+            | ServerGeneral::ExitInGameSuccess
+            | ServerGeneral::InventoryUpdate(_, _)
+            | ServerGeneral::CompSyncDelta(_, _)
+            | ServerGeneral::SetViewDistance(_)
+            | ServerGeneral::Disconnect(_)
+            | ServerGeneral::GroupUpdate(_)
+            | ServerGeneral::Invite { .. }
+            | ServerGeneral::InvitePending(_)
+            | ServerGeneral::InviteComplete { .. }
+            | ServerGeneral::GroupInventoryUpdate(_, _, _)
+            | ServerGeneral::UpdatePendingTrade(_, _, _)
+            | ServerGeneral::FinishedTrade(_)
+            | ServerGeneral::SpectatePosition(_)
+            | ServerGeneral::Spectating(_)
+            | ServerGeneral::SpectatedLookDir(_)
+    )
+}
+
+struct Streams {
+    general: Stream,
+    ping: Stream,
+    register: Stream,
+    character_screen: Stream,
+    in_game: Stream,
+    terrain: Stream,
+}
+
+impl Streams {
+    /// Opened like the server does
+    async fn open(participant: &Participant) -> Result<Self, network::ParticipantError> {
+        let reliable = Promises::ORDERED | Promises::CONSISTENCY;
+        let reliablec = reliable | Promises::COMPRESSED;
+        Ok(Self {
+            general: participant.open(3, reliablec, 500).await?,
+            ping: participant.open(2, reliable, 500).await?,
+            register: participant.open(3, reliablec, 500).await?,
+            character_screen: participant.open(3, reliablec, 500).await?,
+            in_game: participant.open(3, reliablec, 100_000).await?,
+            terrain: participant.open(4, reliable, 20_000).await?,
+        })
+    }
+}
+
+struct Server {
+    reader: ReplayReader,
+    /// The entity of the player who recorded the replay
+    player: EntityPackage<EcsCompPacket>,
+    /// Where the client starts spectating
+    start: Vec3<f32>,
+    /// The entities the client was told about, deleted when seeking backwards
+    uids: HashSet<u64>,
+    /// The terrain sent so far, to answer the requests of the client
+    chunks: HashMap<Vec2<i32>, ServerGeneral>,
+    lod_zones: HashMap<Vec2<i32>, ServerGeneral>,
+}
+
+impl Server {
+    fn serve(
+        mut self,
+        mut network: Network,
+        runtime: &Runtime,
+        server_info: ServerInfo,
+        init: ServerInit,
+        playback: &Mutex<Playback>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let participant =
+            runtime.block_on(tokio::time::timeout(CONNECT_TIMEOUT, network.connected()))??;
+        let mut streams = runtime.block_on(Streams::open(&participant))?;
+
+        streams.register.send(server_info)?;
+        let _: ClientType = runtime.block_on(streams.register.recv())?;
+        let _: ClientRegister = runtime.block_on(streams.register.recv())?;
+        streams.register.send(ServerRegisterAnswer::Ok(()))?;
+        streams.register.send(init)?;
+        streams
+            .general
+            .send(ServerGeneral::CreateEntity(self.player.clone()))?;
+        debug!("Replay client connected");
+
+        let mut next = self.reader.next_frame()?;
+        let mut last_tick = Instant::now();
+        loop {
+            thread::sleep(TICK);
+            if !self.answer(&mut streams)? {
+                return Ok(());
+            }
+
+            let mut state = playback.lock().unwrap();
+            if state.stop {
+                return Ok(());
+            }
+            let dt = last_tick.elapsed().as_secs_f64();
+            last_tick = Instant::now();
+            if let Some(position) = state.seek.take() {
+                if position < state.position {
+                    self.restart(&streams)?;
+                    next = self.reader.next_frame()?;
+                }
+                state.position = position;
+            } else if !state.paused {
+                state.position = (state.position + dt * state.speed).min(state.duration);
+            }
+            let position = state.position;
+            drop(state);
+
+            while let Some(frame) = next.take() {
+                if frame.time > position {
+                    next = Some(frame);
+                    break;
+                }
+                self.send(&streams, frame)?;
+                next = self.reader.next_frame()?;
+            }
+        }
+    }
+
+    /// Answer the messages of the client, returns whether it is still
+    /// connected
+    fn answer(&self, streams: &mut Streams) -> Result<bool, Box<dyn std::error::Error>> {
+        while let Some(msg) = streams.ping.try_recv()? {
+            if let PingMsg::Ping = msg {
+                streams.ping.send(PingMsg::Pong)?;
+            }
+        }
+        while let Some(msg) = streams.character_screen.try_recv()? {
+            match msg {
+                ClientGeneral::Spectate(_) => streams
+                    .character_screen
+                    .send(ServerGeneral::SpectatorSuccess(self.start))?,
+                ClientGeneral::RequestCharacterList => streams
+                    .character_screen
+                    .send(ServerGeneral::CharacterListUpdate(Vec::new()))?,
+                _ => {},
+            }
+        }
+        while let Some(msg) = streams.in_game.try_recv()? {
+            if let ClientGeneral::ExitInGame = msg {
+                streams.in_game.send(ServerGeneral::ExitInGameSuccess)?;
+            }
+        }
+        while let Some(msg) = streams.terrain.try_recv()? {
+            // Terrain which wasn't recorded yet is never sent
+            let update = match msg {
+                ClientGeneral::TerrainChunkRequest { key } => self.chunks.get(&key),
+                ClientGeneral::LodZoneRequest { key } => self.lod_zones.get(&key),
+                _ => None,
+            };
+            if let Some(update) = update {
+                streams.terrain.send(update)?;
+            }
+        }
+        while let Some(msg) = streams.general.try_recv()? {
+            if let ClientGeneral::Terminate = msg {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn send(&mut self, streams: &Streams, frame: ReplayFrame) -> Result<(), network::StreamError> {
+        let ReplayFrame { stream, msg, .. } = frame;
+        if !replayed(&msg) {
+            return Ok(());
+        }
+        match &msg {
+            ServerGeneral::CreateEntity(package) => {
+                self.uids.insert(package.uid);
+            },
+            ServerGeneral::DeleteEntity(uid) => {
+                self.uids.remove(&uid.0);
+            },
+            ServerGeneral::EntitySync(package) => {
+                self.uids.extend(&package.created_entities);
+                for uid in &package.deleted_entities {
+                    self.uids.remove(uid);
+                }
+            },
+            ServerGeneral::TerrainChunkUpdate { key, .. } => {
+                self.chunks.insert(*key, msg.clone());
+            },
+            ServerGeneral::LodZoneUpdate { key, .. } => {
+                self.lod_zones.insert(*key, msg.clone());
+            },
+            _ => {},
+        }
+        match stream {
+            ReplayStream::General => streams.general.send(msg),
+            ReplayStream::InGame => streams.in_game.send(msg),
+            ReplayStream::Terrain => streams.terrain.send(msg),
+            // The client is spectating, it doesn't expect any of these
+            ReplayStream::CharacterScreen => Ok(()),
+        }
+    }
+
+    /// Go back to the start of the replay, with the entities as they were then
+    fn restart(&mut self, streams: &Streams) -> Result<(), Box<dyn std::error::Error>> {
+        for uid in self.uids.drain().chain([self.player.uid]) {
+            streams
+                .general
+                .send(ServerGeneral::DeleteEntity(Uid(uid)))?;
+        }
+        streams
+            .general
+            .send(ServerGeneral::CreateEntity(self.player.clone()))?;
+        self.reader.rewind()?;
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
-use super::{world_msg::SiteId, PingMsg};
-use common::{character::CharacterId, comp, comp::Skill, terrain::block::Block, ViewDistances};
+use super::{server::ResumeToken, world_msg::SiteId, PingMsg};
+use crate::sync::DeltaAck;
+use common::{
+    character::CharacterId, comp, comp::Skill, terrain::block::Block, uid::Uid, ViewDistances,
+};
 use serde::{Deserialize, Serialize};
 use vek::*;
 
@@ -36,6 +39,9 @@ pub enum ClientType {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ClientRegister {
     pub token_or_username: String,
+    /// Resume a session instead of logging in, `token_or_username` is ignored
+    /// then
+    pub resume_token: Option<ResumeToken>,
 }
 
 /// Messages sent from the client to the server
@@ -76,6 +82,8 @@ pub enum ClientGeneral {
     UpdateMapMarker(comp::MapMarkerChange),
 
     SpectatePosition(Vec3<f32>),
+    /// Follow another entity as spectator, `None` to stop following it
+    SpectateEntity(Option<Uid>),
     //Only in Game, via terrain stream
     TerrainChunkRequest {
         key: Vec2<i32>,
@@ -93,6 +101,15 @@ pub enum ClientGeneral {
     RequestLossyTerrainCompression {
         lossy_terrain_compression: bool,
     },
+    //Only in game
+    /// Answer to a [`ServerGeneral::InventoryUpdate`]
+    ///
+    /// [`ServerGeneral::InventoryUpdate`]: super::ServerGeneral::InventoryUpdate
+    InventoryAck(DeltaAck),
+    /// Answer to a [`ServerGeneral::CompSyncDelta`]
+    ///
+    /// [`ServerGeneral::CompSyncDelta`]: super::ServerGeneral::CompSyncDelta
+    CompSyncAck(DeltaAck),
 }
 
 impl ClientMsg {
@@ -133,7 +150,10 @@ impl ClientMsg {
                         | ClientGeneral::RequestPlayerPhysics { .. }
                         | ClientGeneral::RequestLossyTerrainCompression { .. }
                         | ClientGeneral::UpdateMapMarker(_)
-                        | ClientGeneral::SpectatePosition(_) => {
+                        | ClientGeneral::SpectatePosition(_)
+                        | ClientGeneral::SpectateEntity(_)
+                        | ClientGeneral::InventoryAck(_)
+                        | ClientGeneral::CompSyncAck(_) => {
                             c_type == ClientType::Game && presence.is_some()
                         },
                         //Always possible
@@ -1,6 +1,7 @@
 pub mod client;
 pub mod compression;
 pub mod ecs_packet;
+pub mod query;
 pub mod server;
 pub mod world_msg;
 
@@ -12,10 +13,11 @@ pub use self::{
         VoxelImageEncoding, WidePacking, WireChonk,
     },
     ecs_packet::EcsCompPacket,
+    query::{QueryRequest, QueryResponse, ServerStatus},
     server::{
         CharacterInfo, ChatTypeContext, DisconnectReason, InviteAnswer, Notification, PlayerInfo,
-        PlayerListUpdate, RegisterError, SerializedTerrainChunk, ServerGeneral, ServerInfo,
-        ServerInit, ServerMsg, ServerRegisterAnswer,
+        PlayerListUpdate, RegisterError, ResumeToken, SerializedTerrainChunk, ServerGeneral,
+        ServerInfo, ServerInit, ServerMsg, ServerRegisterAnswer,
     },
     world_msg::WorldMapMsg,
 };
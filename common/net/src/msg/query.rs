@@ -0,0 +1,190 @@
+//! Status query of servers, so server lists can show them without connecting.
+//! It runs over UDP, unauthenticated and apart from the game connection: each
+//! message is one datagram of [`QUERY_MAGIC`] followed by the bincode encoded
+//! message.
+//!
+//! To keep the query from being used to flood spoofed addresses with status
+//! answers, the client first has to get a token for its address, and the
+//! answer to that is no larger than the request.
+use common::resources::BattleMode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Port the status queries are answered on by default, next to the game and
+/// metrics ports
+pub const DEFAULT_QUERY_PORT: u16 = 14006;
+/// Prefix of every query datagram
+pub const QUERY_MAGIC: [u8; 4] = *b"VQRY";
+/// Datagrams are padded to this size, so token answers are never larger than
+/// their request
+pub const MIN_QUERY_SIZE: usize = 32;
+/// Largest datagram sent in either direction
+pub const MAX_QUERY_SIZE: usize = 2048;
+/// Server names are cut to this many bytes to fit into [`MAX_QUERY_SIZE`]
+pub const MAX_QUERY_NAME_LEN: usize = 128;
+/// Descriptions are cut to this many bytes to fit into [`MAX_QUERY_SIZE`]
+pub const MAX_QUERY_DESCRIPTION_LEN: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QueryRequest {
+    /// Ask for a token to send with [`QueryRequest::Status`]
+    Token,
+    Status {
+        token: u64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum QueryResponse {
+    /// The token for the address of the client, it expires after a while
+    Token {
+        token: u64,
+    },
+    Status(ServerStatus),
+}
+
+/// What the server list shows about a server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerStatus {
+    pub name: String,
+    /// The server description, shown as message of the day
+    pub description: String,
+    pub players: u32,
+    pub max_players: u32,
+    pub git_hash: String,
+    pub git_date: String,
+    /// The battle mode of the server, or the default one if players can
+    /// choose
+    pub battle_mode: BattleMode,
+    pub battle_mode_choosable: bool,
+}
+
+impl ServerStatus {
+    /// Cut the name to [`MAX_QUERY_NAME_LEN`] and the description to
+    /// [`MAX_QUERY_DESCRIPTION_LEN`], on char boundaries
+    pub fn truncate(&mut self) {
+        truncate_on_char_boundary(&mut self.name, MAX_QUERY_NAME_LEN);
+        truncate_on_char_boundary(&mut self.description, MAX_QUERY_DESCRIPTION_LEN);
+    }
+}
+
+fn truncate_on_char_boundary(s: &mut String, max_len: usize) {
+    if s.len() > max_len {
+        let end = (0..=max_len)
+            .rev()
+            .find(|i| s.is_char_boundary(*i))
+            .unwrap_or(0);
+        s.truncate(end);
+    }
+}
+
+pub fn encode_query<T: Serialize>(msg: &T) -> Vec<u8> {
+    let mut datagram = QUERY_MAGIC.to_vec();
+    bincode::serialize_into(&mut datagram, msg)
+        .expect("bincode serialization can only fail if a byte limit is set");
+    if datagram.len() < MIN_QUERY_SIZE {
+        datagram.resize(MIN_QUERY_SIZE, 0);
+    }
+    datagram
+}
+
+/// Returns `None` for datagrams which aren't a query message of type `T`
+pub fn decode_query<T: DeserializeOwned>(datagram: &[u8]) -> Option<T> {
+    if datagram.len() < MIN_QUERY_SIZE || datagram.len() > MAX_QUERY_SIZE {
+        return None;
+    }
+    let msg = datagram.strip_prefix(&QUERY_MAGIC)?;
+    // The padding is left as trailing bytes
+    bincode::deserialize(msg).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status() -> ServerStatus {
+        ServerStatus {
+            name: "Server".into(),
+            description: "Description".into(),
+            players: 3,
+            max_players: 100,
+            git_hash: "0123abcd".into(),
+            git_date: "2022-11-28".into(),
+            battle_mode: BattleMode::PvE,
+            battle_mode_choosable: true,
+        }
+    }
+
+    #[test]
+    fn encode_and_decode() {
+        let datagram = encode_query(&QueryRequest::Token);
+        assert_eq!(datagram.len(), MIN_QUERY_SIZE);
+        assert!(datagram.starts_with(&QUERY_MAGIC));
+        assert_eq!(decode_query(&datagram), Some(QueryRequest::Token));
+
+        let request = QueryRequest::Status { token: 42 };
+        assert_eq!(decode_query(&encode_query(&request)), Some(request));
+
+        let datagram = encode_query(&QueryResponse::Status(status()));
+        assert!(datagram.len() > MIN_QUERY_SIZE);
+        match decode_query(&datagram) {
+            Some(QueryResponse::Status(decoded)) => {
+                assert_eq!(decoded.name, "Server");
+                assert_eq!(decoded.description, "Description");
+                assert_eq!(decoded.players, 3);
+                assert_eq!(decoded.battle_mode, BattleMode::PvE);
+            },
+            decoded => panic!("Unexpected decoded response {:?}", decoded),
+        }
+    }
+
+    #[test]
+    fn token_answer_not_larger_than_request() {
+        let request = encode_query(&QueryRequest::Token);
+        let response = encode_query(&QueryResponse::Token { token: u64::MAX });
+        assert!(response.len() <= request.len());
+    }
+
+    #[test]
+    fn decode_rejects_invalid_datagrams() {
+        let datagram = encode_query(&QueryRequest::Status { token: 42 });
+        // Too short
+        assert_eq!(
+            decode_query::<QueryRequest>(&datagram[..MIN_QUERY_SIZE - 1]),
+            None
+        );
+        // Too long
+        let mut long = datagram.clone();
+        long.resize(MAX_QUERY_SIZE + 1, 0);
+        assert_eq!(decode_query::<QueryRequest>(&long), None);
+        // Wrong magic
+        let mut wrong_magic = datagram;
+        wrong_magic[0] = b'X';
+        assert_eq!(decode_query::<QueryRequest>(&wrong_magic), None);
+        // Not a message at all
+        let mut garbage = QUERY_MAGIC.to_vec();
+        garbage.resize(MIN_QUERY_SIZE, 0xff);
+        assert_eq!(decode_query::<QueryRequest>(&garbage), None);
+    }
+
+    #[test]
+    fn truncate() {
+        let mut status = status();
+        status.truncate();
+        assert_eq!(status.name, "Server");
+        assert_eq!(status.description, "Description");
+
+        // 'ä' takes two bytes, so the limits fall into the middle of one
+        status.name = "ä".repeat(MAX_QUERY_NAME_LEN);
+        status.description = format!("a{}", "ä".repeat(MAX_QUERY_DESCRIPTION_LEN));
+        status.truncate();
+        assert_eq!(status.name, "ä".repeat(MAX_QUERY_NAME_LEN / 2));
+        assert_eq!(
+            status.description,
+            format!("a{}", "ä".repeat((MAX_QUERY_DESCRIPTION_LEN - 1) / 2))
+        );
+
+        // The longest status still fits into a datagram
+        status.git_hash = "0".repeat(40);
+        assert!(encode_query(&QueryResponse::Status(status)).len() <= MAX_QUERY_SIZE);
+    }
+}
@@ -6,6 +6,7 @@ use crate::sync;
 use common::{
     calendar::Calendar,
     character::{self, CharacterItem},
+    cmd::PluginChatCommand,
     comp::{self, invite::InviteKind, item::MaterialStatManifest},
     event::UpdateCharacterMetadata,
     lod,
@@ -15,6 +16,7 @@ use common::{
     terrain::{Block, TerrainChunk, TerrainChunkMeta, TerrainChunkSize},
     trade::{PendingTrade, SitePrices, TradeId, TradeResult},
     uid::Uid,
+    util::Dir,
     uuid::Uuid,
     weather::WeatherGrid,
 };
@@ -66,9 +68,23 @@ pub enum ServerInit {
         component_recipe_book: ComponentRecipeBook,
         material_stats: MaterialStatManifest,
         ability_map: comp::item::tool::AbilityMap,
+        /// Lets the client resume this session if its connection is lost
+        resume_token: ResumeToken,
+    },
+    /// Answer to a `ClientRegister` with a resume token, the client is back
+    /// in control of the entity it left behind
+    Resume {
+        entity_package: sync::EntityPackage<EcsCompPacket>,
+        time_of_day: TimeOfDay,
     },
 }
 
+/// Secret handed out to a client on registration. When its connection is
+/// lost, the server keeps the client's entity around for the client timeout,
+/// and a client reconnecting with the token takes it over again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ResumeToken(pub u128);
+
 pub type ServerRegisterAnswer = Result<(), RegisterError>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -167,7 +183,11 @@ pub enum ServerGeneral {
     /// Trigger cleanup for when the client goes back to the `Registered` state
     /// from an ingame state
     ExitInGameSuccess,
-    InventoryUpdate(comp::Inventory, comp::InventoryUpdateEvent),
+    /// The inventory of the client's entity, acknowledged with
+    /// [`ClientGeneral::InventoryAck`]
+    ///
+    /// [`ClientGeneral::InventoryAck`]: super::ClientGeneral::InventoryAck
+    InventoryUpdate(sync::CompDelta<comp::Inventory>, comp::InventoryUpdateEvent),
     /// NOTE: The client can infer that entity view distance will be at most the
     /// terrain view distance that we send here (and if lower it won't be
     /// modified). So we just need to send the terrain VD back to the client
@@ -200,6 +220,9 @@ pub enum ServerGeneral {
     Disconnect(DisconnectReason),
     /// Send a popup notification such as "Waypoint Saved"
     Notification(Notification),
+    /// The chat commands registered by the plugins of the server, sent on
+    /// login and whenever they change
+    PluginCommands(Vec<PluginChatCommand>),
     UpdatePendingTrade(TradeId, PendingTrade, Option<SitePrices>),
     FinishedTrade(TradeResult),
     /// Economic information about sites
@@ -209,6 +232,15 @@ pub enum ServerGeneral {
     /// Suggest the client to spectate a position. Called after client has
     /// requested teleport etc.
     SpectatePosition(Vec3<f32>),
+    /// The entity the spectator follows, `None` if it stopped following one
+    Spectating(Option<Uid>),
+    /// Where the entity the spectator follows is looking
+    SpectatedLookDir(Dir),
+    /// The components which are only synced for the client's own entity, like
+    /// `CompSync` but acknowledged with [`ClientGeneral::CompSyncAck`]
+    ///
+    /// [`ClientGeneral::CompSyncAck`]: super::ClientGeneral::CompSyncAck
+    CompSyncDelta(sync::CompDelta<sync::CompSyncPackage<EcsCompPacket>>, u64),
 }
 
 impl ServerGeneral {
@@ -285,6 +317,8 @@ pub enum RegisterError {
     InvalidCharacter,
     NotOnWhitelist,
     TooManyPlayers,
+    /// The session to resume doesn't exist (anymore)
+    SessionExpired,
     //TODO: InvalidAlias,
 }
 
@@ -332,7 +366,9 @@ impl ServerMsg {
                         | ServerGeneral::SiteEconomy(_)
                         | ServerGeneral::MapMarker(_)
                         | ServerGeneral::WeatherUpdate(_)
-                        | ServerGeneral::SpectatePosition(_) => {
+                        | ServerGeneral::SpectatePosition(_)
+                        | ServerGeneral::Spectating(_)
+                        | ServerGeneral::SpectatedLookDir(_) => {
                             c_type == ClientType::Game && presence.is_some()
                         },
                         // Always possible
@@ -343,10 +379,12 @@ impl ServerMsg {
                         | ServerGeneral::TimeOfDay(_, _)
                         | ServerGeneral::EntitySync(_)
                         | ServerGeneral::CompSync(_, _)
+                        | ServerGeneral::CompSyncDelta(_, _)
                         | ServerGeneral::CreateEntity(_)
                         | ServerGeneral::DeleteEntity(_)
                         | ServerGeneral::Disconnect(_)
-                        | ServerGeneral::Notification(_) => true,
+                        | ServerGeneral::Notification(_)
+                        | ServerGeneral::PluginCommands(_) => true,
                     }
             },
             ServerMsg::Ping(_) => true,
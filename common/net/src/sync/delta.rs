@@ -0,0 +1,338 @@
+//! Delta encoding for components which are large but change little at a time,
+//! like the inventory. Instead of the whole component, the server sends the
+//! changes to a state the client acknowledged receiving (the baseline). If
+//! the client can't decode a packet, e.g. because it doesn't have the baseline
+//! of a delta, it requests a full resync.
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::VecDeque, marker::PhantomData};
+use tracing::warn;
+
+/// How many states are kept around while waiting for their acknowledgement
+const MAX_PENDING_STATES: usize = 32;
+
+/// Changes between two serialized states: everything between their common
+/// prefix and suffix is replaced
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BytesDelta {
+    prefix: u32,
+    suffix: u32,
+    middle: Vec<u8>,
+}
+
+impl BytesDelta {
+    pub fn new(old: &[u8], new: &[u8]) -> Self {
+        let prefix = old
+            .iter()
+            .zip(new)
+            .take_while(|(old, new)| old == new)
+            .count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+        Self {
+            prefix: prefix as u32,
+            suffix: suffix as u32,
+            middle: new[prefix..new.len() - suffix].to_vec(),
+        }
+    }
+
+    /// Returns `None` if `old` isn't the state the delta was created from
+    pub fn apply(&self, old: &[u8]) -> Option<Vec<u8>> {
+        let prefix = self.prefix as usize;
+        let suffix = self.suffix as usize;
+        if prefix + suffix > old.len() {
+            return None;
+        }
+        let mut new = Vec::with_capacity(prefix + self.middle.len() + suffix);
+        new.extend_from_slice(&old[..prefix]);
+        new.extend_from_slice(&self.middle);
+        new.extend_from_slice(&old[old.len() - suffix..]);
+        Some(new)
+    }
+
+    /// Approximate size on the wire
+    pub fn size(&self) -> usize { self.middle.len() + 2 * std::mem::size_of::<u32>() }
+}
+
+/// A synced component, either whole or as delta to an earlier state
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum CompDelta<C> {
+    Full {
+        seq: u64,
+        data: Vec<u8>,
+        #[serde(skip)]
+        _phantom: PhantomData<fn() -> C>,
+    },
+    Delta {
+        seq: u64,
+        base: u64,
+        delta: BytesDelta,
+    },
+}
+
+impl<C> CompDelta<C> {
+    pub fn seq(&self) -> u64 {
+        match self {
+            Self::Full { seq, .. } | Self::Delta { seq, .. } => *seq,
+        }
+    }
+
+    /// Approximate size on the wire
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Full { data, .. } => data.len(),
+            Self::Delta { delta, .. } => delta.size(),
+        }
+    }
+
+    pub fn is_full(&self) -> bool { matches!(self, Self::Full { .. }) }
+}
+
+/// Answer of the client to a [`CompDelta`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeltaAck {
+    /// The state `seq` can be used as baseline
+    Received(u64),
+    /// The packet couldn't be decoded, the whole component is needed again
+    Resync,
+}
+
+/// Server side of the delta sync of a component to one client
+pub struct DeltaEncoder<C> {
+    next_seq: u64,
+    /// Sent states which haven't been acknowledged yet, oldest first
+    pending: VecDeque<(u64, Vec<u8>)>,
+    baseline: Option<(u64, Vec<u8>)>,
+    resync: bool,
+    _phantom: PhantomData<fn(&C)>,
+}
+
+impl<C> Default for DeltaEncoder<C> {
+    fn default() -> Self {
+        Self {
+            next_seq: 0,
+            pending: VecDeque::new(),
+            baseline: None,
+            resync: false,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: Serialize> DeltaEncoder<C> {
+    /// Encode the current state of the component. Returns the packet and the
+    /// size of the whole component, to tell how much the delta saved.
+    pub fn encode(&mut self, comp: &C) -> (CompDelta<C>, usize) {
+        let data = bincode::serialize(comp)
+            .expect("bincode serialization can only fail if a byte limit is set");
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.resync = false;
+
+        let delta = self
+            .baseline
+            .as_ref()
+            .map(|(base, old)| (*base, BytesDelta::new(old, &data)))
+            .filter(|(_, delta)| delta.size() < data.len());
+        let packet = match delta {
+            Some((base, delta)) => CompDelta::Delta { seq, base, delta },
+            None => CompDelta::Full {
+                seq,
+                data: data.clone(),
+                _phantom: PhantomData,
+            },
+        };
+
+        let full_size = data.len();
+        self.pending.push_back((seq, data));
+        if self.pending.len() > MAX_PENDING_STATES {
+            self.pending.pop_front();
+        }
+        (packet, full_size)
+    }
+
+    pub fn ack(&mut self, ack: DeltaAck) {
+        match ack {
+            DeltaAck::Received(seq) => {
+                while let Some((pending_seq, data)) = self.pending.pop_front() {
+                    if pending_seq == seq {
+                        self.baseline = Some((seq, data));
+                        break;
+                    }
+                }
+            },
+            DeltaAck::Resync => {
+                self.baseline = None;
+                self.resync = true;
+            },
+        }
+    }
+
+    /// Whether the client lost track of the component and needs it again,
+    /// even if it didn't change
+    pub fn needs_resync(&self) -> bool { self.resync }
+}
+
+/// Client side of the delta sync of a component
+pub struct DeltaDecoder<C> {
+    /// Received states, oldest first
+    states: VecDeque<(u64, Vec<u8>)>,
+    _phantom: PhantomData<fn() -> C>,
+}
+
+impl<C> Default for DeltaDecoder<C> {
+    fn default() -> Self {
+        Self {
+            states: VecDeque::new(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<C: DeserializeOwned> DeltaDecoder<C> {
+    /// Returns the new state of the component and the answer for the server.
+    /// The state is `None` if the packet couldn't be decoded, the answer
+    /// requests a full resync then.
+    pub fn decode(&mut self, packet: CompDelta<C>) -> (Option<C>, DeltaAck) {
+        let (seq, data) = match packet {
+            CompDelta::Full { seq, data, .. } => (seq, data),
+            CompDelta::Delta { seq, base, delta } => {
+                let data = self
+                    .states
+                    .iter()
+                    .find(|(state_seq, _)| *state_seq == base)
+                    .and_then(|(_, old)| delta.apply(old));
+                // The server only uses acknowledged states as baseline, so
+                // older ones aren't needed anymore
+                self.states.retain(|(state_seq, _)| *state_seq >= base);
+                match data {
+                    Some(data) => (seq, data),
+                    None => return (None, DeltaAck::Resync),
+                }
+            },
+        };
+        match bincode::deserialize(&data) {
+            Ok(comp) => {
+                self.states.push_back((seq, data));
+                if self.states.len() > MAX_PENDING_STATES {
+                    self.states.pop_front();
+                }
+                (Some(comp), DeltaAck::Received(seq))
+            },
+            Err(e) => {
+                warn!(?e, "Failed to deserialize delta synced component");
+                (None, DeltaAck::Resync)
+            },
+        }
+    }
+
+    /// Forget all states, when the server starts over with a new connection
+    pub fn reset(&mut self) { self.states.clear(); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_delta() {
+        let cases: [(&[u8], &[u8]); 7] = [
+            (b"", b""),
+            (b"", b"abc"),
+            (b"abc", b""),
+            (b"abcdef", b"abcdef"),
+            (b"abcdef", b"abXYef"),
+            (b"abcdef", b"abcXYZdef"),
+            (b"aaaa", b"aa"),
+        ];
+        for (old, new) in cases {
+            let delta = BytesDelta::new(old, new);
+            assert_eq!(delta.apply(old).as_deref(), Some(new));
+        }
+
+        let delta = BytesDelta::new(b"abcdef", b"abXYef");
+        assert_eq!(delta.middle, b"XY");
+        assert!(delta.size() < 6 + 2 * std::mem::size_of::<u32>());
+        // The prefix and suffix don't fit into a shorter state
+        assert_eq!(delta.apply(b"abc"), None);
+    }
+
+    #[test]
+    fn encode_and_decode() {
+        let mut encoder = DeltaEncoder::<Vec<u32>>::default();
+        let mut decoder = DeltaDecoder::<Vec<u32>>::default();
+        let mut comp = (0..100).collect::<Vec<u32>>();
+
+        // Without a baseline the whole component is sent
+        let (packet, full_size) = encoder.encode(&comp);
+        assert!(packet.is_full());
+        assert_eq!(packet.size(), full_size);
+        let (decoded, ack) = decoder.decode(packet);
+        assert_eq!(decoded.as_ref(), Some(&comp));
+        assert_eq!(ack, DeltaAck::Received(0));
+        encoder.ack(ack);
+
+        comp[50] = 1000;
+        let (packet, full_size) = encoder.encode(&comp);
+        assert!(!packet.is_full());
+        assert!(packet.size() < full_size);
+        assert_eq!(packet.seq(), 1);
+
+        // Deltas stay relative to the acknowledged baseline until the next ack
+        comp[51] = 1000;
+        let (next, _) = encoder.encode(&comp);
+        assert!(matches!(next, CompDelta::Delta { base: 0, .. }));
+
+        let (decoded, ack) = decoder.decode(packet);
+        assert_eq!(decoded.map(|c| c[50]), Some(1000));
+        assert_eq!(ack, DeltaAck::Received(1));
+        let (decoded, ack) = decoder.decode(next);
+        assert_eq!(decoded.as_ref(), Some(&comp));
+        assert_eq!(ack, DeltaAck::Received(2));
+        encoder.ack(DeltaAck::Received(1));
+        encoder.ack(DeltaAck::Received(2));
+
+        comp.push(5);
+        let (packet, _) = encoder.encode(&comp);
+        assert!(matches!(packet, CompDelta::Delta { base: 2, .. }));
+        assert_eq!(decoder.decode(packet).0, Some(comp));
+    }
+
+    #[test]
+    fn resync() {
+        let mut encoder = DeltaEncoder::<Vec<u32>>::default();
+        let mut decoder = DeltaDecoder::<Vec<u32>>::default();
+        let comp = (0..100).collect::<Vec<u32>>();
+
+        let (packet, _) = encoder.encode(&comp);
+        decoder.decode(packet);
+        encoder.ack(DeltaAck::Received(0));
+
+        // The client lost its baseline, e.g. after reconnecting
+        decoder.reset();
+        let (packet, _) = encoder.encode(&comp);
+        assert!(!packet.is_full());
+        let (decoded, ack) = decoder.decode(packet);
+        assert_eq!(decoded, None);
+        assert_eq!(ack, DeltaAck::Resync);
+
+        encoder.ack(ack);
+        assert!(encoder.needs_resync());
+        let (packet, _) = encoder.encode(&comp);
+        assert!(packet.is_full());
+        assert!(!encoder.needs_resync());
+        assert_eq!(decoder.decode(packet).0, Some(comp));
+
+        // Data which doesn't deserialize requests a resync as well
+        let packet = CompDelta::Full {
+            seq: 10,
+            data: vec![0xff],
+            _phantom: PhantomData,
+        };
+        assert_eq!(decoder.decode(packet), (None, DeltaAck::Resync));
+    }
+}
@@ -61,6 +61,7 @@ macro_rules! synced_components {
             combo: Combo,
             active_abilities: ActiveAbilities,
             can_build: CanBuild,
+            vault: Vault,
         }
     };
 }
@@ -237,3 +238,7 @@ impl NetSync for ActiveAbilities {
 impl NetSync for CanBuild {
     const SYNC_FROM: SyncFrom = SyncFrom::ClientEntity;
 }
+
+impl NetSync for Vault {
+    const SYNC_FROM: SyncFrom = SyncFrom::ClientEntity;
+}
@@ -24,3 +24,159 @@ pub struct CharacterItem {
     pub body: comp::Body,
     pub inventory: Inventory,
 }
+
+/// The state of a character's body which is persisted when it logs out, so it
+/// doesn't return at full health whenever it logs in again
+#[derive(Clone, Debug, Default)]
+pub struct CharacterVitals {
+    /// The position of the character if it stands on the ground, not in the
+    /// air or in a liquid. Otherwise it is loaded at its waypoint.
+    pub position: Option<comp::Pos>,
+    /// Fraction of the maximum health, `None` for full health
+    pub health: Option<f32>,
+    /// Fraction of the maximum energy, `None` for full energy
+    pub energy: Option<f32>,
+    /// Buffs which wear off by themselves, with their remaining duration
+    pub buffs: Vec<comp::Buff>,
+}
+
+impl CharacterVitals {
+    pub fn new(
+        pos: Option<&comp::Pos>,
+        physics_state: Option<&comp::PhysicsState>,
+        health: Option<&comp::Health>,
+        energy: Option<&comp::Energy>,
+        buffs: Option<&comp::Buffs>,
+    ) -> Self {
+        // A dead character is revived at full health anyway
+        let health = health.filter(|health| !health.is_dead);
+        let position = pos
+            .filter(|_| {
+                health.is_some()
+                    && physics_state.map_or(false, |physics_state| {
+                        physics_state.on_ground.is_some() && physics_state.in_liquid().is_none()
+                    })
+            })
+            .copied();
+        let buffs = buffs
+            .into_iter()
+            .flat_map(|buffs| buffs.buffs.values())
+            // Buffs without a duration, like the ones of auras, are applied again by
+            // their source when the character is loaded
+            .filter(|buff| {
+                buff.time.is_some()
+                    && !buff
+                        .cat_ids
+                        .iter()
+                        .any(|cat_id| matches!(cat_id, comp::BuffCategory::FromAura(_)))
+            })
+            .cloned()
+            .collect();
+
+        Self {
+            position,
+            health: health
+                .map(|health| health.fraction())
+                .filter(|fraction| *fraction < 1.0),
+            energy: energy
+                .map(|energy| energy.fraction())
+                .filter(|fraction| *fraction < 1.0),
+            buffs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::{Block, BlockKind};
+    use std::time::Duration;
+    use vek::*;
+
+    fn buff(kind: comp::BuffKind, time: Option<Duration>, source: comp::BuffSource) -> comp::Buff {
+        comp::Buff::new(
+            kind,
+            comp::BuffData::new(1.0, time, None),
+            Vec::new(),
+            source,
+        )
+    }
+
+    #[test]
+    fn vitals() {
+        let body = comp::Body::Humanoid(comp::humanoid::Body::random());
+        let pos = comp::Pos(Vec3::new(1.0, 2.0, 3.0));
+        let on_ground = comp::PhysicsState {
+            on_ground: Some(Block::new(BlockKind::Earth, Rgb::zero())),
+            ..Default::default()
+        };
+        let mut health = comp::Health::new(body, 0);
+        health.change_by(comp::HealthChange {
+            amount: -health.maximum() / 2.0,
+            by: None,
+            cause: None,
+            time: crate::resources::Time(0.0),
+            crit: false,
+            instance: 0,
+        });
+        let energy = comp::Energy::new(body, 0);
+        let mut buffs = comp::Buffs::default();
+        let potion = Some(Duration::from_secs(10));
+        buffs.insert(buff(comp::BuffKind::Potion, potion, comp::BuffSource::Item));
+        buffs.insert(buff(
+            comp::BuffKind::Burning,
+            potion,
+            comp::BuffSource::World,
+        ));
+        buffs.insert(buff(
+            comp::BuffKind::IncreaseMaxHealth,
+            None,
+            comp::BuffSource::Item,
+        ));
+        let mut aura = buff(
+            comp::BuffKind::ProtectingWard,
+            potion,
+            comp::BuffSource::Unknown,
+        );
+        aura.cat_ids.push(comp::BuffCategory::FromAura(true));
+        buffs.insert(aura);
+
+        let vitals = CharacterVitals::new(
+            Some(&pos),
+            Some(&on_ground),
+            Some(&health),
+            Some(&energy),
+            Some(&buffs),
+        );
+        assert_eq!(vitals.position, Some(pos));
+        assert!(vitals
+            .health
+            .map_or(false, |health| (health - 0.5).abs() < 0.01));
+        // Full energy isn't stored
+        assert_eq!(vitals.energy, None);
+        // Only buffs which wear off by themselves and aren't from auras are kept
+        let mut kinds = vitals
+            .buffs
+            .iter()
+            .map(|buff| buff.kind)
+            .collect::<Vec<_>>();
+        kinds.sort();
+        assert_eq!(kinds, vec![comp::BuffKind::Potion, comp::BuffKind::Burning]);
+
+        // Characters in the air are loaded at their waypoint
+        let vitals = CharacterVitals::new(
+            Some(&pos),
+            Some(&comp::PhysicsState::default()),
+            Some(&health),
+            None,
+            None,
+        );
+        assert_eq!(vitals.position, None);
+
+        // Dead characters are revived at their waypoint with full health
+        health.is_dead = true;
+        let vitals = CharacterVitals::new(Some(&pos), Some(&on_ground), Some(&health), None, None);
+        assert_eq!(vitals.position, None);
+        assert_eq!(vitals.health, None);
+    }
+}
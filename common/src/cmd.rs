@@ -1,3 +1,4 @@
+pub use crate::cmd_args::{ArgumentSpec, Requirement};
 use crate::{
     assets,
     comp::{self, buff::BuffKind, inventory::item::try_all_item_defs, AdminRole as Role, Skill},
@@ -189,6 +190,21 @@ lazy_static! {
 
     static ref ROLES: Vec<String> = ["admin", "moderator"].iter().copied().map(Into::into).collect();
 
+    /// The chats a player can be muted in
+    pub static ref MUTE_CHATS: Vec<String> = [
+        "all", "say", "region", "world", "faction", "group", "tell",
+    ]
+    .iter()
+    .copied()
+    .map(Into::into)
+    .collect();
+
+    static ref PLUGIN_ACTIONS: Vec<String> = ["list", "reload", "disable", "enable"]
+        .iter()
+        .copied()
+        .map(Into::into)
+        .collect();
+
     /// List of item specifiers. Useful for tab completing
     pub static ref ITEM_SPECS: Vec<String> = {
         let mut items = try_all_item_defs()
@@ -242,6 +258,7 @@ pub enum ServerChatCommand {
     Adminify,
     Airship,
     Alias,
+    AuditLog,
     Ban,
     BattleMode,
     BattleModeForce,
@@ -286,11 +303,14 @@ pub enum ServerChatCommand {
     MakeSprite,
     MakeVolume,
     Motd,
+    Mute,
     Object,
     PermitBuild,
     Players,
+    Plugin,
     Region,
     ReloadChunks,
+    ReloadSettings,
     RemoveLights,
     RevokeBuild,
     RevokeBuildAll,
@@ -308,6 +328,7 @@ pub enum ServerChatCommand {
     Time,
     Tp,
     Unban,
+    Unmute,
     Version,
     Waypoint,
     WeatherZone,
@@ -339,6 +360,12 @@ impl ServerChatCommand {
                 "Change your alias",
                 Some(Moderator),
             ),
+            ServerChatCommand::AuditLog => cmd(
+                vec![PlayerName(Optional), Float("hours", 24.0, Optional)],
+                "Show the admin and moderation actions taken by or against a player in the last \
+                 hours",
+                Some(Moderator),
+            ),
             ServerChatCommand::Buff => cmd(
                 vec![
                     Enum("buff", BUFFS.clone(), Required),
@@ -561,6 +588,18 @@ impl ServerChatCommand {
             ServerChatCommand::Motd => {
                 cmd(vec![Message(Optional)], "View the server description", None)
             },
+            ServerChatCommand::Mute => cmd(
+                vec![
+                    PlayerName(Required),
+                    Enum("chat", MUTE_CHATS.clone(), Optional),
+                    Boolean("overwrite", "true".to_string(), Optional),
+                    Any("mute duration", Optional),
+                    Message(Optional),
+                ],
+                "Mute a player in the given chat (all chats by default), for a given duration (if \
+                 provided).  Pass true for overwrite to replace an existing mute of that chat.",
+                Some(Moderator),
+            ),
             ServerChatCommand::Object => cmd(
                 vec![Enum("object", OBJECTS.clone(), Required)],
                 "Spawn an object",
@@ -572,11 +611,26 @@ impl ServerChatCommand {
                 Some(Admin),
             ),
             ServerChatCommand::Players => cmd(vec![], "Lists players currently online", None),
+            ServerChatCommand::Plugin => cmd(
+                vec![
+                    Enum("action", PLUGIN_ACTIONS.clone(), Required),
+                    Any("plugin", Optional),
+                ],
+                "List, reload, disable or enable plugins. Reloads every plugin if no plugin is \
+                 given",
+                Some(Admin),
+            ),
             ServerChatCommand::ReloadChunks => cmd(
                 vec![],
                 "Reloads all chunks loaded on the server",
                 Some(Admin),
             ),
+            ServerChatCommand::ReloadSettings => cmd(
+                vec![],
+                "Reloads the server settings file, applying the settings which don't need a \
+                 restart",
+                Some(Admin),
+            ),
             ServerChatCommand::RemoveLights => cmd(
                 vec![Float("radius", 20.0, Optional)],
                 "Removes all lights spawned by players",
@@ -680,6 +734,14 @@ impl ServerChatCommand {
                 "Remove the ban for the given username",
                 Some(Moderator),
             ),
+            ServerChatCommand::Unmute => cmd(
+                vec![
+                    PlayerName(Required),
+                    Enum("chat", MUTE_CHATS.clone(), Optional),
+                ],
+                "Lift the mute of the given username in the given chat, or all their mutes",
+                Some(Moderator),
+            ),
             ServerChatCommand::Version => cmd(vec![], "Prints server version", None),
             ServerChatCommand::Waypoint => cmd(
                 vec![],
@@ -734,6 +796,7 @@ impl ServerChatCommand {
             ServerChatCommand::Adminify => "adminify",
             ServerChatCommand::Airship => "airship",
             ServerChatCommand::Alias => "alias",
+            ServerChatCommand::AuditLog => "audit_log",
             ServerChatCommand::Ban => "ban",
             ServerChatCommand::BattleMode => "battlemode",
             ServerChatCommand::BattleModeForce => "battlemode_force",
@@ -773,11 +836,14 @@ impl ServerChatCommand {
             ServerChatCommand::MakeNpc => "make_npc",
             ServerChatCommand::MakeSprite => "make_sprite",
             ServerChatCommand::Motd => "motd",
+            ServerChatCommand::Mute => "mute",
             ServerChatCommand::Object => "object",
             ServerChatCommand::PermitBuild => "permit_build",
             ServerChatCommand::Players => "players",
+            ServerChatCommand::Plugin => "plugin",
             ServerChatCommand::Region => "region",
             ServerChatCommand::ReloadChunks => "reload_chunks",
+            ServerChatCommand::ReloadSettings => "reload_settings",
             ServerChatCommand::RemoveLights => "remove_lights",
             ServerChatCommand::RevokeBuild => "revoke_build",
             ServerChatCommand::RevokeBuildAll => "revoke_build_all",
@@ -795,6 +861,7 @@ impl ServerChatCommand {
             ServerChatCommand::Time => "time",
             ServerChatCommand::Tp => "tp",
             ServerChatCommand::Unban => "unban",
+            ServerChatCommand::Unmute => "unmute",
             ServerChatCommand::Version => "version",
             ServerChatCommand::Waypoint => "waypoint",
             ServerChatCommand::Wiring => "wiring",
@@ -893,116 +960,56 @@ impl FromStr for ServerChatCommand {
     }
 }
 
-#[derive(Eq, PartialEq, Debug)]
-pub enum Requirement {
-    Required,
-    Optional,
+/// A chat command which isn't one of the [`ServerChatCommand`]s, such as the
+/// commands registered by server plugins. The server sends these to the
+/// clients so they show up in `/help` and tab completion.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PluginChatCommand {
+    pub keyword: String,
+    /// A one-line message that explains what the command does
+    pub description: String,
+    /// A list of arguments useful for both tab completion and parsing
+    pub args: Vec<ArgumentSpec<String>>,
+    /// Whether the command requires administrator permissions.
+    pub needs_role: Option<Role>,
 }
 
-/// Representation for chat command arguments
-pub enum ArgumentSpec {
-    /// The argument refers to a player by alias
-    PlayerName(Requirement),
-    // The argument refers to a site, by name.
-    SiteName(Requirement),
-    /// The argument is a float. The associated values are
-    /// * label
-    /// * suggested tab-completion
-    /// * whether it's optional
-    Float(&'static str, f32, Requirement),
-    /// The argument is an integer. The associated values are
-    /// * label
-    /// * suggested tab-completion
-    /// * whether it's optional
-    Integer(&'static str, i32, Requirement),
-    /// The argument is any string that doesn't contain spaces
-    Any(&'static str, Requirement),
-    /// The argument is a command name (such as in /help)
-    Command(Requirement),
-    /// This is the final argument, consuming all characters until the end of
-    /// input.
-    Message(Requirement),
-    /// This command is followed by another command (such as in /sudo)
-    SubCommand,
-    /// The argument is likely an enum. The associated values are
-    /// * label
-    /// * Predefined string completions
-    /// * whether it's optional
-    Enum(&'static str, Vec<String>, Requirement),
-    /// The argument is likely a boolean. The associated values are
-    /// * label
-    /// * suggested tab-completion
-    /// * whether it's optional
-    Boolean(&'static str, String, Requirement),
-}
+impl PluginChatCommand {
+    /// A message that explains what the command does
+    pub fn help_string(&self) -> String {
+        let usage = std::iter::once(format!("/{}", self.keyword))
+            .chain(self.args.iter().map(|arg| arg.usage_string()))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!("{}: {}", usage, self.description)
+    }
 
-impl ArgumentSpec {
-    pub fn usage_string(&self) -> String {
-        match self {
-            ArgumentSpec::PlayerName(req) => {
-                if &Requirement::Required == req {
-                    "<player>".to_string()
-                } else {
-                    "[player]".to_string()
-                }
-            },
-            ArgumentSpec::SiteName(req) => {
-                if &Requirement::Required == req {
-                    "<site>".to_string()
-                } else {
-                    "[site]".to_string()
-                }
-            },
-            ArgumentSpec::Float(label, _, req) => {
-                if &Requirement::Required == req {
-                    format!("<{}>", label)
-                } else {
-                    format!("[{}]", label)
-                }
-            },
-            ArgumentSpec::Integer(label, _, req) => {
-                if &Requirement::Required == req {
-                    format!("<{}>", label)
-                } else {
-                    format!("[{}]", label)
-                }
-            },
-            ArgumentSpec::Any(label, req) => {
-                if &Requirement::Required == req {
-                    format!("<{}>", label)
-                } else {
-                    format!("[{}]", label)
-                }
-            },
-            ArgumentSpec::Command(req) => {
-                if &Requirement::Required == req {
-                    "<[/]command>".to_string()
-                } else {
-                    "[[/]command]".to_string()
-                }
-            },
-            ArgumentSpec::Message(req) => {
-                if &Requirement::Required == req {
-                    "<message>".to_string()
-                } else {
-                    "[message]".to_string()
-                }
-            },
-            ArgumentSpec::SubCommand => "<[/]command> [args...]".to_string(),
-            ArgumentSpec::Enum(label, _, req) => {
-                if &Requirement::Required == req {
-                    format!("<{}>", label)
-                } else {
-                    format!("[{}]", label)
-                }
-            },
-            ArgumentSpec::Boolean(label, _, req) => {
-                if &Requirement::Required == req {
-                    format!("<{}>", label)
-                } else {
-                    format!("[{}]", label)
-                }
-            },
+    /// Check the arguments given to the command against its argument list,
+    /// returning the help string of the command if they don't match. The
+    /// arguments are matched by position.
+    pub fn verify_args(&self, args: &[String]) -> Result<(), String> {
+        let required = self
+            .args
+            .iter()
+            .filter(|arg| arg.requirement() == Requirement::Required)
+            .count();
+        let trailing = matches!(
+            self.args.last(),
+            Some(ArgumentSpec::Message(_) | ArgumentSpec::SubCommand)
+        );
+        let valid = args.len() >= required
+            && (trailing || args.len() <= self.args.len())
+            && self.args.iter().zip(args).all(|(spec, arg)| match spec {
+                ArgumentSpec::Float(_, _, _) => arg.parse::<f32>().is_ok(),
+                ArgumentSpec::Integer(_, _, _) => arg.parse::<i32>().is_ok(),
+                ArgumentSpec::Boolean(_, _, _) => arg.parse::<bool>().is_ok(),
+                ArgumentSpec::Enum(_, values, _) => values.contains(arg),
+                _ => true,
+            });
+        if valid {
+            Ok(())
+        } else {
+            Err(self.help_string())
         }
     }
 }
@@ -1081,4 +1088,28 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_verify_plugin_command_args() {
+        let command = PluginChatCommand {
+            keyword: "warp".to_owned(),
+            description: "Warp to a location".to_owned(),
+            args: vec![
+                ArgumentSpec::Enum(
+                    "location".to_owned(),
+                    vec!["spawn".to_owned()],
+                    Requirement::Required,
+                ),
+                ArgumentSpec::Integer("delay".to_owned(), 0, Requirement::Optional),
+            ],
+            needs_role: None,
+        };
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(command.verify_args(&args(&["spawn"])).is_ok());
+        assert!(command.verify_args(&args(&["spawn", "5"])).is_ok());
+        assert!(command.verify_args(&args(&[])).is_err());
+        assert!(command.verify_args(&args(&["home"])).is_err());
+        assert!(command.verify_args(&args(&["spawn", "soon"])).is_err());
+        assert!(command.verify_args(&args(&["spawn", "5", "6"])).is_err());
+    }
 }
@@ -0,0 +1,137 @@
+//! The arguments of chat commands. These are kept apart from [`crate::cmd`] so
+//! that plugins, which can't use the rest of it, share them.
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Requirement {
+    Required,
+    Optional,
+}
+
+/// Representation for chat command arguments. The labels are `&'static str`
+/// for the built-in commands and `String` for the commands registered by
+/// plugins.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ArgumentSpec<L = &'static str> {
+    /// The argument refers to a player by alias
+    PlayerName(Requirement),
+    // The argument refers to a site, by name.
+    SiteName(Requirement),
+    /// The argument is a float. The associated values are
+    /// * label
+    /// * suggested tab-completion
+    /// * whether it's optional
+    Float(L, f32, Requirement),
+    /// The argument is an integer. The associated values are
+    /// * label
+    /// * suggested tab-completion
+    /// * whether it's optional
+    Integer(L, i32, Requirement),
+    /// The argument is any string that doesn't contain spaces
+    Any(L, Requirement),
+    /// The argument is a command name (such as in /help)
+    Command(Requirement),
+    /// This is the final argument, consuming all characters until the end of
+    /// input.
+    Message(Requirement),
+    /// This command is followed by another command (such as in /sudo)
+    SubCommand,
+    /// The argument is likely an enum. The associated values are
+    /// * label
+    /// * Predefined string completions
+    /// * whether it's optional
+    Enum(L, Vec<String>, Requirement),
+    /// The argument is likely a boolean. The associated values are
+    /// * label
+    /// * suggested tab-completion
+    /// * whether it's optional
+    Boolean(L, String, Requirement),
+}
+
+impl<L: Display> ArgumentSpec<L> {
+    pub fn requirement(&self) -> Requirement {
+        match self {
+            ArgumentSpec::PlayerName(req)
+            | ArgumentSpec::SiteName(req)
+            | ArgumentSpec::Float(_, _, req)
+            | ArgumentSpec::Integer(_, _, req)
+            | ArgumentSpec::Any(_, req)
+            | ArgumentSpec::Command(req)
+            | ArgumentSpec::Message(req)
+            | ArgumentSpec::Enum(_, _, req)
+            | ArgumentSpec::Boolean(_, _, req) => *req,
+            ArgumentSpec::SubCommand => Requirement::Required,
+        }
+    }
+
+    pub fn usage_string(&self) -> String {
+        match self {
+            ArgumentSpec::PlayerName(req) => {
+                if &Requirement::Required == req {
+                    "<player>".to_string()
+                } else {
+                    "[player]".to_string()
+                }
+            },
+            ArgumentSpec::SiteName(req) => {
+                if &Requirement::Required == req {
+                    "<site>".to_string()
+                } else {
+                    "[site]".to_string()
+                }
+            },
+            ArgumentSpec::Float(label, _, req) => {
+                if &Requirement::Required == req {
+                    format!("<{}>", label)
+                } else {
+                    format!("[{}]", label)
+                }
+            },
+            ArgumentSpec::Integer(label, _, req) => {
+                if &Requirement::Required == req {
+                    format!("<{}>", label)
+                } else {
+                    format!("[{}]", label)
+                }
+            },
+            ArgumentSpec::Any(label, req) => {
+                if &Requirement::Required == req {
+                    format!("<{}>", label)
+                } else {
+                    format!("[{}]", label)
+                }
+            },
+            ArgumentSpec::Command(req) => {
+                if &Requirement::Required == req {
+                    "<[/]command>".to_string()
+                } else {
+                    "[[/]command]".to_string()
+                }
+            },
+            ArgumentSpec::Message(req) => {
+                if &Requirement::Required == req {
+                    "<message>".to_string()
+                } else {
+                    "[message]".to_string()
+                }
+            },
+            ArgumentSpec::SubCommand => "<[/]command> [args...]".to_string(),
+            ArgumentSpec::Enum(label, _, req) => {
+                if &Requirement::Required == req {
+                    format!("<{}>", label)
+                } else {
+                    format!("[{}]", label)
+                }
+            },
+            ArgumentSpec::Boolean(label, _, req) => {
+                if &Requirement::Required == req {
+                    format!("<{}>", label)
+                } else {
+                    format!("[{}]", label)
+                }
+            },
+        }
+    }
+}
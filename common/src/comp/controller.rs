@@ -24,6 +24,18 @@ pub enum InventoryEvent {
     SplitSwap(InvSlotId, InvSlotId),
     Drop(InvSlotId),
     SplitDrop(InvSlotId),
+    /// Moves an item into, out of or within the vault, which is accessed
+    /// through the vault sprite at `vault_pos`
+    VaultSwap {
+        from: Slot,
+        to: Slot,
+        vault_pos: Vec3<i32>,
+    },
+    VaultSplitSwap {
+        from: Slot,
+        to: Slot,
+        vault_pos: Vec3<i32>,
+    },
     Sort,
     CraftRecipe {
         craft_event: CraftEvent,
@@ -49,6 +61,16 @@ pub enum InventoryManip {
     SplitSwap(Slot, Slot),
     Drop(Slot),
     SplitDrop(Slot),
+    VaultSwap {
+        from: Slot,
+        to: Slot,
+        vault_pos: Vec3<i32>,
+    },
+    VaultSplitSwap {
+        from: Slot,
+        to: Slot,
+        vault_pos: Vec3<i32>,
+    },
     Sort,
     CraftRecipe {
         craft_event: CraftEvent,
@@ -81,6 +103,24 @@ impl From<InventoryEvent> for InventoryManip {
             },
             InventoryEvent::Drop(inv) => Self::Drop(Slot::Inventory(inv)),
             InventoryEvent::SplitDrop(inv) => Self::SplitDrop(Slot::Inventory(inv)),
+            InventoryEvent::VaultSwap {
+                from,
+                to,
+                vault_pos,
+            } => Self::VaultSwap {
+                from,
+                to,
+                vault_pos,
+            },
+            InventoryEvent::VaultSplitSwap {
+                from,
+                to,
+                vault_pos,
+            } => Self::VaultSplitSwap {
+                from,
+                to,
+                vault_pos,
+            },
             InventoryEvent::Sort => Self::Sort,
             InventoryEvent::CraftRecipe {
                 craft_event,
@@ -474,7 +474,7 @@ pub enum ItemDefinitionId<'a> {
     },
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, Eq, Hash, PartialEq)]
 pub enum ItemDefinitionIdOwned {
     Simple(String),
     Modular {
@@ -28,6 +28,8 @@ pub mod slot;
 #[cfg(test)] mod test;
 #[cfg(test)] mod test_helpers;
 pub mod trade_pricing;
+pub mod vault;
+#[cfg(test)] mod vault_test;
 
 pub type InvSlot = Option<Item>;
 const DEFAULT_INVENTORY_SLOTS: usize = 18;
@@ -719,6 +721,10 @@ impl Inventory {
                 self.loadout.swap_slots(slot_a, slot_b);
                 Vec::new()
             },
+            (Slot::Vault(_), _) | (_, Slot::Vault(_)) => {
+                warn!("swap called with a vault slot, which is handled by Vault::swap");
+                Vec::new()
+            },
         }
     }
 
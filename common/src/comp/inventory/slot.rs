@@ -15,6 +15,8 @@ pub enum SlotError {
 pub enum Slot {
     Inventory(InvSlotId),
     Equip(EquipSlot),
+    /// A slot of the account's [`Vault`](super::vault::Vault)
+    Vault(usize),
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
@@ -109,7 +111,7 @@ pub enum ArmorSlot {
 impl Slot {
     pub fn can_hold(self, item_kind: &ItemKind) -> bool {
         match (self, item_kind) {
-            (Self::Inventory(_), _) => true,
+            (Self::Inventory(_) | Self::Vault(_), _) => true,
             (Self::Equip(slot), item_kind) => slot.can_hold(item_kind),
         }
     }
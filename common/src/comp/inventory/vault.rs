@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use specs::{Component, DerefFlaggedStorage};
+use std::mem;
+
+use crate::comp::{
+    inventory::{
+        item::{tool::AbilityMap, MaterialStatManifest},
+        slot::{InvSlotId, Slot},
+        InvSlot, Inventory,
+    },
+    Item,
+};
+
+pub const VAULT_SLOTS: usize = 36;
+
+/// A container of items which belongs to an account instead of a character, so
+/// that all characters of a player can access its items. Items are moved in
+/// and out of it through a vault sprite.
+///
+/// NOTE: Do not add a PartialEq instance for Vault, for the same reason as for
+/// Inventory
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Vault {
+    slots: Vec<InvSlot>,
+}
+
+impl Default for Vault {
+    fn default() -> Self { Self::with_empty() }
+}
+
+impl Vault {
+    pub fn with_empty() -> Self {
+        Self {
+            slots: vec![None; VAULT_SLOTS],
+        }
+    }
+
+    /// Total number of slots in the vault.
+    pub fn capacity(&self) -> usize { self.slots.len() }
+
+    /// An iterator of all vault slots
+    pub fn slots(&self) -> impl Iterator<Item = &InvSlot> { self.slots.iter() }
+
+    /// An iterator of all vault slots and their index
+    pub fn slots_with_id(&self) -> impl Iterator<Item = (usize, &InvSlot)> {
+        self.slots.iter().enumerate()
+    }
+
+    /// Get content of a slot
+    pub fn get(&self, slot: usize) -> Option<&Item> {
+        self.slots.get(slot).and_then(Option::as_ref)
+    }
+
+    /// Get a mutable reference to the content of a slot
+    pub fn get_mut(&mut self, slot: usize) -> Option<&mut Item> {
+        self.slots.get_mut(slot).and_then(Option::as_mut)
+    }
+
+    /// Replaces the item in a slot of the vault. Returns the old item or the
+    /// same item again if that slot was not found.
+    pub fn insert_at(&mut self, slot: usize, item: Item) -> Result<Option<Item>, Item> {
+        match self.slots.get_mut(slot) {
+            Some(slot) => Ok(mem::replace(slot, Some(item))),
+            None => Err(item),
+        }
+    }
+
+    /// Moves the item from one slot onto another, where at least one of the
+    /// slots is in the vault and the other one is either in the vault or in
+    /// the inventory. Stacks of the same item are merged, other items are
+    /// swapped. Returns whether anything was moved.
+    pub fn swap(&mut self, inventory: &mut Inventory, from: Slot, to: Slot) -> bool {
+        match (from, to) {
+            (Slot::Vault(a), Slot::Vault(b)) => {
+                if a == b || a >= self.slots.len() || b >= self.slots.len() {
+                    return false;
+                }
+                let mut src = mem::take(&mut self.slots[a]);
+                move_item(&mut src, &mut self.slots[b]);
+                self.slots[a] = src;
+                true
+            },
+            (Slot::Inventory(inv_slot), Slot::Vault(vault_slot)) => {
+                match (inventory.slot_mut(inv_slot), self.slots.get_mut(vault_slot)) {
+                    (Some(src), Some(dst)) => {
+                        move_item(src, dst);
+                        true
+                    },
+                    _ => false,
+                }
+            },
+            (Slot::Vault(vault_slot), Slot::Inventory(inv_slot)) => {
+                match (self.slots.get_mut(vault_slot), inventory.slot_mut(inv_slot)) {
+                    (Some(src), Some(dst)) => {
+                        move_item(src, dst);
+                        true
+                    },
+                    _ => false,
+                }
+            },
+            // Items can't be equipped from or unequipped into the vault
+            _ => false,
+        }
+    }
+
+    /// Moves half of the stack in one slot onto another, where the slots are
+    /// the same as for [`Vault::swap`]. Nothing is moved if the target slot
+    /// contains a different item.
+    pub fn split_swap(
+        &mut self,
+        inventory: &mut Inventory,
+        from: Slot,
+        to: Slot,
+        ability_map: &AbilityMap,
+        msm: &MaterialStatManifest,
+    ) -> bool {
+        if from == to || !matches!((from, to), (Slot::Vault(_), _) | (_, Slot::Vault(_))) {
+            return false;
+        }
+        let item = match self.slot_mut(inventory, from) {
+            Some(slot) => take_half(slot, ability_map, msm),
+            None => return false,
+        };
+        let item = match (item, self.slot_mut(inventory, to)) {
+            (Some(item), Some(slot)) => match stack_or_insert(slot, item) {
+                Ok(()) => return true,
+                Err(item) => item,
+            },
+            (Some(item), None) => item,
+            (None, _) => return false,
+        };
+        // The target didn't fit the items, so they go back to where they came from
+        if let Some(slot) = self.slot_mut(inventory, from) {
+            stack_or_insert(slot, item)
+                .expect("The items were just taken from this slot, so they fit back in.");
+        }
+        false
+    }
+
+    fn slot_mut<'a>(
+        &'a mut self,
+        inventory: &'a mut Inventory,
+        slot: Slot,
+    ) -> Option<&'a mut InvSlot> {
+        match slot {
+            Slot::Vault(slot) => self.slots.get_mut(slot),
+            Slot::Inventory(slot) => inventory.slot_mut(slot),
+            Slot::Equip(_) => None,
+        }
+    }
+
+    /// Update internal computed state of all items in this vault.
+    /// Used only when loading in persistence code.
+    pub fn persistence_update_all_item_states(
+        &mut self,
+        ability_map: &AbilityMap,
+        msm: &MaterialStatManifest,
+    ) {
+        self.slots.iter_mut().flatten().for_each(|item| {
+            item.update_item_state(ability_map, msm);
+        });
+    }
+
+    /// Finds the first slot of the inventory an item from the vault can be
+    /// taken into, used when quickly moving an item out of the vault
+    pub fn inventory_slot_for(&self, inventory: &Inventory, slot: usize) -> Option<InvSlotId> {
+        let item = self.get(slot)?;
+        inventory
+            .slots_with_id()
+            .find(|(_, inv_slot)| {
+                inv_slot
+                    .as_ref()
+                    .map_or(false, |inv_item| inv_item == item && item.is_stackable())
+            })
+            .or_else(|| {
+                inventory
+                    .slots_with_id()
+                    .find(|(_, inv_slot)| inv_slot.is_none())
+            })
+            .map(|(slot, _)| slot)
+    }
+
+    /// Finds the first slot of the vault an item from the inventory can be
+    /// stored in, used when quickly moving an item into the vault
+    pub fn vault_slot_for(&self, item: &Item) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| {
+                slot.as_ref().map_or(false, |vault_item| {
+                    vault_item == item && item.is_stackable()
+                })
+            })
+            .or_else(|| self.slots.iter().position(Option::is_none))
+    }
+}
+
+/// Moves the item in `src` onto `dst`, which merges the stacks if both contain
+/// the same stackable item and otherwise swaps the items
+pub(super) fn move_item(src: &mut InvSlot, dst: &mut InvSlot) {
+    if let (Some(src_item), Some(dst_item)) = (src.as_ref(), dst.as_mut()) {
+        // The equality check ensures the items have the same definition, to avoid e.g.
+        // transmuting coins to diamonds
+        if *src_item == *dst_item
+            && src_item.is_stackable()
+            && dst_item.increase_amount(src_item.amount()).is_ok()
+        {
+            *src = None;
+            return;
+        }
+    }
+    mem::swap(src, dst);
+}
+
+/// Puts an item into an empty slot or onto a stack of the same item
+pub(super) fn stack_or_insert(slot: &mut InvSlot, item: Item) -> Result<(), Item> {
+    match slot {
+        None => {
+            *slot = Some(item);
+            Ok(())
+        },
+        Some(slot_item) if *slot_item == item && item.is_stackable() => {
+            slot_item.increase_amount(item.amount()).map_err(|_| item)
+        },
+        Some(_) => Err(item),
+    }
+}
+
+/// Takes half of the items from a slot, or the whole item if it isn't a stack
+fn take_half(
+    slot: &mut InvSlot,
+    ability_map: &AbilityMap,
+    msm: &MaterialStatManifest,
+) -> Option<Item> {
+    let item = slot.as_mut()?;
+    if item.is_stackable() && item.amount() > 1 {
+        let mut return_item = item.duplicate(ability_map, msm);
+        let returning_amount = item.amount() / 2;
+        item.decrease_amount(returning_amount).ok()?;
+        return_item
+            .set_amount(returning_amount)
+            .expect("Items duplicated from a stackable item must be stackable.");
+        Some(return_item)
+    } else {
+        slot.take()
+    }
+}
+
+impl Component for Vault {
+    type Storage = DerefFlaggedStorage<Self, specs::VecStorage<Self>>;
+}
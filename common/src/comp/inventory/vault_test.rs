@@ -0,0 +1,171 @@
+use super::{
+    slot::{ArmorSlot, EquipSlot, InvSlotId, Slot},
+    vault::*,
+    *,
+};
+use crate::comp::Item;
+
+fn coins(amount: u32) -> Item {
+    let mut item = Item::new_from_asset_expect("common.items.utility.coins");
+    item.set_amount(amount).unwrap();
+    item
+}
+
+fn stick() -> Item { Item::new_from_asset_expect("common.items.debug.admin_stick") }
+
+fn amount(slot: Option<&Item>) -> Option<u32> { slot.map(Item::amount) }
+
+/// Stacks of the same item are merged, other items are swapped.
+#[test]
+fn move_item_stacks_or_swaps() {
+    let mut src = Some(coins(3));
+    let mut dst = Some(coins(4));
+    move_item(&mut src, &mut dst);
+    assert!(src.is_none());
+    assert_eq!(amount(dst.as_ref()), Some(7));
+
+    let mut src = Some(stick());
+    move_item(&mut src, &mut dst);
+    assert_eq!(amount(src.as_ref()), Some(7));
+    assert_eq!(dst, Some(stick()));
+
+    // Unstackable items are swapped even if they are the same
+    let mut src = Some(stick());
+    move_item(&mut src, &mut dst);
+    assert_eq!(src, Some(stick()));
+    assert_eq!(dst, Some(stick()));
+
+    let mut empty = None;
+    move_item(&mut src, &mut empty);
+    assert!(src.is_none());
+    assert_eq!(empty, Some(stick()));
+}
+
+#[test]
+fn stack_or_insert_into_slot() {
+    let mut slot = None;
+    assert!(stack_or_insert(&mut slot, coins(2)).is_ok());
+    assert!(stack_or_insert(&mut slot, coins(5)).is_ok());
+    assert_eq!(amount(slot.as_ref()), Some(7));
+    assert_eq!(stack_or_insert(&mut slot, stick()), Err(stick()));
+    assert_eq!(amount(slot.as_ref()), Some(7));
+
+    let mut slot = Some(stick());
+    assert_eq!(stack_or_insert(&mut slot, stick()), Err(stick()));
+}
+
+#[test]
+fn swap_within_vault() {
+    let mut inventory = Inventory::with_empty();
+    let mut vault = Vault::with_empty();
+    vault.insert_at(0, coins(3)).unwrap();
+    vault.insert_at(1, coins(4)).unwrap();
+    vault.insert_at(2, stick()).unwrap();
+
+    assert!(vault.swap(&mut inventory, Slot::Vault(0), Slot::Vault(1)));
+    assert!(vault.get(0).is_none());
+    assert_eq!(amount(vault.get(1)), Some(7));
+
+    assert!(vault.swap(&mut inventory, Slot::Vault(1), Slot::Vault(2)));
+    assert_eq!(vault.get(1), Some(&stick()));
+    assert_eq!(amount(vault.get(2)), Some(7));
+
+    assert!(!vault.swap(&mut inventory, Slot::Vault(2), Slot::Vault(2)));
+    assert!(!vault.swap(&mut inventory, Slot::Vault(2), Slot::Vault(VAULT_SLOTS)));
+    assert_eq!(amount(vault.get(2)), Some(7));
+}
+
+#[test]
+fn swap_with_inventory() {
+    let mut inventory = Inventory::with_empty();
+    let mut vault = Vault::with_empty();
+    let inv_slot = InvSlotId::new(0, 0);
+    inventory.insert_at(inv_slot, stick()).unwrap();
+
+    assert!(vault.swap(&mut inventory, Slot::Inventory(inv_slot), Slot::Vault(5)));
+    assert!(inventory.get(inv_slot).is_none());
+    assert_eq!(vault.get(5), Some(&stick()));
+
+    assert!(vault.swap(&mut inventory, Slot::Vault(5), Slot::Inventory(inv_slot)));
+    assert_eq!(inventory.get(inv_slot), Some(&stick()));
+    assert!(vault.get(5).is_none());
+
+    // Items can't be equipped from the vault, and inventory slots are swapped by
+    // the inventory
+    vault.insert_at(0, stick()).unwrap();
+    let equip = Slot::Equip(EquipSlot::Armor(ArmorSlot::Head));
+    assert!(!vault.swap(&mut inventory, Slot::Vault(0), equip));
+    assert!(!vault.swap(&mut inventory, equip, Slot::Vault(0)));
+    assert!(!vault.swap(
+        &mut inventory,
+        Slot::Inventory(inv_slot),
+        Slot::Inventory(InvSlotId::new(0, 1))
+    ));
+    assert_eq!(vault.get(0), Some(&stick()));
+    assert_eq!(inventory.get(inv_slot), Some(&stick()));
+}
+
+#[test]
+fn split_swap() {
+    let msm = &MaterialStatManifest::load().read();
+    let ability_map = &AbilityMap::load().read();
+    let mut inventory = Inventory::with_empty();
+    let mut vault = Vault::with_empty();
+    let inv_slot = InvSlotId::new(0, 0);
+    vault.insert_at(0, coins(9)).unwrap();
+
+    // Half of the stack goes into the inventory
+    assert!(vault.split_swap(
+        &mut inventory,
+        Slot::Vault(0),
+        Slot::Inventory(inv_slot),
+        ability_map,
+        msm
+    ));
+    assert_eq!(amount(vault.get(0)), Some(5));
+    assert_eq!(amount(inventory.get(inv_slot)), Some(4));
+
+    // And onto the same item
+    assert!(vault.split_swap(
+        &mut inventory,
+        Slot::Vault(0),
+        Slot::Inventory(inv_slot),
+        ability_map,
+        msm
+    ));
+    assert_eq!(amount(vault.get(0)), Some(3));
+    assert_eq!(amount(inventory.get(inv_slot)), Some(6));
+
+    // Nothing is moved onto a different item
+    vault.insert_at(1, stick()).unwrap();
+    assert!(!vault.split_swap(
+        &mut inventory,
+        Slot::Vault(0),
+        Slot::Vault(1),
+        ability_map,
+        msm
+    ));
+    assert_eq!(amount(vault.get(0)), Some(3));
+    assert_eq!(vault.get(1), Some(&stick()));
+
+    // Items which aren't stacks are moved as a whole
+    assert!(vault.split_swap(
+        &mut inventory,
+        Slot::Vault(1),
+        Slot::Vault(2),
+        ability_map,
+        msm
+    ));
+    assert!(vault.get(1).is_none());
+    assert_eq!(vault.get(2), Some(&stick()));
+
+    // Only slots of the vault are handled
+    assert!(!vault.split_swap(
+        &mut inventory,
+        Slot::Inventory(inv_slot),
+        Slot::Inventory(InvSlotId::new(0, 1)),
+        ability_map,
+        msm
+    ));
+    assert_eq!(amount(inventory.get(inv_slot)), Some(6));
+}
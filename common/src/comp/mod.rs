@@ -91,7 +91,9 @@ pub use self::{
             tool::{self, AbilityItem},
             Item, ItemConfig, ItemDrop,
         },
-        slot, CollectFailedReason, Inventory, InventoryUpdate, InventoryUpdateEvent,
+        slot,
+        vault::Vault,
+        CollectFailedReason, Inventory, InventoryUpdate, InventoryUpdateEvent,
     },
     last::Last,
     location::{MapMarker, MapMarkerChange, MapMarkerUpdate, Waypoint, WaypointArea},
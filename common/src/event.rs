@@ -1,5 +1,5 @@
 use crate::{
-    character::CharacterId,
+    character::{CharacterId, CharacterVitals},
     comp::{
         self,
         agent::Sound,
@@ -9,7 +9,7 @@ use crate::{
     lottery::LootSpec,
     outcome::Outcome,
     rtsim::RtSimEntity,
-    terrain::SpriteKind,
+    terrain::{Block, SpriteKind},
     trade::{TradeAction, TradeId},
     uid::Uid,
     util::Dir,
@@ -128,6 +128,8 @@ pub enum ServerEvent {
             Vec<(comp::Pet, comp::Body, comp::Stats)>,
             comp::ActiveAbilities,
             Option<comp::MapMarker>,
+            CharacterVitals,
+            comp::Vault,
         ),
         metadata: UpdateCharacterMetadata,
     },
@@ -161,6 +163,12 @@ pub enum ServerEvent {
     CreateWaypoint(Vec3<f32>),
     ClientDisconnect(EcsEntity, DisconnectReason),
     ClientDisconnectWithoutPersistence(EcsEntity),
+    /// The client of `entity` resumed the session it lost the connection of,
+    /// and takes over the entity of the session
+    ClientResume {
+        entity: EcsEntity,
+        resumed: EcsEntity,
+    },
     Command(EcsEntity, String, Vec<String>),
     /// Send a chat message to the player from an npc or other player
     Chat(comp::UnresolvedChatMsg),
@@ -194,6 +202,17 @@ pub enum ServerEvent {
         pos: Vec3<i32>,
         tool: Option<comp::tool::ToolKind>,
     },
+    /// Break a block in one of the build areas of `entity`
+    BreakBlock {
+        entity: EcsEntity,
+        pos: Vec3<i32>,
+    },
+    /// Place a block in one of the build areas of `entity`
+    PlaceBlock {
+        entity: EcsEntity,
+        pos: Vec3<i32>,
+        block: Block,
+    },
     TeleportTo {
         entity: EcsEntity,
         target: Uid,
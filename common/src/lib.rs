@@ -32,6 +32,7 @@ cfg_not_wasm! {
 
 // Modules
 
+pub mod cmd_args;
 pub mod combat;
 pub mod comp;
 pub mod consts;
@@ -3,6 +3,7 @@
 // `Agent`). When possible, this should be moved to the `rtsim`
 // module in `server`.
 
+use serde::{Deserialize, Serialize};
 use specs::Component;
 use vek::*;
 
@@ -25,13 +26,13 @@ pub enum RtSimEvent {
     PrintMemories,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Memory {
     pub item: MemoryItem,
     pub time_to_forget: f64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum MemoryItem {
     // These are structs to allow more data beyond name to be stored
     // such as clothing worn, weapon used, etc.
@@ -291,6 +291,7 @@ impl Block {
                 | SpriteKind::DismantlingBench
                 | SpriteKind::TanningRack
                 | SpriteKind::Chest
+                | SpriteKind::Vault
                 | SpriteKind::DungeonChest0
                 | SpriteKind::DungeonChest1
                 | SpriteKind::DungeonChest2
@@ -226,6 +226,7 @@ make_case_elim!(
         SeaDecorPillar = 0xC7,
         SeashellLantern = 0xC8,
         Rope = 0xC9,
+        Vault = 0xCA,
     }
 );
 
@@ -242,6 +243,7 @@ impl SpriteKind {
             SpriteKind::Pumpkin => 0.81,
             SpriteKind::Cabbage => 0.45,
             SpriteKind::Chest => 1.09,
+            SpriteKind::Vault => 1.09,
             SpriteKind::DungeonChest0 => 1.09,
             SpriteKind::DungeonChest1 => 1.09,
             SpriteKind::DungeonChest2 => 1.09,
@@ -508,6 +510,7 @@ impl SpriteKind {
                 | SpriteKind::WardrobeDouble
                 | SpriteKind::Pot
                 | SpriteKind::Chest
+                | SpriteKind::Vault
                 | SpriteKind::DungeonChest0
                 | SpriteKind::DungeonChest1
                 | SpriteKind::DungeonChest2
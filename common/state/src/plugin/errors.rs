@@ -7,6 +7,11 @@ pub enum PluginError {
     Toml(toml::de::Error),
     NoConfig,
     NoSuchModule,
+    /// No plugin with this name is loaded (or disabled)
+    NoSuchPlugin(String),
+    /// The plugins weren't loaded from a directory
+    NoPluginDir,
+    Watcher(notify::Error),
     Encoding(Box<ErrorKind>),
     PluginModuleError(String, String, PluginModuleError),
 }
@@ -18,6 +23,8 @@ pub enum PluginModuleError {
     MemoryUninit(ExportError),
     FindFunction(ExportError),
     RunFunction(RuntimeError),
+    /// The call was interrupted because it exceeded its fuel budget
+    OutOfFuel,
     InvalidArgumentType(),
     Encoding(Box<ErrorKind>),
 }
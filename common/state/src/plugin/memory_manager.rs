@@ -7,7 +7,7 @@ use specs::{
 use wasmer::{Function, Memory, Value};
 
 use common::{
-    comp::{Health, Player},
+    comp::{Energy, Group, Health, Inventory, Player, Pos, Stats},
     uid::{Uid, UidAllocator},
 };
 
@@ -18,6 +18,11 @@ pub struct EcsWorld<'a, 'b> {
     pub health: EcsComponentAccess<'a, 'b, Health>,
     pub uid: EcsComponentAccess<'a, 'b, Uid>,
     pub player: EcsComponentAccess<'a, 'b, Player>,
+    pub pos: EcsComponentAccess<'a, 'b, Pos>,
+    pub inventory: EcsComponentAccess<'a, 'b, Inventory>,
+    pub stats: EcsComponentAccess<'a, 'b, Stats>,
+    pub energy: EcsComponentAccess<'a, 'b, Energy>,
+    pub group: EcsComponentAccess<'a, 'b, Group>,
     pub uid_allocator: &'b Read<'a, UidAllocator>,
 }
 
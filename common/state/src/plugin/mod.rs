@@ -1,28 +1,40 @@
 pub mod errors;
 pub mod memory_manager;
 pub mod module;
+pub mod storage;
+pub mod timer;
 pub mod wasm_env;
+pub mod watcher;
 
-use common::assets::ASSETS_PATH;
+use common::{assets::ASSETS_PATH, cmd::PluginChatCommand, comp::AdminRole};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
     fs,
     io::Read,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
 };
 use tracing::{error, info};
 
-use plugin_api::Event;
+use plugin_api::{
+    event::{PluginLoadEvent, TickEvent, TimerEvent},
+    CommandRole, CommandSpec, Event, GameMode,
+};
 
 use self::{
     errors::PluginError,
     memory_manager::EcsWorld,
     module::{PluginModule, PreparedEventQuery},
+    storage::PluginStorage,
+    watcher::{is_plugin_file, PluginWatcher},
 };
 
 use rayon::prelude::*;
 
+pub use plugin_api::Action;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PluginData {
     name: String,
@@ -33,13 +45,19 @@ pub struct PluginData {
 #[derive(Clone)]
 pub struct Plugin {
     data: PluginData,
-    modules: Vec<PluginModule>,
+    /// The modules of the plugin, by their path in the plugin archive
+    modules: HashMap<PathBuf, PluginModule>,
     #[allow(dead_code)]
     files: HashMap<PathBuf, Vec<u8>>,
+    /// The file the plugin was loaded from, if any
+    path: Option<PathBuf>,
 }
 
 impl Plugin {
-    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, PluginError> {
+    pub fn from_reader<R: Read>(
+        mut reader: R,
+        storage: Arc<PluginStorage>,
+    ) -> Result<Self, PluginError> {
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf).map_err(PluginError::Io)?;
 
@@ -69,9 +87,16 @@ impl Plugin {
             .iter()
             .map(|path| {
                 let wasm_data = files.remove(path).ok_or(PluginError::NoSuchModule)?;
-                PluginModule::new(data.name.to_owned(), &wasm_data).map_err(|e| {
-                    PluginError::PluginModuleError(data.name.to_owned(), "<init>".to_owned(), e)
-                })
+                let module =
+                    PluginModule::new(data.name.to_owned(), &wasm_data, Arc::clone(&storage))
+                        .map_err(|e| {
+                            PluginError::PluginModuleError(
+                                data.name.to_owned(),
+                                "<init>".to_owned(),
+                                e,
+                            )
+                        })?;
+                Ok((path.clone(), module))
             })
             .collect::<Result<_, _>>()?;
 
@@ -79,9 +104,20 @@ impl Plugin {
             data,
             modules,
             files,
+            path: None,
         })
     }
 
+    pub fn from_file(path: &Path, storage: Arc<PluginStorage>) -> Result<Self, PluginError> {
+        info!("Loading plugin at {:?}", path);
+        let mut plugin =
+            Self::from_reader(fs::File::open(path).map_err(PluginError::Io)?, storage)?;
+        plugin.path = Some(path.to_owned());
+        Ok(plugin)
+    }
+
+    pub fn name(&self) -> &str { &self.data.name }
+
     pub fn execute_prepared<T>(
         &self,
         ecs: &EcsWorld,
@@ -91,7 +127,7 @@ impl Plugin {
         T: Event,
     {
         self.modules
-            .iter()
+            .values()
             .flat_map(|module| {
                 module.try_execute(ecs, event).map(|x| {
                     x.map_err(|e| {
@@ -105,11 +141,62 @@ impl Plugin {
             })
             .collect::<Result<Vec<_>, _>>()
     }
+
+    pub fn take_actions(&self) -> impl Iterator<Item = Action> + '_ {
+        self.modules
+            .values()
+            .flat_map(|module| module.take_actions())
+    }
+
+    /// The chat commands registered by the modules of this plugin
+    pub fn commands(&self) -> impl Iterator<Item = CommandSpec> + '_ {
+        self.modules.values().flat_map(|module| module.commands())
+    }
+
+    /// Call `on_timer` for the timers of each module which ran out
+    pub fn run_timers(&self, ecs: &EcsWorld, time: f64, now: Instant) {
+        for module in self.modules.values() {
+            for name in module.take_due_timers(time, now) {
+                let result = PreparedEventQuery::new(&TimerEvent { name }).and_then(|event| {
+                    module.try_execute(ecs, &event).transpose().map_err(|e| {
+                        PluginError::PluginModuleError(
+                            self.data.name.to_owned(),
+                            event.get_function_name().to_owned(),
+                            e,
+                        )
+                    })
+                });
+                if let Err(e) = result {
+                    error!(?e, "Failed to run plugin timer");
+                }
+            }
+        }
+    }
+
+    /// Carry the pending actions and timers of `old`, the plugin replaced by
+    /// this one, over so they aren't lost when the plugin is reloaded. Timers
+    /// stay with the module which set them, the actions of modules which were
+    /// removed are queued by any other module.
+    fn carry_over(&self, old: &Plugin) {
+        for (path, old_module) in &old.modules {
+            if let Some(module) = self.modules.get(path) {
+                module.carry_over(old_module);
+            } else if let Some(module) = self.modules.values().next() {
+                module.carry_over_actions(old_module.take_actions());
+            }
+        }
+    }
 }
 
 #[derive(Clone, Default)]
 pub struct PluginMgr {
     plugins: Vec<Plugin>,
+    /// The files of the plugins which were disabled, by plugin name
+    disabled: HashMap<String, PathBuf>,
+    /// The directory the plugins were loaded from
+    dir: Option<PathBuf>,
+    watcher: Option<Arc<PluginWatcher>>,
+    storage: Arc<PluginStorage>,
 }
 
 impl PluginMgr {
@@ -138,6 +225,22 @@ impl PluginMgr {
             .collect())
     }
 
+    /// The key/value storage of the plugins
+    pub fn storage(&self) -> &PluginStorage { &self.storage }
+
+    /// Take the actions emitted by all plugins which need to be applied to
+    /// the game state, along with the name of the plugin that emitted them
+    pub fn take_actions(&self) -> Vec<(String, Action)> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| {
+                plugin
+                    .take_actions()
+                    .map(move |action| (plugin.data.name.clone(), action))
+            })
+            .collect()
+    }
+
     pub fn execute_event<T>(
         &self,
         ecs: &EcsWorld,
@@ -149,27 +252,207 @@ impl PluginMgr {
         self.execute_prepared(ecs, &PreparedEventQuery::new(event)?)
     }
 
-    pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Self, PluginError> {
-        let plugins = fs::read_dir(path)
-            .map_err(PluginError::Io)?
-            .filter_map(|e| e.ok())
-            .map(|entry| {
-                if entry.file_type().map(|ft| ft.is_file()).unwrap_or(false)
-                    && entry
-                        .path()
-                        .file_name()
-                        .and_then(|n| n.to_str())
-                        .map(|s| s.ends_with(".plugin.tar"))
-                        .unwrap_or(false)
-                {
-                    info!("Loading plugin at {:?}", entry.path());
-                    Plugin::from_reader(fs::File::open(entry.path()).map_err(PluginError::Io)?)
-                        .map(Some)
-                } else {
-                    Ok(None)
+    /// Call `on_tick` and the timers of the plugins which ran out, `time` is
+    /// the game time in seconds. This is run by the server every tick.
+    pub fn tick(&self, ecs: &EcsWorld, time: f64, dt: f32) {
+        if let Err(e) = self.execute_event(ecs, &TickEvent { time, dt }) {
+            error!(?e, "Failed to run plugin tick");
+        }
+        let now = Instant::now();
+        for plugin in &self.plugins {
+            plugin.run_timers(ecs, time, now);
+        }
+    }
+
+    /// The chat commands registered by the loaded plugins. If several plugins
+    /// register the same command, the first plugin loaded wins.
+    pub fn commands(&self) -> Vec<PluginChatCommand> {
+        let mut commands: Vec<PluginChatCommand> = Vec::new();
+        for spec in self.plugins.iter().flat_map(|plugin| plugin.commands()) {
+            if commands.iter().all(|command| command.keyword != spec.name) {
+                commands.push(chat_command(spec));
+            }
+        }
+        commands
+    }
+
+    /// The names of the loaded plugins
+    pub fn plugin_names(&self) -> impl Iterator<Item = &str> {
+        self.plugins.iter().map(|plugin| plugin.name())
+    }
+
+    /// The names of the plugins which were disabled
+    pub fn disabled_plugin_names(&self) -> impl Iterator<Item = &str> {
+        self.disabled.keys().map(|name| name.as_str())
+    }
+
+    /// Start watching the plugin directory, changed plugins are then reloaded
+    /// by [`PluginMgr::reload_changed`]
+    pub fn watch(&mut self) -> Result<(), PluginError> {
+        let dir = self.dir.as_ref().ok_or(PluginError::NoPluginDir)?;
+        self.watcher = Some(Arc::new(
+            PluginWatcher::new(dir).map_err(PluginError::Watcher)?,
+        ));
+        Ok(())
+    }
+
+    /// Load, reload or unload the plugins whose file changed since the last
+    /// call. Plugins which fail to load are logged and kept as they were.
+    /// Returns whether any plugin was loaded or unloaded.
+    pub fn reload_changed(&mut self, ecs: &EcsWorld, game_mode: GameMode) -> bool {
+        let changed = match &self.watcher {
+            Some(watcher) => watcher.take_changed(),
+            None => return false,
+        };
+        let mut reloaded = false;
+        for path in changed {
+            // Disabled plugins stay disabled until they are enabled again
+            if self.disabled.values().any(|disabled| *disabled == path) {
+                continue;
+            }
+            let index = self
+                .plugins
+                .iter()
+                .position(|plugin| plugin.path.as_ref() == Some(&path));
+            if !path.exists() {
+                if let Some(index) = index {
+                    let plugin = self.plugins.remove(index);
+                    info!(
+                        "Unloaded plugin '{}' as its file was removed",
+                        plugin.name()
+                    );
+                    reloaded = true;
                 }
-            })
-            .filter_map(Result::transpose)
+                continue;
+            }
+            match self.load(&path, ecs, game_mode) {
+                Ok(plugin) => {
+                    info!("Hot reloaded plugin '{}'", plugin.name());
+                    match index {
+                        Some(index) => {
+                            plugin.carry_over(&self.plugins[index]);
+                            self.plugins[index] = plugin;
+                        },
+                        None => self.plugins.push(plugin),
+                    }
+                    reloaded = true;
+                },
+                Err(e) => error!(?e, ?path, "Failed to hot reload plugin"),
+            }
+        }
+        reloaded
+    }
+
+    /// Reload the plugin with the given name from its file
+    pub fn reload(
+        &mut self,
+        name: &str,
+        ecs: &EcsWorld,
+        game_mode: GameMode,
+    ) -> Result<(), PluginError> {
+        let index = self.position(name)?;
+        let path = self.plugins[index]
+            .path
+            .clone()
+            .ok_or(PluginError::NoPluginDir)?;
+        let plugin = self.load(&path, ecs, game_mode)?;
+        plugin.carry_over(&self.plugins[index]);
+        self.plugins[index] = plugin;
+        Ok(())
+    }
+
+    /// Reload every plugin from the plugin directory, loading the new files
+    /// and unloading the plugins whose file was removed. Plugins which fail to
+    /// load are logged and kept as they were. Returns the names of the loaded
+    /// plugins.
+    pub fn reload_all(
+        &mut self,
+        ecs: &EcsWorld,
+        game_mode: GameMode,
+    ) -> Result<Vec<String>, PluginError> {
+        let dir = self.dir.as_ref().ok_or(PluginError::NoPluginDir)?;
+        let mut plugins = Vec::new();
+        for path in plugin_files(dir)? {
+            if self.disabled.values().any(|disabled| *disabled == path) {
+                continue;
+            }
+            let old = self
+                .plugins
+                .iter()
+                .find(|plugin| plugin.path.as_ref() == Some(&path));
+            match self.load(&path, ecs, game_mode) {
+                Ok(plugin) => {
+                    if let Some(old) = old {
+                        plugin.carry_over(old);
+                    }
+                    plugins.push(plugin);
+                },
+                Err(e) => {
+                    error!(?e, ?path, "Failed to reload plugin");
+                    plugins.extend(old.cloned());
+                },
+            }
+        }
+        self.plugins = plugins;
+        Ok(self.plugin_names().map(|name| name.to_owned()).collect())
+    }
+
+    /// Unload a plugin until it is enabled again (or the game is restarted)
+    pub fn disable(&mut self, name: &str) -> Result<(), PluginError> {
+        let index = self.position(name)?;
+        let path = self.plugins[index]
+            .path
+            .clone()
+            .ok_or(PluginError::NoPluginDir)?;
+        let plugin = self.plugins.remove(index);
+        self.disabled.insert(plugin.data.name, path);
+        Ok(())
+    }
+
+    /// Load a plugin which was disabled
+    pub fn enable(
+        &mut self,
+        name: &str,
+        ecs: &EcsWorld,
+        game_mode: GameMode,
+    ) -> Result<(), PluginError> {
+        let path = self
+            .disabled
+            .get(name)
+            .ok_or_else(|| PluginError::NoSuchPlugin(name.to_owned()))?;
+        let plugin = self.load(path, ecs, game_mode)?;
+        self.disabled.remove(name);
+        self.plugins.push(plugin);
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Result<usize, PluginError> {
+        self.plugins
+            .iter()
+            .position(|plugin| plugin.name() == name)
+            .ok_or_else(|| PluginError::NoSuchPlugin(name.to_owned()))
+    }
+
+    /// Load the plugin file at `path` and call its `on_load`
+    fn load(
+        &self,
+        path: &Path,
+        ecs: &EcsWorld,
+        game_mode: GameMode,
+    ) -> Result<Plugin, PluginError> {
+        let plugin = Plugin::from_file(path, Arc::clone(&self.storage))?;
+        plugin.execute_prepared(
+            ecs,
+            &PreparedEventQuery::new(&PluginLoadEvent { game_mode })?,
+        )?;
+        Ok(plugin)
+    }
+
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> Result<Self, PluginError> {
+        let storage = Arc::new(PluginStorage::default());
+        let plugins = plugin_files(path.as_ref())?
+            .into_iter()
+            .map(|path| Plugin::from_file(&path, Arc::clone(&storage)))
             .inspect(|p| {
                 let _ = p.as_ref().map_err(|e| error!(?e, "Failed to load plugin"));
             })
@@ -183,6 +466,35 @@ impl PluginMgr {
             );
         }
 
-        Ok(Self { plugins })
+        Ok(Self {
+            plugins,
+            disabled: HashMap::new(),
+            dir: Some(path.as_ref().to_owned()),
+            watcher: None,
+            storage,
+        })
     }
 }
+
+fn chat_command(spec: CommandSpec) -> PluginChatCommand {
+    PluginChatCommand {
+        keyword: spec.name,
+        description: spec.description,
+        args: spec.args,
+        needs_role: spec.needs_role.map(|role| match role {
+            CommandRole::Moderator => AdminRole::Moderator,
+            CommandRole::Admin => AdminRole::Admin,
+        }),
+    }
+}
+
+/// The plugin files in `dir`
+fn plugin_files(dir: &Path) -> Result<Vec<PathBuf>, PluginError> {
+    Ok(fs::read_dir(dir)
+        .map_err(PluginError::Io)?
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+        .map(|entry| entry.path())
+        .filter(|path| is_plugin_file(path))
+        .collect())
+}
@@ -3,18 +3,36 @@ use std::{
     convert::TryInto,
     marker::PhantomData,
     sync::{Arc, Mutex},
+    time::Instant,
 };
 
-use specs::saveload::MarkerAllocator;
-use wasmer::{imports, Cranelift, Function, Instance, Memory, Module, Store, Universal, Value};
+use specs::{saveload::MarkerAllocator, Component, Entity, Join};
+use wasmer::{
+    imports, wasmparser::Operator, CompilerConfig, Cranelift, Function, Instance, Memory, Module,
+    Store, Universal, Value,
+};
+use wasmer_middlewares::{
+    metering::{get_remaining_points, set_remaining_points, MeteringPoints},
+    Metering,
+};
 
 use super::{
     errors::{PluginError, PluginModuleError},
-    memory_manager::{self, EcsAccessManager, EcsWorld, MemoryManager},
+    memory_manager::{self, EcsAccessManager, EcsComponentAccess, EcsWorld, MemoryManager},
+    storage::PluginStorage,
+    timer::Timers,
     wasm_env::HostFunctionEnvironement,
 };
 
-use plugin_api::{Action, EcsAccessError, Event, Retrieve, RetrieveError, RetrieveResult};
+use common::{cmd::ServerChatCommand, comp::group, uid::Uid};
+use plugin_api::{
+    Action, CommandSpec, EcsAccessError, EntityStats, Event, ItemStack, Retrieve, RetrieveError,
+    RetrieveResult,
+};
+
+/// The number of WASM operators a single call into a plugin may execute before
+/// it is interrupted, so a misbehaving plugin cannot stall the game
+pub const FUEL_BUDGET: u64 = 10_000_000;
 
 #[derive(Clone)]
 /// This structure represent the WASM State of the plugin.
@@ -22,6 +40,9 @@ pub struct PluginModule {
     ecs: Arc<EcsAccessManager>,
     wasm_state: Arc<Mutex<Instance>>,
     memory_manager: Arc<MemoryManager>,
+    pending_actions: Arc<Mutex<Vec<Action>>>,
+    timers: Arc<Mutex<Timers>>,
+    commands: Arc<Mutex<Vec<CommandSpec>>>,
     events: HashSet<String>,
     allocator: Function,
     memory: Memory,
@@ -31,9 +52,16 @@ pub struct PluginModule {
 
 impl PluginModule {
     /// This function takes bytes from a WASM File and compile them
-    pub fn new(name: String, wasm_data: &[u8]) -> Result<Self, PluginModuleError> {
-        // This is creating the engine is this case a JIT based on Cranelift
-        let engine = Universal::new(Cranelift::default()).engine();
+    pub fn new(
+        name: String,
+        wasm_data: &[u8],
+        storage: Arc<PluginStorage>,
+    ) -> Result<Self, PluginModuleError> {
+        // This is creating the engine is this case a JIT based on Cranelift, metering
+        // counts the operators executed by the plugin to enforce the fuel budget
+        let mut compiler = Cranelift::default();
+        compiler.push_middleware(Arc::new(Metering::new(FUEL_BUDGET, |_: &Operator| 1)));
+        let engine = Universal::new(compiler).engine();
         // We are creating an enironnement
         let store = Store::new(&engine);
         // We are compiling the WASM file in the previously generated environement
@@ -41,7 +69,7 @@ impl PluginModule {
 
         // This is the function imported into the wasm environement
         fn raw_emit_actions(env: &HostFunctionEnvironement, ptr: i64, len: i64) {
-            handle_actions(match env.read_data(from_i64(ptr), from_i64(len)) {
+            handle_actions(env, match env.read_data(from_i64(ptr), from_i64(len)) {
                 Ok(e) => e,
                 Err(e) => {
                     tracing::error!(?e, "Can't decode action");
@@ -52,7 +80,7 @@ impl PluginModule {
 
         fn raw_retrieve_action(env: &HostFunctionEnvironement, ptr: i64, len: i64) -> i64 {
             let out = match env.read_data(from_i64(ptr), from_i64(len)) {
-                Ok(data) => retrieve_action(&env.ecs, data),
+                Ok(data) => retrieve_action(env, data),
                 Err(e) => Err(RetrieveError::BincodeError(e.to_string())),
             };
 
@@ -67,12 +95,15 @@ impl PluginModule {
 
         let ecs = Arc::new(EcsAccessManager::default());
         let memory_manager = Arc::new(MemoryManager::default());
+        let pending_actions = Arc::new(Mutex::new(Vec::new()));
+        let timers = Arc::new(Mutex::new(Timers::default()));
+        let commands = Arc::new(Mutex::new(Vec::new()));
 
         // Create an import object.
         let import_object = imports! {
             "env" => {
-                "raw_emit_actions" => Function::new_native_with_env(&store, HostFunctionEnvironement::new(name.clone(), ecs.clone(),memory_manager.clone()), raw_emit_actions),
-                "raw_retrieve_action" => Function::new_native_with_env(&store, HostFunctionEnvironement::new(name.clone(), ecs.clone(),memory_manager.clone()), raw_retrieve_action),
+                "raw_emit_actions" => Function::new_native_with_env(&store, HostFunctionEnvironement::new(name.clone(), ecs.clone(), memory_manager.clone(), pending_actions.clone(), storage.clone(), timers.clone(), commands.clone()), raw_emit_actions),
+                "raw_retrieve_action" => Function::new_native_with_env(&store, HostFunctionEnvironement::new(name.clone(), ecs.clone(), memory_manager.clone(), pending_actions.clone(), storage.clone(), timers.clone(), commands.clone()), raw_retrieve_action),
                 "dbg" => Function::new_native(&store, dbg),
             }
         };
@@ -82,6 +113,9 @@ impl PluginModule {
             .map_err(|err| PluginModuleError::InstantiationError(Box::new(err)))?;
         Ok(Self {
             memory_manager,
+            pending_actions,
+            timers,
+            commands,
             ecs,
             memory: instance
                 .exports
@@ -126,6 +160,37 @@ impl PluginModule {
         };
         Some(bincode::deserialize(&bytes).map_err(PluginModuleError::Encoding))
     }
+
+    /// Take all the actions emitted by this module since the last call, in
+    /// the order they were emitted
+    pub fn take_actions(&self) -> Vec<Action> {
+        std::mem::take(&mut *self.pending_actions.lock().unwrap())
+    }
+
+    /// Returns the names of the timers of this module which ran out, `time`
+    /// is the game time in seconds
+    pub fn take_due_timers(&self, time: f64, now: Instant) -> Vec<String> {
+        self.timers.lock().unwrap().take_due(time, now)
+    }
+
+    /// The chat commands registered by this module
+    pub fn commands(&self) -> Vec<CommandSpec> { self.commands.lock().unwrap().clone() }
+
+    /// Move the pending actions and timers of `old`, the module of a previous
+    /// load of the plugin, to this module
+    pub fn carry_over(&self, old: &PluginModule) {
+        self.carry_over_actions(old.take_actions());
+        let old_timers = std::mem::take(&mut *old.timers.lock().unwrap());
+        self.timers.lock().unwrap().carry_over(old_timers);
+    }
+
+    /// Queue `actions` in front of the actions emitted by this module, as they
+    /// were emitted before
+    pub fn carry_over_actions(&self, mut actions: Vec<Action>) {
+        let mut pending_actions = self.pending_actions.lock().unwrap();
+        actions.append(&mut pending_actions);
+        *pending_actions = actions;
+    }
 }
 
 /// This structure represent a Pre-encoded event object (Useful to avoid
@@ -184,6 +249,9 @@ fn execute_raw(
     event_name: &str,
     bytes: &[u8],
 ) -> Result<Vec<u8>, PluginModuleError> {
+    // Every call gets the full fuel budget, including the allocations it needs
+    set_remaining_points(instance, FUEL_BUDGET);
+
     // This write into memory `bytes` using allocation if necessary returning a
     // pointer and a length
 
@@ -203,7 +271,10 @@ fn execute_raw(
 
     let function_result = func
         .call(&[Value::I64(to_i64(mem_position)), Value::I64(to_i64(len))])
-        .map_err(PluginModuleError::RunFunction)?;
+        .map_err(|e| match get_remaining_points(instance) {
+            MeteringPoints::Exhausted => PluginModuleError::OutOfFuel,
+            MeteringPoints::Remaining(_) => PluginModuleError::RunFunction(e),
+        })?;
 
     // Waiting for `multi-value` to be added to LLVM. So we encode a pointer to a
     // u128 that represent [u64; 2]
@@ -228,62 +299,139 @@ fn execute_raw(
 }
 
 fn retrieve_action(
-    ecs: &EcsAccessManager,
+    env: &HostFunctionEnvironement,
     action: Retrieve,
 ) -> Result<RetrieveResult, RetrieveError> {
+    // The ECS is only looked up by the actions needing it, as the storage is
+    // available even when the ECS isn't
+    // Safety: No reference is leaked out the function so it is safe.
+    let world = || unsafe {
+        env.ecs.get().ok_or(RetrieveError::EcsAccessError(
+            EcsAccessError::EcsPointerNotAvailable,
+        ))
+    };
     match action {
+        Retrieve::GetStorage(key) => {
+            Ok(RetrieveResult::GetStorage(env.storage.get(&env.name, &key)))
+        },
+        Retrieve::ListStorageKeys => {
+            Ok(RetrieveResult::ListStorageKeys(env.storage.keys(&env.name)))
+        },
+        Retrieve::SetStorage { key, value } => env
+            .storage
+            .set(&env.name, key, value)
+            .map(|()| RetrieveResult::SetStorage)
+            .map_err(|e| RetrieveError::OtherError(e.to_string())),
         Retrieve::GetPlayerName(e) => {
-            // Safety: No reference is leaked out the function so it is safe.
-            let world = unsafe {
-                ecs.get().ok_or(RetrieveError::EcsAccessError(
-                    EcsAccessError::EcsPointerNotAvailable,
-                ))?
-            };
-            let player = world.uid_allocator.retrieve_entity_internal(e.0).ok_or(
-                RetrieveError::EcsAccessError(EcsAccessError::EcsEntityNotFound(e)),
-            )?;
-
+            let world = world()?;
+            let player = find_entity(world, e)?;
             Ok(RetrieveResult::GetPlayerName(
-                world
-                    .player
-                    .get(player)
-                    .ok_or_else(|| {
-                        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(
-                            e,
-                            "Player".to_owned(),
-                        ))
-                    })?
+                find_component(&world.player, player, e, "Player")?
                     .alias
                     .to_owned(),
             ))
         },
         Retrieve::GetEntityHealth(e) => {
-            // Safety: No reference is leaked out the function so it is safe.
-            let world = unsafe {
-                ecs.get().ok_or(RetrieveError::EcsAccessError(
-                    EcsAccessError::EcsPointerNotAvailable,
-                ))?
-            };
-            let player = world.uid_allocator.retrieve_entity_internal(e.0).ok_or(
-                RetrieveError::EcsAccessError(EcsAccessError::EcsEntityNotFound(e)),
-            )?;
+            let world = world()?;
+            let player = find_entity(world, e)?;
             Ok(RetrieveResult::GetEntityHealth(
+                find_component(&world.health, player, e, "Health")?.clone(),
+            ))
+        },
+        Retrieve::GetEntityPosition(e) => {
+            let world = world()?;
+            let entity = find_entity(world, e)?;
+            Ok(RetrieveResult::GetEntityPosition(
+                find_component(&world.pos, entity, e, "Pos")?.0,
+            ))
+        },
+        Retrieve::GetEntityInventory(e) => {
+            let world = world()?;
+            let entity = find_entity(world, e)?;
+            Ok(RetrieveResult::GetEntityInventory(
+                find_component(&world.inventory, entity, e, "Inventory")?
+                    .slots()
+                    .flatten()
+                    .map(|item| ItemStack {
+                        item: item.persistence_item_id().to_owned(),
+                        amount: item.amount(),
+                    })
+                    .collect(),
+            ))
+        },
+        Retrieve::GetEntityStats(e) => {
+            let world = world()?;
+            let entity = find_entity(world, e)?;
+            let stats = find_component(&world.stats, entity, e, "Stats")?;
+            let energy = find_component(&world.energy, entity, e, "Energy")?;
+            Ok(RetrieveResult::GetEntityStats(EntityStats {
+                name: stats.name.clone(),
+                energy: energy.current(),
+                maximum_energy: energy.maximum(),
+                damage_reduction: stats.damage_reduction,
+                move_speed_modifier: stats.move_speed_modifier,
+                attack_speed_modifier: stats.attack_speed_modifier,
+            }))
+        },
+        Retrieve::GetPlayerGroup(e) => {
+            let world = world()?;
+            let entity = find_entity(world, e)?;
+            // NPC and enemy groups are not real groups
+            let group = world
+                .group
+                .get(entity)
+                .filter(|group| **group != group::ENEMY && **group != group::NPC);
+            Ok(RetrieveResult::GetPlayerGroup(group.map(|group| {
                 world
-                    .health
-                    .get(player)
-                    .ok_or_else(|| {
-                        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(
-                            e,
-                            "Health".to_owned(),
-                        ))
-                    })?
-                    .clone(),
+                    .entities
+                    .join()
+                    .filter(|member| world.group.get(*member) == Some(group))
+                    .filter_map(|member| world.uid.get(member).copied())
+                    .collect()
+            })))
+        },
+        Retrieve::GetNearbyEntities(pos, radius) => {
+            let world = world()?;
+            Ok(RetrieveResult::GetNearbyEntities(
+                world
+                    .entities
+                    .join()
+                    .filter(|entity| {
+                        world
+                            .pos
+                            .get(*entity)
+                            .map_or(false, |p| p.0.distance_squared(pos) <= radius.powi(2))
+                    })
+                    .filter_map(|entity| world.uid.get(entity).copied())
+                    .collect(),
             ))
         },
     }
 }
 
-fn handle_actions(actions: Vec<Action>) {
+fn find_entity(world: &EcsWorld, uid: Uid) -> Result<Entity, RetrieveError> {
+    world
+        .uid_allocator
+        .retrieve_entity_internal(uid.0)
+        .ok_or(RetrieveError::EcsAccessError(
+            EcsAccessError::EcsEntityNotFound(uid),
+        ))
+}
+
+fn find_component<'c, T: Component>(
+    storage: &'c EcsComponentAccess<T>,
+    entity: Entity,
+    uid: Uid,
+    name: &str,
+) -> Result<&'c T, RetrieveError> {
+    storage.get(entity).ok_or_else(|| {
+        RetrieveError::EcsAccessError(EcsAccessError::EcsComponentNotFound(uid, name.to_owned()))
+    })
+}
+
+/// Actions which don't need access to the game state are handled immediately,
+/// the others are queued to be applied by the server on its next tick.
+fn handle_actions(env: &HostFunctionEnvironement, actions: Vec<Action>) {
     for action in actions {
         match action {
             Action::ServerClose => {
@@ -293,12 +441,112 @@ fn handle_actions(actions: Vec<Action>) {
             Action::Print(e) => {
                 tracing::info!("{}", e);
             },
-            Action::PlayerSendMessage(a, b) => {
-                tracing::info!("SendMessage {} -> {}", a, b);
-            },
-            Action::KillEntity(e) => {
-                tracing::info!("Kill Entity {}", e);
+            Action::DeleteStorage(key) => env.storage.delete(&env.name, key),
+            Action::SetTimer {
+                name,
+                delay,
+                repeat,
+                clock,
+            } => env.timers.lock().unwrap().set(name, delay, repeat, clock),
+            Action::CancelTimer(name) => env.timers.lock().unwrap().cancel(&name),
+            Action::RegisterCommand(spec) => {
+                if spec.name.parse::<ServerChatCommand>().is_ok() {
+                    tracing::warn!(
+                        "Plugin '{}' can't register '/{}' as it is a built-in command",
+                        env.name,
+                        spec.name
+                    );
+                    continue;
+                }
+                let mut commands = env.commands.lock().unwrap();
+                commands.retain(|command| command.name != spec.name);
+                commands.push(spec);
             },
+            action => env.pending_actions.lock().unwrap().push(action),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::Plugin;
+    use plugin_api::TimerClock;
+    use std::{path::Path, time::Duration};
+
+    /// The smallest module the runtime accepts, it doesn't handle any event
+    const EMPTY_MODULE: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "wasm_prepare_buffer") (param i32) (result i64)
+                i64.const 0))
+    "#;
+
+    fn plugin(modules: &[&str], storage: &Arc<PluginStorage>) -> Plugin {
+        let mut archive = tar::Builder::new(Vec::new());
+        let mut append = |path: &str, data: &[u8]| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            archive.append_data(&mut header, path, data).unwrap();
+        };
+        let config = format!(
+            "name = \"test\"\nmodules = {:?}\ndependencies = []\n",
+            modules
+        );
+        append("plugin.toml", config.as_bytes());
+        for module in modules {
+            append(module, EMPTY_MODULE.as_bytes());
         }
+        let archive = archive.into_inner().unwrap();
+        Plugin::from_reader(&*archive, Arc::clone(storage)).unwrap()
+    }
+
+    fn print(module: &PluginModule, msg: &str) {
+        module
+            .pending_actions
+            .lock()
+            .unwrap()
+            .push(Action::Print(msg.to_owned()));
+    }
+
+    #[test]
+    fn reload_keeps_actions_and_timers() {
+        let storage = Arc::new(PluginStorage::default());
+        let old = plugin(&["a.wasm", "b.wasm"], &storage);
+        let old_a = &old.modules[Path::new("a.wasm")];
+        print(old_a, "a");
+        old_a.timers.lock().unwrap().set(
+            "timer".to_owned(),
+            Duration::ZERO,
+            false,
+            TimerClock::Game,
+        );
+        print(&old.modules[Path::new("b.wasm")], "b");
+
+        // The new version of the plugin dropped `b.wasm` and emitted an action
+        // when it was loaded
+        let new = plugin(&["a.wasm"], &storage);
+        let new_a = &new.modules[Path::new("a.wasm")];
+        print(new_a, "new");
+        new.carry_over(&old);
+
+        let mut printed = new
+            .take_actions()
+            .map(|action| match action {
+                Action::Print(msg) => msg,
+                action => panic!("Unexpected action {:?}", action),
+            })
+            .collect::<Vec<_>>();
+        // The actions emitted before the reload come first
+        assert_eq!(printed.pop().as_deref(), Some("new"));
+        printed.sort();
+        assert_eq!(printed, vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(old.take_actions().count(), 0);
+
+        let now = Instant::now();
+        assert!(new_a.take_due_timers(0.0, now).is_empty());
+        assert_eq!(new_a.take_due_timers(0.0, now), vec!["timer".to_owned()]);
     }
 }
@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// The maximum length in bytes of a storage key
+pub const MAX_KEY_LEN: usize = 256;
+/// The maximum length in bytes of a storage value
+pub const MAX_VALUE_LEN: usize = 64 * 1024;
+/// The maximum total length in bytes of the keys and values stored by a
+/// plugin
+pub const MAX_PLUGIN_STORAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// A modification made by a plugin to its storage
+#[derive(Clone, Debug)]
+pub enum StorageChange {
+    Set {
+        plugin: String,
+        key: String,
+        value: Vec<u8>,
+    },
+    Delete {
+        plugin: String,
+        key: String,
+    },
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    KeyTooLong(usize),
+    ValueTooLong(usize),
+    /// Storing the value would make the storage of the plugin this many bytes
+    /// long
+    StorageFull(usize),
+}
+
+impl core::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StorageError::KeyTooLong(len) => write!(
+                f,
+                "Storage key is {} bytes long, the maximum is {}",
+                len, MAX_KEY_LEN
+            ),
+            StorageError::ValueTooLong(len) => write!(
+                f,
+                "Storage value is {} bytes long, the maximum is {}",
+                len, MAX_VALUE_LEN
+            ),
+            StorageError::StorageFull(len) => write!(
+                f,
+                "Storage would be {} bytes long, the maximum for a plugin is {}",
+                len, MAX_PLUGIN_STORAGE_LEN
+            ),
+        }
+    }
+}
+
+/// Key/value storage shared by all the plugins, each plugin can only access
+/// the keys under its own name.
+///
+/// The data is kept in memory so plugins can read it synchronously, the owner
+/// of the storage (the server) is responsible for loading the persisted data
+/// with [`PluginStorage::load`] and persisting the changes returned by
+/// [`PluginStorage::take_changes`].
+#[derive(Default)]
+pub struct PluginStorage {
+    data: Mutex<HashMap<String, PluginEntries>>,
+    /// The pending change of each `(plugin, key)`, `None` if the key was
+    /// deleted. Only the last change of a key is kept, so this can't grow
+    /// beyond the keys the plugins stored.
+    changes: Mutex<HashMap<(String, String), Option<Vec<u8>>>>,
+    /// Changes are only recorded once the storage has been loaded, as nothing
+    /// would take them otherwise
+    persistent: AtomicBool,
+}
+
+#[derive(Default)]
+struct PluginEntries {
+    entries: HashMap<String, Vec<u8>>,
+    /// Total length in bytes of the keys and values
+    len: usize,
+}
+
+impl PluginEntries {
+    fn insert(&mut self, key: String, value: Vec<u8>) {
+        let key_len = key.len();
+        self.len += key_len + value.len();
+        if let Some(old) = self.entries.insert(key, value) {
+            self.len -= key_len + old.len();
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Vec<u8>> {
+        let removed = self.entries.remove(key)?;
+        self.len -= key.len() + removed.len();
+        Some(removed)
+    }
+}
+
+impl PluginStorage {
+    /// Replace the content of the storage with the persisted entries, given
+    /// as `(plugin, key, value)`, and start recording changes
+    pub fn load(&self, entries: impl IntoIterator<Item = (String, String, Vec<u8>)>) {
+        let mut data = self.data.lock().unwrap();
+        data.clear();
+        for (plugin, key, value) in entries {
+            data.entry(plugin).or_default().insert(key, value);
+        }
+        self.persistent.store(true, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, plugin: &str, key: &str) -> Option<Vec<u8>> {
+        self.data
+            .lock()
+            .unwrap()
+            .get(plugin)
+            .and_then(|storage| storage.entries.get(key))
+            .cloned()
+    }
+
+    /// The keys stored by `plugin`, sorted
+    pub fn keys(&self, plugin: &str) -> Vec<String> {
+        let mut keys = self
+            .data
+            .lock()
+            .unwrap()
+            .get(plugin)
+            .map(|storage| storage.entries.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        keys.sort_unstable();
+        keys
+    }
+
+    pub fn set(&self, plugin: &str, key: String, value: Vec<u8>) -> Result<(), StorageError> {
+        if key.len() > MAX_KEY_LEN {
+            return Err(StorageError::KeyTooLong(key.len()));
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(StorageError::ValueTooLong(value.len()));
+        }
+        {
+            let mut data = self.data.lock().unwrap();
+            let entries = data.entry(plugin.to_owned()).or_default();
+            let replaced = entries
+                .entries
+                .get(&key)
+                .map_or(0, |old| key.len() + old.len());
+            let len = entries.len - replaced + key.len() + value.len();
+            if len > MAX_PLUGIN_STORAGE_LEN {
+                return Err(StorageError::StorageFull(len));
+            }
+            entries.insert(key.clone(), value.clone());
+        }
+        self.record(plugin, key, Some(value));
+        Ok(())
+    }
+
+    pub fn delete(&self, plugin: &str, key: String) {
+        let removed = self
+            .data
+            .lock()
+            .unwrap()
+            .get_mut(plugin)
+            .and_then(|entries| entries.remove(&key))
+            .is_some();
+        if removed {
+            self.record(plugin, key, None);
+        }
+    }
+
+    /// Take the changes made since the last call, only the last one of each
+    /// key is returned
+    pub fn take_changes(&self) -> Vec<StorageChange> {
+        std::mem::take(&mut *self.changes.lock().unwrap())
+            .into_iter()
+            .map(|((plugin, key), value)| match value {
+                Some(value) => StorageChange::Set { plugin, key, value },
+                None => StorageChange::Delete { plugin, key },
+            })
+            .collect()
+    }
+
+    fn record(&self, plugin: &str, key: String, value: Option<Vec<u8>>) {
+        if self.persistent.load(Ordering::Relaxed) {
+            self.changes
+                .lock()
+                .unwrap()
+                .insert((plugin.to_owned(), key), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_is_scoped_per_plugin() {
+        let storage = PluginStorage::default();
+        storage.set("a", "key".to_owned(), vec![1]).unwrap();
+        storage.set("b", "key".to_owned(), vec![2]).unwrap();
+        assert_eq!(storage.get("a", "key"), Some(vec![1]));
+        assert_eq!(storage.get("b", "key"), Some(vec![2]));
+        storage.delete("a", "key".to_owned());
+        assert_eq!(storage.get("a", "key"), None);
+        assert_eq!(storage.keys("b"), vec!["key".to_owned()]);
+    }
+
+    #[test]
+    fn changes_are_recorded_once_loaded() {
+        let storage = PluginStorage::default();
+        storage.set("a", "before".to_owned(), vec![]).unwrap();
+        assert!(storage.take_changes().is_empty());
+
+        storage.load(vec![("a".to_owned(), "loaded".to_owned(), vec![0])]);
+        assert_eq!(storage.get("a", "before"), None);
+        assert_eq!(storage.get("a", "loaded"), Some(vec![0]));
+        storage.set("a", "after".to_owned(), vec![1]).unwrap();
+        // Deleting a missing key isn't a change
+        storage.delete("a", "missing".to_owned());
+        storage.delete("a", "loaded".to_owned());
+        // Only the last change of a key is kept
+        storage.set("a", "deleted".to_owned(), vec![2]).unwrap();
+        storage.delete("a", "deleted".to_owned());
+        let mut changes = storage
+            .take_changes()
+            .into_iter()
+            .map(|change| match change {
+                StorageChange::Set { key, value, .. } => (key, Some(value)),
+                StorageChange::Delete { key, .. } => (key, None),
+            })
+            .collect::<Vec<_>>();
+        changes.sort();
+        assert_eq!(changes, vec![
+            ("after".to_owned(), Some(vec![1])),
+            ("deleted".to_owned(), None),
+            ("loaded".to_owned(), None),
+        ]);
+        assert!(storage.take_changes().is_empty());
+    }
+
+    #[test]
+    fn storage_is_bounded_per_plugin() {
+        let storage = PluginStorage::default();
+        let value = vec![0; MAX_VALUE_LEN];
+        let keys = MAX_PLUGIN_STORAGE_LEN / (MAX_VALUE_LEN + 8);
+        for i in 0..keys {
+            storage
+                .set("a", format!("{:08}", i), value.clone())
+                .unwrap();
+        }
+        assert!(matches!(
+            storage.set("a", "full".to_owned(), value.clone()),
+            Err(StorageError::StorageFull(_))
+        ));
+        // Deleting makes room again
+        storage.delete("a", format!("{:08}", 1));
+        storage.set("a", "full".to_owned(), value.clone()).unwrap();
+        // Replacing a value only counts the difference, and other plugins have their
+        // own storage
+        storage
+            .set("a", format!("{:08}", 0), value.clone())
+            .unwrap();
+        storage.set("b", "key".to_owned(), value).unwrap();
+    }
+}
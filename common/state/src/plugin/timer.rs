@@ -0,0 +1,141 @@
+use plugin_api::TimerClock;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug)]
+enum Deadline {
+    /// The game time in seconds
+    Game(f64),
+    Real(Instant),
+}
+
+#[derive(Debug)]
+struct Timer {
+    name: String,
+    delay: Duration,
+    repeat: bool,
+    clock: TimerClock,
+    /// `None` until the timer is picked up by the first tick after it was set
+    deadline: Option<Deadline>,
+}
+
+/// The timers set by a plugin module
+#[derive(Debug, Default)]
+pub struct Timers {
+    timers: Vec<Timer>,
+}
+
+impl Timers {
+    /// Set a timer, replacing the timer with the same name if any
+    pub fn set(&mut self, name: String, delay: Duration, repeat: bool, clock: TimerClock) {
+        self.cancel(&name);
+        self.timers.push(Timer {
+            name,
+            delay,
+            repeat,
+            clock,
+            deadline: None,
+        });
+    }
+
+    pub fn cancel(&mut self, name: &str) { self.timers.retain(|timer| timer.name != name); }
+
+    /// Keep the timers of `old`, the timers of a previous load of the module,
+    /// which weren't set again since
+    pub fn carry_over(&mut self, old: Timers) {
+        for timer in old.timers {
+            if self.timers.iter().all(|t| t.name != timer.name) {
+                self.timers.push(timer);
+            }
+        }
+    }
+
+    /// Returns the names of the timers which ran out, repeating timers are
+    /// rescheduled and the others are removed
+    pub fn take_due(&mut self, time: f64, now: Instant) -> Vec<String> {
+        let mut due = Vec::new();
+        self.timers.retain_mut(|timer| {
+            let schedule = |timer: &Timer| match timer.clock {
+                TimerClock::Game => Deadline::Game(time + timer.delay.as_secs_f64()),
+                TimerClock::Real => Deadline::Real(now + timer.delay),
+            };
+            let ran_out = match timer.deadline {
+                None => {
+                    timer.deadline = Some(schedule(timer));
+                    false
+                },
+                Some(Deadline::Game(deadline)) => time >= deadline,
+                Some(Deadline::Real(deadline)) => now >= deadline,
+            };
+            if !ran_out {
+                return true;
+            }
+            due.push(timer.name.clone());
+            // Rescheduling from the current time rather than the deadline avoids
+            // firing a burst of calls after a long tick
+            timer.deadline = Some(schedule(timer));
+            timer.repeat
+        });
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timers_run_out_and_repeat() {
+        let mut timers = Timers::default();
+        let now = Instant::now();
+        timers.set(
+            "once".to_owned(),
+            Duration::from_secs(1),
+            false,
+            TimerClock::Game,
+        );
+        timers.set(
+            "repeat".to_owned(),
+            Duration::from_secs(2),
+            true,
+            TimerClock::Game,
+        );
+        assert!(timers.take_due(0.0, now).is_empty());
+        assert_eq!(timers.take_due(1.0, now), vec!["once".to_owned()]);
+        assert_eq!(timers.take_due(2.0, now), vec!["repeat".to_owned()]);
+        assert!(timers.take_due(3.0, now).is_empty());
+        assert_eq!(timers.take_due(4.0, now), vec!["repeat".to_owned()]);
+        timers.cancel("repeat");
+        assert!(timers.take_due(10.0, now).is_empty());
+    }
+
+    #[test]
+    fn timers_carry_over() {
+        let mut old = Timers::default();
+        let now = Instant::now();
+        old.set(
+            "a".to_owned(),
+            Duration::from_secs(1),
+            false,
+            TimerClock::Game,
+        );
+        old.set(
+            "b".to_owned(),
+            Duration::from_secs(1),
+            false,
+            TimerClock::Game,
+        );
+        assert!(old.take_due(0.0, now).is_empty());
+
+        // The timers set again take precedence over the old ones
+        let mut timers = Timers::default();
+        timers.set(
+            "b".to_owned(),
+            Duration::from_secs(5),
+            false,
+            TimerClock::Game,
+        );
+        timers.carry_over(old);
+        assert_eq!(timers.take_due(1.0, now), vec!["a".to_owned()]);
+        assert_eq!(timers.take_due(6.0, now), vec!["b".to_owned()]);
+    }
+}
@@ -1,4 +1,6 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+
+use plugin_api::{Action, CommandSpec};
 
 use serde::{de::DeserializeOwned, Serialize};
 use wasmer::{Function, HostEnvInitError, Instance, LazyInit, Memory, WasmerEnv};
@@ -6,6 +8,8 @@ use wasmer::{Function, HostEnvInitError, Instance, LazyInit, Memory, WasmerEnv};
 use super::{
     errors::PluginModuleError,
     memory_manager::{self, EcsAccessManager, MemoryManager},
+    storage::PluginStorage,
+    timer::Timers,
 };
 
 #[derive(Clone)]
@@ -17,7 +21,11 @@ pub struct HostFunctionEnvironement {
     pub allocator: LazyInit<Function>, // Linked to: wasm_prepare_buffer
     pub memory_manager: Arc<MemoryManager>, /* This object represent the current buffer size and
                                    * pointer */
-    pub name: String, // This represent the plugin name
+    pub name: String,                             // This represent the plugin name
+    pub pending_actions: Arc<Mutex<Vec<Action>>>, // Actions waiting for the server
+    pub storage: Arc<PluginStorage>,              // Key/value storage shared by all plugins
+    pub timers: Arc<Mutex<Timers>>,               // Timers set by this module
+    pub commands: Arc<Mutex<Vec<CommandSpec>>>,   // Chat commands registered by this module
 }
 
 impl HostFunctionEnvironement {
@@ -25,9 +33,17 @@ impl HostFunctionEnvironement {
         name: String,
         ecs: Arc<EcsAccessManager>,
         memory_manager: Arc<MemoryManager>,
+        pending_actions: Arc<Mutex<Vec<Action>>>,
+        storage: Arc<PluginStorage>,
+        timers: Arc<Mutex<Timers>>,
+        commands: Arc<Mutex<Vec<CommandSpec>>>,
     ) -> Self {
         Self {
             memory_manager,
+            pending_actions,
+            storage,
+            timers,
+            commands,
             ecs,
             allocator: LazyInit::new(),
             memory: LazyInit::new(),
@@ -0,0 +1,82 @@
+use notify::{recommended_watcher, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{mpsc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::error;
+
+/// How long a plugin file must stay untouched before it is reloaded, so
+/// partially written files aren't loaded
+const DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Watches the plugin directory for plugin files which are added, modified or
+/// removed
+pub struct PluginWatcher {
+    _watcher: Mutex<RecommendedWatcher>,
+    changes: Mutex<mpsc::Receiver<PathBuf>>,
+    /// The changed files, with the time of their last change
+    pending: Mutex<HashMap<PathBuf, Instant>>,
+}
+
+impl PluginWatcher {
+    pub fn new(dir: &Path) -> notify::Result<Self> {
+        let (change_send, change_recv) = mpsc::channel();
+        let mut watcher =
+            recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+                Ok(event) => {
+                    if matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    ) {
+                        event
+                            .paths
+                            .into_iter()
+                            .filter(|path| is_plugin_file(path))
+                            .for_each(|path| {
+                                let _ = change_send.send(path);
+                            });
+                    }
+                },
+                Err(e) => error!(?e, "Plugin watcher error"),
+            })?;
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            _watcher: Mutex::new(watcher),
+            changes: Mutex::new(change_recv),
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the plugin files which changed and have been left untouched
+    /// since
+    pub fn take_changed(&self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        let mut pending = self.pending.lock().unwrap();
+        pending.extend(
+            self.changes
+                .lock()
+                .unwrap()
+                .try_iter()
+                .map(|path| (path, now)),
+        );
+
+        let changed = pending
+            .iter()
+            .filter(|(_, changed_at)| now.duration_since(**changed_at) >= DEBOUNCE)
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        for path in &changed {
+            pending.remove(path);
+        }
+        changed
+    }
+}
+
+pub fn is_plugin_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map_or(false, |s| s.ends_with(".plugin.tar"))
+}
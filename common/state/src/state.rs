@@ -216,6 +216,7 @@ impl State {
         ecs.register::<comp::Vel>();
         ecs.register::<comp::Ori>();
         ecs.register::<comp::Inventory>();
+        ecs.register::<comp::Vault>();
 
         // Register common unsynced components
         ecs.register::<comp::PreviousPhysCache>();
@@ -292,6 +293,11 @@ impl State {
                     uid: ecs.read_component().into(),
                     uid_allocator: &ecs.read_resource::<UidAllocator>().into(),
                     player: ecs.read_component().into(),
+                    pos: ecs.read_component().into(),
+                    inventory: ecs.read_component().into(),
+                    stats: ecs.read_component().into(),
+                    energy: ecs.read_component().into(),
+                    group: ecs.read_component().into(),
                 };
                 if let Err(e) = plugin_mgr
                     .execute_event(&ecs_world, &plugin_api::event::PluginLoadEvent {
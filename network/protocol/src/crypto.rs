@@ -0,0 +1,361 @@
+//! Authenticated encryption of the TCP protocol.
+//!
+//! Both sides exchange ephemeral X25519 keys during the handshake and derive
+//! one ChaCha20-Poly1305 key per direction from the shared secret. The server
+//! signs both ephemeral keys with its long-term Ed25519 [`ServerKey`], the
+//! client checks the signature against the [`PublicKey`] it pinned for the
+//! server, like SSH does with its known hosts.
+//!
+//! After the key exchange everything is sent in records of
+//! `[u32 length][ciphertext + tag]`, the nonce is the number of the record.
+use bytes::{Buf, BufMut, BytesMut};
+use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305},
+    agreement::{self, EphemeralPrivateKey, UnparsedPublicKey, X25519},
+    hkdf,
+    rand::SystemRandom,
+    signature::{self, Ed25519KeyPair, KeyPair, ED25519},
+};
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// Signatures of the server cover this and both ephemeral keys
+const SIGNATURE_CONTEXT: &[u8] = b"veloren tcp key exchange v1";
+const CLIENT_TO_SERVER: &[u8] = b"client to server";
+const SERVER_TO_CLIENT: &[u8] = b"server to client";
+/// Plaintext bytes per record, larger buffers are split
+const MAX_RECORD: usize = 1 << 14;
+const RECORD_HEADER: usize = 4;
+
+/// Encryption to negotiate during the handshake of a TCP connection
+#[derive(Clone, Debug)]
+pub enum Encryption {
+    /// Listening side, proves that it owns `key`. If `required`, clients
+    /// which don't ask for encryption are rejected.
+    Server { key: Arc<ServerKey>, required: bool },
+    /// Connecting side, the server has to own the `pinned` key. With `None`
+    /// the handshake fails with [`EncryptionError::UnknownServerKey`] once the
+    /// server proved it owns its key, so the caller can decide whether to
+    /// trust the key and connect again.
+    Client { pinned: Option<PublicKey> },
+}
+
+/// Why an encrypted handshake failed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncryptionError {
+    /// The server has no key to encrypt with
+    Unsupported,
+    /// The server only accepts encrypted connections
+    Required,
+    /// The server couldn't prove it owns the key it claims
+    BadSignature,
+    /// The server proved it owns a key which isn't pinned yet
+    UnknownServerKey(PublicKey),
+    /// The server owns another key than the pinned one, either it changed its
+    /// key or someone is intercepting the connection
+    ServerKeyMismatch {
+        expected: PublicKey,
+        actual: PublicKey,
+    },
+    /// Keys couldn't be agreed on
+    Failed,
+}
+
+impl fmt::Display for EncryptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptionError::Unsupported => write!(f, "remote side doesn't support encryption"),
+            EncryptionError::Required => write!(f, "remote side requires encryption"),
+            EncryptionError::BadSignature => write!(f, "server key signature is invalid"),
+            EncryptionError::UnknownServerKey(key) => write!(f, "unknown server key {}", key),
+            EncryptionError::ServerKeyMismatch { expected, actual } => write!(
+                f,
+                "server key {} doesn't match the pinned key {}",
+                actual, expected
+            ),
+            EncryptionError::Failed => write!(f, "encryption failed"),
+        }
+    }
+}
+
+/// Long-term identity of a server
+pub struct ServerKey {
+    pair: Ed25519KeyPair,
+    pkcs8: Vec<u8>,
+}
+
+/// The key was rejected, it's no PKCS#8 encoded Ed25519 key
+#[derive(Debug)]
+pub struct InvalidKey;
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid PKCS#8 Ed25519 key")
+    }
+}
+
+impl std::error::Error for InvalidKey {}
+
+impl ServerKey {
+    pub fn generate() -> Self {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .expect("system randomness is available");
+        Self::from_pkcs8(pkcs8.as_ref()).expect("freshly generated key is valid")
+    }
+
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Self, InvalidKey> {
+        Ok(Self {
+            pair: Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|_| InvalidKey)?,
+            pkcs8: pkcs8.to_vec(),
+        })
+    }
+
+    /// The encoded key, to store it
+    pub fn to_pkcs8(&self) -> &[u8] { &self.pkcs8 }
+
+    pub fn public_key(&self) -> PublicKey {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(self.pair.public_key().as_ref());
+        PublicKey(key)
+    }
+
+    pub(crate) fn sign(&self, client: &[u8; 32], server: &[u8; 32]) -> [u8; 64] {
+        let mut signature = [0u8; 64];
+        signature.copy_from_slice(self.pair.sign(&transcript(client, server)).as_ref());
+        signature
+    }
+}
+
+impl fmt::Debug for ServerKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ServerKey")
+            .field(&self.public_key())
+            .finish()
+    }
+}
+
+/// Public half of a [`ServerKey`], displayed and parsed as hex
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PublicKey(pub [u8; 32]);
+
+impl PublicKey {
+    /// The hex of the key in groups of four digits, to be compared by humans
+    pub fn fingerprint(&self) -> String {
+        self.0
+            .chunks(2)
+            .map(|group| format!("{:02x}{:02x}", group[0], group[1]))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub(crate) fn verify(&self, client: &[u8; 32], server: &[u8; 32], signature: &[u8]) -> bool {
+        signature::UnparsedPublicKey::new(&ED25519, &self.0)
+            .verify(&transcript(client, server), signature)
+            .is_ok()
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = InvalidKey;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 64 || !s.is_ascii() {
+            return Err(InvalidKey);
+        }
+        let mut key = [0u8; 32];
+        for (i, b) in key.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).map_err(|_| InvalidKey)?;
+        }
+        Ok(PublicKey(key))
+    }
+}
+
+fn transcript(client: &[u8; 32], server: &[u8; 32]) -> Vec<u8> {
+    [SIGNATURE_CONTEXT, client, server].concat()
+}
+
+/// Ephemeral half of the key exchange
+pub(crate) struct KeyExchange {
+    private: EphemeralPrivateKey,
+    public: [u8; 32],
+}
+
+impl KeyExchange {
+    pub(crate) fn new() -> Self {
+        let private = EphemeralPrivateKey::generate(&X25519, &SystemRandom::new())
+            .expect("system randomness is available");
+        let mut public = [0u8; 32];
+        public.copy_from_slice(
+            private
+                .compute_public_key()
+                .expect("X25519 public key can always be computed")
+                .as_ref(),
+        );
+        Self { private, public }
+    }
+
+    pub(crate) fn public_key(&self) -> [u8; 32] { self.public }
+
+    /// Returns the keys to seal outgoing and open incoming records with
+    pub(crate) fn agree(
+        self,
+        remote: &[u8; 32],
+        client: bool,
+    ) -> Result<(RecordKey, RecordKey), EncryptionError> {
+        let (client_key, server_key) = if client {
+            (&self.public, remote)
+        } else {
+            (remote, &self.public)
+        };
+        let salt = [client_key.as_slice(), server_key.as_slice()].concat();
+        agreement::agree_ephemeral(
+            self.private,
+            &UnparsedPublicKey::new(&X25519, remote),
+            EncryptionError::Failed,
+            |secret| {
+                let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &salt).extract(secret);
+                let derive = |info: &[u8]| {
+                    let info = [info];
+                    prk.expand(&info, &CHACHA20_POLY1305)
+                        .map(|okm| RecordKey::new(UnboundKey::from(okm)))
+                        .map_err(|_| EncryptionError::Failed)
+                };
+                let c2s = derive(CLIENT_TO_SERVER)?;
+                let s2c = derive(SERVER_TO_CLIENT)?;
+                Ok(if client { (c2s, s2c) } else { (s2c, c2s) })
+            },
+        )
+    }
+}
+
+/// Key of one direction of an encrypted connection
+#[derive(Debug)]
+pub struct RecordKey {
+    key: LessSafeKey,
+    counter: u64,
+}
+
+impl RecordKey {
+    fn new(key: UnboundKey) -> Self {
+        Self {
+            key: LessSafeKey::new(key),
+            counter: 0,
+        }
+    }
+
+    fn next_nonce(&mut self) -> Nonce {
+        let mut nonce = [0u8; aead::NONCE_LEN];
+        nonce[4..].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+        Nonce::assume_unique_for_key(nonce)
+    }
+
+    /// Encrypt `data` into records appended to `out`
+    pub(crate) fn seal(&mut self, data: &[u8], out: &mut BytesMut) {
+        for chunk in data.chunks(MAX_RECORD) {
+            let tag_len = CHACHA20_POLY1305.tag_len();
+            out.reserve(RECORD_HEADER + chunk.len() + tag_len);
+            out.put_u32_le((chunk.len() + tag_len) as u32);
+            let start = out.len();
+            out.put_slice(chunk);
+            let nonce = self.next_nonce();
+            let tag = self
+                .key
+                .seal_in_place_separate_tag(nonce, Aad::empty(), &mut out[start..])
+                .expect("records are far smaller than the ChaCha20 limit");
+            out.put_slice(tag.as_ref());
+        }
+    }
+
+    /// Decrypt all complete records in `sealed` and append them to `out`.
+    /// Incomplete records stay in `sealed`.
+    pub(crate) fn open(&mut self, sealed: &mut BytesMut, out: &mut BytesMut) -> Result<(), ()> {
+        while sealed.len() >= RECORD_HEADER {
+            let len = u32::from_le_bytes([sealed[0], sealed[1], sealed[2], sealed[3]]) as usize;
+            if len > MAX_RECORD + CHACHA20_POLY1305.tag_len() {
+                return Err(());
+            }
+            if sealed.len() < RECORD_HEADER + len {
+                break;
+            }
+            sealed.advance(RECORD_HEADER);
+            let mut record = sealed.split_to(len);
+            let nonce = self.next_nonce();
+            let data = self
+                .key
+                .open_in_place(nonce, Aad::empty(), &mut record)
+                .map_err(|_| ())?;
+            out.extend_from_slice(data);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> ((RecordKey, RecordKey), (RecordKey, RecordKey)) {
+        let client = KeyExchange::new();
+        let server = KeyExchange::new();
+        let (client_public, server_public) = (client.public_key(), server.public_key());
+        (
+            client.agree(&server_public, true).unwrap(),
+            server.agree(&client_public, false).unwrap(),
+        )
+    }
+
+    #[test]
+    fn records_roundtrip() {
+        let ((mut client_seal, _), (_, mut server_open)) = keys();
+        let data = (0..40_000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut sealed = BytesMut::new();
+        client_seal.seal(&data, &mut sealed);
+        client_seal.seal(b"second", &mut sealed);
+        // records may arrive in pieces
+        let mut rest = sealed.split_off(20_000);
+        let mut out = BytesMut::new();
+        server_open.open(&mut sealed, &mut out).unwrap();
+        assert_eq!(out.len(), MAX_RECORD);
+        sealed.unsplit(rest.split());
+        server_open.open(&mut sealed, &mut out).unwrap();
+        assert!(sealed.is_empty());
+        assert_eq!(&out[..data.len()], &data[..]);
+        assert_eq!(&out[data.len()..], b"second");
+    }
+
+    #[test]
+    fn records_tampered() {
+        let ((mut client_seal, _), (_, mut server_open)) = keys();
+        let mut sealed = BytesMut::new();
+        client_seal.seal(b"hello world", &mut sealed);
+        sealed[6] ^= 1;
+        assert_eq!(server_open.open(&mut sealed, &mut BytesMut::new()), Err(()));
+    }
+
+    #[test]
+    fn signature() {
+        let key = ServerKey::generate();
+        let key = ServerKey::from_pkcs8(key.to_pkcs8()).unwrap();
+        let signature = key.sign(&[1; 32], &[2; 32]);
+        assert!(key.public_key().verify(&[1; 32], &[2; 32], &signature));
+        assert!(!key.public_key().verify(&[2; 32], &[1; 32], &signature));
+        assert!(!ServerKey::generate()
+            .public_key()
+            .verify(&[1; 32], &[2; 32], &signature));
+    }
+
+    #[test]
+    fn public_key_hex() {
+        let key = ServerKey::generate().public_key();
+        assert_eq!(key.to_string().parse::<PublicKey>().unwrap(), key);
+        assert!("abc".parse::<PublicKey>().is_err());
+        assert_eq!(key.fingerprint().replace(' ', ""), key.to_string());
+        assert_eq!(PublicKey([0xab; 32]).fingerprint().split(' ').count(), 16);
+    }
+}
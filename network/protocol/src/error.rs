@@ -1,3 +1,5 @@
+use crate::crypto::EncryptionError;
+
 /// All possible Errors that can happen during Handshake [`InitProtocol`]
 ///
 /// [`InitProtocol`]: crate::InitProtocol
@@ -10,6 +12,7 @@ pub enum InitProtocolError<E: std::fmt::Debug + Send> {
     NotId,
     WrongMagicNumber([u8; 7]),
     WrongVersion([u32; 3]),
+    Encryption(EncryptionError),
 }
 
 /// When you return closed you must stay closed!
@@ -28,9 +31,9 @@ impl<E: std::fmt::Debug + Send> From<ProtocolError<E>> for InitProtocolError<E>
     fn from(err: ProtocolError<E>) -> Self {
         match err {
             ProtocolError::Custom(e) => InitProtocolError::Custom(e),
-            ProtocolError::Violated => {
-                unreachable!("not possible as the Init has raw access to the I/O")
-            },
+            // Init has raw access to the I/O, so the remote side sent garbage or
+            // corrupted the records of an encrypted connection
+            ProtocolError::Violated => InitProtocolError::NotHandshake,
         }
     }
 }
@@ -58,6 +61,7 @@ impl<E: std::fmt::Debug + Send> core::fmt::Display for InitProtocolError<E> {
                 &r,
                 &crate::types::VELOREN_NETWORK_VERSION
             ),
+            InitProtocolError::Encryption(e) => write!(f, "Encryption error: {}", e),
         }
     }
 }
@@ -10,7 +10,9 @@ const FRAME_CLOSE_STREAM: u8 = 5;
 const FRAME_DATA_HEADER: u8 = 6;
 const FRAME_DATA: u8 = 7;
 const FRAME_RAW: u8 = 8;
+const FRAME_KEY_EXCHANGE: u8 = 9;
 //const FRAME_RESERVED_2: u8 = 10;
+const FRAME_KEY_EXCHANGE_REPLY: u8 = 11;
 //const FRAME_RESERVED_3: u8 = 13;
 
 /// Used for Communication between Channel <----(TCP/UDP)----> Channel
@@ -24,6 +26,18 @@ pub enum InitFrame {
         pid: Pid,
         secret: u128,
     },
+    /// Ephemeral key of the client, to start encryption. See
+    /// [`crypto`](crate::crypto).
+    KeyExchange {
+        public_key: [u8; 32],
+    },
+    /// Ephemeral key of the server, and the long-term key of the server
+    /// which signed both ephemeral keys
+    KeyExchangeReply {
+        public_key: [u8; 32],
+        server_key: [u8; 32],
+        signature: [u8; 64],
+    },
     /// WARNING: sending RAW is only for debug purposes and will drop the
     /// connection
     Raw(Vec<u8>),
@@ -83,6 +97,8 @@ impl InitFrame {
     // Size WITHOUT the 1rst indicating byte
     pub(crate) const HANDSHAKE_CNS: usize = 19;
     pub(crate) const INIT_CNS: usize = 32;
+    pub(crate) const KEY_EXCHANGE_CNS: usize = 32;
+    pub(crate) const KEY_EXCHANGE_REPLY_CNS: usize = 128;
     /// const part of the RAW frame, actual size is variable
     pub(crate) const RAW_CNS: usize = 2;
 
@@ -104,6 +120,20 @@ impl InitFrame {
                 pid.to_bytes(bytes);
                 bytes.put_u128_le(secret);
             },
+            InitFrame::KeyExchange { public_key } => {
+                bytes.put_u8(FRAME_KEY_EXCHANGE);
+                bytes.put_slice(&public_key);
+            },
+            InitFrame::KeyExchangeReply {
+                public_key,
+                server_key,
+                signature,
+            } => {
+                bytes.put_u8(FRAME_KEY_EXCHANGE_REPLY);
+                bytes.put_slice(&public_key);
+                bytes.put_slice(&server_key);
+                bytes.put_slice(&signature);
+            },
             InitFrame::Raw(data) => {
                 bytes.put_u8(FRAME_RAW);
                 bytes.put_u16_le(data.len() as u16);
@@ -141,6 +171,32 @@ impl InitFrame {
                     secret: bytes.get_u128_le(),
                 }
             },
+            FRAME_KEY_EXCHANGE => {
+                if bytes.len() < Self::KEY_EXCHANGE_CNS + 1 {
+                    return None;
+                }
+                bytes.advance(1);
+                let mut public_key = [0u8; 32];
+                bytes.copy_to_slice(&mut public_key);
+                InitFrame::KeyExchange { public_key }
+            },
+            FRAME_KEY_EXCHANGE_REPLY => {
+                if bytes.len() < Self::KEY_EXCHANGE_REPLY_CNS + 1 {
+                    return None;
+                }
+                bytes.advance(1);
+                let mut public_key = [0u8; 32];
+                let mut server_key = [0u8; 32];
+                let mut signature = [0u8; 64];
+                bytes.copy_to_slice(&mut public_key);
+                bytes.copy_to_slice(&mut server_key);
+                bytes.copy_to_slice(&mut signature);
+                InitFrame::KeyExchangeReply {
+                    public_key,
+                    server_key,
+                    signature,
+                }
+            },
             FRAME_RAW => {
                 if bytes.len() < Self::RAW_CNS + 1 {
                     return None;
@@ -316,6 +372,14 @@ mod tests {
                 pid: Pid::fake(0),
                 secret: 0u128,
             },
+            InitFrame::KeyExchange {
+                public_key: [7; 32],
+            },
+            InitFrame::KeyExchangeReply {
+                public_key: [1; 32],
+                server_key: [2; 32],
+                signature: [3; 64],
+            },
             InitFrame::Raw(vec![1, 2, 3]),
         ]
     }
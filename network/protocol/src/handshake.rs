@@ -1,4 +1,5 @@
 use crate::{
+    crypto::{Encryption, EncryptionError, KeyExchange, PublicKey, RecordKey, ServerKey},
     error::{InitProtocolError, ProtocolError},
     frame::InitFrame,
     types::{
@@ -8,6 +9,7 @@ use crate::{
     InitProtocol,
 };
 use async_trait::async_trait;
+use std::sync::Arc;
 use tracing::{debug, error, info, trace};
 
 /// Implement this for auto Handshake with [`ReliableSink`].
@@ -23,6 +25,13 @@ use tracing::{debug, error, info, trace};
 pub trait ReliableDrain {
     type CustomErr: std::fmt::Debug + Send;
     async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError<Self::CustomErr>>;
+
+    /// Encryption to negotiate during the handshake, only protocols which
+    /// don't encrypt on their own support it
+    fn encryption(&self) -> Option<&Encryption> { None }
+
+    /// Encrypt everything sent after this
+    fn seal(&mut self, _key: RecordKey) {}
 }
 
 /// Implement this for auto Handshake with [`ReliableDrain`]. See
@@ -33,6 +42,9 @@ pub trait ReliableDrain {
 pub trait ReliableSink {
     type CustomErr: std::fmt::Debug + Send;
     async fn recv(&mut self) -> Result<InitFrame, ProtocolError<Self::CustomErr>>;
+
+    /// Decrypt everything received after this
+    fn open(&mut self, _key: RecordKey) {}
 }
 
 #[async_trait]
@@ -58,13 +70,30 @@ where
         const WRONG_VERSION: &str = "Handshake does contain a correct magic number, but invalid \
                                      version.\nWe don't know how to communicate with \
                                      you.\nClosing the connection";
+        #[cfg(debug_assertions)]
+        const NO_ENCRYPTION: &str = "Handshake asks for encryption, but this server has no key to \
+                                     encrypt with.\nClosing the connection";
         const ERR_S: &str = "Got A Raw Message, these are usually Debug Messages indicating that \
                              something went wrong on network layer and connection will be closed";
 
         let drain = &mut self.0;
         let sink = &mut self.1;
 
+        // The client exchanges keys before it sends its first frame, so the
+        // server knows whether to encrypt before anything but the handshake
+        // is sent
+        let (client, server) = match drain.encryption() {
+            Some(Encryption::Client { pinned }) => (Some(*pinned), None),
+            Some(Encryption::Server { key, required }) => {
+                (None, Some((Arc::clone(key), *required)))
+            },
+            None => (None, None),
+        };
+
         if initializer {
+            if let Some(pinned) = client {
+                key_exchange_client(drain, sink, pinned).await?;
+            }
             drain
                 .send(InitFrame::Handshake {
                     magic_number: VELOREN_MAGIC_NUMBER,
@@ -73,7 +102,11 @@ where
                 .await?;
         }
 
-        match sink.recv().await? {
+        let frame = match server {
+            Some((key, required)) => recv_key_exchange(drain, sink, &key, required).await?,
+            None => sink.recv().await?,
+        };
+        match frame {
             InitFrame::Handshake {
                 magic_number,
                 version,
@@ -104,14 +137,10 @@ where
                     Err(InitProtocolError::WrongVersion(version))
                 } else {
                     trace!("Handshake Frame completed");
-                    if initializer {
-                        drain
-                            .send(InitFrame::Init {
-                                pid: local_pid,
-                                secret: local_secret,
-                            })
-                            .await?;
-                    } else {
+                    if !initializer {
+                        if let Some(pinned) = client {
+                            key_exchange_client(drain, sink, pinned).await?;
+                        }
                         drain
                             .send(InitFrame::Handshake {
                                 magic_number: VELOREN_MAGIC_NUMBER,
@@ -129,12 +158,29 @@ where
                 }
                 Err(InitProtocolError::NotHandshake)
             },
+            InitFrame::KeyExchange { .. } => {
+                info!("Remote side asked for encryption, but we have no key");
+                #[cfg(debug_assertions)]
+                drain
+                    .send(InitFrame::Raw(NO_ENCRYPTION.as_bytes().to_vec()))
+                    .await?;
+                Err(InitProtocolError::Encryption(EncryptionError::Unsupported))
+            },
             _ => {
                 info!("Handshake failed");
                 Err(InitProtocolError::NotHandshake)
             },
         }?;
 
+        if initializer {
+            drain
+                .send(InitFrame::Init {
+                    pid: local_pid,
+                    secret: local_secret,
+                })
+                .await?;
+        }
+
         match sink.recv().await? {
             InitFrame::Init { pid, secret } => {
                 debug!(?pid, "Participant send their ID");
@@ -167,6 +213,136 @@ where
     }
 }
 
+/// Exchange keys as client, the server has to prove it owns the `pinned` key
+async fn key_exchange_client<D, S, E>(
+    drain: &mut D,
+    sink: &mut S,
+    pinned: Option<PublicKey>,
+) -> Result<(), InitProtocolError<E>>
+where
+    D: ReliableDrain<CustomErr = E> + Send,
+    S: ReliableSink<CustomErr = E> + Send,
+    E: std::fmt::Debug + Send,
+{
+    let exchange = KeyExchange::new();
+    let client_key = exchange.public_key();
+    drain
+        .send(InitFrame::KeyExchange {
+            public_key: client_key,
+        })
+        .await?;
+    let (public_key, server_key, signature) = match sink.recv().await? {
+        InitFrame::KeyExchangeReply {
+            public_key,
+            server_key,
+            signature,
+        } => (public_key, PublicKey(server_key), signature),
+        InitFrame::Raw(bytes) => {
+            match std::str::from_utf8(bytes.as_slice()) {
+                Ok(string) => error!(?string, "Key exchange failed"),
+                _ => error!(?bytes, "Key exchange failed"),
+            }
+            return Err(InitProtocolError::Encryption(EncryptionError::Unsupported));
+        },
+        _ => {
+            info!("Key exchange failed");
+            return Err(InitProtocolError::Encryption(EncryptionError::Unsupported));
+        },
+    };
+    if !server_key.verify(&client_key, &public_key, &signature) {
+        error!(%server_key, "Server couldn't prove it owns its key");
+        return Err(InitProtocolError::Encryption(EncryptionError::BadSignature));
+    }
+    match pinned {
+        Some(expected) if expected != server_key => {
+            error!(%expected, actual = %server_key, "Server key doesn't match the pinned key");
+            return Err(InitProtocolError::Encryption(
+                EncryptionError::ServerKeyMismatch {
+                    expected,
+                    actual: server_key,
+                },
+            ));
+        },
+        Some(_) => {},
+        None => {
+            return Err(InitProtocolError::Encryption(
+                EncryptionError::UnknownServerKey(server_key),
+            ));
+        },
+    }
+    let (seal, open) = exchange
+        .agree(&public_key, true)
+        .map_err(InitProtocolError::Encryption)?;
+    drain.seal(seal);
+    sink.open(open);
+    debug!(%server_key, "Connection is encrypted");
+    Ok(())
+}
+
+/// Receive the first frame as server, exchanging keys first if the client
+/// asks for it
+async fn recv_key_exchange<D, S, E>(
+    drain: &mut D,
+    sink: &mut S,
+    key: &ServerKey,
+    required: bool,
+) -> Result<InitFrame, InitProtocolError<E>>
+where
+    D: ReliableDrain<CustomErr = E> + Send,
+    S: ReliableSink<CustomErr = E> + Send,
+    E: std::fmt::Debug + Send,
+{
+    #[cfg(debug_assertions)]
+    const ENCRYPTION_REQUIRED: &str =
+        "This server only accepts encrypted connections.\nClosing the connection";
+
+    match sink.recv().await? {
+        InitFrame::KeyExchange { public_key } => {
+            key_exchange_server(drain, sink, key, &public_key).await?;
+            Ok(sink.recv().await?)
+        },
+        frame @ InitFrame::Handshake { .. } if required => {
+            info!(?frame, "Remote side didn't ask for the required encryption");
+            #[cfg(debug_assertions)]
+            drain
+                .send(InitFrame::Raw(ENCRYPTION_REQUIRED.as_bytes().to_vec()))
+                .await?;
+            Err(InitProtocolError::Encryption(EncryptionError::Required))
+        },
+        frame => Ok(frame),
+    }
+}
+
+/// Exchange keys as server, proving that we own `key`
+async fn key_exchange_server<D, S, E>(
+    drain: &mut D,
+    sink: &mut S,
+    key: &ServerKey,
+    client_key: &[u8; 32],
+) -> Result<(), InitProtocolError<E>>
+where
+    D: ReliableDrain<CustomErr = E> + Send,
+    S: ReliableSink<CustomErr = E> + Send,
+    E: std::fmt::Debug + Send,
+{
+    let exchange = KeyExchange::new();
+    let public_key = exchange.public_key();
+    let (seal, open) = exchange
+        .agree(client_key, false)
+        .map_err(InitProtocolError::Encryption)?;
+    drain
+        .send(InitFrame::KeyExchangeReply {
+            public_key,
+            server_key: key.public_key().0,
+            signature: key.sign(client_key, &public_key),
+        })
+        .await?;
+    drain.seal(seal);
+    sink.open(open);
+    debug!("Connection is encrypted");
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -15,8 +15,7 @@
 //!  - TCP
 //!  - MPSC
 //!  - QUIC
-//!
-//! eventually a pure UDP implementation will follow
+//!  - UDP
 //!
 //! warning: don't mix protocol, using the TCP variant for actual UDP socket
 //! will result in dropped data  using UDP with a TCP socket will be a waste of
@@ -50,6 +49,7 @@
 //! [`RecvProtocol`]: crate::RecvProtocol
 //! [`InitProtocol`]: crate::InitProtocol
 
+mod crypto;
 mod error;
 mod event;
 mod frame;
@@ -61,8 +61,10 @@ mod prio;
 mod quic;
 mod tcp;
 mod types;
+mod udp;
 mod util;
 
+pub use crypto::{Encryption, EncryptionError, InvalidKey, PublicKey, ServerKey};
 pub use error::{InitProtocolError, ProtocolError};
 pub use event::ProtocolEvent;
 pub use metrics::ProtocolMetricCache;
@@ -72,6 +74,7 @@ pub use mpsc::{MpscMsg, MpscRecvProtocol, MpscSendProtocol};
 pub use quic::{QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, QuicSendProtocol};
 pub use tcp::{TcpRecvProtocol, TcpSendProtocol};
 pub use types::{Bandwidth, Cid, Pid, Prio, Promises, Sid, HIGHEST_PRIO, VELOREN_NETWORK_VERSION};
+pub use udp::{udp_protocols, UdpRecvProtocol, UdpSendProtocol, MAX_UNRELIABLE_MESSAGE_SIZE};
 
 ///use at own risk, might change any time, for internal benchmarks
 pub mod _internal {
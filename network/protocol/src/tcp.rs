@@ -1,4 +1,5 @@
 use crate::{
+    crypto::{Encryption, RecordKey},
     error::ProtocolError,
     event::ProtocolEvent,
     frame::{ITFrame, InitFrame, OTFrame},
@@ -35,6 +36,8 @@ where
     #[allow(dead_code)]
     last: Instant,
     metrics: ProtocolMetricCache,
+    encryption: Option<Encryption>,
+    sealing: Option<RecordKey>,
 }
 
 /// TCP implementation of [`RecvProtocol`]
@@ -50,6 +53,9 @@ where
     incoming: HashMap<Mid, ITMessage>,
     sink: S,
     metrics: ProtocolMetricCache,
+    /// Received records which aren't complete yet
+    sealed: BytesMut,
+    opening: Option<RecordKey>,
 }
 
 impl<D> TcpSendProtocol<D>
@@ -67,9 +73,31 @@ where
             drain,
             last: Instant::now(),
             metrics,
+            encryption: None,
+            sealing: None,
         }
     }
 
+    /// Negotiate encryption during the handshake, see
+    /// [`crypto`](crate::crypto)
+    pub fn with_encryption(mut self, encryption: Encryption) -> Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    async fn send_buffer(&mut self) -> Result<(), ProtocolError<D::CustomErr>> {
+        let data = self.buffer.split();
+        let data = match &mut self.sealing {
+            Some(key) => {
+                let mut sealed = BytesMut::with_capacity(data.len() + 64);
+                key.seal(&data, &mut sealed);
+                sealed
+            },
+            None => data,
+        };
+        self.drain.send(data).await
+    }
+
     /// returns all promises that this Protocol can take care of
     /// If you open a Stream anyway, unsupported promises are ignored.
     pub fn supported_promises() -> Promises {
@@ -91,7 +119,24 @@ where
             incoming: HashMap::new(),
             sink,
             metrics,
+            sealed: BytesMut::new(),
+            opening: None,
+        }
+    }
+
+    /// Receive more data into the buffer, decrypting it if needed
+    async fn fill_buffer(&mut self) -> Result<(), ProtocolError<S::CustomErr>> {
+        let chunk = self.sink.recv().await?;
+        match &mut self.opening {
+            Some(key) => {
+                self.sealed.extend_from_slice(&chunk);
+                key.open(&mut self.sealed, &mut self.buffer)
+                    .map_err(|()| ProtocolError::Violated)?;
+            },
+            None if self.buffer.is_empty() => self.buffer = chunk,
+            None => self.buffer.extend_from_slice(&chunk),
         }
+        Ok(())
     }
 }
 
@@ -137,12 +182,12 @@ where
                 self.store
                     .open_stream(sid, prio, promises, guaranteed_bandwidth);
                 event.to_frame().write_bytes(&mut self.buffer);
-                self.drain.send(self.buffer.split()).await?;
+                self.send_buffer().await?;
             },
             ProtocolEvent::CloseStream { sid } => {
                 if self.store.try_close_stream(sid) {
                     event.to_frame().write_bytes(&mut self.buffer);
-                    self.drain.send(self.buffer.split()).await?;
+                    self.send_buffer().await?;
                 } else {
                     #[cfg(feature = "trace_pedantic")]
                     trace!(?sid, "hold back close stream");
@@ -152,7 +197,7 @@ where
             ProtocolEvent::Shutdown => {
                 if self.store.is_empty() {
                     event.to_frame().write_bytes(&mut self.buffer);
-                    self.drain.send(self.buffer.split()).await?;
+                    self.send_buffer().await?;
                 } else {
                     #[cfg(feature = "trace_pedantic")]
                     trace!("hold back shutdown");
@@ -184,7 +229,7 @@ where
             }
             frame.write_bytes(&mut self.buffer);
         }
-        self.drain.send(self.buffer.split()).await?;
+        self.send_buffer().await?;
         self.metrics
             .sdata_frames_b(data_frames, data_bandwidth as u64);
 
@@ -194,10 +239,12 @@ where
                 #[cfg(feature = "trace_pedantic")]
                 trace!(?sid, "close stream, as it's now empty");
                 OTFrame::CloseStream { sid }.write_bytes(&mut self.buffer);
-                self.drain.send(self.buffer.split()).await?;
                 finished_streams.push(i);
             }
         }
+        if !finished_streams.is_empty() {
+            self.send_buffer().await?;
+        }
         for i in finished_streams.iter().rev() {
             self.closing_streams.remove(*i);
         }
@@ -218,7 +265,7 @@ where
             #[cfg(feature = "trace_pedantic")]
             trace!("shutdown, as it's now empty");
             OTFrame::Shutdown {}.write_bytes(&mut self.buffer);
-            self.drain.send(self.buffer.split()).await?;
+            self.send_buffer().await?;
             self.pending_shutdown = false;
         }
         Ok(data_bandwidth as u64)
@@ -296,12 +343,7 @@ where
                     Err(()) => return Err(ProtocolError::Violated),
                 }
             }
-            let chunk = self.sink.recv().await?;
-            if self.buffer.is_empty() {
-                self.buffer = chunk;
-            } else {
-                self.buffer.extend_from_slice(&chunk);
-            }
+            self.fill_buffer().await?;
         }
     }
 }
@@ -314,10 +356,13 @@ where
     type CustomErr = D::CustomErr;
 
     async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError<Self::CustomErr>> {
-        let mut buffer = BytesMut::with_capacity(500);
-        frame.write_bytes(&mut buffer);
-        self.drain.send(buffer).await
+        frame.write_bytes(&mut self.buffer);
+        self.send_buffer().await
     }
+
+    fn encryption(&self) -> Option<&Encryption> { self.encryption.as_ref() }
+
+    fn seal(&mut self, key: RecordKey) { self.sealing = Some(key); }
 }
 
 #[async_trait]
@@ -328,15 +373,20 @@ where
     type CustomErr = S::CustomErr;
 
     async fn recv(&mut self) -> Result<InitFrame, ProtocolError<Self::CustomErr>> {
-        while self.buffer.len() < 100 {
-            let chunk = self.sink.recv().await?;
-            self.buffer.extend_from_slice(&chunk);
+        while self.buffer.len() < 200 {
+            self.fill_buffer().await?;
             if let Some(frame) = InitFrame::read_frame(&mut self.buffer) {
                 return Ok(frame);
             }
         }
         Err(ProtocolError::Violated)
     }
+
+    fn open(&mut self, key: RecordKey) {
+        // Anything left over was already sent encrypted
+        self.sealed.unsplit(self.buffer.split());
+        self.opening = Some(key);
+    }
 }
 
 #[cfg(test)]
@@ -410,7 +460,8 @@ mod test_utils {
 #[cfg(test)]
 mod tests {
     use crate::{
-        error::ProtocolError,
+        crypto::{Encryption, EncryptionError, ServerKey},
+        error::{InitProtocolError, ProtocolError},
         frame::OTFrame,
         metrics::{ProtocolMetricCache, ProtocolMetrics, RemoveReason},
         tcp::test_utils::*,
@@ -430,6 +481,88 @@ mod tests {
         assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
     }
 
+    #[tokio::test]
+    async fn handshake_encrypted() {
+        // The listening server initializes the handshake, like the network does
+        let key = Arc::new(ServerKey::generate());
+        let [mut p1, mut p2] = tcp_bound(10, None);
+        p2.0 = p2.0.with_encryption(Encryption::Client {
+            pinned: Some(key.public_key()),
+        });
+        p1.0 = p1.0.with_encryption(Encryption::Server {
+            key,
+            required: true,
+        });
+        let r1 = tokio::spawn(async move {
+            let r = p1.initialize(true, Pid::fake(2), 1337).await;
+            (p1, r)
+        });
+        let r2 = tokio::spawn(async move {
+            let r = p2.initialize(false, Pid::fake(3), 42).await;
+            (p2, r)
+        });
+        let (r1, r2) = tokio::join!(r1, r2);
+        let ((p1, r1), (p2, r2)) = (r1.unwrap(), r2.unwrap());
+        assert_eq!(r1, Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
+        assert_eq!(r2, Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+
+        let (mut s, mut r) = (p1.0, p2.1);
+        let event = ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+        };
+        s.send(event.clone()).await.unwrap();
+        assert_eq!(event, r.recv().await.unwrap());
+        let event = ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::from(&[188u8; 60_000][..]),
+        };
+        s.send(event.clone()).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(event, r.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn handshake_encrypted_unknown_key() {
+        let key = Arc::new(ServerKey::generate());
+        let public_key = key.public_key();
+        let [mut p1, mut p2] = tcp_bound(10, None);
+        p1.0 = p1.0.with_encryption(Encryption::Client { pinned: None });
+        p2.0 = p2.0.with_encryption(Encryption::Server {
+            key,
+            required: false,
+        });
+        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(
+            r1.unwrap(),
+            Err(InitProtocolError::Encryption(
+                EncryptionError::UnknownServerKey(public_key)
+            ))
+        );
+        assert_eq!(r2.unwrap(), Err(InitProtocolError::Custom(())));
+    }
+
+    #[tokio::test]
+    async fn handshake_encryption_required() {
+        let [mut p1, mut p2] = tcp_bound(10, None);
+        p2.0 = p2.0.with_encryption(Encryption::Server {
+            key: Arc::new(ServerKey::generate()),
+            required: true,
+        });
+        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r1.unwrap(), Err(InitProtocolError::NotHandshake));
+        assert_eq!(
+            r2.unwrap(),
+            Err(InitProtocolError::Encryption(EncryptionError::Required))
+        );
+    }
+
     #[tokio::test]
     async fn open_stream() {
         let [p1, p2] = tcp_bound(10, None);
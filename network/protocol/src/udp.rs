@@ -1,37 +1,1174 @@
-// TODO: quick and dirty which activly waits for an ack!
-/*
-UDP protocol
-
-All Good Case:
-S --HEADER--> R
-S --DATA--> R
-S --DATA--> R
-S <--FINISHED-- R
-
-
-Delayed HEADER:
-S --HEADER-->
-S --DATA--> R // STORE IT
-  --HEADER--> R // apply left data and continue
-S --DATA--> R
-S <--FINISHED-- R
-
-
-NO HEADER:
-S --HEADER--> !
-S --DATA--> R // STORE IT
-S --DATA--> R // STORE IT
-S <--MISSING_HEADER-- R // SEND AFTER 10 ms after DATA1
-S --HEADER--> R
-S <--FINISHED-- R
-
-
-NO DATA:
-S --HEADER--> R
-S --DATA--> R
-S --DATA--> !
-S --STATUS--> R
-S <--MISSING_DATA -- R
-S --DATA--> R
-S <--FINISHED-- R
-*/
+//! UDP implementation of the protocol.
+//!
+//! Every [`UnreliableDrain::send`] and [`UnreliableSink::recv`] is a single
+//! datagram, which might be lost, duplicated or arrive out of order.
+//!
+//! Streams with [`Promises::ORDERED`] or [`Promises::GUARANTEED_DELIVERY`]
+//! use a reliable channel: the same frames as TCP are packed into numbered
+//! packets, which the remote acknowledges and which are resent until they are.
+//! The remote puts them back in order before reading the frames.
+//!
+//! All other streams send every fragment of a message in a self contained
+//! packet, a message is dropped if one of its fragments is lost.
+//!
+//! The first byte of every datagram is the kind of packet:
+//! ```text
+//! INIT:       InitFrame
+//! RELIABLE:   seq: u64, OTFrame, OTFrame, ...
+//! UNRELIABLE: mid: u64, sid: u64, length: u64, offset: u64, data
+//! ACK:        next_seq: u64, count: u16, seq: u64 * count
+//! ```
+//! Empty datagrams are ignored, the connecting side sends them to get a cookie
+//! from the listener, which it echoes back before the handshake. Messages of
+//! unreliable streams are limited to [`MAX_UNRELIABLE_MESSAGE_SIZE`].
+//!
+//! [`UnreliableDrain::send`]: crate::UnreliableDrain::send
+//! [`UnreliableSink::recv`]: crate::UnreliableSink::recv
+use crate::{
+    error::ProtocolError,
+    event::ProtocolEvent,
+    frame::{ITFrame, InitFrame, OTFrame},
+    handshake::{ReliableDrain, ReliableSink},
+    message::{ITMessage, ALLOC_BLOCK},
+    metrics::{ProtocolMetricCache, RemoveReason},
+    prio::PrioManager,
+    types::{Bandwidth, Mid, Prio, Promises, Sid},
+    RecvProtocol, SendProtocol, UnreliableDrain, UnreliableSink,
+};
+use async_trait::async_trait;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use hashbrown::{HashMap, HashSet};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::info;
+#[cfg(feature = "trace_pedantic")]
+use tracing::trace;
+
+const PACKET_INIT: u8 = 1;
+const PACKET_RELIABLE: u8 = 2;
+const PACKET_UNRELIABLE: u8 = 3;
+const PACKET_ACK: u8 = 4;
+
+// Size WITH the 1rst indicating byte
+const RELIABLE_HEADER_SIZE: usize = 9;
+const UNRELIABLE_HEADER_SIZE: usize = 33;
+const ACK_HEADER_SIZE: usize = 11;
+/// Frames packed into one reliable packet, a full DATA frame always fits
+const MAX_RELIABLE_PAYLOAD: usize = 1420;
+/// Reliable packets which are sent without waiting for their acknowledgement
+const MAX_IN_FLIGHT: usize = 256;
+/// Reliable packets further ahead are dropped, they will be resent
+const MAX_RECV_AHEAD: u64 = 4 * MAX_IN_FLIGHT as u64;
+const MAX_SELECTIVE_ACKS: usize = 128;
+/// Messages of unreliable streams waiting for missing fragments
+const MAX_INCOMPLETE_UNRELIABLE: usize = 32;
+/// Messages of unreliable streams can't be larger than this, larger ones are
+/// a protocol violation
+pub const MAX_UNRELIABLE_MESSAGE_SIZE: usize = ALLOC_BLOCK;
+const MIN_RESEND_TIMEOUT: Duration = Duration::from_millis(20);
+const MAX_RESEND_TIMEOUT: Duration = Duration::from_secs(1);
+/// Send an ACK when nothing else was sent for this long, so the remote can
+/// tell a quiet channel from a dead one
+const KEEP_ALIVE: Duration = Duration::from_millis(500);
+/// The handshake happens before anything can be resent, so every frame of it
+/// is sent multiple times instead
+const INIT_REDUNDANCY: usize = 3;
+/// Give up on a handshake after this many packets without the next frame
+const MAX_HANDSHAKE_SKIPPED: usize = 256;
+
+/// Acknowledgements shared by the send and recv half of a channel. The recv
+/// half records what to acknowledge and what the remote acknowledged, the send
+/// half acts on it when flushed.
+#[derive(Debug, Default)]
+struct AckState {
+    /// All reliable packets before this were received
+    recv_next_seq: u64,
+    /// Reliable packets received after a missing one
+    recv_ahead: Vec<u64>,
+    ack_pending: bool,
+    /// The remote received all reliable packets before this
+    remote_next_seq: u64,
+    /// Reliable packets the remote received after a missing one
+    remote_acked: Vec<u64>,
+}
+
+impl AckState {
+    fn ack_packet(&self) -> BytesMut {
+        let mut packet = BytesMut::with_capacity(ACK_HEADER_SIZE + self.recv_ahead.len() * 8);
+        packet.put_u8(PACKET_ACK);
+        packet.put_u64_le(self.recv_next_seq);
+        packet.put_u16_le(self.recv_ahead.len() as u16);
+        for seq in &self.recv_ahead {
+            packet.put_u64_le(*seq);
+        }
+        packet
+    }
+}
+
+#[derive(Debug)]
+struct ReliablePacket {
+    seq: u64,
+    data: Bytes,
+    sent: Option<Instant>,
+    resends: u32,
+}
+
+#[derive(Debug)]
+struct UnreliableHeader {
+    sid: Sid,
+    length: u64,
+    offset: u64,
+}
+
+#[derive(Debug)]
+struct UnreliableMessage {
+    sid: Sid,
+    length: u64,
+    /// The fragments by their offset, they are only put together once all of
+    /// them arrived, so a message never takes more memory than was received
+    fragments: BTreeMap<u64, BytesMut>,
+    received: u64,
+}
+
+fn is_reliable(p: &Promises) -> bool {
+    p.contains(Promises::ORDERED) || p.contains(Promises::GUARANTEED_DELIVERY)
+}
+
+/// UDP implementation of [`SendProtocol`]
+///
+/// [`SendProtocol`]: crate::SendProtocol
+#[derive(Debug)]
+pub struct UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    /// Reliable frames which are not packed yet
+    buffer: BytesMut,
+    unreliable_streams: HashSet<Sid>,
+    unreliable_headers: HashMap<Mid, UnreliableHeader>,
+    /// Reliable packets which are not acknowledged yet, the first
+    /// `MAX_IN_FLIGHT` are sent
+    packets: VecDeque<ReliablePacket>,
+    next_seq: u64,
+    rtt: Duration,
+    last_send: Instant,
+    acks: Arc<Mutex<AckState>>,
+    store: PrioManager,
+    next_mid: Mid,
+    closing_streams: Vec<Sid>,
+    notify_closing_streams: Vec<Sid>,
+    pending_shutdown: bool,
+    drain: D,
+    metrics: ProtocolMetricCache,
+}
+
+/// UDP implementation of [`RecvProtocol`]
+///
+/// [`RecvProtocol`]: crate::RecvProtocol
+#[derive(Debug)]
+pub struct UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    /// Reliable frames in order, which are not read yet
+    buffer: BytesMut,
+    next_seq: u64,
+    ahead: BTreeMap<u64, BytesMut>,
+    itmsg_allocator: BytesMut,
+    incoming: HashMap<Mid, ITMessage>,
+    incoming_unreliable: BTreeMap<Mid, UnreliableMessage>,
+    completed_unreliable: VecDeque<Mid>,
+    last_init: Option<InitFrame>,
+    acks: Arc<Mutex<AckState>>,
+    sink: S,
+    metrics: ProtocolMetricCache,
+}
+
+/// Create both halves of a UDP channel, they only work together as the recv
+/// half handles the acknowledgements for the send half.
+pub fn udp_protocols<D, S>(
+    drain: D,
+    sink: S,
+    metrics: ProtocolMetricCache,
+) -> (UdpSendProtocol<D>, UdpRecvProtocol<S>)
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    let acks = Arc::new(Mutex::new(AckState::default()));
+    (
+        UdpSendProtocol::new(drain, Arc::clone(&acks), metrics.clone()),
+        UdpRecvProtocol::new(sink, acks, metrics),
+    )
+}
+
+impl<D> UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    fn new(drain: D, acks: Arc<Mutex<AckState>>, metrics: ProtocolMetricCache) -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            unreliable_streams: HashSet::new(),
+            unreliable_headers: HashMap::new(),
+            packets: VecDeque::new(),
+            next_seq: 0,
+            rtt: Duration::from_millis(100),
+            last_send: Instant::now(),
+            acks,
+            store: PrioManager::new(metrics.clone()),
+            next_mid: 0u64,
+            closing_streams: vec![],
+            notify_closing_streams: vec![],
+            pending_shutdown: false,
+            drain,
+            metrics,
+        }
+    }
+
+    /// returns all promises that this Protocol can take care of
+    /// If you open a Stream anyway, unsupported promises are ignored.
+    pub fn supported_promises() -> Promises {
+        Promises::ORDERED
+            | Promises::CONSISTENCY
+            | Promises::GUARANTEED_DELIVERY
+            | Promises::COMPRESSED
+    }
+
+    fn open_stream(
+        &mut self,
+        sid: Sid,
+        prio: Prio,
+        promises: Promises,
+        guaranteed_bandwidth: Bandwidth,
+    ) {
+        self.store
+            .open_stream(sid, prio, promises, guaranteed_bandwidth);
+        if !is_reliable(&promises) {
+            self.unreliable_streams.insert(sid);
+        }
+    }
+
+    fn try_close_stream(&mut self, sid: Sid) -> bool {
+        let closed = self.store.try_close_stream(sid);
+        if closed {
+            self.unreliable_streams.remove(&sid);
+        }
+        closed
+    }
+
+    /// Add a frame to the reliable channel, it's sent with the next packet
+    fn push_reliable(&mut self, frame: OTFrame) {
+        let start = self.buffer.len();
+        frame.write_bytes(&mut self.buffer);
+        if self.buffer.len() > MAX_RELIABLE_PAYLOAD && start > 0 {
+            let frame = self.buffer.split_off(start);
+            self.pack_reliable();
+            self.buffer = frame;
+        }
+    }
+
+    fn pack_reliable(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        let mut packet = BytesMut::with_capacity(RELIABLE_HEADER_SIZE + self.buffer.len());
+        packet.put_u8(PACKET_RELIABLE);
+        packet.put_u64_le(self.next_seq);
+        packet.extend_from_slice(&self.buffer);
+        self.buffer.clear();
+        self.packets.push_back(ReliablePacket {
+            seq: self.next_seq,
+            data: packet.freeze(),
+            sent: None,
+            resends: 0,
+        });
+        self.next_seq += 1;
+    }
+
+    /// Sends packets which weren't sent yet, and resends the ones which
+    /// weren't acknowledged in time
+    async fn send_packets(&mut self) -> Result<(), ProtocolError<D::CustomErr>> {
+        let now = Instant::now();
+        let timeout = (self.rtt * 2).clamp(MIN_RESEND_TIMEOUT, MAX_RESEND_TIMEOUT);
+        for packet in self.packets.iter_mut().take(MAX_IN_FLIGHT) {
+            if let Some(sent) = packet.sent {
+                // back off, in case the remote is overwhelmed
+                let timeout = (timeout * 2u32.pow(packet.resends.min(6))).min(MAX_RESEND_TIMEOUT);
+                if now.duration_since(sent) < timeout {
+                    continue;
+                }
+                #[cfg(feature = "trace_pedantic")]
+                trace!(seq = packet.seq, "resend reliable packet");
+                packet.resends += 1;
+            }
+            packet.sent = Some(now);
+            self.last_send = now;
+            self.drain.send(BytesMut::from(&packet.data[..])).await?;
+        }
+        Ok(())
+    }
+
+    /// Forget the packets the remote acknowledged. Returns an ACK packet if
+    /// one needs to be sent.
+    fn handle_acks(&mut self, keep_alive: bool) -> Option<BytesMut> {
+        let mut acks = self.acks.lock().unwrap();
+        let remote_next_seq = acks.remote_next_seq;
+        let remote_acked = std::mem::take(&mut acks.remote_acked);
+        let ack = (std::mem::take(&mut acks.ack_pending) || keep_alive).then(|| acks.ack_packet());
+        drop(acks);
+
+        let now = Instant::now();
+        let mut rtt = None;
+        self.packets.retain(|packet| {
+            let acked = packet.seq < remote_next_seq || remote_acked.contains(&packet.seq);
+            if acked && packet.resends == 0 {
+                rtt = packet.sent.map(|sent| now.duration_since(sent));
+            }
+            !acked
+        });
+        if let Some(rtt) = rtt {
+            self.rtt = (self.rtt * 7 + rtt) / 8;
+        }
+        ack
+    }
+
+    fn unreliable_packet(mid: Mid, header: &UnreliableHeader, data: &[u8]) -> BytesMut {
+        let mut packet = BytesMut::with_capacity(UNRELIABLE_HEADER_SIZE + data.len());
+        packet.put_u8(PACKET_UNRELIABLE);
+        packet.put_u64_le(mid);
+        header.sid.to_bytes(&mut packet);
+        packet.put_u64_le(header.length);
+        packet.put_u64_le(header.offset);
+        packet.put_slice(data);
+        packet
+    }
+
+    async fn send_unreliable(&mut self, frame: OTFrame) -> Result<(), ProtocolError<D::CustomErr>> {
+        let packet = match frame {
+            OTFrame::DataHeader { mid, sid, length } => {
+                let header = UnreliableHeader {
+                    sid,
+                    length,
+                    offset: 0,
+                };
+                if length == 0 {
+                    // no DATA frame follows
+                    Self::unreliable_packet(mid, &header, &[])
+                } else {
+                    self.unreliable_headers.insert(mid, header);
+                    return Ok(());
+                }
+            },
+            OTFrame::Data { mid, data } => {
+                let header = match self.unreliable_headers.get_mut(&mid) {
+                    Some(header) => header,
+                    None => return Ok(()),
+                };
+                let packet = Self::unreliable_packet(mid, header, &data);
+                header.offset += data.len() as u64;
+                if header.offset >= header.length {
+                    self.unreliable_headers.remove(&mid);
+                }
+                packet
+            },
+            frame => {
+                self.push_reliable(frame);
+                return Ok(());
+            },
+        };
+        self.last_send = Instant::now();
+        self.drain.send(packet).await
+    }
+}
+
+impl<S> UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    fn new(sink: S, acks: Arc<Mutex<AckState>>, metrics: ProtocolMetricCache) -> Self {
+        Self {
+            buffer: BytesMut::new(),
+            next_seq: 0,
+            ahead: BTreeMap::new(),
+            itmsg_allocator: BytesMut::with_capacity(ALLOC_BLOCK),
+            incoming: HashMap::new(),
+            incoming_unreliable: BTreeMap::new(),
+            completed_unreliable: VecDeque::new(),
+            last_init: None,
+            acks,
+            sink,
+            metrics,
+        }
+    }
+
+    fn append_reliable(&mut self, payload: BytesMut) {
+        if self.buffer.is_empty() {
+            self.buffer = payload;
+        } else {
+            self.buffer.extend_from_slice(&payload);
+        }
+        self.next_seq += 1;
+    }
+
+    fn recv_reliable(&mut self, mut packet: BytesMut) -> Result<(), ProtocolError<S::CustomErr>> {
+        if packet.len() < RELIABLE_HEADER_SIZE - 1 {
+            return Err(ProtocolError::Violated);
+        }
+        let seq = packet.get_u64_le();
+        if seq == self.next_seq {
+            self.append_reliable(packet);
+            while let Some(packet) = self.ahead.remove(&self.next_seq) {
+                self.append_reliable(packet);
+            }
+        } else if seq > self.next_seq && seq - self.next_seq < MAX_RECV_AHEAD {
+            self.ahead.entry(seq).or_insert(packet);
+        }
+        // Duplicates are acknowledged again, the last ACK might have been lost
+        let mut acks = self.acks.lock().unwrap();
+        acks.recv_next_seq = self.next_seq;
+        acks.recv_ahead = self
+            .ahead
+            .keys()
+            .take(MAX_SELECTIVE_ACKS)
+            .copied()
+            .collect();
+        acks.ack_pending = true;
+        Ok(())
+    }
+
+    fn recv_ack(&mut self, mut packet: BytesMut) -> Result<(), ProtocolError<S::CustomErr>> {
+        if packet.len() < ACK_HEADER_SIZE - 1 {
+            return Err(ProtocolError::Violated);
+        }
+        let next_seq = packet.get_u64_le();
+        let count = packet.get_u16_le() as usize;
+        if packet.len() < count * 8 {
+            return Err(ProtocolError::Violated);
+        }
+        let mut acks = self.acks.lock().unwrap();
+        acks.remote_next_seq = acks.remote_next_seq.max(next_seq);
+        for _ in 0..count {
+            acks.remote_acked.push(packet.get_u64_le());
+        }
+        Ok(())
+    }
+
+    fn recv_unreliable(
+        &mut self,
+        mut packet: BytesMut,
+    ) -> Result<Option<ProtocolEvent>, ProtocolError<S::CustomErr>> {
+        if packet.len() < UNRELIABLE_HEADER_SIZE - 1 {
+            return Err(ProtocolError::Violated);
+        }
+        let mid = packet.get_u64_le();
+        let sid = Sid::from_bytes(&mut packet);
+        let length = packet.get_u64_le();
+        let offset = packet.get_u64_le();
+        let end = offset.checked_add(packet.len() as u64);
+        if length > MAX_UNRELIABLE_MESSAGE_SIZE as u64 || end.map_or(true, |end| end > length) {
+            return Err(ProtocolError::Violated);
+        }
+        self.metrics.rdata_frames_b(packet.len() as u64);
+        if self.completed_unreliable.contains(&mid) {
+            return Ok(None);
+        }
+
+        if self.incoming_unreliable.len() >= MAX_INCOMPLETE_UNRELIABLE
+            && !self.incoming_unreliable.contains_key(&mid)
+        {
+            // the oldest one most likely lost a fragment
+            match self.incoming_unreliable.first_key_value() {
+                Some((first, _)) if *first < mid => {
+                    if let Some((_, m)) = self.incoming_unreliable.pop_first() {
+                        self.metrics
+                            .rmsg_ob(m.sid, RemoveReason::Dropped, m.received);
+                    }
+                },
+                _ => return Ok(None),
+            }
+        }
+        let m = match self.incoming_unreliable.entry(mid) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => {
+                self.metrics.rmsg_ib(sid, length);
+                e.insert(UnreliableMessage {
+                    sid,
+                    length,
+                    fragments: BTreeMap::new(),
+                    received: 0,
+                })
+            },
+        };
+        if m.length != length {
+            return Err(ProtocolError::Violated);
+        }
+        match m.fragments.entry(offset) {
+            Entry::Occupied(_) => return Ok(None),
+            Entry::Vacant(e) => {
+                m.received += packet.len() as u64;
+                e.insert(packet);
+            },
+        }
+        if m.received < length {
+            return Ok(None);
+        }
+
+        // finished, yay
+        let m = self
+            .incoming_unreliable
+            .remove(&mid)
+            .ok_or(ProtocolError::Violated)?;
+        self.completed_unreliable.push_back(mid);
+        if self.completed_unreliable.len() > MAX_INCOMPLETE_UNRELIABLE {
+            self.completed_unreliable.pop_front();
+        }
+        let mut data = BytesMut::with_capacity(m.length as usize);
+        for (offset, fragment) in m.fragments {
+            // overlapping fragments
+            if offset != data.len() as u64 {
+                return Err(ProtocolError::Violated);
+            }
+            data.extend_from_slice(&fragment);
+        }
+        if data.len() as u64 != m.length {
+            return Err(ProtocolError::Violated);
+        }
+        self.metrics
+            .rmsg_ob(m.sid, RemoveReason::Finished, data.len() as u64);
+        Ok(Some(ProtocolEvent::Message {
+            sid: m.sid,
+            data: data.freeze(),
+        }))
+    }
+
+    fn recv_frame(
+        &mut self,
+        frame: ITFrame,
+    ) -> Result<Option<ProtocolEvent>, ProtocolError<S::CustomErr>> {
+        #[cfg(feature = "trace_pedantic")]
+        trace!(?frame, "recv");
+        match frame {
+            ITFrame::Shutdown => Ok(Some(ProtocolEvent::Shutdown)),
+            ITFrame::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => Ok(Some(ProtocolEvent::OpenStream {
+                sid,
+                prio: prio.min(crate::types::HIGHEST_PRIO),
+                promises,
+                guaranteed_bandwidth,
+            })),
+            ITFrame::CloseStream { sid } => Ok(Some(ProtocolEvent::CloseStream { sid })),
+            ITFrame::DataHeader { sid, mid, length } => {
+                let m = ITMessage::new(sid, length, &mut self.itmsg_allocator);
+                self.metrics.rmsg_ib(sid, length);
+                self.incoming.insert(mid, m);
+                Ok(None)
+            },
+            ITFrame::Data { mid, data } => {
+                self.metrics.rdata_frames_b(data.len() as u64);
+                let m = match self.incoming.get_mut(&mid) {
+                    Some(m) => m,
+                    None => {
+                        info!(
+                            ?mid,
+                            "protocol violation by remote side: send Data before Header"
+                        );
+                        return Err(ProtocolError::Violated);
+                    },
+                };
+                m.data.extend_from_slice(&data);
+                if m.data.len() < m.length as usize {
+                    return Ok(None);
+                }
+                // finished, yay
+                let m = self.incoming.remove(&mid).unwrap();
+                self.metrics
+                    .rmsg_ob(m.sid, RemoveReason::Finished, m.data.len() as u64);
+                Ok(Some(ProtocolEvent::Message {
+                    sid: m.sid,
+                    data: m.data.freeze(),
+                }))
+            },
+        }
+    }
+}
+
+#[async_trait]
+impl<D> SendProtocol for UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    type CustomErr = D::CustomErr;
+
+    fn notify_from_recv(&mut self, event: ProtocolEvent) {
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => {
+                self.open_stream(sid, prio, promises, guaranteed_bandwidth);
+            },
+            ProtocolEvent::CloseStream { sid } => {
+                if !self.try_close_stream(sid) {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?sid, "hold back notify close stream");
+                    self.notify_closing_streams.push(sid);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    async fn send(&mut self, event: ProtocolEvent) -> Result<(), ProtocolError<Self::CustomErr>> {
+        #[cfg(feature = "trace_pedantic")]
+        trace!(?event, "send");
+        match event {
+            ProtocolEvent::OpenStream {
+                sid,
+                prio,
+                promises,
+                guaranteed_bandwidth,
+            } => {
+                self.open_stream(sid, prio, promises, guaranteed_bandwidth);
+                self.push_reliable(event.to_frame());
+                self.pack_reliable();
+                self.send_packets().await?;
+            },
+            ProtocolEvent::CloseStream { sid } => {
+                if self.try_close_stream(sid) {
+                    self.push_reliable(event.to_frame());
+                    self.pack_reliable();
+                    self.send_packets().await?;
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!(?sid, "hold back close stream");
+                    self.closing_streams.push(sid);
+                }
+            },
+            ProtocolEvent::Shutdown => {
+                if self.store.is_empty() {
+                    self.push_reliable(event.to_frame());
+                    self.pack_reliable();
+                    self.send_packets().await?;
+                    // This half is usually dropped right after, so it might not be around to
+                    // resend the shutdown
+                    if let Some(packet) = self.packets.back() {
+                        for _ in 1..INIT_REDUNDANCY {
+                            self.drain.send(BytesMut::from(&packet.data[..])).await?;
+                        }
+                    }
+                } else {
+                    #[cfg(feature = "trace_pedantic")]
+                    trace!("hold back shutdown");
+                    self.pending_shutdown = true;
+                }
+            },
+            ProtocolEvent::Message { data, sid } => {
+                self.metrics.smsg_ib(sid, data.len() as u64);
+                self.store.add(data, self.next_mid, sid);
+                self.next_mid += 1;
+            },
+        }
+        Ok(())
+    }
+
+    async fn flush(
+        &mut self,
+        bandwidth: Bandwidth,
+        dt: Duration,
+    ) -> Result</* actual */ Bandwidth, ProtocolError<Self::CustomErr>> {
+        let keep_alive = self.last_send.elapsed() >= KEEP_ALIVE;
+        let ack = self.handle_acks(keep_alive);
+
+        let (frames, _) = self.store.grab(bandwidth, dt);
+        let mut data_frames = 0;
+        let mut data_bandwidth = 0;
+        for (sid, frame) in frames {
+            if let OTFrame::Data { mid: _, data } = &frame {
+                data_bandwidth += data.len();
+                data_frames += 1;
+            }
+            if self.unreliable_streams.contains(&sid) {
+                self.send_unreliable(frame).await?;
+            } else {
+                self.push_reliable(frame);
+            }
+        }
+        self.metrics
+            .sdata_frames_b(data_frames, data_bandwidth as u64);
+
+        for sid in std::mem::take(&mut self.closing_streams) {
+            if self.try_close_stream(sid) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?sid, "close stream, as it's now empty");
+                self.push_reliable(OTFrame::CloseStream { sid });
+            } else {
+                self.closing_streams.push(sid);
+            }
+        }
+
+        for sid in std::mem::take(&mut self.notify_closing_streams) {
+            if self.try_close_stream(sid) {
+                #[cfg(feature = "trace_pedantic")]
+                trace!(?sid, "close stream, as it's now empty");
+            } else {
+                self.notify_closing_streams.push(sid);
+            }
+        }
+
+        if self.pending_shutdown && self.store.is_empty() {
+            #[cfg(feature = "trace_pedantic")]
+            trace!("shutdown, as it's now empty");
+            self.push_reliable(OTFrame::Shutdown {});
+            self.pending_shutdown = false;
+        }
+
+        self.pack_reliable();
+        self.send_packets().await?;
+        if let Some(ack) = ack {
+            self.last_send = Instant::now();
+            self.drain.send(ack).await?;
+        }
+        Ok(data_bandwidth as u64)
+    }
+}
+
+#[async_trait]
+impl<S> RecvProtocol for UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    type CustomErr = S::CustomErr;
+
+    async fn recv(&mut self) -> Result<ProtocolEvent, ProtocolError<Self::CustomErr>> {
+        loop {
+            loop {
+                match ITFrame::read_frame(&mut self.buffer) {
+                    Ok(Some(frame)) => {
+                        if let Some(event) = self.recv_frame(frame)? {
+                            return Ok(event);
+                        }
+                    },
+                    Ok(None) => break, //inner => read more data
+                    Err(()) => return Err(ProtocolError::Violated),
+                }
+            }
+
+            let mut packet = self.sink.recv().await?;
+            if packet.is_empty() {
+                continue;
+            }
+            match packet.get_u8() {
+                // late duplicates of the handshake
+                PACKET_INIT => {},
+                PACKET_RELIABLE => self.recv_reliable(packet)?,
+                PACKET_UNRELIABLE => {
+                    if let Some(event) = self.recv_unreliable(packet)? {
+                        return Ok(event);
+                    }
+                },
+                PACKET_ACK => self.recv_ack(packet)?,
+                _ => return Err(ProtocolError::Violated),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<D> ReliableDrain for UdpSendProtocol<D>
+where
+    D: UnreliableDrain<DataFormat = BytesMut>,
+{
+    type CustomErr = D::CustomErr;
+
+    async fn send(&mut self, frame: InitFrame) -> Result<(), ProtocolError<Self::CustomErr>> {
+        let mut packet = BytesMut::with_capacity(500);
+        packet.put_u8(PACKET_INIT);
+        frame.write_bytes(&mut packet);
+        for _ in 0..INIT_REDUNDANCY {
+            self.drain.send(packet.clone()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S> ReliableSink for UdpRecvProtocol<S>
+where
+    S: UnreliableSink<DataFormat = BytesMut>,
+{
+    type CustomErr = S::CustomErr;
+
+    async fn recv(&mut self) -> Result<InitFrame, ProtocolError<Self::CustomErr>> {
+        for _ in 0..MAX_HANDSHAKE_SKIPPED {
+            let mut packet = self.sink.recv().await?;
+            // the remote might already send other packets if our last frame was lost
+            if packet.first() != Some(&PACKET_INIT) {
+                continue;
+            }
+            packet.advance(1);
+            let frame = InitFrame::read_frame(&mut packet)
+                .unwrap_or_else(|| InitFrame::Raw(packet.to_vec()));
+            // skip the redundant copies
+            if self.last_init.as_ref() != Some(&frame) {
+                self.last_init = Some(frame.clone());
+                return Ok(frame);
+            }
+        }
+        Ok(InitFrame::Raw(
+            b"Didn't receive the next handshake frame".to_vec(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test_utils {
+    //UDP protocol based on Channel, which loses datagrams
+    use super::*;
+    use crate::metrics::{ProtocolMetricCache, ProtocolMetrics};
+    use async_channel::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    pub struct UdpDrain {
+        pub sender: Sender<BytesMut>,
+        /// chance to lose a datagram
+        pub loss: f64,
+        /// lose the first datagrams
+        pub lose_first: usize,
+        rng: StdRng,
+    }
+
+    pub struct UdpSink {
+        pub receiver: Receiver<BytesMut>,
+    }
+
+    impl UdpDrain {
+        pub fn new(sender: Sender<BytesMut>, loss: f64, seed: u64) -> Self {
+            Self {
+                sender,
+                loss,
+                lose_first: 0,
+                rng: StdRng::seed_from_u64(seed),
+            }
+        }
+    }
+
+    /// emulate Udp protocol on Channels, losing `loss` of all datagrams
+    pub fn udp_bound(
+        cap: usize,
+        loss: f64,
+        metrics: Option<ProtocolMetricCache>,
+    ) -> [(UdpSendProtocol<UdpDrain>, UdpRecvProtocol<UdpSink>); 2] {
+        let (s1, r1) = bounded(cap);
+        let (s2, r2) = bounded(cap);
+        let m = metrics.unwrap_or_else(|| {
+            ProtocolMetricCache::new("udp", Arc::new(ProtocolMetrics::new().unwrap()))
+        });
+        [
+            udp_protocols(
+                UdpDrain::new(s1, loss, 1),
+                UdpSink { receiver: r2 },
+                m.clone(),
+            ),
+            udp_protocols(UdpDrain::new(s2, loss, 2), UdpSink { receiver: r1 }, m),
+        ]
+    }
+
+    #[async_trait]
+    impl UnreliableDrain for UdpDrain {
+        type CustomErr = ();
+        type DataFormat = BytesMut;
+
+        async fn send(
+            &mut self,
+            data: Self::DataFormat,
+        ) -> Result<(), ProtocolError<Self::CustomErr>> {
+            if self.lose_first > 0 {
+                self.lose_first -= 1;
+                return Ok(());
+            }
+            if self.rng.gen_bool(self.loss) {
+                return Ok(());
+            }
+            // like a full socket buffer, a full channel drops the datagram
+            match self.sender.try_send(data) {
+                Ok(()) | Err(TrySendError::Full(_)) => Ok(()),
+                Err(TrySendError::Closed(_)) => Err(ProtocolError::Custom(())),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl UnreliableSink for UdpSink {
+        type CustomErr = ();
+        type DataFormat = BytesMut;
+
+        async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError<Self::CustomErr>> {
+            self.receiver
+                .recv()
+                .await
+                .map_err(|_| ProtocolError::Custom(()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        frame::OTFrame,
+        metrics::{ProtocolMetricCache, ProtocolMetrics},
+        types::{Pid, Promises, Sid, STREAM_ID_OFFSET1, STREAM_ID_OFFSET2},
+        udp::{test_utils::*, UdpRecvProtocol, UdpSendProtocol},
+        InitProtocol, ProtocolError, ProtocolEvent, RecvProtocol, SendProtocol,
+    };
+    use bytes::{BufMut, Bytes, BytesMut};
+    use std::{sync::Arc, time::Duration};
+    use tokio::task::JoinHandle;
+
+    type Udp = (UdpSendProtocol<UdpDrain>, UdpRecvProtocol<UdpSink>);
+
+    /// Flush both sides until `events` events were received by `p2`, `p1`
+    /// only receives the acknowledgements.
+    async fn send_and_recv(p1: Udp, p2: Udp, events: usize) -> Vec<ProtocolEvent> {
+        let ((mut s1, mut r1), (mut s2, mut r2)) = (p1, p2);
+        let mut recv: JoinHandle<Vec<ProtocolEvent>> = tokio::spawn(async move {
+            let mut received = vec![];
+            while received.len() < events {
+                received.push(r2.recv().await.unwrap());
+            }
+            received
+        });
+        let acks = tokio::spawn(async move { r1.recv().await });
+        let received = loop {
+            s1.flush(10_000_000, Duration::from_millis(5))
+                .await
+                .unwrap();
+            s2.flush(10_000_000, Duration::from_millis(5))
+                .await
+                .unwrap();
+            tokio::select! {
+                received = &mut recv => break received.unwrap(),
+                _ = tokio::time::sleep(Duration::from_millis(5)) => {},
+            }
+        };
+        acks.abort();
+        received
+    }
+
+    #[tokio::test]
+    async fn handshake_all_good() {
+        let [mut p1, mut p2] = udp_bound(10, 0.0, None);
+        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r1.unwrap(), Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
+        assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+    }
+
+    #[tokio::test]
+    async fn handshake_first_datagrams_lost() {
+        let [mut p1, mut p2] = udp_bound(10, 0.0, None);
+        p1.0.drain.lose_first = 2;
+        p2.0.drain.lose_first = 2;
+        let r1 = tokio::spawn(async move { p1.initialize(true, Pid::fake(2), 1337).await });
+        let r2 = tokio::spawn(async move { p2.initialize(false, Pid::fake(3), 42).await });
+        let (r1, r2) = tokio::join!(r1, r2);
+        assert_eq!(r1.unwrap(), Ok((Pid::fake(3), STREAM_ID_OFFSET1, 42)));
+        assert_eq!(r2.unwrap(), Ok((Pid::fake(2), STREAM_ID_OFFSET2, 1337)));
+    }
+
+    #[tokio::test]
+    async fn send_short_msg() {
+        let [p1, p2] = udp_bound(10, 0.0, None);
+        let (mut s, mut r) = (p1.0, p2.1);
+        let event = ProtocolEvent::OpenStream {
+            sid: Sid::new(10),
+            prio: 3u8,
+            promises: Promises::ORDERED,
+            guaranteed_bandwidth: 1_000_000,
+        };
+        s.send(event.clone()).await.unwrap();
+        assert_eq!(event, r.recv().await.unwrap());
+        let event = ProtocolEvent::Message {
+            sid: Sid::new(10),
+            data: Bytes::from(&[188u8; 600][..]),
+        };
+        s.send(event.clone()).await.unwrap();
+        s.flush(1_000_000, Duration::from_secs(1)).await.unwrap();
+        assert_eq!(event, r.recv().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn reliable_with_loss() {
+        let sid = Sid::new(1);
+        let [mut p1, p2] = udp_bound(1000, 0.3, None);
+        let open = ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::GUARANTEED_DELIVERY,
+            guaranteed_bandwidth: 1_000_000,
+        };
+        p1.0.send(open.clone()).await.unwrap();
+        let mut sent = vec![open];
+        for i in 0..50u8 {
+            let event = ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(vec![i; 1 + i as usize * 300]),
+            };
+            p1.0.send(event.clone()).await.unwrap();
+            sent.push(event);
+        }
+        let received = send_and_recv(p1, p2, sent.len()).await;
+        assert_eq!(sent, received);
+    }
+
+    #[tokio::test]
+    async fn unreliable_without_loss() {
+        let sid = Sid::new(1);
+        let [mut p1, p2] = udp_bound(1000, 0.0, None);
+        p1.0.send(ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::empty(),
+            guaranteed_bandwidth: 1_000_000,
+        })
+        .await
+        .unwrap();
+        let mut sent = vec![];
+        for i in 0..20u8 {
+            let event = ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(vec![i; i as usize * 500]),
+            };
+            p1.0.send(event.clone()).await.unwrap();
+            sent.push(event);
+        }
+        let received = send_and_recv(p1, p2, sent.len() + 1).await;
+        assert_eq!(sent, received[1..]);
+    }
+
+    #[tokio::test]
+    async fn unreliable_with_loss() {
+        let sid = Sid::new(1);
+        let [mut p1, p2] = udp_bound(1000, 0.3, None);
+        p1.0.send(ProtocolEvent::OpenStream {
+            sid,
+            prio: 5u8,
+            promises: Promises::empty(),
+            guaranteed_bandwidth: 1_000_000,
+        })
+        .await
+        .unwrap();
+        let mut sent = vec![];
+        for i in 0..100u8 {
+            let event = ProtocolEvent::Message {
+                sid,
+                data: Bytes::from(vec![i; 100 + i as usize * 20]),
+            };
+            p1.0.send(event.clone()).await.unwrap();
+            sent.push(event);
+        }
+        // the reliable CloseStream arrives after all messages which weren't lost
+        p1.0.send(ProtocolEvent::CloseStream { sid }).await.unwrap();
+        let (mut s1, mut r1) = p1;
+        let (mut s2, mut r2) = p2;
+        let mut recv = tokio::spawn(async move {
+            let mut received = vec![];
+            loop {
+                match r2.recv().await.unwrap() {
+                    ProtocolEvent::CloseStream { .. } => break received,
+                    event => received.push(event),
+                }
+            }
+        });
+        let acks = tokio::spawn(async move { r1.recv().await });
+        let received = loop {
+            s1.flush(10_000_000, Duration::from_millis(5))
+                .await
+                .unwrap();
+            s2.flush(10_000_000, Duration::from_millis(5))
+                .await
+                .unwrap();
+            tokio::select! {
+                received = &mut recv => break received.unwrap(),
+                _ = tokio::time::sleep(Duration::from_millis(5)) => {},
+            }
+        };
+        acks.abort();
+        assert!(matches!(received[0], ProtocolEvent::OpenStream { .. }));
+        let messages = &received[1..];
+        assert!(!messages.is_empty());
+        assert!(messages.len() < sent.len(), "no message was lost");
+        // unordered, but complete and unique
+        for (i, event) in messages.iter().enumerate() {
+            assert!(sent.contains(event));
+            assert!(!messages[i + 1..].contains(event));
+        }
+    }
+
+    #[tokio::test]
+    async fn unreliable_fragments_out_of_order() {
+        let sid = Sid::new(1);
+        let (s, r) = async_channel::bounded(10);
+        let m = ProtocolMetricCache::new("udp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let (s2, _) = async_channel::bounded(10);
+        let (_, mut r) =
+            super::udp_protocols(UdpDrain::new(s2, 0.0, 0), UdpSink { receiver: r }, m);
+
+        let fragment = |offset: u64, data: &[u8]| {
+            let mut bytes = BytesMut::new();
+            bytes.put_u8(super::PACKET_UNRELIABLE);
+            bytes.put_u64_le(7);
+            sid.to_bytes(&mut bytes);
+            bytes.put_u64_le(5);
+            bytes.put_u64_le(offset);
+            bytes.put_slice(data);
+            bytes
+        };
+        s.send(fragment(3, b"ld")).await.unwrap();
+        s.send(fragment(0, b"wor")).await.unwrap();
+        // a duplicate is only received once
+        s.send(fragment(0, b"wor")).await.unwrap();
+        let mut bytes = BytesMut::new();
+        OTFrame::CloseStream { sid }.write_bytes(&mut bytes);
+        let mut packet = BytesMut::new();
+        packet.put_u8(super::PACKET_RELIABLE);
+        packet.put_u64_le(0);
+        packet.put_slice(&bytes);
+        s.send(packet).await.unwrap();
+
+        assert_eq!(r.recv().await.unwrap(), ProtocolEvent::Message {
+            sid,
+            data: Bytes::from_static(b"world"),
+        });
+        assert_eq!(r.recv().await.unwrap(), ProtocolEvent::CloseStream { sid });
+    }
+
+    #[tokio::test]
+    async fn unreliable_length_limit() {
+        let (s, r) = async_channel::bounded(10);
+        let m = ProtocolMetricCache::new("udp", Arc::new(ProtocolMetrics::new().unwrap()));
+        let (s2, _) = async_channel::bounded(10);
+        let (_, mut r) =
+            super::udp_protocols(UdpDrain::new(s2, 0.0, 0), UdpSink { receiver: r }, m);
+
+        let mut bytes = BytesMut::new();
+        bytes.put_u8(super::PACKET_UNRELIABLE);
+        bytes.put_u64_le(7);
+        Sid::new(1).to_bytes(&mut bytes);
+        bytes.put_u64_le(super::MAX_UNRELIABLE_MESSAGE_SIZE as u64 + 1);
+        bytes.put_u64_le(0);
+        bytes.put_slice(b"hello");
+        s.send(bytes).await.unwrap();
+        assert_eq!(r.recv().await, Err(ProtocolError::Violated));
+    }
+}
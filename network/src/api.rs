@@ -3,12 +3,16 @@ use crate::{
     message::{partial_eq_bincode, Message},
     participant::{A2bStreamOpen, S2bShutdownBparticipant},
     scheduler::{A2sConnect, Scheduler},
+    sim::LinkConditions,
 };
 use bytes::Bytes;
 use hashbrown::HashMap;
 #[cfg(feature = "compression")]
 use lz_fear::raw::DecodeError;
-use network_protocol::{Bandwidth, InitProtocolError, Pid, Prio, Promises, Sid};
+use network_protocol::{
+    Bandwidth, InitProtocolError, Pid, Prio, Promises, PublicKey, ServerKey, Sid,
+    MAX_UNRELIABLE_MESSAGE_SIZE,
+};
 #[cfg(feature = "metrics")]
 use prometheus::Registry;
 use serde::{de::DeserializeOwned, Serialize};
@@ -29,24 +33,44 @@ use tracing::*;
 
 type A2sDisconnect = Arc<Mutex<Option<mpsc::UnboundedSender<(Pid, S2bShutdownBparticipant)>>>>;
 
-/// Represents a Tcp, Quic, Udp or Mpsc connection address
+/// Represents a Tcp, Quic, Udp, Mpsc or Sim connection address
 #[derive(Clone, Debug)]
 pub enum ConnectAddr {
     Tcp(SocketAddr),
+    /// Tcp with encryption, the server has to own the pinned key. Without a
+    /// pinned key the handshake fails with
+    /// [`EncryptionError::UnknownServerKey`], so the key can be shown to the
+    /// user and pinned.
+    ///
+    /// [`EncryptionError::UnknownServerKey`]: network_protocol::EncryptionError::UnknownServerKey
+    EncryptedTcp(SocketAddr, Option<PublicKey>),
     Udp(SocketAddr),
     #[cfg(feature = "quic")]
     Quic(SocketAddr, quinn::ClientConfig, String),
     Mpsc(u64),
+    /// Mpsc over a simulated link, it can connect to an Mpsc or Sim listener
+    Sim(u64, LinkConditions),
 }
 
-/// Represents a Tcp, Quic, Udp or Mpsc listen address
+/// Represents a Tcp, Quic, Udp, Mpsc or Sim listen address
 #[derive(Clone, Debug)]
 pub enum ListenAddr {
     Tcp(SocketAddr),
+    /// Tcp which encrypts connections of clients asking for it, with the key
+    /// identifying the server
+    EncryptedTcp {
+        addr: SocketAddr,
+        key: Arc<ServerKey>,
+        /// Reject clients which don't ask for encryption
+        required: bool,
+    },
     Udp(SocketAddr),
     #[cfg(feature = "quic")]
     Quic(SocketAddr, quinn::ServerConfig),
     Mpsc(u64),
+    /// Mpsc over a simulated link, it shares its addresses with Mpsc. If both
+    /// ends use a simulated link, the conditions of both apply.
+    Sim(u64, LinkConditions),
 }
 
 /// a Participant can throw different events, you are obligated to carefully
@@ -92,10 +116,8 @@ pub struct Stream {
     local_pid: Pid,
     remote_pid: Pid,
     sid: Sid,
-    #[allow(dead_code)]
     prio: Prio,
     promises: Promises,
-    #[allow(dead_code)]
     guaranteed_bandwidth: Bandwidth,
     send_closed: Arc<AtomicBool>,
     a2b_msg_s: crossbeam_channel::Sender<(Sid, Bytes)>,
@@ -142,6 +164,10 @@ pub enum StreamError {
     #[cfg(feature = "compression")]
     Compression(DecodeError),
     Deserialize(bincode::Error),
+    /// The message is larger than [`MAX_UNRELIABLE_MESSAGE_SIZE`], which
+    /// streams without [`Promises::ORDERED`] or
+    /// [`Promises::GUARANTEED_DELIVERY`] can't send
+    MessageTooLarge,
 }
 
 /// All Parameters of a Stream, can be used to generate RawMessages
@@ -370,9 +396,6 @@ impl Network {
     ///     let p1 = network
     ///         .connect(ConnectAddr::Tcp("127.0.0.1:2010".parse().unwrap()))
     ///         .await?;
-    ///     # //this doesn't work yet, so skip the test
-    ///     # //TODO fixme!
-    ///     # return Ok(());
     ///     let p2 = network
     ///         .connect(ConnectAddr::Udp("127.0.0.1:2011".parse().unwrap()))
     ///         .await?;
@@ -859,7 +882,9 @@ impl Stream {
     /// correctly. If a error occurred, the next call will return an Error.
     /// If the [`Participant`] disconnected it will also be unable to be used
     /// any more. A [`StreamError`] will be returned in the error case, e.g.
-    /// when the `Stream` got closed already.
+    /// when the `Stream` got closed already, or when the message is too large
+    /// for a `Stream` without [`Promises::ORDERED`] or
+    /// [`Promises::GUARANTEED_DELIVERY`].
     ///
     /// Note when a `Stream` is dropped locally, it will still send all
     /// messages, though the `drop` will return immediately, however, when a
@@ -965,6 +990,13 @@ impl Stream {
         if self.send_closed.load(Ordering::Relaxed) {
             return Err(StreamError::StreamClosed);
         }
+        // UDP channels put these together in memory, so the remote limits their size
+        if !self.promises.contains(Promises::ORDERED)
+            && !self.promises.contains(Promises::GUARANTEED_DELIVERY)
+            && message.data.len() > MAX_UNRELIABLE_MESSAGE_SIZE
+        {
+            return Err(StreamError::MessageTooLarge);
+        }
         #[cfg(debug_assertions)]
         message.verify(self.params());
         self.a2b_msg_s.send((self.sid, message.data))?;
@@ -1126,6 +1158,13 @@ impl Stream {
             promises: self.promises,
         }
     }
+
+    /// The arguments this `Stream` was opened with, see [`Participant::open`]
+    pub fn prio(&self) -> Prio { self.prio }
+
+    pub fn promises(&self) -> Promises { self.promises }
+
+    pub fn guaranteed_bandwidth(&self) -> Bandwidth { self.guaranteed_bandwidth }
 }
 
 impl PartialEq for Participant {
@@ -1295,6 +1334,7 @@ impl core::fmt::Display for StreamError {
             #[cfg(feature = "compression")]
             StreamError::Compression(err) => write!(f, "compression error on message: {}", err),
             StreamError::Deserialize(err) => write!(f, "deserialize error on message: {}", err),
+            StreamError::MessageTooLarge => write!(f, "message too large for an unreliable stream"),
         }
     }
 }
@@ -1341,6 +1381,7 @@ impl PartialEq for StreamError {
                 #[cfg(feature = "compression")]
                 StreamError::Compression(_) => false,
                 StreamError::Deserialize(_) => false,
+                StreamError::MessageTooLarge => false,
             },
             #[cfg(feature = "compression")]
             StreamError::Compression(err) => match other {
@@ -1348,13 +1389,16 @@ impl PartialEq for StreamError {
                 #[cfg(feature = "compression")]
                 StreamError::Compression(other_err) => err == other_err,
                 StreamError::Deserialize(_) => false,
+                StreamError::MessageTooLarge => false,
             },
             StreamError::Deserialize(err) => match other {
                 StreamError::StreamClosed => false,
                 #[cfg(feature = "compression")]
                 StreamError::Compression(_) => false,
                 StreamError::Deserialize(other_err) => partial_eq_bincode(err, other_err),
+                StreamError::MessageTooLarge => false,
             },
+            StreamError::MessageTooLarge => matches!(other, StreamError::MessageTooLarge),
         }
     }
 }
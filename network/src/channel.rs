@@ -1,4 +1,7 @@
-use crate::api::{ConnectAddr, NetworkConnectError};
+use crate::{
+    api::{ConnectAddr, NetworkConnectError},
+    sim::{LinkConditions, SimLink},
+};
 use async_trait::async_trait;
 use bytes::BytesMut;
 use futures_util::FutureExt;
@@ -6,27 +9,29 @@ use futures_util::FutureExt;
 use futures_util::StreamExt;
 use hashbrown::HashMap;
 use network_protocol::{
-    Bandwidth, Cid, InitProtocolError, MpscMsg, MpscRecvProtocol, MpscSendProtocol, Pid,
-    ProtocolError, ProtocolEvent, ProtocolMetricCache, ProtocolMetrics, Sid, TcpRecvProtocol,
-    TcpSendProtocol, UnreliableDrain, UnreliableSink,
+    Bandwidth, Cid, Encryption, InitProtocolError, MpscMsg, MpscRecvProtocol, MpscSendProtocol,
+    Pid, ProtocolError, ProtocolEvent, ProtocolMetricCache, ProtocolMetrics, Sid, TcpRecvProtocol,
+    TcpSendProtocol, UdpRecvProtocol, UdpSendProtocol, UnreliableDrain, UnreliableSink,
 };
 #[cfg(feature = "quic")]
 use network_protocol::{QuicDataFormat, QuicDataFormatStream, QuicRecvProtocol, QuicSendProtocol};
 use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
     io,
     net::SocketAddr,
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net,
     net::tcp::{OwnedReadHalf, OwnedWriteHalf},
     select,
-    sync::{mpsc, oneshot, Mutex},
+    sync::{mpsc, mpsc::error::TrySendError, oneshot, Mutex},
 };
 use tracing::{error, info, trace, warn};
 
@@ -35,6 +40,7 @@ use tracing::{error, info, trace, warn};
 pub(crate) enum Protocols {
     Tcp((TcpSendProtocol<TcpDrain>, TcpRecvProtocol<TcpSink>)),
     Mpsc((MpscSendProtocol<MpscDrain>, MpscRecvProtocol<MpscSink>)),
+    Udp((UdpSendProtocol<UdpDrain>, UdpRecvProtocol<UdpSink>)),
     #[cfg(feature = "quic")]
     Quic((QuicSendProtocol<QuicDrain>, QuicRecvProtocol<QuicSink>)),
 }
@@ -43,6 +49,7 @@ pub(crate) enum Protocols {
 pub(crate) enum SendProtocols {
     Tcp(TcpSendProtocol<TcpDrain>),
     Mpsc(MpscSendProtocol<MpscDrain>),
+    Udp(UdpSendProtocol<UdpDrain>),
     #[cfg(feature = "quic")]
     Quic(QuicSendProtocol<QuicDrain>),
 }
@@ -51,6 +58,7 @@ pub(crate) enum SendProtocols {
 pub(crate) enum RecvProtocols {
     Tcp(TcpRecvProtocol<TcpSink>),
     Mpsc(MpscRecvProtocol<MpscSink>),
+    Udp(UdpRecvProtocol<UdpSink>),
     #[cfg(feature = "quic")]
     Quic(QuicRecvProtocol<QuicSink>),
 }
@@ -69,9 +77,15 @@ pub(crate) type C2sProtocol = (Protocols, ConnectAddr, Cid);
 
 impl Protocols {
     const MPSC_CHANNEL_BOUND: usize = 1000;
+    /// Datagrams received but not processed yet, more are dropped
+    const UDP_CHANNEL_BOUND: usize = 1000;
+    /// How often a connecting client asks for a cookie, and how often it echoes
+    /// it back, see [`UdpCookies`]
+    const UDP_HELLO_REDUNDANCY: usize = 3;
 
     pub(crate) async fn with_tcp_connect(
         addr: SocketAddr,
+        encryption: Option<Encryption>,
         metrics: ProtocolMetricCache,
     ) -> Result<Self, NetworkConnectError> {
         let stream = net::TcpStream::connect(addr)
@@ -85,11 +99,12 @@ impl Protocols {
             "Connecting Tcp to: {}",
             stream.peer_addr().map_err(NetworkConnectError::Io)?
         );
-        Ok(Self::new_tcp(stream, metrics))
+        Ok(Self::new_tcp(stream, encryption, metrics))
     }
 
     pub(crate) async fn with_tcp_listen(
         addr: SocketAddr,
+        encryption: Option<Encryption>,
         cids: Arc<AtomicU64>,
         metrics: Arc<ProtocolMetrics>,
         s2s_stop_listening_r: oneshot::Receiver<()>,
@@ -134,7 +149,7 @@ impl Protocols {
                 info!(?remote_addr, ?cid, "Accepting Tcp from");
                 let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
                 let _ = c2s_protocol_s.send((
-                    Self::new_tcp(stream, metrics.clone()),
+                    Self::new_tcp(stream, encryption.clone(), metrics.clone()),
                     ConnectAddr::Tcp(remote_addr),
                     cid,
                 ));
@@ -143,9 +158,16 @@ impl Protocols {
         Ok(())
     }
 
-    pub(crate) fn new_tcp(stream: net::TcpStream, metrics: ProtocolMetricCache) -> Self {
+    pub(crate) fn new_tcp(
+        stream: net::TcpStream,
+        encryption: Option<Encryption>,
+        metrics: ProtocolMetricCache,
+    ) -> Self {
         let (r, w) = stream.into_split();
-        let sp = TcpSendProtocol::new(TcpDrain { half: w }, metrics.clone());
+        let mut sp = TcpSendProtocol::new(TcpDrain { half: w }, metrics.clone());
+        if let Some(encryption) = encryption {
+            sp = sp.with_encryption(encryption);
+        }
         let rp = TcpRecvProtocol::new(
             TcpSink {
                 half: r,
@@ -158,6 +180,7 @@ impl Protocols {
 
     pub(crate) async fn with_mpsc_connect(
         addr: u64,
+        conditions: Option<LinkConditions>,
         metrics: ProtocolMetricCache,
     ) -> Result<Self, NetworkConnectError> {
         let mpsc_s = MPSC_POOL
@@ -171,7 +194,12 @@ impl Protocols {
                 ))
             })?
             .clone();
+        let link = conditions.map(SimLink::new);
         let (remote_to_local_s, remote_to_local_r) = mpsc::channel(Self::MPSC_CHANNEL_BOUND);
+        let remote_to_local_s = match &link {
+            Some(link) => link.relay(remote_to_local_s, 0, Self::MPSC_CHANNEL_BOUND),
+            None => remote_to_local_s,
+        };
         let (local_to_remote_oneshot_s, local_to_remote_oneshot_r) = oneshot::channel();
         mpsc_s
             .send((remote_to_local_s, local_to_remote_oneshot_s))
@@ -184,7 +212,11 @@ impl Protocols {
         let local_to_remote_s = local_to_remote_oneshot_r
             .await
             .map_err(|e| NetworkConnectError::Io(io::Error::new(io::ErrorKind::BrokenPipe, e)))?;
-        info!(?addr, "Connecting Mpsc");
+        let local_to_remote_s = match &link {
+            Some(link) => link.relay(local_to_remote_s, 1, Self::MPSC_CHANNEL_BOUND),
+            None => local_to_remote_s,
+        };
+        info!(?addr, simulated = link.is_some(), "Connecting Mpsc");
         Ok(Self::new_mpsc(
             local_to_remote_s,
             remote_to_local_r,
@@ -194,6 +226,7 @@ impl Protocols {
 
     pub(crate) async fn with_mpsc_listen(
         addr: u64,
+        conditions: Option<LinkConditions>,
         cids: Arc<AtomicU64>,
         metrics: Arc<ProtocolMetrics>,
         s2s_stop_listening_r: oneshot::Receiver<()>,
@@ -208,8 +241,16 @@ impl Protocols {
                     next = mpsc_r.recv().fuse() => next,
                     _ = &mut end_receiver => None,
             } {
+                let link = conditions.clone().map(SimLink::new);
                 let (remote_to_local_s, remote_to_local_r) =
                     mpsc::channel(Self::MPSC_CHANNEL_BOUND);
+                let (remote_to_local_s, local_to_remote_s) = match &link {
+                    Some(link) => (
+                        link.relay(remote_to_local_s, 0, Self::MPSC_CHANNEL_BOUND),
+                        link.relay(local_to_remote_s, 1, Self::MPSC_CHANNEL_BOUND),
+                    ),
+                    None => (remote_to_local_s, local_to_remote_s),
+                };
                 if let Err(e) = local_remote_to_local_s.send(remote_to_local_s) {
                     error!(?e, "mpsc listen aborted");
                 }
@@ -217,9 +258,13 @@ impl Protocols {
                 let cid = cids.fetch_add(1, Ordering::Relaxed);
                 info!(?addr, ?cid, "Accepting Mpsc from");
                 let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
+                let connect_addr = match &conditions {
+                    Some(conditions) => ConnectAddr::Sim(addr, conditions.clone()),
+                    None => ConnectAddr::Mpsc(addr),
+                };
                 let _ = c2s_protocol_s.send((
                     Self::new_mpsc(local_to_remote_s, remote_to_local_r, metrics.clone()),
-                    ConnectAddr::Mpsc(addr),
+                    connect_addr,
                     cid,
                 ));
             }
@@ -238,6 +283,202 @@ impl Protocols {
         Protocols::Mpsc((sp, rp))
     }
 
+    pub(crate) async fn with_udp_connect(
+        addr: SocketAddr,
+        metrics: ProtocolMetricCache,
+    ) -> Result<Self, NetworkConnectError> {
+        use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+        let bindsock = match addr {
+            SocketAddr::V4(_) => SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+            SocketAddr::V6(_) => SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+        };
+        let socket = Arc::new(
+            net::UdpSocket::bind(bindsock)
+                .await
+                .map_err(NetworkConnectError::Io)?,
+        );
+        let cookie = Self::request_udp_cookie(&socket, addr).await?;
+        let (udp_s, udp_r) = mpsc::channel(Self::UDP_CHANNEL_BOUND);
+        let reader = Arc::clone(&socket);
+        tokio::spawn(async move {
+            let mut buffer = [0u8; UDP_BUFFER_SIZE];
+            while let Some(data) = select! {
+                next = reader.recv_from(&mut buffer).fuse() => Some(next),
+                _ = udp_s.closed().fuse() => None,
+            } {
+                let (size, remote_addr) = match data {
+                    Ok(v) => v,
+                    Err(e) => {
+                        trace!(?e, "UdpSocket Error, ignoring datagram");
+                        continue;
+                    },
+                };
+                // late duplicates of the cookie
+                if remote_addr != addr || UdpCookies::is_cookie(&buffer[..size]) {
+                    continue;
+                }
+                if let Err(TrySendError::Closed(_)) =
+                    udp_s.try_send(BytesMut::from(&buffer[..size]))
+                {
+                    break;
+                }
+            }
+        });
+        for _ in 0..Self::UDP_HELLO_REDUNDANCY {
+            socket
+                .send_to(&cookie, addr)
+                .await
+                .map_err(NetworkConnectError::Io)?;
+        }
+        info!("Connecting Udp to: {}", &addr);
+        Ok(Self::new_udp(socket, addr, udp_r, metrics))
+    }
+
+    /// Send empty hellos to the listener at `addr` until it answers with a
+    /// cookie
+    async fn request_udp_cookie(
+        socket: &net::UdpSocket,
+        addr: SocketAddr,
+    ) -> Result<[u8; UdpCookies::SIZE], NetworkConnectError> {
+        let mut buffer = [0u8; UDP_BUFFER_SIZE];
+        for _ in 0..Self::UDP_HELLO_REDUNDANCY {
+            socket
+                .send_to(&[], addr)
+                .await
+                .map_err(NetworkConnectError::Io)?;
+            let deadline = tokio::time::Instant::now() + UDP_COOKIE_TIMEOUT;
+            while let Ok(received) =
+                tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await
+            {
+                match received {
+                    Ok((size, remote_addr))
+                        if remote_addr == addr && UdpCookies::is_cookie(&buffer[..size]) =>
+                    {
+                        let mut cookie = [0u8; UdpCookies::SIZE];
+                        cookie.copy_from_slice(&buffer[..size]);
+                        return Ok(cookie);
+                    },
+                    Ok(_) => {},
+                    Err(e) => trace!(?e, "UdpSocket Error, ignoring datagram"),
+                }
+            }
+        }
+        Err(NetworkConnectError::Io(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "the udp listener didn't answer with a cookie",
+        )))
+    }
+
+    pub(crate) async fn with_udp_listen(
+        addr: SocketAddr,
+        cids: Arc<AtomicU64>,
+        metrics: Arc<ProtocolMetrics>,
+        s2s_stop_listening_r: oneshot::Receiver<()>,
+        c2s_protocol_s: mpsc::UnboundedSender<C2sProtocol>,
+    ) -> io::Result<()> {
+        use socket2::{Domain, Socket, Type};
+        let domain = Domain::for_address(addr);
+        let socket2_socket = Socket::new(domain, Type::DGRAM, None)?;
+        if domain == Domain::IPV6 {
+            socket2_socket.set_only_v6(true)?
+        }
+        socket2_socket.set_nonblocking(true)?; // Needed by Tokio
+        let socket2_addr = addr.into();
+        socket2_socket.bind(&socket2_addr)?;
+        let std_socket: std::net::UdpSocket = socket2_socket.into();
+        let socket = Arc::new(net::UdpSocket::from_std(std_socket)?);
+        trace!(?addr, "Udp Listener bound");
+        let mut end_receiver = s2s_stop_listening_r.fuse();
+        tokio::spawn(async move {
+            let cookies = UdpCookies::new();
+            // All channels share the socket, the datagrams are forwarded by their sender
+            let mut remotes: HashMap<SocketAddr, mpsc::Sender<BytesMut>> = HashMap::new();
+            let mut listening = true;
+            let mut buffer = [0u8; UDP_BUFFER_SIZE];
+            loop {
+                let data = select! {
+                    next = socket.recv_from(&mut buffer).fuse() => next,
+                    _ = &mut end_receiver => {
+                        // existing channels keep working, but no new ones are accepted
+                        listening = false;
+                        remotes.retain(|_, sender| !sender.is_closed());
+                        if remotes.is_empty() {
+                            break;
+                        }
+                        continue;
+                    },
+                };
+                let (size, remote_addr) = match data {
+                    Ok(v) => v,
+                    Err(e) => {
+                        trace!(?e, "UdpSocket Error, ignoring datagram");
+                        continue;
+                    },
+                };
+                let datagram = &buffer[..size];
+                if let Some(sender) = remotes.get(&remote_addr) {
+                    // late duplicates of the hello or the cookie
+                    if datagram.is_empty() || UdpCookies::is_cookie(datagram) {
+                        continue;
+                    }
+                    match sender.try_send(BytesMut::from(datagram)) {
+                        // a full channel drops the datagram, like a full socket buffer would
+                        Ok(()) | Err(TrySendError::Full(_)) => continue,
+                        Err(TrySendError::Closed(_)) => {
+                            remotes.remove(&remote_addr);
+                        },
+                    }
+                }
+                if !listening {
+                    if remotes.is_empty() {
+                        break;
+                    }
+                    continue;
+                }
+                if datagram.is_empty() {
+                    // answer the hello of a connecting client without keeping any state, so a
+                    // spoofed address can't make us allocate anything
+                    let _ = socket.try_send_to(&cookies.issue(remote_addr), remote_addr);
+                    continue;
+                }
+                // only the echoed cookie opens a new channel
+                if !cookies.verify(remote_addr, datagram) {
+                    continue;
+                }
+                let (udp_s, udp_r) = mpsc::channel(Self::UDP_CHANNEL_BOUND);
+                remotes.insert(remote_addr, udp_s);
+
+                let cid = cids.fetch_add(1, Ordering::Relaxed);
+                info!(?remote_addr, ?cid, "Accepting Udp from");
+                let metrics = ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&metrics));
+                let _ = c2s_protocol_s.send((
+                    Self::new_udp(Arc::clone(&socket), remote_addr, udp_r, metrics.clone()),
+                    ConnectAddr::Udp(remote_addr),
+                    cid,
+                ));
+            }
+        });
+        Ok(())
+    }
+
+    pub(crate) fn new_udp(
+        socket: Arc<net::UdpSocket>,
+        remote_addr: SocketAddr,
+        receiver: mpsc::Receiver<BytesMut>,
+        metrics: ProtocolMetricCache,
+    ) -> Self {
+        let (sp, rp) = network_protocol::udp_protocols(
+            UdpDrain {
+                socket,
+                remote_addr,
+            },
+            UdpSink { receiver },
+            metrics,
+        );
+        Protocols::Udp((sp, rp))
+    }
+
     #[cfg(feature = "quic")]
     pub(crate) async fn with_quic_connect(
         addr: SocketAddr,
@@ -376,6 +617,7 @@ impl Protocols {
         match self {
             Protocols::Tcp((s, r)) => (SendProtocols::Tcp(s), RecvProtocols::Tcp(r)),
             Protocols::Mpsc((s, r)) => (SendProtocols::Mpsc(s), RecvProtocols::Mpsc(r)),
+            Protocols::Udp((s, r)) => (SendProtocols::Udp(s), RecvProtocols::Udp(r)),
             #[cfg(feature = "quic")]
             Protocols::Quic((s, r)) => (SendProtocols::Quic(s), RecvProtocols::Quic(r)),
         }
@@ -395,6 +637,7 @@ impl network_protocol::InitProtocol for Protocols {
         match self {
             Protocols::Tcp(p) => p.initialize(initializer, local_pid, secret).await,
             Protocols::Mpsc(p) => p.initialize(initializer, local_pid, secret).await,
+            Protocols::Udp(p) => p.initialize(initializer, local_pid, secret).await,
             #[cfg(feature = "quic")]
             Protocols::Quic(p) => p.initialize(initializer, local_pid, secret).await,
         }
@@ -409,6 +652,7 @@ impl network_protocol::SendProtocol for SendProtocols {
         match self {
             SendProtocols::Tcp(s) => s.notify_from_recv(event),
             SendProtocols::Mpsc(s) => s.notify_from_recv(event),
+            SendProtocols::Udp(s) => s.notify_from_recv(event),
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.notify_from_recv(event),
         }
@@ -418,6 +662,7 @@ impl network_protocol::SendProtocol for SendProtocols {
         match self {
             SendProtocols::Tcp(s) => s.send(event).await,
             SendProtocols::Mpsc(s) => s.send(event).await,
+            SendProtocols::Udp(s) => s.send(event).await,
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.send(event).await,
         }
@@ -431,6 +676,7 @@ impl network_protocol::SendProtocol for SendProtocols {
         match self {
             SendProtocols::Tcp(s) => s.flush(bandwidth, dt).await,
             SendProtocols::Mpsc(s) => s.flush(bandwidth, dt).await,
+            SendProtocols::Udp(s) => s.flush(bandwidth, dt).await,
             #[cfg(feature = "quic")]
             SendProtocols::Quic(s) => s.flush(bandwidth, dt).await,
         }
@@ -445,6 +691,7 @@ impl network_protocol::RecvProtocol for RecvProtocols {
         match self {
             RecvProtocols::Tcp(r) => r.recv().await,
             RecvProtocols::Mpsc(r) => r.recv().await,
+            RecvProtocols::Udp(r) => r.recv().await,
             #[cfg(feature = "quic")]
             RecvProtocols::Quic(r) => r.recv().await,
         }
@@ -561,6 +808,120 @@ impl UnreliableSink for MpscSink {
     }
 }
 
+///////////////////////////////////////
+//// UDP
+/// Big enough for every datagram the protocol sends
+const UDP_BUFFER_SIZE: usize = 1500;
+/// How long a connecting client waits for a cookie before asking again
+const UDP_COOKIE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Stateless cookies a connecting UDP client has to echo back before the
+/// listener opens a channel for it, which proves that the client can receive
+/// datagrams at its source address.
+///
+/// The datagram of a cookie is a 0 followed by a keyed hash of the address
+/// and the current time window, no valid packet of the protocol starts with
+/// a 0.
+struct UdpCookies {
+    key: RandomState,
+    start: Instant,
+}
+
+impl UdpCookies {
+    const LIFETIME: Duration = Duration::from_secs(10);
+    const MARKER: u8 = 0;
+    const SIZE: usize = 9;
+
+    fn new() -> Self {
+        Self {
+            key: RandomState::new(),
+            start: Instant::now(),
+        }
+    }
+
+    fn is_cookie(datagram: &[u8]) -> bool {
+        datagram.len() == Self::SIZE && datagram[0] == Self::MARKER
+    }
+
+    fn window(&self) -> u64 { self.start.elapsed().as_secs() / Self::LIFETIME.as_secs() }
+
+    fn hash(&self, addr: SocketAddr, window: u64) -> u64 {
+        let mut hasher = self.key.build_hasher();
+        (addr, window).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn issue(&self, addr: SocketAddr) -> [u8; Self::SIZE] {
+        let mut cookie = [Self::MARKER; Self::SIZE];
+        cookie[1..].copy_from_slice(&self.hash(addr, self.window()).to_le_bytes());
+        cookie
+    }
+
+    /// Cookies of the previous time window are still accepted, so one issued
+    /// right before the window changes can be used
+    fn verify(&self, addr: SocketAddr, datagram: &[u8]) -> bool {
+        if !Self::is_cookie(datagram) {
+            return false;
+        }
+        let mut hash = [0u8; 8];
+        hash.copy_from_slice(&datagram[1..]);
+        let hash = u64::from_le_bytes(hash);
+        let window = self.window();
+        hash == self.hash(addr, window)
+            || window
+                .checked_sub(1)
+                .map_or(false, |window| hash == self.hash(addr, window))
+    }
+}
+/// The remote sends at least a keep-alive every 500ms, after this long without
+/// anything it's considered gone
+const UDP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub struct UdpDrain {
+    socket: Arc<net::UdpSocket>,
+    remote_addr: SocketAddr,
+}
+
+#[derive(Debug)]
+pub struct UdpSink {
+    receiver: mpsc::Receiver<BytesMut>,
+}
+
+#[async_trait]
+impl UnreliableDrain for UdpDrain {
+    type CustomErr = ProtocolsError;
+    type DataFormat = BytesMut;
+
+    async fn send(&mut self, data: Self::DataFormat) -> Result<(), ProtocolError<Self::CustomErr>> {
+        self.socket
+            .send_to(&data, self.remote_addr)
+            .await
+            .map(|_| ())
+            .map_err(|e| ProtocolError::Custom(ProtocolsError::Udp(e)))
+    }
+}
+
+#[async_trait]
+impl UnreliableSink for UdpSink {
+    type CustomErr = ProtocolsError;
+    type DataFormat = BytesMut;
+
+    async fn recv(&mut self) -> Result<Self::DataFormat, ProtocolError<Self::CustomErr>> {
+        match tokio::time::timeout(UDP_TIMEOUT, self.receiver.recv()).await {
+            Ok(Some(data)) => Ok(data),
+            Ok(None) => Err(ProtocolError::Custom(ProtocolsError::Udp(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "udp socket closed",
+            )))),
+            Err(_) => Err(ProtocolError::Custom(ProtocolsError::Udp(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no datagram received in time",
+            )))),
+        }
+    }
+}
+
 ///////////////////////////////////////
 //// QUIC
 #[cfg(feature = "quic")]
@@ -737,6 +1098,19 @@ mod tests {
     use std::sync::Arc;
     use tokio::net::{TcpListener, TcpStream};
 
+    #[test]
+    fn udp_cookies() {
+        let cookies = UdpCookies::new();
+        let addr = SocketAddr::from(([127, 0, 0, 1], 14004));
+        let cookie = cookies.issue(addr);
+        assert!(UdpCookies::is_cookie(&cookie));
+        assert!(cookies.verify(addr, &cookie));
+        assert!(!cookies.verify(SocketAddr::from(([127, 0, 0, 1], 14005)), &cookie));
+        assert!(!UdpCookies::new().verify(addr, &cookie));
+        assert!(!cookies.verify(addr, &cookie[..UdpCookies::SIZE - 1]));
+        assert!(!cookies.verify(addr, &[]));
+    }
+
     #[tokio::test]
     async fn tokio_sinks() {
         let listener = TcpListener::bind("127.0.0.1:5000").await.unwrap();
@@ -747,8 +1121,8 @@ mod tests {
         let client = TcpStream::connect("127.0.0.1:5000").await.unwrap();
         let (_listener, server) = r1.await.unwrap();
         let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
-        let client = Protocols::new_tcp(client, metrics.clone());
-        let server = Protocols::new_tcp(server, metrics);
+        let client = Protocols::new_tcp(client, None, metrics.clone());
+        let server = Protocols::new_tcp(server, None, metrics);
         let (mut s, _) = client.split();
         let (_, mut r) = server.split();
         let event = ProtocolEvent::OpenStream {
@@ -796,8 +1170,8 @@ mod tests {
         let client = TcpStream::connect("127.0.0.1:5001").await.unwrap();
         let (_listener, server) = r1.await.unwrap();
         let metrics = ProtocolMetricCache::new("0", Arc::new(ProtocolMetrics::new().unwrap()));
-        let client = Protocols::new_tcp(client, metrics.clone());
-        let server = Protocols::new_tcp(server, metrics);
+        let client = Protocols::new_tcp(client, None, metrics.clone());
+        let server = Protocols::new_tcp(server, None, metrics);
         let (s, _) = client.split();
         let (_, mut r) = server.split();
         let e = tokio::spawn(async move { r.recv().await });
@@ -105,6 +105,7 @@ mod message;
 mod metrics;
 mod participant;
 mod scheduler;
+mod sim;
 mod util;
 
 pub use api::{
@@ -112,4 +113,8 @@ pub use api::{
     ParticipantError, ParticipantEvent, Stream, StreamError, StreamParams,
 };
 pub use message::Message;
-pub use network_protocol::{InitProtocolError, Pid, Promises};
+pub use network_protocol::{
+    EncryptionError, InitProtocolError, InvalidKey, Pid, Promises, PublicKey, ServerKey,
+    MAX_UNRELIABLE_MESSAGE_SIZE,
+};
+pub use sim::LinkConditions;
@@ -16,11 +16,11 @@ pub(crate) enum ProtocolInfo {
 impl From<ListenAddr> for ProtocolInfo {
     fn from(other: ListenAddr) -> ProtocolInfo {
         match other {
-            ListenAddr::Tcp(s) => ProtocolInfo::Tcp(s),
+            ListenAddr::Tcp(s) | ListenAddr::EncryptedTcp { addr: s, .. } => ProtocolInfo::Tcp(s),
             ListenAddr::Udp(s) => ProtocolInfo::Udp(s),
             #[cfg(feature = "quic")]
             ListenAddr::Quic(s, _) => ProtocolInfo::Quic(s),
-            ListenAddr::Mpsc(s) => ProtocolInfo::Mpsc(s),
+            ListenAddr::Mpsc(s) | ListenAddr::Sim(s, _) => ProtocolInfo::Mpsc(s),
         }
     }
 }
@@ -247,8 +247,10 @@ impl NetworkMetrics {
 fn protocolconnect_name(protocol: &ConnectAddr) -> &str {
     match protocol {
         ConnectAddr::Tcp(_) => "tcp",
+        ConnectAddr::EncryptedTcp(_, _) => "encrypted_tcp",
         ConnectAddr::Udp(_) => "udp",
         ConnectAddr::Mpsc(_) => "mpsc",
+        ConnectAddr::Sim(_, _) => "sim",
         #[cfg(feature = "quic")]
         ConnectAddr::Quic(_, _, _) => "quic",
     }
@@ -258,8 +260,10 @@ fn protocolconnect_name(protocol: &ConnectAddr) -> &str {
 fn protocollisten_name(protocol: &ListenAddr) -> &str {
     match protocol {
         ListenAddr::Tcp(_) => "tcp",
+        ListenAddr::EncryptedTcp { .. } => "encrypted_tcp",
         ListenAddr::Udp(_) => "udp",
         ListenAddr::Mpsc(_) => "mpsc",
+        ListenAddr::Sim(_, _) => "sim",
         #[cfg(feature = "quic")]
         ListenAddr::Quic(_, _) => "quic",
     }
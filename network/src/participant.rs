@@ -221,6 +221,15 @@ impl BParticipant {
             } else {
                 None
             }
+        ).or_else(
+            // check for udp
+            || if network_protocol::UdpSendProtocol::<crate::channel::UdpDrain>::supported_promises()
+                .contains(promises)
+            {
+                all.data.iter().find(|(_, p)| matches!(p, SendProtocols::Udp(_))).map(|(c, _)| *c)
+            } else {
+                None
+            }
         ).or_else(
             || {
                 warn!("couldn't satisfy promises");
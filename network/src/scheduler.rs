@@ -6,7 +6,7 @@ use crate::{
 };
 use futures_util::StreamExt;
 use hashbrown::HashMap;
-use network_protocol::{Cid, Pid, ProtocolMetricCache, ProtocolMetrics};
+use network_protocol::{Cid, Encryption, Pid, ProtocolMetricCache, ProtocolMetrics};
 #[cfg(feature = "metrics")]
 use prometheus::Registry;
 use rand::Rng;
@@ -190,6 +190,25 @@ impl Scheduler {
                         ListenAddr::Tcp(addr) => {
                             Protocols::with_tcp_listen(
                                 addr,
+                                None,
+                                cids,
+                                metrics,
+                                s2s_stop_listening_r,
+                                c2s_protocol_s,
+                            )
+                            .await
+                        },
+                        ListenAddr::EncryptedTcp {
+                            addr,
+                            ref key,
+                            required,
+                        } => {
+                            Protocols::with_tcp_listen(
+                                addr,
+                                Some(Encryption::Server {
+                                    key: Arc::clone(key),
+                                    required,
+                                }),
                                 cids,
                                 metrics,
                                 s2s_stop_listening_r,
@@ -211,6 +230,28 @@ impl Scheduler {
                         },
                         ListenAddr::Mpsc(addr) => {
                             Protocols::with_mpsc_listen(
+                                addr,
+                                None,
+                                cids,
+                                metrics,
+                                s2s_stop_listening_r,
+                                c2s_protocol_s,
+                            )
+                            .await
+                        },
+                        ListenAddr::Sim(addr, conditions) => {
+                            Protocols::with_mpsc_listen(
+                                addr,
+                                Some(conditions),
+                                cids,
+                                metrics,
+                                s2s_stop_listening_r,
+                                c2s_protocol_s,
+                            )
+                            .await
+                        },
+                        ListenAddr::Udp(addr) => {
+                            Protocols::with_udp_listen(
                                 addr,
                                 cids,
                                 metrics,
@@ -219,7 +260,6 @@ impl Scheduler {
                             )
                             .await
                         },
-                        _ => unimplemented!(),
                     };
                     let _ = s2a_listen_result_s.send(res);
 
@@ -240,13 +280,20 @@ impl Scheduler {
                 ProtocolMetricCache::new(&cid.to_string(), Arc::clone(&self.protocol_metrics));
             self.metrics.connect_request(&addr);
             let protocol = match addr.clone() {
-                ConnectAddr::Tcp(addr) => Protocols::with_tcp_connect(addr, metrics).await,
+                ConnectAddr::Tcp(addr) => Protocols::with_tcp_connect(addr, None, metrics).await,
+                ConnectAddr::EncryptedTcp(addr, pinned) => {
+                    let encryption = Encryption::Client { pinned };
+                    Protocols::with_tcp_connect(addr, Some(encryption), metrics).await
+                },
                 #[cfg(feature = "quic")]
                 ConnectAddr::Quic(addr, ref config, name) => {
                     Protocols::with_quic_connect(addr, config.clone(), name, metrics).await
                 },
-                ConnectAddr::Mpsc(addr) => Protocols::with_mpsc_connect(addr, metrics).await,
-                _ => unimplemented!(),
+                ConnectAddr::Mpsc(addr) => Protocols::with_mpsc_connect(addr, None, metrics).await,
+                ConnectAddr::Sim(addr, conditions) => {
+                    Protocols::with_mpsc_connect(addr, Some(conditions), metrics).await
+                },
+                ConnectAddr::Udp(addr) => Protocols::with_udp_connect(addr, metrics).await,
             };
             let protocol = match protocol {
                 Ok(p) => p,
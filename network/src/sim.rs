@@ -0,0 +1,212 @@
+//! A simulated link between the two ends of an MPSC channel, to test how
+//! participants and streams behave on a bad network without real sockets.
+use hashbrown::HashMap;
+use network_protocol::{MpscMsg, Promises, ProtocolEvent, Sid};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{select, sync::mpsc, time::Instant};
+
+/// Size accounted for every message in addition to its data, when limiting
+/// the throughput
+const EVENT_OVERHEAD: usize = 24;
+
+/// Conditions of a simulated link, see [`ListenAddr::Sim`] and
+/// [`ConnectAddr::Sim`].
+///
+/// They apply to both directions of the link. Messages of streams without
+/// [`Promises::GUARANTEED_DELIVERY`] might get lost, and with jitter, messages
+/// of streams without [`Promises::ORDERED`] might overtake each other.
+/// Everything else only arrives later.
+///
+/// The same seed leads to the same messages being lost and delayed by the
+/// same jitter.
+///
+/// [`ListenAddr::Sim`]: crate::api::ListenAddr::Sim
+/// [`ConnectAddr::Sim`]: crate::api::ConnectAddr::Sim
+/// [`Promises::GUARANTEED_DELIVERY`]: network_protocol::Promises::GUARANTEED_DELIVERY
+/// [`Promises::ORDERED`]: network_protocol::Promises::ORDERED
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkConditions {
+    /// Added to every message
+    pub delay: Duration,
+    /// Random additional delay, up to this
+    pub jitter: Duration,
+    /// Chance to lose a message, from 0.0 to 1.0
+    pub loss: f64,
+    /// Bytes per second, `None` for no limit
+    pub throughput: Option<u64>,
+    pub seed: u64,
+}
+
+impl Default for LinkConditions {
+    fn default() -> Self {
+        Self {
+            delay: Duration::ZERO,
+            jitter: Duration::ZERO,
+            loss: 0.0,
+            throughput: None,
+            seed: 0,
+        }
+    }
+}
+
+/// Both directions of a simulated link, they share the promises of the
+/// streams, as each stream is opened in only one direction
+#[derive(Clone, Debug)]
+pub(crate) struct SimLink {
+    conditions: LinkConditions,
+    promises: Arc<Mutex<HashMap<Sid, Promises>>>,
+}
+
+impl SimLink {
+    pub(crate) fn new(conditions: LinkConditions) -> Self {
+        Self {
+            conditions,
+            promises: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns a sender, whose messages are forwarded to `output` under the
+    /// conditions of the link. `direction` tells the directions apart, so
+    /// they don't lose the same messages.
+    pub(crate) fn relay(
+        &self,
+        output: mpsc::Sender<MpscMsg>,
+        direction: u64,
+        bound: usize,
+    ) -> mpsc::Sender<MpscMsg> {
+        let (input_s, input_r) = mpsc::channel(bound);
+        let now = Instant::now();
+        let relay = Relay {
+            conditions: self.conditions.clone(),
+            promises: Arc::clone(&self.promises),
+            rng: StdRng::seed_from_u64(self.conditions.seed.wrapping_add(direction)),
+            link_free: now,
+            last_any: now,
+            last_control: now,
+            last_ordered: now,
+        };
+        tokio::spawn(relay.run(input_r, output));
+        input_s
+    }
+}
+
+enum Delivery {
+    /// Stream management and the handshake, they keep their order with all
+    /// other messages
+    Control,
+    Ordered,
+    Unordered,
+}
+
+struct Relay {
+    conditions: LinkConditions,
+    promises: Arc<Mutex<HashMap<Sid, Promises>>>,
+    rng: StdRng,
+    /// When the link is done sending the previous messages
+    link_free: Instant,
+    last_any: Instant,
+    last_control: Instant,
+    last_ordered: Instant,
+}
+
+impl Relay {
+    async fn run(mut self, mut input: mpsc::Receiver<MpscMsg>, output: mpsc::Sender<MpscMsg>) {
+        // sorted by arrival, then by the order they were sent in
+        let mut queue = BTreeMap::new();
+        let mut seq = 0u64;
+        let mut open = true;
+        loop {
+            let next = queue.keys().next().map(|(at, _): &(Instant, u64)| *at);
+            select! {
+                msg = input.recv(), if open => match msg {
+                    Some(msg) => {
+                        if let Some(at) = self.schedule(&msg) {
+                            queue.insert((at, seq), msg);
+                            seq += 1;
+                        }
+                    },
+                    // deliver what's in flight, then close the output
+                    None => open = false,
+                },
+                _ = tokio::time::sleep_until(next.unwrap_or_else(Instant::now)), if next.is_some() => {
+                    let now = Instant::now();
+                    while let Some(entry) = queue.first_entry() {
+                        if entry.key().0 > now {
+                            break;
+                        }
+                        if output.send(entry.remove()).await.is_err() {
+                            return;
+                        }
+                    }
+                },
+                else => break,
+            }
+        }
+    }
+
+    /// Returns when the message arrives, or `None` if it's lost
+    fn schedule(&mut self, msg: &MpscMsg) -> Option<Instant> {
+        let (size, delivery) = match msg {
+            MpscMsg::Event(ProtocolEvent::Message { sid, data }) => {
+                let promises = self
+                    .promises
+                    .lock()
+                    .unwrap()
+                    .get(sid)
+                    .copied()
+                    .unwrap_or(Promises::ORDERED | Promises::GUARANTEED_DELIVERY);
+                if !promises.contains(Promises::GUARANTEED_DELIVERY)
+                    && self.rng.gen_bool(self.conditions.loss.clamp(0.0, 1.0))
+                {
+                    return None;
+                }
+                let delivery = if promises.contains(Promises::ORDERED) {
+                    Delivery::Ordered
+                } else {
+                    Delivery::Unordered
+                };
+                (data.len(), delivery)
+            },
+            MpscMsg::Event(ProtocolEvent::OpenStream { sid, promises, .. }) => {
+                self.promises.lock().unwrap().insert(*sid, *promises);
+                (0, Delivery::Control)
+            },
+            MpscMsg::Event(_) | MpscMsg::InitFrame(_) => (0, Delivery::Control),
+        };
+
+        let now = Instant::now();
+        let sent = match self.conditions.throughput {
+            Some(throughput) if throughput > 0 => {
+                let duration = (size + EVENT_OVERHEAD) as f64 / throughput as f64;
+                self.link_free = self.link_free.max(now) + Duration::from_secs_f64(duration);
+                self.link_free
+            },
+            _ => now,
+        };
+        let jitter = if self.conditions.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            self.rng.gen_range(Duration::ZERO..=self.conditions.jitter)
+        };
+        let mut at = sent + self.conditions.delay + jitter;
+        match delivery {
+            Delivery::Control => {
+                at = at.max(self.last_any);
+                self.last_control = at;
+                self.last_ordered = at;
+            },
+            Delivery::Ordered => {
+                at = at.max(self.last_ordered);
+                self.last_ordered = at;
+            },
+            Delivery::Unordered => at = at.max(self.last_control),
+        }
+        self.last_any = self.last_any.max(at);
+        Some(at)
+    }
+}
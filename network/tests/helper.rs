@@ -11,7 +11,9 @@ use std::{
 use tokio::runtime::Runtime;
 use tracing::*;
 use tracing_subscriber::EnvFilter;
-use veloren_network::{ConnectAddr, ListenAddr, Network, Participant, Pid, Promises, Stream};
+use veloren_network::{
+    ConnectAddr, LinkConditions, ListenAddr, Network, Participant, Pid, Promises, ServerKey, Stream,
+};
 
 // sleep time when only internal rust calculations are done
 #[allow(dead_code)]
@@ -94,6 +96,24 @@ pub fn tcp() -> (ListenAddr, ConnectAddr) {
     )
 }
 
+/// Tcp with encryption, the connecting side pinned the key of the listener
+#[allow(dead_code)]
+pub fn encrypted_tcp() -> (ListenAddr, ConnectAddr) {
+    let (ListenAddr::Tcp(listen), ConnectAddr::Tcp(connect)) = tcp() else {
+        unreachable!()
+    };
+    let key = Arc::new(ServerKey::generate());
+    let pinned = Some(key.public_key());
+    (
+        ListenAddr::EncryptedTcp {
+            addr: listen,
+            key,
+            required: true,
+        },
+        ConnectAddr::EncryptedTcp(connect, pinned),
+    )
+}
+
 lazy_static! {
     static ref UDP_PORTS: AtomicU16 = AtomicU16::new(5000);
 }
@@ -140,11 +160,19 @@ pub fn udp() -> (ListenAddr, ConnectAddr) {
     )
 }
 
+lazy_static! {
+    static ref MPSC_PORTS: AtomicU64 = AtomicU64::new(5000);
+}
+
 #[allow(dead_code)]
 pub fn mpsc() -> (ListenAddr, ConnectAddr) {
-    lazy_static! {
-        static ref PORTS: AtomicU64 = AtomicU64::new(5000);
-    }
-    let port = PORTS.fetch_add(1, Ordering::Relaxed);
+    let port = MPSC_PORTS.fetch_add(1, Ordering::Relaxed);
     (ListenAddr::Mpsc(port), ConnectAddr::Mpsc(port))
 }
+
+/// The conditions only apply once, on the connecting side
+#[allow(dead_code)]
+pub fn sim(conditions: LinkConditions) -> (ListenAddr, ConnectAddr) {
+    let port = MPSC_PORTS.fetch_add(1, Ordering::Relaxed);
+    (ListenAddr::Mpsc(port), ConnectAddr::Sim(port, conditions))
+}
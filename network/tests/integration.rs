@@ -1,11 +1,20 @@
 #![feature(assert_matches)]
-use std::sync::Arc;
+use std::{assert_matches::assert_matches, sync::Arc};
 use tokio::runtime::Runtime;
 use veloren_network::{NetworkError, StreamError};
 mod helper;
-use helper::{mpsc, network_participant_stream, quic, tcp, udp, SLEEP_EXTERNAL, SLEEP_INTERNAL};
-use std::io::ErrorKind;
-use veloren_network::{ConnectAddr, ListenAddr, Network, ParticipantEvent, Pid, Promises};
+use helper::{
+    encrypted_tcp, mpsc, network_participant_stream, quic, sim, tcp, udp, SLEEP_EXTERNAL,
+    SLEEP_INTERNAL,
+};
+use std::{
+    io::ErrorKind,
+    time::{Duration, Instant},
+};
+use veloren_network::{
+    ConnectAddr, EncryptionError, InitProtocolError, LinkConditions, ListenAddr, Network,
+    NetworkConnectError, ParticipantEvent, Pid, Promises, ServerKey, MAX_UNRELIABLE_MESSAGE_SIZE,
+};
 
 #[test]
 fn stream_simple() {
@@ -91,7 +100,6 @@ fn stream_simple_quic_3msg() {
 }
 
 #[test]
-#[ignore]
 fn stream_simple_udp() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, _p_a, s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(udp());
@@ -102,7 +110,6 @@ fn stream_simple_udp() {
 }
 
 #[test]
-#[ignore]
 fn stream_simple_udp_3msg() {
     let (_, _) = helper::setup(false, 0);
     let (r, _n_a, _p_a, s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(udp());
@@ -116,6 +123,53 @@ fn stream_simple_udp_3msg() {
     drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
 }
 
+#[test]
+fn stream_simple_encrypted_tcp() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, _p_a, s1_a, _n_b, _p_b, mut s1_b) = network_participant_stream(encrypted_tcp());
+
+    s1_a.send("Hello World").unwrap();
+    s1_a.send(vec![42u8; 100_000]).unwrap();
+    assert_eq!(r.block_on(s1_b.recv()), Ok("Hello World".to_string()));
+    assert_eq!(r.block_on(s1_b.recv()), Ok(vec![42u8; 100_000]));
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+}
+
+#[test]
+fn encrypted_tcp_unknown_key() {
+    let (_, _) = helper::setup(false, 0);
+    let r = Arc::new(Runtime::new().unwrap());
+    let network = Network::new(Pid::new(), &r);
+    let remote = Network::new(Pid::new(), &r);
+    let key = Arc::new(ServerKey::generate());
+    let public_key = key.public_key();
+    let addr = "127.0.0.1:1240".parse().unwrap();
+    r.block_on(network.listen(ListenAddr::EncryptedTcp {
+        addr,
+        key,
+        required: true,
+    }))
+    .unwrap();
+    let other_key = ServerKey::generate().public_key();
+    let result = r.block_on(remote.connect(ConnectAddr::EncryptedTcp(addr, Some(other_key))));
+    assert_matches!(
+        result,
+        Err(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
+            InitProtocolError::Encryption(EncryptionError::ServerKeyMismatch { expected, actual })
+        ))) if expected == other_key && actual == public_key
+    );
+    let result = r.block_on(remote.connect(ConnectAddr::EncryptedTcp(addr, None)));
+    assert_matches!(
+        result,
+        Err(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
+            InitProtocolError::Encryption(EncryptionError::UnknownServerKey(key))
+        ))) if key == public_key
+    );
+    let result = r.block_on(remote.connect(ConnectAddr::Tcp(addr)));
+    assert_matches!(result, Err(NetworkError::ConnectFailed(_)));
+    drop((network, remote)); //clean teardown
+}
+
 #[test]
 #[ignore]
 fn tcp_and_udp_2_connections() -> Result<(), Box<dyn std::error::Error>> {
@@ -144,7 +198,6 @@ fn tcp_and_udp_2_connections() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 #[test]
-#[ignore]
 fn failed_listen_on_used_ports() -> Result<(), Box<dyn std::error::Error>> {
     let (_, _) = helper::setup(false, 0);
     let r = Arc::new(Runtime::new().unwrap());
@@ -170,6 +223,77 @@ fn failed_listen_on_used_ports() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn stream_sim_guaranteed_with_loss() -> Result<(), Box<dyn std::error::Error>> {
+    let (_, _) = helper::setup(false, 0);
+    let delay = Duration::from_millis(50);
+    let (listen, connect) = sim(LinkConditions {
+        delay,
+        jitter: Duration::from_millis(20),
+        loss: 0.5,
+        ..Default::default()
+    });
+    let r = Arc::new(Runtime::new().unwrap());
+    let network = Network::new(Pid::new(), &r);
+    let remote = Network::new(Pid::new(), &r);
+    r.block_on(async {
+        let mut network = network;
+        let remote = remote;
+        network.listen(listen).await?;
+        let remote_p = remote.connect(connect).await?;
+        let stream_p = remote_p
+            .open(4, Promises::ORDERED | Promises::GUARANTEED_DELIVERY, 0)
+            .await?;
+        let mut participant_a = network.connected().await?;
+        let mut stream_a = participant_a.opened().await?;
+
+        let start = Instant::now();
+        for i in 0..100u32 {
+            stream_p.send(i)?;
+        }
+        for i in 0..100u32 {
+            assert_eq!(stream_a.recv::<u32>().await?, i);
+        }
+        assert!(start.elapsed() >= delay);
+        Ok(())
+    })
+}
+
+#[test]
+fn stream_sim_unreliable_with_loss() -> Result<(), Box<dyn std::error::Error>> {
+    let (_, _) = helper::setup(false, 0);
+    let (listen, connect) = sim(LinkConditions {
+        loss: 0.5,
+        seed: 42,
+        ..Default::default()
+    });
+    let r = Arc::new(Runtime::new().unwrap());
+    let network = Network::new(Pid::new(), &r);
+    let remote = Network::new(Pid::new(), &r);
+    let (_n_a, _n_b, _p_b, _p_a, mut s1_a) = r.block_on(async {
+        let mut network = network;
+        let remote = remote;
+        network.listen(listen).await?;
+        let remote_p = remote.connect(connect).await?;
+        let stream_p = remote_p.open(4, Promises::empty(), 0).await?;
+        let mut participant_a = network.connected().await?;
+        let stream_a = participant_a.opened().await?;
+        for i in 0..100u32 {
+            stream_p.send(i)?;
+        }
+        Ok::<_, Box<dyn std::error::Error>>((network, remote, remote_p, participant_a, stream_a))
+    })?;
+    std::thread::sleep(SLEEP_INTERNAL);
+    let mut received = 0;
+    while let Ok(Some(_)) = s1_a.try_recv::<u32>() {
+        received += 1;
+    }
+    assert!(received > 0);
+    assert!(received < 100);
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+    Ok(())
+}
+
 /// There is a bug an impris-desktop-1 which fails the DOC tests,
 /// it fails exactly `api_stream_send_main` and `api_stream_recv_main` by
 /// deadlocking at different times!
@@ -246,6 +370,20 @@ fn wrong_parse() {
     drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
 }
 
+#[test]
+fn unreliable_message_too_large() {
+    let (_, _) = helper::setup(false, 0);
+    let (r, _n_a, _p_a, _s1_a, _n_b, _p_b, _s1_b) = network_participant_stream(mpsc());
+
+    let s2_a = r.block_on(_p_a.open(4, Promises::empty(), 0)).unwrap();
+    assert_eq!(
+        s2_a.send(vec![0u8; MAX_UNRELIABLE_MESSAGE_SIZE]),
+        Err(StreamError::MessageTooLarge)
+    );
+    s2_a.send("Hello World").unwrap();
+    drop((_n_a, _n_b, _p_a, _p_b)); //clean teardown
+}
+
 #[test]
 fn multiple_try_recv() {
     let (_, _) = helper::setup(false, 0);
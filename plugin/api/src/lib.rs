@@ -1,9 +1,15 @@
 pub extern crate common;
 
-pub use common::comp::Health;
+pub use common::comp::{buff::BuffKind, Health};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
 
-pub use common::{resources::GameMode, uid::Uid};
+pub use common::{
+    cmd_args::{ArgumentSpec, Requirement},
+    resources::GameMode,
+    uid::Uid,
+};
+pub use vek::{Rgb, Vec3};
 
 mod errors;
 
@@ -34,7 +40,103 @@ pub enum Action {
     ServerClose,
     Print(String),
     PlayerSendMessage(Uid, String),
+    /// Send a message to every player on the server
+    BroadcastMessage(String),
     KillEntity(Uid),
+    /// Move an entity (or the entity it is riding) to the given position
+    TeleportEntity(Uid, Vec3<f32>),
+    /// Give `amount` of the item with the asset specifier `item` (e.g.
+    /// `common.items.food.cheese`)
+    GiveItem {
+        target: Uid,
+        item: String,
+        amount: u32,
+    },
+    /// Remove up to `amount` of the item with the asset specifier `item` from
+    /// the inventory of the entity
+    RemoveItem {
+        target: Uid,
+        item: String,
+        amount: u32,
+    },
+    /// Apply a buff to an entity, a `duration` of `None` means the buff never
+    /// runs out
+    ApplyBuff {
+        target: Uid,
+        kind: BuffKind,
+        strength: f32,
+        duration: Option<Duration>,
+    },
+    /// Spawn an NPC from an entity config (e.g.
+    /// `common.entity.wild.peaceful.pig`)
+    SpawnNpc {
+        pos: Vec3<f32>,
+        entity_config: String,
+    },
+    /// Replace the block at `pos`, `block_kind` is the name of a `BlockKind`
+    /// (e.g. `Rock`)
+    PlaceBlock {
+        pos: Vec3<i32>,
+        block_kind: String,
+        color: Rgb<u8>,
+    },
+    /// Place a sprite in the block at `pos`, `sprite_kind` is the name of a
+    /// `SpriteKind` (e.g. `Lantern`)
+    PlaceSprite {
+        pos: Vec3<i32>,
+        sprite_kind: String,
+    },
+    /// Remove `key` from the storage of this plugin. Unlike the other actions
+    /// this is applied immediately.
+    DeleteStorage(String),
+    /// Call `on_timer` with `name` once `delay` has elapsed, and then every
+    /// `delay` if `repeat` is set. Setting a timer replaces the timer of this
+    /// plugin with the same name. Timers only run on the server.
+    SetTimer {
+        name: String,
+        delay: Duration,
+        repeat: bool,
+        clock: TimerClock,
+    },
+    /// Stop the timer of this plugin with the given name
+    CancelTimer(String),
+    /// Register a chat command, usually from `on_load`. Registered commands are
+    /// listed in `/help` and completed by clients, running one calls the
+    /// `on_command_<name>` event of the plugin (see
+    /// [`event::ChatCommandEvent`]). Registering a command with the name of an
+    /// existing command of the plugin replaces it. Unlike the other actions
+    /// this is applied immediately.
+    RegisterCommand(CommandSpec),
+}
+
+/// The description of a chat command registered by a plugin
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct CommandSpec {
+    /// The keyword of the command, used without the leading `/`
+    pub name: String,
+    /// A one-line message that explains what the command does
+    pub description: String,
+    /// The arguments of the command, the server makes sure they parse before
+    /// calling the plugin
+    pub args: Vec<ArgumentSpec<String>>,
+    /// The role needed to run the command, if any
+    pub needs_role: Option<CommandRole>,
+}
+
+/// The admin role needed to run a plugin command
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CommandRole {
+    Moderator,
+    Admin,
+}
+
+/// The clock used to measure the delay of a timer
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TimerClock {
+    /// The game time, which advances with the simulation of the server
+    Game,
+    /// The wall clock time
+    Real,
 }
 
 /// The [`Retrieve`] enum represents read of the ECS is sync and blocking.
@@ -68,6 +170,24 @@ pub enum Action {
 pub enum Retrieve {
     GetPlayerName(Uid),
     GetEntityHealth(Uid),
+    GetEntityPosition(Uid),
+    GetEntityInventory(Uid),
+    GetEntityStats(Uid),
+    GetPlayerGroup(Uid),
+    /// Get all the entities within `radius` blocks of the position
+    GetNearbyEntities(Vec3<f32>, f32),
+    /// Get the value stored under a key in the storage of this plugin
+    GetStorage(String),
+    /// Get all the keys in the storage of this plugin
+    ListStorageKeys,
+    /// Store `value` under `key` in the storage of this plugin, which is kept
+    /// across server restarts. This is a retrieve rather than an action so the
+    /// plugin learns when the value was rejected, e.g. because its storage is
+    /// full.
+    SetStorage {
+        key: String,
+        value: Vec<u8>,
+    },
 }
 
 /// The [`RetrieveResult`] struct is generated while using the `retrieve_action`
@@ -95,6 +215,35 @@ pub enum Retrieve {
 pub enum RetrieveResult {
     GetPlayerName(String),
     GetEntityHealth(Health),
+    GetEntityPosition(Vec3<f32>),
+    GetEntityInventory(Vec<ItemStack>),
+    GetEntityStats(EntityStats),
+    /// The members of the group, `None` if the entity isn't in a group
+    GetPlayerGroup(Option<Vec<Uid>>),
+    GetNearbyEntities(Vec<Uid>),
+    /// The stored value, `None` if nothing is stored under the key
+    GetStorage(Option<Vec<u8>>),
+    ListStorageKeys(Vec<String>),
+    SetStorage,
+}
+
+/// This struct represent a stack of items in an inventory
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct ItemStack {
+    /// The asset specifier of the item (e.g. `common.items.food.cheese`)
+    pub item: String,
+    pub amount: u32,
+}
+
+/// This struct represent the stats of an entity
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EntityStats {
+    pub name: String,
+    pub energy: f32,
+    pub maximum_energy: f32,
+    pub damage_reduction: f32,
+    pub move_speed_modifier: f32,
+    pub attack_speed_modifier: f32,
 }
 
 /// This trait is implement by all events and ensure type safety of FFI.
@@ -220,6 +369,258 @@ pub mod event {
         fn get_event_name(&self) -> String { "on_load".to_owned() }
     }
 
+    /// This event is called when a player leaves the server.
+    /// Your event should be named `on_leave`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct PlayerLeaveEvent {
+        pub player: Player,
+        pub player_name: String,
+    }
+
+    impl Event for PlayerLeaveEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_leave".to_owned() }
+    }
+
+    /// This event is called when an entity dies.
+    /// Your event should be named `on_entity_death`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct EntityDeathEvent {
+        pub entity: Uid,
+        /// The entity which dealt the last damage, if any
+        pub killer: Option<Uid>,
+    }
+
+    impl Event for EntityDeathEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_entity_death".to_owned() }
+    }
+
+    /// This event is called before an entity takes damage.
+    /// Your event should be named `on_entity_damage`
+    ///
+    /// You can return `Cancel` to prevent the damage or `SetAmount` to change
+    /// the amount of damage dealt.
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_entity_damage(damage: EntityDamageEvent) -> EntityDamageResult {
+    ///     EntityDamageResult::SetAmount(damage.amount / 2.0)
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct EntityDamageEvent {
+        pub target: Uid,
+        pub attacker: Option<Uid>,
+        pub amount: f32,
+    }
+
+    impl Event for EntityDamageEvent {
+        type Response = EntityDamageResult;
+
+        fn get_event_name(&self) -> String { "on_entity_damage".to_owned() }
+    }
+
+    /// This is the return type of an `on_entity_damage` event. See
+    /// [`EntityDamageEvent`]
+    ///
+    /// Variants:
+    ///  - `Cancel` will prevent the damage.
+    ///  - `SetAmount` will replace the amount of damage dealt.
+    ///  - `None` will let the damage be dealt unchanged.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub enum EntityDamageResult {
+        Cancel,
+        SetAmount(f32),
+        None,
+    }
+
+    impl Default for EntityDamageResult {
+        fn default() -> Self { Self::None }
+    }
+
+    /// The channel a chat message was sent to
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub enum ChatChannel {
+        /// A private message to the player with this id
+        Tell(Uid),
+        Say,
+        Group,
+        Faction(String),
+        Region,
+        World,
+    }
+
+    /// This event is called when a player sends a chat message.
+    /// Your event should be named `on_chat_message`
+    ///
+    /// You can return `Cancel` to prevent the message from being sent or
+    /// `Rewrite` to replace its content.
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_chat_message(chat: ChatMessageEvent) -> ChatMessageResult {
+    ///     ChatMessageResult::Rewrite(chat.message.replace("darn", "****"))
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct ChatMessageEvent {
+        pub player: Player,
+        pub channel: ChatChannel,
+        pub message: String,
+    }
+
+    impl Event for ChatMessageEvent {
+        type Response = ChatMessageResult;
+
+        fn get_event_name(&self) -> String { "on_chat_message".to_owned() }
+    }
+
+    /// This is the return type of an `on_chat_message` event. See
+    /// [`ChatMessageEvent`]
+    ///
+    /// Variants:
+    ///  - `Cancel` will prevent the message from being sent.
+    ///  - `Rewrite` will replace the content of the message.
+    ///  - `None` will let the message be sent unchanged.
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub enum ChatMessageResult {
+        Cancel,
+        Rewrite(String),
+        None,
+    }
+
+    impl Default for ChatMessageResult {
+        fn default() -> Self { Self::None }
+    }
+
+    /// This event is called before a block is broken, either by mining it or
+    /// in build mode.
+    /// Your event should be named `on_block_break`
+    ///
+    /// You can return `Cancel` to prevent the block from being broken.
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct BlockBreakEvent {
+        pub player: Player,
+        pub pos: Vec3<i32>,
+        /// The name of the `BlockKind` of the block
+        pub block_kind: String,
+    }
+
+    impl Event for BlockBreakEvent {
+        type Response = BlockEditResult;
+
+        fn get_event_name(&self) -> String { "on_block_break".to_owned() }
+    }
+
+    /// This event is called before a block is placed in build mode.
+    /// Your event should be named `on_block_place`
+    ///
+    /// You can return `Cancel` to prevent the block from being placed.
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct BlockPlaceEvent {
+        pub player: Player,
+        pub pos: Vec3<i32>,
+        /// The name of the `BlockKind` of the new block
+        pub block_kind: String,
+    }
+
+    impl Event for BlockPlaceEvent {
+        type Response = BlockEditResult;
+
+        fn get_event_name(&self) -> String { "on_block_place".to_owned() }
+    }
+
+    /// This is the return type of the `on_block_break` and `on_block_place`
+    /// events. See [`BlockBreakEvent`] and [`BlockPlaceEvent`]
+    ///
+    /// Variants:
+    ///  - `Cancel` will prevent the block from being changed.
+    ///  - `None` will let the block be changed.
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub enum BlockEditResult {
+        Cancel,
+        None,
+    }
+
+    impl Default for BlockEditResult {
+        fn default() -> Self { Self::None }
+    }
+
+    /// This event is called when a player crafts items.
+    /// Your event should be named `on_item_crafted`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct ItemCraftedEvent {
+        pub player: Player,
+        pub items: Vec<ItemStack>,
+    }
+
+    impl Event for ItemCraftedEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_item_crafted".to_owned() }
+    }
+
+    /// This event is called when a trade between two entities completes.
+    /// Your event should be named `on_trade_completed`
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct TradeCompletedEvent {
+        pub parties: [Uid; 2],
+        /// The items offered by each party, in the same order as `parties`
+        pub offers: [Vec<ItemStack>; 2],
+    }
+
+    impl Event for TradeCompletedEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_trade_completed".to_owned() }
+    }
+
+    /// This event is called on the server every tick, `time` is the game
+    /// time in seconds and `dt` the duration of the tick in seconds.
+    /// Your event should be named `on_tick`
+    ///
+    /// Prefer timers for logic which doesn't need to run every tick.
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    pub struct TickEvent {
+        pub time: f64,
+        pub dt: f32,
+    }
+
+    impl Event for TickEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_tick".to_owned() }
+    }
+
+    /// This event is called on the server when a timer set with
+    /// [`Action::SetTimer`] runs out, only for the plugin which set it.
+    /// Your event should be named `on_timer`
+    ///
+    /// # Example
+    /// ```ignore
+    /// #[event_handler]
+    /// pub fn on_timer(timer: TimerEvent) {
+    ///     if timer.name == "announce" {
+    ///         emit_action(Action::BroadcastMessage("Hello, world!".to_owned()));
+    ///     }
+    /// }
+    /// ```
+    #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
+    pub struct TimerEvent {
+        pub name: String,
+    }
+
+    impl Event for TimerEvent {
+        type Response = ();
+
+        fn get_event_name(&self) -> String { "on_timer".to_owned() }
+    }
+
     // impl Default for PlayerJoinResult {
     //     fn default() -> Self {
     //         Self::None
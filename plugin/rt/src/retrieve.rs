@@ -1,4 +1,4 @@
-use plugin_api::{Health, RetrieveError};
+use plugin_api::{EntityStats, Health, ItemStack, RetrieveError, Uid, Vec3};
 
 use crate::api::{Retrieve, RetrieveResult};
 
@@ -10,6 +10,22 @@ pub trait GetEntityHealth {
     fn get_entity_health(&self) -> Result<Health, RetrieveError>;
 }
 
+pub trait GetEntityPosition {
+    fn get_entity_position(&self) -> Result<Vec3<f32>, RetrieveError>;
+}
+
+pub trait GetEntityInventory {
+    fn get_entity_inventory(&self) -> Result<Vec<ItemStack>, RetrieveError>;
+}
+
+pub trait GetEntityStats {
+    fn get_entity_stats(&self) -> Result<EntityStats, RetrieveError>;
+}
+
+pub trait GetPlayerGroup {
+    fn get_player_group(&self) -> Result<Option<Vec<Uid>>, RetrieveError>;
+}
+
 impl GetEntityHealth for crate::api::event::Player {
     fn get_entity_health(&self) -> Result<Health, RetrieveError> {
         if let RetrieveResult::GetEntityHealth(e) =
@@ -33,3 +49,62 @@ impl GetPlayerName for crate::api::event::Player {
         }
     }
 }
+
+impl GetEntityPosition for crate::api::event::Player {
+    fn get_entity_position(&self) -> Result<Vec3<f32>, RetrieveError> {
+        if let RetrieveResult::GetEntityPosition(e) =
+            crate::retrieve_action(&Retrieve::GetEntityPosition(self.id))?
+        {
+            Ok(e)
+        } else {
+            Err(RetrieveError::InvalidType)
+        }
+    }
+}
+
+impl GetEntityInventory for crate::api::event::Player {
+    fn get_entity_inventory(&self) -> Result<Vec<ItemStack>, RetrieveError> {
+        if let RetrieveResult::GetEntityInventory(e) =
+            crate::retrieve_action(&Retrieve::GetEntityInventory(self.id))?
+        {
+            Ok(e)
+        } else {
+            Err(RetrieveError::InvalidType)
+        }
+    }
+}
+
+impl GetEntityStats for crate::api::event::Player {
+    fn get_entity_stats(&self) -> Result<EntityStats, RetrieveError> {
+        if let RetrieveResult::GetEntityStats(e) =
+            crate::retrieve_action(&Retrieve::GetEntityStats(self.id))?
+        {
+            Ok(e)
+        } else {
+            Err(RetrieveError::InvalidType)
+        }
+    }
+}
+
+impl GetPlayerGroup for crate::api::event::Player {
+    fn get_player_group(&self) -> Result<Option<Vec<Uid>>, RetrieveError> {
+        if let RetrieveResult::GetPlayerGroup(e) =
+            crate::retrieve_action(&Retrieve::GetPlayerGroup(self.id))?
+        {
+            Ok(e)
+        } else {
+            Err(RetrieveError::InvalidType)
+        }
+    }
+}
+
+/// Get all the entities within `radius` blocks of `pos`
+pub fn get_nearby_entities(pos: Vec3<f32>, radius: f32) -> Result<Vec<Uid>, RetrieveError> {
+    if let RetrieveResult::GetNearbyEntities(e) =
+        crate::retrieve_action(&Retrieve::GetNearbyEntities(pos, radius))?
+    {
+        Ok(e)
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
@@ -0,0 +1,42 @@
+//! Key/value storage scoped to the plugin, which is kept across server
+//! restarts. Values are raw bytes, serialize them as you like (e.g. with
+//! `bincode`).
+
+use crate::api::{Action, Retrieve, RetrieveError, RetrieveResult};
+
+/// Get the value stored under `key`, `None` if nothing is stored under it
+pub fn get(key: &str) -> Result<Option<Vec<u8>>, RetrieveError> {
+    if let RetrieveResult::GetStorage(e) =
+        crate::retrieve_action(&Retrieve::GetStorage(key.to_owned()))?
+    {
+        Ok(e)
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
+
+/// Get all the keys which have a value stored under them, sorted
+pub fn list() -> Result<Vec<String>, RetrieveError> {
+    if let RetrieveResult::ListStorageKeys(e) = crate::retrieve_action(&Retrieve::ListStorageKeys)?
+    {
+        Ok(e)
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
+
+/// Store `value` under `key`, replacing the previous value if any. Fails if
+/// the key or value is too long, or the storage of the plugin is full.
+pub fn set(key: &str, value: Vec<u8>) -> Result<(), RetrieveError> {
+    if let RetrieveResult::SetStorage = crate::retrieve_action(&Retrieve::SetStorage {
+        key: key.to_owned(),
+        value,
+    })? {
+        Ok(())
+    } else {
+        Err(RetrieveError::InvalidType)
+    }
+}
+
+/// Remove the value stored under `key`
+pub fn delete(key: &str) { crate::emit_action(Action::DeleteStorage(key.to_owned())) }
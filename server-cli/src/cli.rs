@@ -1,7 +1,7 @@
 use clap::StructOpt;
 use common::comp;
 use server::persistence::SqlLogMode;
-use std::sync::mpsc::Sender;
+use std::{path::PathBuf, sync::mpsc::Sender};
 use tracing::error;
 
 #[derive(Clone, Debug, StructOpt)]
@@ -20,6 +20,27 @@ pub enum Admin {
     },
 }
 
+#[derive(Clone, Debug, StructOpt)]
+pub enum Character {
+    /// Writes a character to a file, to import it on another server
+    Export {
+        /// ID of the character to export
+        character_id: i64,
+        /// File to write the character to
+        file: PathBuf,
+    },
+    /// Adds a character from an export file to the characters of a player
+    Import {
+        /// Name of the player who gets the character
+        username: String,
+        /// File to read the character from
+        file: PathBuf,
+        /// Name of the character, instead of the exported one
+        #[structopt(long)]
+        alias: Option<String>,
+    },
+}
+
 #[derive(Clone, Debug, StructOpt)]
 pub enum Shutdown {
     /// Closes the server immediately
@@ -36,6 +57,28 @@ pub enum Shutdown {
     Cancel,
 }
 
+#[cfg(feature = "plugins")]
+#[derive(Clone, Debug, StructOpt)]
+pub enum Plugin {
+    /// Lists the loaded and the disabled plugins
+    List,
+    /// Reloads a plugin from its file, or every plugin if no name is given
+    Reload {
+        /// Name of the plugin to reload
+        name: Option<String>,
+    },
+    /// Unloads a plugin until it is enabled again
+    Disable {
+        /// Name of the plugin to disable
+        name: String,
+    },
+    /// Loads a previously disabled plugin
+    Enable {
+        /// Name of the plugin to enable
+        name: String,
+    },
+}
+
 #[derive(Clone, Debug, StructOpt)]
 pub enum SharedCommand {
     /// Perform operations on the admin list
@@ -67,6 +110,31 @@ pub enum Message {
     },
     /// Disconnects all connected clients
     DisconnectAllClients,
+    /// Reloads the settings file, applying the settings which don't need a
+    /// restart
+    ReloadSettings,
+    /// Backs up the database now, in addition to the scheduled backups
+    BackupDatabase,
+    /// Show the admin and moderation actions taken on the server
+    AuditLog {
+        /// Only show the actions taken by or against this player
+        player: Option<String>,
+        /// Show the actions taken since this many hours ago
+        #[structopt(long, default_value = "24")]
+        since: f64,
+        /// Show the actions taken until this many hours ago
+        #[structopt(long)]
+        until: Option<f64>,
+        /// The maximum number of actions to show
+        #[structopt(long, default_value = "50")]
+        limit: u32,
+    },
+    /// Manage the server plugins
+    #[cfg(feature = "plugins")]
+    Plugin {
+        #[structopt(subcommand)]
+        command: Plugin,
+    },
 }
 
 #[derive(StructOpt)]
@@ -86,6 +154,11 @@ pub struct TuiApp {
 pub enum ArgvCommand {
     #[structopt(flatten)]
     Shared(SharedCommand),
+    /// Move characters between the databases of servers
+    Character {
+        #[structopt(subcommand)]
+        command: Character,
+    },
 }
 
 #[derive(StructOpt)]
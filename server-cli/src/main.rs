@@ -12,8 +12,10 @@ mod settings;
 mod shutdown_coordinator;
 mod tui_runner;
 mod tuilog;
+#[cfg(feature = "plugins")]
+use crate::cli::Plugin;
 use crate::{
-    cli::{Admin, ArgvApp, ArgvCommand, Message, SharedCommand, Shutdown},
+    cli::{Admin, ArgvApp, ArgvCommand, Character, Message, SharedCommand, Shutdown},
     shutdown_coordinator::ShutdownCoordinator,
     tui_runner::Tui,
     tuilog::TuiLog,
@@ -27,7 +29,7 @@ use std::{
     sync::{atomic::AtomicBool, mpsc, Arc},
     time::Duration,
 };
-use tracing::{info, trace};
+use tracing::{error, info, trace};
 
 lazy_static::lazy_static! {
     pub static ref LOG: TuiLog<'static> = TuiLog::default();
@@ -111,6 +113,10 @@ fn main() -> io::Result<()> {
     let database_settings = DatabaseSettings {
         db_dir: server_data_dir.join(PERSISTENCE_DB_DIR),
         sql_log_mode,
+        backup_interval: settings
+            .database_backup_interval_secs
+            .map(Duration::from_secs),
+        backup_retention: settings.database_backup_retention,
     };
 
     if let Some(command) = app.command {
@@ -152,6 +158,69 @@ fn main() -> io::Result<()> {
                 }
                 Ok(())
             },
+            ArgvCommand::Character { command } => {
+                use server::persistence::character_transfer::{
+                    export_character, import_character, CharacterExport,
+                };
+                server::persistence::run_migrations(&database_settings);
+
+                let result = match command {
+                    Character::Export { character_id, file } => {
+                        export_character(&database_settings, character_id)
+                            .and_then(|export| export.write(&file))
+                            .map(|()| {
+                                info!("Exported character {} to {}", character_id, file.display())
+                            })
+                    },
+                    Character::Import {
+                        username,
+                        file,
+                        alias,
+                    } => {
+                        let login_provider = server::login_provider::LoginProvider::new(
+                            server_settings.auth_server_address,
+                            runtime,
+                        );
+                        let uuid = match login_provider.username_to_uuid(&username) {
+                            Ok(uuid) => uuid,
+                            Err(err) => {
+                                error!(
+                                    ?err,
+                                    "Could not find uuid for this name; either the user does not \
+                                     exist or there was an error communicating with the auth \
+                                     server."
+                                );
+                                return Err(io::Error::new(
+                                    io::ErrorKind::Other,
+                                    format!("{:?}", err),
+                                ));
+                            },
+                        };
+                        CharacterExport::read(&file)
+                            .and_then(|export| {
+                                import_character(
+                                    &database_settings,
+                                    &uuid.to_string(),
+                                    alias,
+                                    export,
+                                    &server_settings.moderation.load_censor(&server_data_dir),
+                                )
+                            })
+                            .map(|character_id| {
+                                info!(
+                                    "Imported {} as character {} of {}",
+                                    file.display(),
+                                    character_id,
+                                    username
+                                )
+                            })
+                    },
+                };
+                result.map_err(|err| {
+                    error!("{}", err);
+                    io::Error::new(io::ErrorKind::Other, err.to_string())
+                })
+            },
         };
     }
 
@@ -180,6 +249,7 @@ fn main() -> io::Result<()> {
         runtime,
     )
     .expect("Failed to create server instance!");
+    server.watch_settings();
 
     // Collect addresses that the server is listening to log.
     let gameserver_addresses = protocols_and_addresses
@@ -191,6 +261,7 @@ fn main() -> io::Result<()> {
                 cert_file_path: _,
                 key_file_path: _,
             } => ("QUIC", address),
+            Protocol::EncryptedTcp { address, .. } => ("Encrypted TCP", address),
         });
 
     info!(
@@ -274,6 +345,46 @@ fn main() -> io::Result<()> {
                     Message::DisconnectAllClients => {
                         server.disconnect_all_clients();
                     },
+                    Message::ReloadSettings => match server.reload_settings() {
+                        Ok(msg) => info!("{}", msg),
+                        Err(msg) => error!("{}", msg),
+                    },
+                    Message::BackupDatabase => server.backup_database(),
+                    Message::AuditLog {
+                        player,
+                        since,
+                        until,
+                        limit,
+                    } => {
+                        let query = server::persistence::audit_log::AuditQuery {
+                            limit,
+                            ..server::persistence::audit_log::AuditQuery::hours_ago(
+                                player, since, until,
+                            )
+                        };
+                        match server.query_audit_log(&query) {
+                            Ok(entries) => {
+                                info!("{} audit log entries", entries.len());
+                                for entry in entries.iter().rev() {
+                                    info!("{}", entry);
+                                }
+                            },
+                            Err(msg) => error!("{}", msg),
+                        }
+                    },
+                    #[cfg(feature = "plugins")]
+                    Message::Plugin { command } => {
+                        let command = match command {
+                            Plugin::List => server::PluginCommand::List,
+                            Plugin::Reload { name } => server::PluginCommand::Reload(name),
+                            Plugin::Disable { name } => server::PluginCommand::Disable(name),
+                            Plugin::Enable { name } => server::PluginCommand::Enable(name),
+                        };
+                        match server.plugin_command(command) {
+                            Ok(msg) => info!("{}", msg),
+                            Err(msg) => error!("{}", msg),
+                        }
+                    },
                 },
                 Err(mpsc::TryRecvError::Empty) | Err(mpsc::TryRecvError::Disconnected) => {},
             }
@@ -7,6 +7,10 @@ use tracing::warn;
 pub struct Settings {
     pub update_shutdown_grace_period_secs: u32,
     pub update_shutdown_message: String,
+    /// Time between the scheduled database backups, `None` disables them
+    pub database_backup_interval_secs: Option<u64>,
+    /// Number of database backups to keep, `0` keeps all of them
+    pub database_backup_retention: usize,
 }
 
 impl Default for Settings {
@@ -14,6 +18,8 @@ impl Default for Settings {
         Self {
             update_shutdown_grace_period_secs: 120,
             update_shutdown_message: "The server is restarting for an update".to_owned(),
+            database_backup_interval_secs: Some(6 * 60 * 60),
+            database_backup_retention: 4,
         }
     }
 }
@@ -32,6 +32,8 @@ pub enum ActionErr {
     BannedWord,
     TooLong,
     SpamMuted(Duration),
+    /// The message got the player muted
+    SpamMuteStarted(Duration),
 }
 
 impl fmt::Display for ActionErr {
@@ -47,7 +49,7 @@ impl fmt::Display for ActionErr {
                 "Your message was too long, no more than {} characters are permitted.",
                 MAX_BYTES_CHAT_MSG
             ),
-            ActionErr::SpamMuted(dur) => write!(
+            ActionErr::SpamMuted(dur) | ActionErr::SpamMuteStarted(dur) => write!(
                 f,
                 "You have sent too many messages and are muted for {} seconds.",
                 dur.as_secs_f32() as u64
@@ -84,6 +86,12 @@ impl AutoMod {
         }
     }
 
+    /// Apply reloaded moderation settings, keeping the state of the players
+    pub fn update_settings(&mut self, settings: &ModerationSettings, censor: Arc<Censor>) {
+        self.settings = settings.clone();
+        self.censor = censor;
+    }
+
     pub fn enabled(&self) -> bool { self.settings.automod }
 
     fn player_mut(&mut self, player: Uuid) -> &mut PlayerState {
@@ -111,10 +119,19 @@ impl AutoMod {
         } else if self.censor.check(msg) {
             Err(ActionErr::BannedWord)
         } else {
+            let was_muted = self
+                .player_mut(player)
+                .muted_until
+                .map_or(false, |u| u > now);
             let volume = self.player_mut(player).enforce_message_volume(now);
 
             if let Some(until) = self.player_mut(player).muted_until {
-                Err(ActionErr::SpamMuted(until.saturating_duration_since(now)))
+                let dur = until.saturating_duration_since(now);
+                Err(if was_muted {
+                    ActionErr::SpamMuted(dur)
+                } else {
+                    ActionErr::SpamMuteStarted(dur)
+                })
             } else if volume > 0.75 {
                 Ok(Some(ActionNote::SpamWarn))
             } else {
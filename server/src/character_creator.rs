@@ -1,7 +1,9 @@
 use crate::persistence::{character_updater::CharacterUpdater, PersistedComponents};
 use common::{
     character::CharacterId,
-    comp::{inventory::loadout_builder::LoadoutBuilder, Body, Inventory, Item, SkillSet, Stats},
+    comp::{
+        inventory::loadout_builder::LoadoutBuilder, Body, Inventory, Item, SkillSet, Stats, Vault,
+    },
 };
 use specs::{Entity, WriteExpect};
 
@@ -75,6 +77,8 @@ pub fn create_character(
         pets: Vec::new(),
         active_abilities: Default::default(),
         map_marker,
+        vitals: Default::default(),
+        vault: Vault::with_empty(),
     });
     Ok(())
 }
@@ -1,8 +1,13 @@
-use common_net::msg::{ClientType, ServerGeneral, ServerMsg};
+use crate::rate_limit::RateLimiter;
+use common::comp::Inventory;
+use common_net::{
+    msg::{ClientType, EcsCompPacket, ServerGeneral, ServerMsg},
+    sync::{CompSyncPackage, DeltaEncoder},
+};
 use network::{Message, Participant, Stream, StreamError, StreamParams};
 use serde::{de::DeserializeOwned, Serialize};
 use specs::Component;
-use std::sync::atomic::AtomicBool;
+use std::sync::{atomic::AtomicBool, Mutex};
 
 /// Client handles ALL network related information of everything that connects
 /// to the server Client DOES NOT handle game states
@@ -15,6 +20,11 @@ pub struct Client {
     pub participant: Option<Participant>,
     pub last_ping: f64,
     pub login_msg_sent: AtomicBool,
+    /// The inventory is synced as delta to what the client acknowledged
+    pub inventory_delta: Mutex<DeltaEncoder<Inventory>>,
+    /// So are the components only synced for the client's own entity
+    pub comp_sync_delta: Mutex<DeltaEncoder<CompSyncPackage<EcsCompPacket>>>,
+    pub rate_limiter: Mutex<RateLimiter>,
 
     //TODO: Consider splitting each of these out into their own components so all the message
     //processing systems can run in parallel with each other (though it may turn out not to
@@ -66,6 +76,9 @@ impl Client {
             participant: Some(participant),
             last_ping,
             login_msg_sent: AtomicBool::new(false),
+            inventory_delta: Mutex::new(DeltaEncoder::default()),
+            comp_sync_delta: Mutex::new(DeltaEncoder::default()),
+            rate_limiter: Mutex::new(RateLimiter::default()),
             general_stream,
             ping_stream,
             register_stream,
@@ -134,7 +147,10 @@ impl Client {
                     | ServerGeneral::CreateEntity(_)
                     | ServerGeneral::DeleteEntity(_)
                     | ServerGeneral::Disconnect(_)
-                    | ServerGeneral::Notification(_) => self.general_stream.lock().unwrap().send(g),
+                    | ServerGeneral::Notification(_)
+                    | ServerGeneral::PluginCommands(_) => {
+                        self.general_stream.lock().unwrap().send(g)
+                    },
                 }
             },
             ServerMsg::Ping(m) => self.ping_stream.lock().unwrap().send(m),
@@ -189,7 +205,9 @@ impl Client {
                     | ServerGeneral::FinishedTrade(_)
                     | ServerGeneral::MapMarker(_)
                     | ServerGeneral::WeatherUpdate(_)
-                    | ServerGeneral::SpectatePosition(_) => {
+                    | ServerGeneral::SpectatePosition(_)
+                    | ServerGeneral::Spectating(_)
+                    | ServerGeneral::SpectatedLookDir(_) => {
                         PreparedMsg::new(2, &g, &self.in_game_stream_params)
                     },
                     //In-game related, terrain
@@ -206,10 +224,12 @@ impl Client {
                     | ServerGeneral::TimeOfDay(_, _)
                     | ServerGeneral::EntitySync(_)
                     | ServerGeneral::CompSync(_, _)
+                    | ServerGeneral::CompSyncDelta(_, _)
                     | ServerGeneral::CreateEntity(_)
                     | ServerGeneral::DeleteEntity(_)
                     | ServerGeneral::Disconnect(_)
-                    | ServerGeneral::Notification(_) => {
+                    | ServerGeneral::Notification(_)
+                    | ServerGeneral::PluginCommands(_) => {
                         PreparedMsg::new(3, &g, &self.general_stream_params)
                     },
                 }
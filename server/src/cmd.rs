@@ -5,9 +5,11 @@ use crate::{
     client::Client,
     location::Locations,
     login_provider::LoginProvider,
+    persistence::audit_log::{AuditEntry, AuditLogger, AuditQuery},
     presence::Presence,
     settings::{
-        Ban, BanAction, BanInfo, EditableSetting, SettingError, WhitelistInfo, WhitelistRecord,
+        Ban, BanAction, BanInfo, EditableSetting, Mute, MuteAction, MuteChat, SettingError,
+        WhitelistInfo, WhitelistRecord,
     },
     sys::terrain::NpcData,
     weather::WeatherSim,
@@ -22,8 +24,8 @@ use common::{
     assets,
     calendar::Calendar,
     cmd::{
-        KitSpec, ServerChatCommand, BUFF_PACK, BUFF_PARSER, ITEM_SPECS, KIT_MANIFEST_PATH,
-        PRESET_MANIFEST_PATH,
+        ArgumentSpec, KitSpec, ServerChatCommand, BUFF_PACK, BUFF_PARSER, ITEM_SPECS,
+        KIT_MANIFEST_PATH, PRESET_MANIFEST_PATH,
     },
     comp::{
         self,
@@ -124,6 +126,7 @@ fn do_command(
         ServerChatCommand::Adminify => handle_adminify,
         ServerChatCommand::Airship => handle_spawn_airship,
         ServerChatCommand::Alias => handle_alias,
+        ServerChatCommand::AuditLog => handle_audit_log,
         ServerChatCommand::Ban => handle_ban,
         ServerChatCommand::BattleMode => handle_battlemode,
         ServerChatCommand::BattleModeForce => handle_battlemode_force,
@@ -163,11 +166,14 @@ fn do_command(
         ServerChatCommand::MakeNpc => handle_make_npc,
         ServerChatCommand::MakeSprite => handle_make_sprite,
         ServerChatCommand::Motd => handle_motd,
+        ServerChatCommand::Mute => handle_mute,
         ServerChatCommand::Object => handle_object,
         ServerChatCommand::PermitBuild => handle_permit_build,
         ServerChatCommand::Players => handle_players,
+        ServerChatCommand::Plugin => handle_plugin,
         ServerChatCommand::Region => handle_region,
         ServerChatCommand::ReloadChunks => handle_reload_chunks,
+        ServerChatCommand::ReloadSettings => handle_reload_settings,
         ServerChatCommand::RemoveLights => handle_remove_lights,
         ServerChatCommand::RevokeBuild => handle_revoke_build,
         ServerChatCommand::RevokeBuildAll => handle_revoke_build_all,
@@ -185,6 +191,7 @@ fn do_command(
         ServerChatCommand::Time => handle_time,
         ServerChatCommand::Tp => handle_tp,
         ServerChatCommand::Unban => handle_unban,
+        ServerChatCommand::Unmute => handle_unmute,
         ServerChatCommand::Version => handle_version,
         ServerChatCommand::Waypoint => handle_waypoint,
         ServerChatCommand::Wiring => handle_spawn_wiring,
@@ -198,7 +205,69 @@ fn do_command(
         ServerChatCommand::Lightning => handle_lightning,
     };
 
-    handler(server, client, target, args, cmd)
+    // Keep a record of the admin and moderation commands
+    if cmd.needs_role().is_some() {
+        let audit_target = audit_target(server, client, target, &args, cmd);
+        let audit_args = args.clone();
+        let res = handler(server, client, target, args, cmd);
+        record_audit(
+            server,
+            client,
+            audit_target,
+            cmd.keyword(),
+            audit_args,
+            res.as_ref().err(),
+        );
+        res
+    } else {
+        handler(server, client, target, args, cmd)
+    }
+}
+
+/// The alias of the player a command is used against: the target of `/sudo`,
+/// or else the first player name argument
+fn audit_target(
+    server: &Server,
+    client: EcsEntity,
+    target: EcsEntity,
+    args: &[String],
+    cmd: &ServerChatCommand,
+) -> Option<String> {
+    if client != target {
+        server
+            .state
+            .ecs()
+            .read_storage::<comp::Player>()
+            .get(target)
+            .map(|player| player.alias.clone())
+    } else {
+        cmd.data()
+            .args
+            .iter()
+            .position(|arg| matches!(arg, ArgumentSpec::PlayerName(_)))
+            .and_then(|i| args.get(i).cloned())
+    }
+}
+
+fn record_audit(
+    server: &Server,
+    client: EcsEntity,
+    target: Option<String>,
+    action: &str,
+    args: Vec<String>,
+    error: Option<&String>,
+) {
+    let ecs = server.state.ecs();
+    let players = ecs.read_storage::<comp::Player>();
+    let player = players.get(client);
+    ecs.read_resource::<AuditLogger>().record(AuditEntry::new(
+        player.map_or_else(|| "???".to_owned(), |player| player.alias.clone()),
+        player.map(|player| player.uuid()),
+        target,
+        action.to_owned(),
+        args,
+        error.cloned(),
+    ));
 }
 
 // Fallibly get position of entity with the given descriptor (used for error
@@ -213,7 +282,7 @@ fn position(server: &Server, entity: EcsEntity, descriptor: &str) -> CmdResult<c
         .ok_or_else(|| format!("Cannot get position for {:?}!", descriptor))
 }
 
-fn position_mut<T>(
+pub(crate) fn position_mut<T>(
     server: &mut Server,
     entity: EcsEntity,
     descriptor: &str,
@@ -516,69 +585,79 @@ fn handle_give_item(
     action: &ServerChatCommand,
 ) -> CmdResult<()> {
     if let (Some(item_name), give_amount_opt) = parse_cmd_args!(args, String, u32) {
-        let give_amount = give_amount_opt.unwrap_or(1);
-        if let Ok(item) = Item::new_from_asset(&item_name.replace(['/', '\\'], ".")) {
-            let mut item: Item = item;
-            let mut res = Ok(());
-
-            const MAX_GIVE_AMOUNT: u32 = 2000;
-            // Cap give_amount for non-stackable items
-            let give_amount = if item.is_stackable() {
-                give_amount
-            } else {
-                give_amount.min(MAX_GIVE_AMOUNT)
-            };
+        give_item(server, target, &item_name, give_amount_opt.unwrap_or(1))
+    } else {
+        Err(action.help_string())
+    }
+}
 
-            if let Ok(()) = item.set_amount(give_amount) {
-                server
-                    .state
-                    .ecs()
-                    .write_storage::<Inventory>()
-                    .get_mut(target)
-                    .map(|mut inv| {
+/// Give `give_amount` of the item with the asset specifier `item_name` to the
+/// inventory of `target`.
+pub(crate) fn give_item(
+    server: &mut Server,
+    target: EcsEntity,
+    item_name: &str,
+    give_amount: u32,
+) -> CmdResult<()> {
+    if let Ok(item) = Item::new_from_asset(&item_name.replace(['/', '\\'], ".")) {
+        let mut item: Item = item;
+        let mut res = Ok(());
+
+        const MAX_GIVE_AMOUNT: u32 = 2000;
+        // Cap give_amount for non-stackable items
+        let give_amount = if item.is_stackable() {
+            give_amount
+        } else {
+            give_amount.min(MAX_GIVE_AMOUNT)
+        };
+
+        if let Ok(()) = item.set_amount(give_amount) {
+            server
+                .state
+                .ecs()
+                .write_storage::<Inventory>()
+                .get_mut(target)
+                .map(|mut inv| {
+                    // NOTE: Deliberately ignores items that couldn't be pushed.
+                    if inv.push(item).is_err() {
+                        res = Err(format!(
+                            "Player inventory full. Gave 0 of {} items.",
+                            give_amount
+                        ));
+                    }
+                });
+        } else {
+            let ability_map = server.state.ecs().read_resource::<AbilityMap>();
+            let msm = server.state.ecs().read_resource::<MaterialStatManifest>();
+            // This item can't stack. Give each item in a loop.
+            server
+                .state
+                .ecs()
+                .write_storage::<Inventory>()
+                .get_mut(target)
+                .map(|mut inv| {
+                    for i in 0..give_amount {
                         // NOTE: Deliberately ignores items that couldn't be pushed.
-                        if inv.push(item).is_err() {
+                        if inv.push(item.duplicate(&ability_map, &msm)).is_err() {
                             res = Err(format!(
-                                "Player inventory full. Gave 0 of {} items.",
-                                give_amount
+                                "Player inventory full. Gave {} of {} items.",
+                                i, give_amount
                             ));
+                            break;
                         }
-                    });
-            } else {
-                let ability_map = server.state.ecs().read_resource::<AbilityMap>();
-                let msm = server.state.ecs().read_resource::<MaterialStatManifest>();
-                // This item can't stack. Give each item in a loop.
-                server
-                    .state
-                    .ecs()
-                    .write_storage::<Inventory>()
-                    .get_mut(target)
-                    .map(|mut inv| {
-                        for i in 0..give_amount {
-                            // NOTE: Deliberately ignores items that couldn't be pushed.
-                            if inv.push(item.duplicate(&ability_map, &msm)).is_err() {
-                                res = Err(format!(
-                                    "Player inventory full. Gave {} of {} items.",
-                                    i, give_amount
-                                ));
-                                break;
-                            }
-                        }
-                    });
-            }
-
-            insert_or_replace_component(
-                server,
-                target,
-                comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Given),
-                "target",
-            )?;
-            res
-        } else {
-            Err(format!("Invalid item: {}", item_name))
+                    }
+                });
         }
+
+        insert_or_replace_component(
+            server,
+            target,
+            comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Given),
+            "target",
+        )?;
+        res
     } else {
-        Err(action.help_string())
+        Err(format!("Invalid item: {}", item_name))
     }
 }
 
@@ -1161,6 +1240,28 @@ fn handle_alias(
     }
 }
 
+fn handle_audit_log(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    let (player, hours) = parse_cmd_args!(args, String, f64);
+    let entries =
+        server.query_audit_log(&AuditQuery::hours_ago(player, hours.unwrap_or(24.0), None))?;
+
+    let mut msg = format!("{} audit log entries:", entries.len());
+    for entry in entries.iter().rev() {
+        let _ = write!(msg, "\n{}", entry);
+    }
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+    );
+    Ok(())
+}
+
 fn handle_tp(
     server: &mut Server,
     client: EcsEntity,
@@ -1656,6 +1757,38 @@ fn handle_players(
     Ok(())
 }
 
+#[cfg_attr(not(feature = "plugins"), allow(unused_variables))]
+fn handle_plugin(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    #[cfg(feature = "plugins")]
+    {
+        use crate::PluginCommand;
+
+        let command = match parse_cmd_args!(args, String, String) {
+            (Some(action), None) if action == "list" => PluginCommand::List,
+            (Some(action), plugin) if action == "reload" => PluginCommand::Reload(plugin),
+            (Some(action), Some(plugin)) if action == "disable" => PluginCommand::Disable(plugin),
+            (Some(action), Some(plugin)) if action == "enable" => PluginCommand::Enable(plugin),
+            _ => return Err(action.help_string()),
+        };
+        let msg = server.plugin_command(command)?;
+        server.notify_client(
+            client,
+            ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+        );
+        Ok(())
+    }
+    #[cfg(not(feature = "plugins"))]
+    {
+        Err("The server was compiled without plugin support".to_owned())
+    }
+}
+
 fn handle_build(
     server: &mut Server,
     client: EcsEntity,
@@ -2990,6 +3123,21 @@ fn handle_reload_chunks(
     Ok(())
 }
 
+fn handle_reload_settings(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    _args: Vec<String>,
+    _action: &ServerChatCommand,
+) -> CmdResult<()> {
+    let msg = server.reload_settings()?;
+    server.notify_client(
+        client,
+        ServerGeneral::server_msg(ChatType::CommandInfo, msg),
+    );
+    Ok(())
+}
+
 fn handle_remove_lights(
     server: &mut Server,
     client: EcsEntity,
@@ -3477,6 +3625,129 @@ fn handle_unban(
     }
 }
 
+fn handle_mute(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let (Some(username), chat, overwrite, parse_duration, reason_opt) =
+        parse_cmd_args!(args, String, String, bool, HumanDuration, String)
+    {
+        let chat = chat
+            .as_deref()
+            .map(MuteChat::from_str)
+            .transpose()?
+            .unwrap_or(MuteChat::All);
+        let reason = reason_opt.unwrap_or_default();
+        let overwrite = overwrite.unwrap_or(false);
+
+        let player_uuid = find_username(server, &username)?;
+
+        let client_uuid = uuid(server, client, "client")?;
+        let client_username = uuid_to_username(server, client, client_uuid)?;
+        let client_role = real_role(server, client_uuid, "client")?;
+
+        let now = Utc::now();
+        let end_date = parse_duration
+            .map(|duration| chrono::Duration::from_std(duration.into()))
+            .transpose()
+            .map_err(|err| format!("Error converting to duration: {}", err))?
+            // On overflow (someone adding some ridiculous time span), just make the mute infinite.
+            .and_then(|duration| now.checked_add_signed(duration));
+
+        let mute = Mute {
+            chat,
+            reason: reason.clone(),
+            info: BanInfo {
+                performed_by: client_uuid,
+                performed_by_username: client_username,
+                performed_by_role: client_role.into(),
+            },
+            date: now,
+            end_date,
+        };
+
+        let edit = server
+            .editable_settings_mut()
+            .banlist
+            .mute_action(
+                server.data_dir().as_ref(),
+                now,
+                player_uuid,
+                username.clone(),
+                MuteAction::Mute(mute),
+                overwrite,
+            )
+            .map(|result| {
+                (
+                    format!("Muted {} with reason: {}", username, reason),
+                    result,
+                )
+            });
+
+        edit_setting_feedback(server, client, edit, || {
+            format!("{} is already muted", username)
+        })?;
+        if let Ok(target_player) = find_uuid(server.state.ecs(), player_uuid) {
+            server.notify_client(
+                target_player,
+                ServerGeneral::server_msg(
+                    ChatType::CommandInfo,
+                    format!("You have been muted: {}", reason),
+                ),
+            );
+        }
+        Ok(())
+    } else {
+        Err(action.help_string())
+    }
+}
+
+fn handle_unmute(
+    server: &mut Server,
+    client: EcsEntity,
+    _target: EcsEntity,
+    args: Vec<String>,
+    action: &ServerChatCommand,
+) -> CmdResult<()> {
+    if let (Some(username), chat) = parse_cmd_args!(args, String, String) {
+        let chat = chat.as_deref().map(MuteChat::from_str).transpose()?;
+
+        let player_uuid = find_username(server, &username)?;
+
+        let client_uuid = uuid(server, client, "client")?;
+        let client_username = uuid_to_username(server, client, client_uuid)?;
+        let client_role = real_role(server, client_uuid, "client")?;
+
+        let unmute = MuteAction::Unmute(chat, BanInfo {
+            performed_by: client_uuid,
+            performed_by_username: client_username,
+            performed_by_role: client_role.into(),
+        });
+
+        let edit = server
+            .editable_settings_mut()
+            .banlist
+            .mute_action(
+                server.data_dir().as_ref(),
+                Utc::now(),
+                player_uuid,
+                username.clone(),
+                unmute,
+                false,
+            )
+            .map(|result| (format!("{} was successfully unmuted", username), result));
+
+        edit_setting_feedback(server, client, edit, || {
+            format!("{} is not muted", username)
+        })
+    } else {
+        Err(action.help_string())
+    }
+}
+
 fn handle_server_physics(
     server: &mut Server,
     client: EcsEntity,
@@ -67,6 +67,11 @@ pub fn handle_poise(server: &Server, entity: EcsEntity, change: comp::PoiseChang
 }
 
 pub fn handle_health_change(server: &Server, entity: EcsEntity, change: HealthChange) {
+    #[cfg(feature = "plugins")]
+    let change = match super::plugin::on_entity_damage(&server.state, entity, change) {
+        Some(change) => change,
+        None => return,
+    };
     let ecs = &server.state.ecs();
     if let Some(mut health) = ecs.write_storage::<Health>().get_mut(entity) {
         // If the change amount was not zero
@@ -140,6 +145,9 @@ pub fn handle_destroy(server: &mut Server, entity: EcsEntity, last_change: Healt
         return;
     }
 
+    #[cfg(feature = "plugins")]
+    super::plugin::on_entity_death(state, entity, last_change.by.map(|by| by.uid()));
+
     let get_attacker_name = |cause_of_death: KillType, by: Uid| -> KillSource {
         // Get attacker entity
         if let Some(char_entity) = state.ecs().entity_from_uid(by.into()) {
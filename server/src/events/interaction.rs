@@ -22,6 +22,7 @@ use common::{
     vol::ReadVol,
 };
 use common_net::sync::WorldSyncExt;
+use common_state::BlockChange;
 
 use crate::{state_ext::StateExt, Server, Time};
 
@@ -168,6 +169,10 @@ pub fn handle_mine_block(
     if state.can_set_block(pos) {
         let block = state.terrain().get(pos).ok().copied();
         if let Some(block) = block.filter(|b| b.mine_tool().map_or(false, |t| Some(t) == tool)) {
+            #[cfg(feature = "plugins")]
+            if !super::plugin::on_block_break(state, entity, pos) {
+                return;
+            }
             // Drop item if one is recoverable from the block
             if let Some(mut item) = comp::Item::try_reclaim_from_block(block) {
                 let maybe_uid = state.ecs().uid_from_entity(entity);
@@ -255,6 +260,63 @@ pub fn handle_mine_block(
     }
 }
 
+#[cfg_attr(not(feature = "plugins"), allow(unused_variables))]
+pub fn handle_break_block(server: &mut Server, entity: EcsEntity, pos: Vec3<i32>) {
+    let state = server.state_mut();
+    if let Some(old_block) = state.get_block(pos).filter(|_| state.can_set_block(pos)) {
+        #[cfg(feature = "plugins")]
+        if !super::plugin::on_block_break(state, entity, pos) {
+            return;
+        }
+        try_set_block_persistent(server, pos, old_block.into_vacant());
+    }
+}
+
+#[cfg_attr(not(feature = "plugins"), allow(unused_variables))]
+pub fn handle_place_block(server: &mut Server, entity: EcsEntity, pos: Vec3<i32>, block: Block) {
+    let state = server.state_mut();
+    if state.can_set_block(pos) {
+        #[cfg(feature = "plugins")]
+        if !super::plugin::on_block_place(state, entity, pos, block) {
+            return;
+        }
+        try_set_block_persistent(server, pos, block);
+    }
+}
+
+/// Set a block, also recording the change in the persisted terrain if enabled
+pub(super) fn set_block_persistent(server: &mut Server, pos: Vec3<i32>, block: Block) {
+    server.state.set_block(pos, block);
+    persist_block(server, pos, block);
+}
+
+/// Like [`set_block_persistent`], but leaves the block untouched if another
+/// change to it is already pending this tick
+fn try_set_block_persistent(server: &mut Server, pos: Vec3<i32>, block: Block) {
+    let was_set = server
+        .state
+        .ecs()
+        .write_resource::<BlockChange>()
+        .try_set(pos, block)
+        .is_some();
+    if was_set {
+        persist_block(server, pos, block);
+    }
+}
+
+#[cfg_attr(not(feature = "persistent_world"), allow(unused_variables))]
+fn persist_block(server: &mut Server, pos: Vec3<i32>, block: Block) {
+    #[cfg(feature = "persistent_world")]
+    if let Some(terrain_persistence) = server
+        .state
+        .ecs()
+        .try_fetch_mut::<crate::TerrainPersistence>()
+        .as_mut()
+    {
+        terrain_persistence.set_block(pos, block);
+    }
+}
+
 pub fn handle_sound(server: &mut Server, sound: &Sound) {
     let ecs = &server.state.ecs();
     let positions = &ecs.read_storage::<Pos>();
@@ -480,6 +480,8 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                     }
                     Some(InventoryUpdateEvent::Used)
                 },
+                // Items in the vault have to be taken out before they can be used
+                Slot::Vault(_) => None,
             };
 
             drop(inventories);
@@ -497,6 +499,60 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                     .expect("We know entity exists since we got its inventory.");
             }
         },
+        comp::InventoryManip::VaultSwap {
+            from,
+            to,
+            vault_pos,
+        } => {
+            let swapped = vault_in_range(state, get_cylinder(state, entity), vault_pos)
+                && state
+                    .ecs()
+                    .write_storage::<comp::Vault>()
+                    .get_mut(entity)
+                    .map_or(false, |mut vault| vault.swap(&mut inventory, from, to));
+            drop(inventories);
+
+            if swapped {
+                state
+                    .ecs()
+                    .write_storage()
+                    .insert(
+                        entity,
+                        comp::InventoryUpdate::new(InventoryUpdateEvent::Swapped),
+                    )
+                    .expect("We know entity exists since we got its inventory.");
+            }
+        },
+        comp::InventoryManip::VaultSplitSwap {
+            from,
+            to,
+            vault_pos,
+        } => {
+            let ability_map = state.ecs().read_resource::<AbilityMap>();
+            let msm = state.ecs().read_resource::<MaterialStatManifest>();
+            let swapped = vault_in_range(state, get_cylinder(state, entity), vault_pos)
+                && state
+                    .ecs()
+                    .write_storage::<comp::Vault>()
+                    .get_mut(entity)
+                    .map_or(false, |mut vault| {
+                        vault.split_swap(&mut inventory, from, to, &ability_map, &msm)
+                    });
+            drop(msm);
+            drop(ability_map);
+            drop(inventories);
+
+            if swapped {
+                state
+                    .ecs()
+                    .write_storage()
+                    .insert(
+                        entity,
+                        comp::InventoryUpdate::new(InventoryUpdateEvent::Swapped),
+                    )
+                    .expect("We know entity exists since we got its inventory.");
+            }
+        },
         comp::InventoryManip::Swap(a, b) => {
             use item::ItemKind;
             let ecs = state.ecs();
@@ -571,7 +627,7 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
 
             let item = match slot {
                 Slot::Inventory(slot) => inventory.take_half(slot, &ability_map, &msm),
-                Slot::Equip(_) => None,
+                Slot::Equip(_) | Slot::Vault(_) => None,
             };
 
             if let Some(item) = item {
@@ -595,6 +651,8 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
             let item = match slot {
                 Slot::Inventory(slot) => inventory.remove(slot),
                 Slot::Equip(slot) => inventory.replace_loadout_item(slot, None),
+                // Items can't be dropped straight out of the vault
+                Slot::Vault(_) => None,
             };
 
             // FIXME: We should really require the drop and write to be atomic!
@@ -625,7 +683,7 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
             let msm = state.ecs().read_resource::<MaterialStatManifest>();
             let item = match slot {
                 Slot::Inventory(slot) => inventory.take_half(slot, ability_map, &msm),
-                Slot::Equip(_) => None,
+                Slot::Equip(_) | Slot::Vault(_) => None,
             };
 
             // FIXME: We should really require the drop and write to be atomic!
@@ -789,6 +847,14 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                 },
             };
 
+            #[cfg(feature = "plugins")]
+            let crafted_stacks = crafted_items.as_ref().map(|items| {
+                items
+                    .iter()
+                    .map(super::plugin::item_stack)
+                    .collect::<Vec<_>>()
+            });
+
             // Attempt to insert items into inventory, dropping them if there is not enough
             // space
             let items_were_crafted = if let Some(crafted_items) = crafted_items {
@@ -819,6 +885,11 @@ pub fn handle_inventory(server: &mut Server, entity: EcsEntity, manip: comp::Inv
                     comp::InventoryUpdate::new(InventoryUpdateEvent::Craft),
                 );
             }
+
+            #[cfg(feature = "plugins")]
+            if let Some(crafted_stacks) = crafted_stacks {
+                super::plugin::on_item_crafted(state, entity, crafted_stacks);
+            }
         },
         comp::InventoryManip::Sort => {
             inventory.sort();
@@ -918,6 +989,27 @@ fn within_pickup_range<S: FindDist<find_dist::Cylinder>>(
         .unwrap_or(false)
 }
 
+/// Whether the block at `vault_pos` is a vault sprite close enough to the
+/// entity for it to access its vault
+fn vault_in_range(
+    state: &State,
+    entity_cylinder: Option<find_dist::Cylinder>,
+    vault_pos: Vec3<i32>,
+) -> bool {
+    state
+        .terrain()
+        .get(vault_pos)
+        .ok()
+        .and_then(|block| block.get_sprite())
+        == Some(SpriteKind::Vault)
+        && within_pickup_range(entity_cylinder, || {
+            Some(find_dist::Cube {
+                min: vault_pos.as_(),
+                side_length: 1.0,
+            })
+        })
+}
+
 fn announce_loot_to_group(
     group_id: &Group,
     ecs: &specs::World,
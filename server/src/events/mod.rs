@@ -19,16 +19,18 @@ use entity_manipulation::{
 use group_manip::handle_group;
 use information::handle_site_info;
 use interaction::{
-    handle_create_sprite, handle_lantern, handle_mine_block, handle_mount, handle_npc_interaction,
-    handle_sound, handle_unmount,
+    handle_break_block, handle_create_sprite, handle_lantern, handle_mine_block, handle_mount,
+    handle_npc_interaction, handle_place_block, handle_sound, handle_unmount,
 };
 use inventory_manip::handle_inventory;
 use invite::{handle_invite, handle_invite_response};
-use player::{handle_client_disconnect, handle_exit_ingame, handle_possess};
+use player::{handle_client_disconnect, handle_client_resume, handle_exit_ingame, handle_possess};
 use specs::{Builder, Entity as EcsEntity, WorldExt};
 use trade::handle_process_trade_action;
 
 pub use group_manip::update_map_markers;
+#[cfg(feature = "plugins")]
+pub use plugin::handle_plugin_actions;
 pub(crate) use trade::cancel_trades_for;
 
 mod entity_creation;
@@ -39,6 +41,7 @@ mod interaction;
 mod inventory_manip;
 mod invite;
 mod player;
+#[cfg(feature = "plugins")] mod plugin;
 mod trade;
 
 pub enum Event {
@@ -165,6 +168,8 @@ impl Server {
                         pets,
                         active_abilities,
                         map_marker,
+                        vitals,
+                        vault,
                     ) = components;
                     let components = PersistedComponents {
                         body,
@@ -175,6 +180,8 @@ impl Server {
                         pets,
                         active_abilities,
                         map_marker,
+                        vitals,
+                        vault,
                     };
                     handle_loaded_character_data(self, entity, components, metadata);
                 },
@@ -232,6 +239,9 @@ impl Server {
                         true,
                     ))
                 },
+                ServerEvent::ClientResume { entity, resumed } => {
+                    handle_client_resume(self, entity, resumed)
+                },
                 ServerEvent::Command(entity, name, args) => {
                     commands.push((entity, name, args));
                 },
@@ -259,6 +269,10 @@ impl Server {
                 ServerEvent::MineBlock { entity, pos, tool } => {
                     handle_mine_block(self, entity, pos, tool)
                 },
+                ServerEvent::BreakBlock { entity, pos } => handle_break_block(self, entity, pos),
+                ServerEvent::PlaceBlock { entity, pos, block } => {
+                    handle_place_block(self, entity, pos, block)
+                },
                 ServerEvent::TeleportTo {
                     entity,
                     target,
@@ -318,6 +332,11 @@ impl Server {
         }
 
         for msg in chat_messages {
+            #[cfg(feature = "plugins")]
+            let msg = match plugin::on_chat_message(&self.state, msg) {
+                Some(msg) => msg,
+                None => continue,
+            };
             self.state.send_chat(msg);
         }
 
@@ -1,17 +1,25 @@
 use super::Event;
 use crate::{
-    client::Client, metrics::PlayerMetrics, persistence::character_updater::CharacterUpdater,
-    presence::Presence, state_ext::StateExt, BattleModeBuffer, Server,
+    client::Client,
+    metrics::PlayerMetrics,
+    persistence::character_updater::CharacterUpdater,
+    presence::{Presence, RegionSubscription},
+    resume::ResumeSessions,
+    state_ext::StateExt,
+    BattleModeBuffer, Server,
 };
 use common::{
+    character::CharacterVitals,
     comp,
     comp::{group, pet::is_tameable},
+    event::{EventBus, ServerEvent},
     uid::{Uid, UidAllocator},
 };
 use common_base::span;
 use common_net::msg::{PlayerListUpdate, PresenceKind, ServerGeneral};
 use common_state::State;
 use specs::{saveload::MarkerAllocator, Builder, Entity as EcsEntity, Join, WorldExt};
+use std::sync::atomic::Ordering;
 use tracing::{debug, error, trace, warn, Instrument};
 
 pub fn handle_exit_ingame(server: &mut Server, entity: EcsEntity) {
@@ -73,6 +81,10 @@ pub fn handle_exit_ingame(server: &mut Server, entity: EcsEntity) {
             .write_resource::<UidAllocator>()
             .allocate(entity_builder.entity, Some(uid.into()));
         let new_entity = entity_builder.with(uid).build();
+        state
+            .ecs()
+            .write_resource::<ResumeSessions>()
+            .transfer(entity, new_entity);
         if let Some(group) = maybe_group {
             let mut group_manager = state.ecs().write_resource::<group::GroupManager>();
             if group_manager
@@ -172,6 +184,31 @@ pub fn handle_client_disconnect(
 
     let state = server.state_mut();
 
+    // Keep the character of a client that lost its connection in the world, so
+    // the client can resume its session
+    if matches!(
+        reason,
+        comp::DisconnectReason::NetworkError | comp::DisconnectReason::Timeout
+    ) && state.ecs().read_storage::<Presence>().contains(entity)
+    {
+        let time = state.get_time();
+        if state
+            .ecs()
+            .write_resource::<ResumeSessions>()
+            .detach(entity, time)
+        {
+            state.ecs().write_storage::<Client>().remove(entity);
+            return Event::ClientDisconnected { entity };
+        }
+    }
+    state
+        .ecs()
+        .write_resource::<ResumeSessions>()
+        .remove(entity);
+
+    #[cfg(feature = "plugins")]
+    super::plugin::on_leave(state, entity);
+
     // Tell other clients to remove from player list
     // And send a disconnected message
     if let (Some(uid), Some(_)) = (
@@ -198,6 +235,69 @@ pub fn handle_client_disconnect(
     Event::ClientDisconnected { entity }
 }
 
+/// Move the client of `entity`, which just resumed a session, onto the entity
+/// the session was detached from.
+pub fn handle_client_resume(server: &mut Server, entity: EcsEntity, resumed: EcsEntity) {
+    span!(_guard, "handle_client_resume");
+    let state = &mut server.state;
+    let Some(client) = state.ecs().write_storage::<Client>().remove(entity) else {
+        return;
+    };
+
+    if !state.ecs().is_alive(resumed) {
+        // The entity was deleted in the meantime, so is its session
+        state
+            .ecs()
+            .write_resource::<ResumeSessions>()
+            .remove(resumed);
+        let _ = state.ecs().write_storage::<Client>().insert(entity, client);
+        state
+            .ecs()
+            .read_resource::<EventBus<ServerEvent>>()
+            .emit_now(ServerEvent::ClientDisconnectWithoutPersistence(entity));
+        return;
+    }
+
+    // The character already entered the game, don't announce it again
+    client.login_msg_sent.store(true, Ordering::Relaxed);
+    match state
+        .ecs()
+        .write_storage::<Client>()
+        .insert(resumed, client)
+    {
+        // The server didn't notice the old connection being lost yet
+        Ok(Some(mut old_client)) => {
+            if let Some(participant) = old_client.participant.take() {
+                server.runtime.spawn(async move {
+                    if let Err(e) = participant.disconnect().await {
+                        debug!(?e, "Error when disconnecting the replaced client");
+                    }
+                });
+            }
+        },
+        Ok(None) => {},
+        Err(e) => error!(
+            ?e,
+            ?resumed,
+            "Failed to attach the client of a resumed session"
+        ),
+    }
+    // The client lost track of the entities around it, send them all again
+    state
+        .ecs()
+        .write_storage::<RegionSubscription>()
+        .remove(resumed);
+    crate::sys::subscription::initialize_region_subscription(state.ecs(), resumed);
+
+    if let Err(e) = state.delete_entity_recorded(entity) {
+        error!(
+            ?e,
+            ?entity,
+            "Failed to delete the entity of a resumed client"
+        );
+    }
+}
+
 // When a player logs out, their data is queued for persistence in the next tick
 // of the persistence batch update. The player will be
 // temporarily unable to log in during this period to avoid
@@ -237,6 +337,13 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
                     .read_storage::<comp::MapMarker>()
                     .get(entity)
                     .cloned();
+                let vitals = CharacterVitals::new(
+                    state.read_storage::<comp::Pos>().get(entity),
+                    state.read_storage::<comp::PhysicsState>().get(entity),
+                    state.read_storage::<comp::Health>().get(entity),
+                    state.read_storage::<comp::Energy>().get(entity),
+                    state.read_storage::<comp::Buffs>().get(entity),
+                );
                 // Store last battle mode change
                 if let Some(change) = player_info.last_battlemode_change {
                     let mode = player_info.battle_mode;
@@ -266,6 +373,7 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
 
                 character_updater.add_pending_logout_update(
                     char_id,
+                    player_info.uuid().to_string(),
                     (
                         skill_set.clone(),
                         inventory.clone(),
@@ -273,6 +381,8 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
                         waypoint,
                         active_abilities.clone(),
                         map_marker,
+                        vitals,
+                        state.read_storage::<comp::Vault>().get(entity).cloned(),
                     ),
                 );
             },
@@ -288,7 +398,6 @@ fn persist_entity(state: &mut State, entity: EcsEntity) -> EcsEntity {
 /// comment it out, but it needs to be fixed for a variety of reasons.  Get rid
 /// of this ASAP!
 pub fn handle_possess(server: &mut Server, possessor_uid: Uid, possessee_uid: Uid) {
-    use crate::presence::RegionSubscription;
     use common::{
         comp::{inventory::slot::EquipSlot, item, slot::Slot, Inventory},
         region::RegionMap,
@@ -378,6 +487,8 @@ pub fn handle_possess(server: &mut Server, possessor_uid: Uid, possessee_uid: Ui
         clients
             .insert(possessee, client)
             .expect("Checked entity was alive!");
+        ecs.write_resource::<ResumeSessions>()
+            .transfer(possessor, possessee);
 
         // Other components to transfer if they exist.
         fn transfer_component<C: specs::Component>(
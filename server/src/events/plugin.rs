@@ -0,0 +1,483 @@
+use super::interaction::set_block_persistent;
+use crate::{cmd, state_ext::StateExt, sys::terrain::NpcData, Server};
+use common::{
+    assets::AssetExt,
+    comp::{
+        self,
+        buff::{Buff, BuffData, BuffKind, BuffSource},
+        ChatType, HealthChange, Inventory, Item, UnresolvedChatMsg,
+    },
+    event::{EventBus, ServerEvent},
+    generation::{EntityConfig, EntityInfo},
+    terrain::{Block, BlockKind, SpriteKind},
+    uid::Uid,
+};
+use common_net::{msg::ServerGeneral, sync::WorldSyncExt};
+use common_state::{plugin::PluginMgr, State};
+use plugin_api::{
+    event::{
+        BlockBreakEvent, BlockEditResult, BlockPlaceEvent, ChatChannel, ChatMessageEvent,
+        ChatMessageResult, EntityDamageEvent, EntityDamageResult, EntityDeathEvent,
+        ItemCraftedEvent, Player, PlayerLeaveEvent, TradeCompletedEvent,
+    },
+    Action, ItemStack,
+};
+use rand::thread_rng;
+use specs::{Entity as EcsEntity, WorldExt};
+use std::{str::FromStr, time::Duration};
+use tracing::warn;
+use vek::*;
+
+/// Apply the actions emitted by plugins since the last tick, in the order
+/// they were emitted.
+pub fn handle_plugin_actions(server: &mut Server) {
+    let actions = server
+        .state
+        .ecs()
+        .read_resource::<PluginMgr>()
+        .take_actions();
+    for (plugin, action) in actions {
+        if let Err(e) = handle_plugin_action(server, action) {
+            warn!("Failed to apply an action of plugin '{}': {}", plugin, e);
+        }
+    }
+}
+
+fn handle_plugin_action(server: &mut Server, action: Action) -> Result<(), String> {
+    match action {
+        Action::ServerClose | Action::Print(_) => {
+            // These are handled by the plugin runtime as soon as they are
+            // emitted
+        },
+        Action::PlayerSendMessage(uid, msg) => {
+            let entity = entity_from_uid(server, uid)?;
+            server.notify_client(entity, ServerGeneral::server_msg(ChatType::Meta, msg));
+        },
+        Action::BroadcastMessage(msg) => {
+            server.notify_players(ServerGeneral::server_msg(ChatType::Meta, msg));
+        },
+        Action::KillEntity(uid) => {
+            let entity = entity_from_uid(server, uid)?;
+            server
+                .state
+                .ecs_mut()
+                .write_storage::<comp::Health>()
+                .get_mut(entity)
+                .map(|mut health| health.kill());
+        },
+        Action::TeleportEntity(uid, pos) => {
+            let entity = entity_from_uid(server, uid)?;
+            cmd::position_mut(server, entity, "target", |current_pos| current_pos.0 = pos)?;
+        },
+        Action::GiveItem {
+            target,
+            item,
+            amount,
+        } => {
+            let entity = entity_from_uid(server, target)?;
+            cmd::give_item(server, entity, &item, amount)?;
+        },
+        Action::RemoveItem {
+            target,
+            item,
+            amount,
+        } => {
+            let entity = entity_from_uid(server, target)?;
+            remove_item(server, entity, &item, amount)?;
+        },
+        Action::ApplyBuff {
+            target,
+            kind,
+            strength,
+            duration,
+        } => {
+            let entity = entity_from_uid(server, target)?;
+            apply_buff(server.state.ecs(), entity, kind, strength, duration)?;
+        },
+        Action::SpawnNpc { pos, entity_config } => {
+            let config = EntityConfig::load(&entity_config)
+                .map_err(|_| format!("Failed to load entity config: {}", entity_config))?
+                .read();
+            let entity_info = EntityInfo::at(pos).with_entity_config(
+                config.clone(),
+                Some(&entity_config),
+                &mut thread_rng(),
+            );
+            match NpcData::from_entity_info(entity_info) {
+                NpcData::Waypoint(_) => {
+                    return Err("Waypoint spawning is not implemented".to_owned());
+                },
+                NpcData::Data {
+                    inventory,
+                    pos,
+                    stats,
+                    skill_set,
+                    poise,
+                    health,
+                    body,
+                    agent,
+                    alignment,
+                    scale,
+                    loot,
+                } => server
+                    .state
+                    .ecs()
+                    .read_resource::<EventBus<ServerEvent>>()
+                    .emit_now(ServerEvent::CreateNpc {
+                        pos,
+                        stats,
+                        skill_set,
+                        health,
+                        poise,
+                        inventory,
+                        body,
+                        agent,
+                        alignment,
+                        scale,
+                        anchor: None,
+                        loot,
+                        rtsim_entity: None,
+                        projectile: None,
+                    }),
+            }
+        },
+        Action::PlaceBlock {
+            pos,
+            block_kind,
+            color,
+        } => {
+            let block_kind = BlockKind::from_str(&block_kind)
+                .map_err(|_| format!("Invalid block kind: {}", block_kind))?;
+            set_block_persistent(server, pos, Block::new(block_kind, color));
+        },
+        Action::PlaceSprite { pos, sprite_kind } => {
+            let sprite_kind = SpriteKind::try_from(sprite_kind.as_str())
+                .map_err(|_| format!("Invalid sprite kind: {}", sprite_kind))?;
+            let block = server
+                .state
+                .get_block(pos)
+                .unwrap_or_else(|| Block::air(SpriteKind::Empty))
+                .with_sprite(sprite_kind);
+            set_block_persistent(server, pos, block);
+        },
+    }
+    Ok(())
+}
+
+fn entity_from_uid(server: &Server, uid: Uid) -> Result<EcsEntity, String> {
+    server
+        .state
+        .ecs()
+        .entity_from_uid(uid.0)
+        .ok_or_else(|| format!("No entity with uid {}", uid))
+}
+
+fn apply_buff(
+    ecs: &specs::World,
+    entity: EcsEntity,
+    kind: BuffKind,
+    strength: f32,
+    duration: Option<Duration>,
+) -> Result<(), String> {
+    let mut buffs_storage = ecs.write_storage::<comp::Buffs>();
+    let mut buffs = buffs_storage
+        .get_mut(entity)
+        .ok_or_else(|| "Entity can't have buffs".to_owned())?;
+    buffs.insert(Buff::new(
+        kind,
+        BuffData::new(strength, duration, None),
+        vec![],
+        BuffSource::Unknown,
+    ));
+    Ok(())
+}
+
+/// Remove up to `amount` of the item with the asset specifier `item` from the
+/// inventory of `entity`
+fn remove_item(
+    server: &Server,
+    entity: EcsEntity,
+    item: &str,
+    mut amount: u32,
+) -> Result<(), String> {
+    let ecs = server.state.ecs();
+    let mut inventories = ecs.write_storage::<Inventory>();
+    let mut inventory = inventories
+        .get_mut(entity)
+        .ok_or_else(|| "Entity has no inventory".to_owned())?;
+    let slots = inventory
+        .slots_with_id()
+        .filter_map(|(slot, stack)| Some((slot, stack.as_ref()?)))
+        .filter(|(_, stack)| stack.persistence_item_id() == item)
+        .map(|(slot, stack)| (slot, stack.amount()))
+        .collect::<Vec<_>>();
+    for (slot, stack_amount) in slots {
+        if amount == 0 {
+            break;
+        }
+        if stack_amount <= amount {
+            inventory.remove(slot);
+            amount -= stack_amount;
+        } else if let Some(Some(stack)) = inventory.slot_mut(slot) {
+            stack
+                .decrease_amount(amount)
+                .map_err(|_| "Failed to remove items".to_owned())?;
+            amount = 0;
+        }
+    }
+    ecs.write_storage()
+        .insert(
+            entity,
+            comp::InventoryUpdate::new(comp::InventoryUpdateEvent::Gave),
+        )
+        .map_err(|_| "Entity is dead".to_owned())?;
+    Ok(())
+}
+
+pub(super) fn item_stack(item: &Item) -> ItemStack {
+    ItemStack {
+        item: item.persistence_item_id().to_owned(),
+        amount: item.amount(),
+    }
+}
+
+/// Let plugins veto or change damage dealt to `entity`, returns `None` if the
+/// damage should not be dealt
+pub(super) fn on_entity_damage(
+    state: &State,
+    entity: EcsEntity,
+    change: HealthChange,
+) -> Option<HealthChange> {
+    // Healing isn't exposed to plugins
+    if change.amount >= 0.0 {
+        return Some(change);
+    }
+    let target = match state.ecs().uid_from_entity(entity) {
+        Some(uid) => uid,
+        None => return Some(change),
+    };
+    let responses = state.execute_plugin_event(&EntityDamageEvent {
+        target,
+        attacker: change.by.map(|by| by.uid()),
+        amount: -change.amount,
+    });
+    apply_damage_responses(change, responses)
+}
+
+/// Apply the responses of plugins to a damage event, in the order the plugins
+/// responded
+fn apply_damage_responses(
+    mut change: HealthChange,
+    responses: Vec<EntityDamageResult>,
+) -> Option<HealthChange> {
+    for response in responses {
+        match response {
+            EntityDamageResult::Cancel => return None,
+            EntityDamageResult::SetAmount(amount) => change.amount = -amount.max(0.0),
+            EntityDamageResult::None => {},
+        }
+    }
+    Some(change)
+}
+
+pub(super) fn on_entity_death(state: &State, entity: EcsEntity, killer: Option<Uid>) {
+    if let Some(uid) = state.ecs().uid_from_entity(entity) {
+        state.execute_plugin_event(&EntityDeathEvent {
+            entity: uid,
+            killer,
+        });
+    }
+}
+
+/// Let plugins cancel or rewrite a chat message sent by a player, returns
+/// `None` if the message should not be sent
+pub(super) fn on_chat_message(state: &State, msg: UnresolvedChatMsg) -> Option<UnresolvedChatMsg> {
+    let (id, channel) = match &msg.chat_type {
+        ChatType::Tell(from, to) => (*from, ChatChannel::Tell(*to)),
+        ChatType::Say(from) => (*from, ChatChannel::Say),
+        ChatType::Group(from, _) => (*from, ChatChannel::Group),
+        ChatType::Faction(from, faction) => (*from, ChatChannel::Faction(faction.clone())),
+        ChatType::Region(from) => (*from, ChatChannel::Region),
+        ChatType::World(from) => (*from, ChatChannel::World),
+        // Only messages sent by players are exposed to plugins
+        _ => return Some(msg),
+    };
+    let responses = state.execute_plugin_event(&ChatMessageEvent {
+        player: Player { id },
+        channel,
+        message: msg.message.clone(),
+    });
+    apply_chat_responses(msg, responses)
+}
+
+/// Apply the responses of plugins to a chat message event, in the order the
+/// plugins responded
+fn apply_chat_responses(
+    mut msg: UnresolvedChatMsg,
+    responses: Vec<ChatMessageResult>,
+) -> Option<UnresolvedChatMsg> {
+    for response in responses {
+        match response {
+            ChatMessageResult::Cancel => return None,
+            ChatMessageResult::Rewrite(message) => msg.message = message,
+            ChatMessageResult::None => {},
+        }
+    }
+    Some(msg)
+}
+
+/// Returns whether plugins allow `entity` to break the block at `pos`
+pub(super) fn on_block_break(state: &State, entity: EcsEntity, pos: Vec3<i32>) -> bool {
+    match (state.ecs().uid_from_entity(entity), state.get_block(pos)) {
+        (Some(id), Some(block)) => state
+            .execute_plugin_event(&BlockBreakEvent {
+                player: Player { id },
+                pos,
+                block_kind: format!("{:?}", block.kind()),
+            })
+            .into_iter()
+            .all(allows_block_edit),
+        _ => true,
+    }
+}
+
+/// Returns whether plugins allow `entity` to place `block` at `pos`
+pub(super) fn on_block_place(
+    state: &State,
+    entity: EcsEntity,
+    pos: Vec3<i32>,
+    block: Block,
+) -> bool {
+    match state.ecs().uid_from_entity(entity) {
+        Some(id) => state
+            .execute_plugin_event(&BlockPlaceEvent {
+                player: Player { id },
+                pos,
+                block_kind: format!("{:?}", block.kind()),
+            })
+            .into_iter()
+            .all(allows_block_edit),
+        None => true,
+    }
+}
+
+fn allows_block_edit(response: BlockEditResult) -> bool { response != BlockEditResult::Cancel }
+
+pub(super) fn on_item_crafted(state: &State, entity: EcsEntity, items: Vec<ItemStack>) {
+    if let Some(id) = state.ecs().uid_from_entity(entity) {
+        state.execute_plugin_event(&ItemCraftedEvent {
+            player: Player { id },
+            items,
+        });
+    }
+}
+
+pub(super) fn on_trade_completed(state: &State, parties: [Uid; 2], offers: [Vec<ItemStack>; 2]) {
+    state.execute_plugin_event(&TradeCompletedEvent { parties, offers });
+}
+
+pub(super) fn on_leave(state: &State, entity: EcsEntity) {
+    let player = state
+        .ecs()
+        .read_storage::<comp::Player>()
+        .get(entity)
+        .map(|player| player.alias.clone());
+    if let (Some(id), Some(player_name)) = (state.ecs().uid_from_entity(entity), player) {
+        state.execute_plugin_event(&PlayerLeaveEvent {
+            player: Player { id },
+            player_name,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{comp::ChatType, resources::Time};
+    use specs::Builder;
+
+    fn damage(amount: f32) -> HealthChange {
+        HealthChange {
+            amount: -amount,
+            by: None,
+            cause: None,
+            time: Time(0.0),
+            crit: false,
+            instance: 0,
+        }
+    }
+
+    #[test]
+    fn damage_responses() {
+        let amount = |responses| apply_damage_responses(damage(10.0), responses).map(|c| c.amount);
+        assert_eq!(amount(Vec::new()), Some(-10.0));
+        assert_eq!(amount(vec![EntityDamageResult::None]), Some(-10.0));
+        assert_eq!(amount(vec![EntityDamageResult::SetAmount(4.0)]), Some(-4.0));
+        // Plugins can't turn damage into healing
+        assert_eq!(amount(vec![EntityDamageResult::SetAmount(-4.0)]), Some(0.0));
+        assert_eq!(
+            amount(vec![
+                EntityDamageResult::SetAmount(4.0),
+                EntityDamageResult::Cancel
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn chat_responses() {
+        let msg = ChatType::World(Uid(1)).chat_msg("hello");
+        let message = |responses| apply_chat_responses(msg.clone(), responses).map(|m| m.message);
+        assert_eq!(message(Vec::new()), Some("hello".to_owned()));
+        assert_eq!(
+            message(vec![
+                ChatMessageResult::Rewrite("hi".to_owned()),
+                ChatMessageResult::None
+            ]),
+            Some("hi".to_owned())
+        );
+        assert_eq!(
+            message(vec![
+                ChatMessageResult::None,
+                ChatMessageResult::Cancel,
+                ChatMessageResult::Rewrite("hi".to_owned())
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn block_edit_responses() {
+        let allowed =
+            |responses: Vec<BlockEditResult>| responses.into_iter().all(allows_block_edit);
+        assert!(allowed(Vec::new()));
+        assert!(allowed(vec![BlockEditResult::None, BlockEditResult::None]));
+        assert!(!allowed(vec![
+            BlockEditResult::None,
+            BlockEditResult::Cancel
+        ]));
+    }
+
+    #[test]
+    fn apply_buff_requires_buffs() {
+        let mut ecs = specs::World::new();
+        ecs.register::<comp::Buffs>();
+        let without_buffs = ecs.create_entity().build();
+        let with_buffs = ecs.create_entity().with(comp::Buffs::default()).build();
+
+        assert!(apply_buff(&ecs, without_buffs, BuffKind::Regeneration, 1.0, None).is_err());
+        assert!(apply_buff(
+            &ecs,
+            with_buffs,
+            BuffKind::Regeneration,
+            1.0,
+            Some(Duration::from_secs(5))
+        )
+        .is_ok());
+        assert!(ecs
+            .read_storage::<comp::Buffs>()
+            .get(with_buffs)
+            .unwrap()
+            .contains(BuffKind::Regeneration));
+    }
+}
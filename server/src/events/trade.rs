@@ -89,8 +89,14 @@ pub(super) fn handle_process_trade_action(
             if let Entry::Occupied(entry) = trades.trades.entry(trade_id) {
                 let parties = entry.get().parties;
                 if entry.get().should_commit() {
+                    #[cfg(feature = "plugins")]
+                    let offers = trade_offers(server.state.ecs(), entry.get());
                     let result = commit_trade(server.state.ecs(), entry.get());
                     entry.remove();
+                    #[cfg(feature = "plugins")]
+                    if let (TradeResult::Completed, Some(offers)) = (&result, offers) {
+                        super::plugin::on_trade_completed(&server.state, parties, offers);
+                    }
                     for party in parties.iter() {
                         if let Some(e) = server.state.ecs().entity_from_uid(party.0) {
                             server.notify_client(e, ServerGeneral::FinishedTrade(result.clone()));
@@ -193,6 +199,31 @@ pub(crate) fn cancel_trades_for(state: &mut common_state::State, entity: EcsEnti
     }
 }
 
+/// The items offered by each party of `trade`, as exposed to plugins. Returns
+/// `None` if either party no longer has an inventory.
+#[cfg(feature = "plugins")]
+fn trade_offers(
+    ecs: &specs::World,
+    trade: &PendingTrade,
+) -> Option<[Vec<plugin_api::ItemStack>; 2]> {
+    let inventories = ecs.read_component::<Inventory>();
+    let mut offers: [Vec<plugin_api::ItemStack>; 2] = [Vec::new(), Vec::new()];
+    for who in 0..2 {
+        let inventory = inventories.get(ecs.entity_from_uid(trade.parties[who].0)?)?;
+        offers[who] = trade.offers[who]
+            .iter()
+            .filter_map(|(slot, quantity)| {
+                let item = inventory.get(*slot)?;
+                Some(plugin_api::ItemStack {
+                    item: item.persistence_item_id().to_owned(),
+                    amount: item.amount().min(*quantity),
+                })
+            })
+            .collect();
+    }
+    Some(offers)
+}
+
 /// Commit a trade that both parties have agreed to, modifying their respective
 /// inventories
 fn commit_trade(ecs: &specs::World, trade: &PendingTrade) -> TradeResult {
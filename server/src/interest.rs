@@ -0,0 +1,197 @@
+//! Interest management for the entity sync: how often a client gets physics
+//! updates of an entity, by how relevant the entity is to the client and how
+//! much bandwidth the client's connection has.
+
+use hashbrown::HashMap;
+use specs::Entity as EcsEntity;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Approximate size of the physics update of one entity in bytes, the
+/// position, velocity and orientation with the uid of the entity
+const UPDATE_SIZE: f32 = 48.0;
+/// Share of the bandwidth of a client which physics updates may use, the rest
+/// is left for terrain, chat, inventories, etc.
+const PHYSICS_SHARE: f32 = 0.5;
+/// Below this speed (squared) entities count as idle
+const IDLE_SPEED_SQ: f32 = 0.25;
+/// The slowest rate at which entities in view are updated, in ticks
+const MAX_INTERVAL: u64 = 32;
+/// How quickly the pressure follows the bandwidth, per second
+const PRESSURE_ADAPTION: f32 = 2.0;
+
+/// How interesting an entity is to a client
+pub struct Interest {
+    pub distance_sq: f32,
+    pub speed_sq: f32,
+    /// Members of the client's group and entities targeting the client's
+    /// entity
+    pub relevant: bool,
+}
+
+impl Interest {
+    /// Number of ticks between physics updates of the entity, `pressure`
+    /// stretches the interval for clients with too little bandwidth, see
+    /// [`InterestBudgets::pressure`].
+    pub fn interval(&self, pressure: f32) -> u64 {
+        // Relevant entities are updated as if they were much closer
+        let distance_sq = if self.relevant {
+            self.distance_sq / 16.0
+        } else {
+            self.distance_sq
+        };
+        let mut interval = if distance_sq > 500.0f32.powi(2) {
+            32
+        } else if distance_sq > 300.0f32.powi(2) {
+            16
+        } else if distance_sq > 200.0f32.powi(2) {
+            8
+        } else if distance_sq > 120.0f32.powi(2) {
+            6
+        } else if distance_sq > 64.0f32.powi(2) {
+            3
+        } else if distance_sq > 24.0f32.powi(2) {
+            2
+        } else {
+            1
+        };
+        // Idle entities don't go anywhere, the client's prediction stays right
+        if self.speed_sq < IDLE_SPEED_SQ {
+            interval *= 2;
+        }
+        // Relevant entities keep their rate even on a slow connection
+        if !self.relevant {
+            interval = (interval as f32 * pressure.max(1.0)).ceil() as u64;
+        }
+        interval.min(MAX_INTERVAL)
+    }
+}
+
+struct Budget {
+    pressure: f32,
+    /// Physics updates sent to the client in this tick
+    updates: AtomicU32,
+}
+
+/// Tracks the physics updates sent to each client to fit them into its
+/// bandwidth
+#[derive(Default)]
+pub struct InterestBudgets {
+    budgets: HashMap<EcsEntity, Budget>,
+}
+
+impl InterestBudgets {
+    /// How many times longer the update intervals of the client have to be to
+    /// fit into its bandwidth, 1.0 if they fit already
+    pub fn pressure(&self, client: EcsEntity) -> f32 {
+        self.budgets
+            .get(&client)
+            .map_or(1.0, |budget| budget.pressure)
+    }
+
+    /// Count physics updates sent to the client in this tick. Clients without
+    /// a budget are ignored, they get one with the next
+    /// [`InterestBudgets::update`].
+    pub fn count(&self, client: EcsEntity, updates: u32) {
+        if let Some(budget) = self.budgets.get(&client) {
+            budget.updates.fetch_add(updates, Ordering::Relaxed);
+        }
+    }
+
+    /// Adapt the pressure of each client after a tick of `dt` seconds, by
+    /// comparing the updates it got to its `bandwidth` in bytes per second.
+    /// Clients not in `clients` are forgotten.
+    pub fn update(&mut self, clients: impl Iterator<Item = (EcsEntity, f32)>, dt: f32) {
+        let mut budgets = HashMap::with_capacity(self.budgets.len());
+        for (client, bandwidth) in clients {
+            let (pressure, updates) = self.budgets.remove(&client).map_or((1.0, 0), |budget| {
+                (budget.pressure, budget.updates.into_inner())
+            });
+            // The bandwidth is unknown until the connection sent something
+            let pressure = if bandwidth > 0.0 && dt > 0.0 {
+                let used = updates as f32 * UPDATE_SIZE / dt;
+                let ratio = used / (bandwidth * PHYSICS_SHARE);
+                let adaption = (PRESSURE_ADAPTION * dt).min(1.0);
+                (pressure + (pressure * ratio - pressure) * adaption)
+                    .clamp(1.0, MAX_INTERVAL as f32)
+            } else {
+                1.0
+            };
+            budgets.insert(client, Budget {
+                pressure,
+                updates: AtomicU32::new(0),
+            });
+        }
+        self.budgets = budgets;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, World, WorldExt};
+
+    fn interest(distance: f32, moving: bool, relevant: bool) -> Interest {
+        Interest {
+            distance_sq: distance.powi(2),
+            speed_sq: if moving { 10.0 } else { 0.0 },
+            relevant,
+        }
+    }
+
+    #[test]
+    fn interval() {
+        assert_eq!(interest(10.0, true, false).interval(1.0), 1);
+        assert_eq!(interest(100.0, true, false).interval(1.0), 3);
+        assert_eq!(interest(1000.0, true, false).interval(1.0), 32);
+        // Idle entities are updated half as often, but not slower than the maximum
+        assert_eq!(interest(100.0, false, false).interval(1.0), 6);
+        assert_eq!(interest(1000.0, false, false).interval(1.0), MAX_INTERVAL);
+        // Relevant entities are updated as if they were closer
+        assert_eq!(interest(100.0, true, true).interval(1.0), 2);
+
+        // Pressure stretches the interval, except for relevant entities
+        assert_eq!(interest(100.0, true, false).interval(2.5), 8);
+        assert_eq!(interest(100.0, true, true).interval(2.5), 2);
+        assert_eq!(interest(1000.0, true, false).interval(2.5), MAX_INTERVAL);
+        // Pressure never shortens the interval
+        assert_eq!(interest(100.0, true, false).interval(0.5), 3);
+    }
+
+    #[test]
+    fn update_budgets() {
+        let mut world = World::new();
+        let [client, other] = [(); 2].map(|_| world.create_entity().build());
+        let mut budgets = InterestBudgets::default();
+        let dt = 0.1;
+
+        // New clients start without pressure, and clients without a budget aren't
+        // counted
+        budgets.count(client, 100);
+        budgets.update([(client, 1000.0), (other, 0.0)].into_iter(), dt);
+        assert_eq!(budgets.pressure(client), 1.0);
+        assert_eq!(budgets.pressure(other), 1.0);
+
+        // Too many updates for the bandwidth raise the pressure
+        budgets.count(client, 100);
+        budgets.count(other, 100);
+        budgets.update([(client, 1000.0), (other, 0.0)].into_iter(), dt);
+        let pressure = budgets.pressure(client);
+        assert!(pressure > 1.0 && pressure <= MAX_INTERVAL as f32);
+        // The bandwidth of the other client is unknown
+        assert_eq!(budgets.pressure(other), 1.0);
+
+        // Fewer updates lower it again, but not below 1
+        budgets.update([(client, 1000.0)].into_iter(), dt);
+        assert!(budgets.pressure(client) < pressure);
+        for _ in 0..100 {
+            budgets.update([(client, 1000.0)].into_iter(), dt);
+        }
+        assert_eq!(budgets.pressure(client), 1.0);
+
+        // Clients which aren't updated anymore are forgotten
+        budgets.count(client, 100);
+        budgets.update(std::iter::empty(), dt);
+        budgets.update([(client, 1000.0)].into_iter(), dt);
+        assert_eq!(budgets.pressure(client), 1.0);
+    }
+}
@@ -21,6 +21,7 @@ mod data_dir;
 pub mod error;
 pub mod events;
 pub mod input;
+pub mod interest;
 pub mod location;
 pub mod lod;
 pub mod login_provider;
@@ -28,6 +29,9 @@ pub mod metrics;
 pub mod persistence;
 mod pet;
 pub mod presence;
+mod query_server;
+pub mod rate_limit;
+pub mod resume;
 pub mod rtsim;
 pub mod settings;
 pub mod state_ext;
@@ -62,7 +66,9 @@ use crate::{
     login_provider::LoginProvider,
     persistence::PersistedComponents,
     presence::{Presence, RegionSubscription, RepositionOnChunkLoad},
+    query_server::QueryServer,
     rtsim::RtSim,
+    settings::SettingsWatcher,
     state_ext::StateExt,
     sys::sentinel::DeletedEntities,
 };
@@ -84,14 +90,16 @@ use common::{
 };
 use common_ecs::run_now;
 use common_net::{
-    msg::{ClientType, DisconnectReason, ServerGeneral, ServerInfo, ServerMsg},
+    msg::{ClientType, DisconnectReason, ServerGeneral, ServerInfo, ServerMsg, ServerStatus},
     sync::WorldSyncExt,
 };
 use common_state::{BuildAreas, State};
 use common_systems::add_local_systems;
 use metrics::{EcsSystemMetrics, PhysicsMetrics, TickMetrics};
-use network::{ListenAddr, Network, Pid};
+use network::{ListenAddr, Network, Pid, ServerKey};
 use persistence::{
+    audit_log::{AuditEntry, AuditLogger, AuditQuery},
+    backup::DatabaseBackups,
     character_loader::{CharacterLoader, CharacterLoaderResponseKind},
     character_updater::CharacterUpdater,
 };
@@ -121,8 +129,9 @@ use crate::settings::Protocol;
 
 #[cfg(feature = "plugins")]
 use {
-    common::uid::UidAllocator,
+    common::{cmd::PluginChatCommand, uid::UidAllocator},
     common_state::plugin::{memory_manager::EcsWorld, PluginMgr},
+    persistence::plugin_storage::PluginStorageUpdater,
 };
 
 use common::comp::Anchor;
@@ -145,6 +154,10 @@ impl Default for SpawnPoint {
 // various mechanics working fluidly (i.e: not unloading nearby entities).
 pub const MIN_VD: u32 = 6;
 
+// The status answered to server lists is updated every this many ticks, about
+// once a second is recent enough for them
+const QUERY_STATUS_UPDATE_TICKS: u64 = 30;
+
 // Tick count used for throttling network updates
 // Note this doesn't account for dt (so update rate changes with tick rate)
 #[derive(Copy, Clone, Default)]
@@ -191,18 +204,36 @@ pub struct ChunkRequest {
     key: Vec2<i32>,
 }
 
+/// A command to manage the plugins loaded by the server
+#[cfg(feature = "plugins")]
+#[derive(Clone, Debug)]
+pub enum PluginCommand {
+    List,
+    /// Reload a plugin, or every plugin (loading the new ones) if `None`
+    Reload(Option<String>),
+    /// Unload a plugin until it is enabled again or the server restarts
+    Disable(String),
+    Enable(String),
+}
+
 pub struct Server {
     state: State,
     world: Arc<World>,
     index: IndexOwned,
 
     connection_handler: ConnectionHandler,
+    query_servers: Vec<QueryServer>,
 
     runtime: Arc<Runtime>,
 
     metrics_shutdown: Arc<Notify>,
     database_settings: Arc<RwLock<DatabaseSettings>>,
+    database_backups: DatabaseBackups,
     disconnect_all_clients_requested: bool,
+    settings_watcher: Option<SettingsWatcher>,
+    /// The plugin chat commands last sent to the clients
+    #[cfg(feature = "plugins")]
+    plugin_commands: Vec<PluginChatCommand>,
 }
 
 impl Server {
@@ -292,6 +323,8 @@ impl Server {
             .ecs_mut()
             .insert(EventBus::<chunk_serialize::ChunkSendEntry>::default());
         state.ecs_mut().insert(Locations::default());
+        state.ecs_mut().insert(resume::ResumeSessions::default());
+        state.ecs_mut().insert(interest::InterestBudgets::default());
         state.ecs_mut().insert(LoginProvider::new(
             settings.auth_server_address.clone(),
             Arc::clone(&runtime),
@@ -333,6 +366,7 @@ impl Server {
             pool.configure("CHUNK_DROP", |_n| 1);
             pool.configure("CHUNK_GENERATOR", |n| n / 2 + n / 4);
             pool.configure("CHUNK_SERIALIZER", |n| n / 2);
+            pool.configure("RTSIM_SAVE", |_n| 1);
         }
         state
             .ecs_mut()
@@ -360,6 +394,34 @@ impl Server {
             Arc::<RwLock<DatabaseSettings>>::clone(&database_settings),
         )?);
 
+        state
+            .ecs_mut()
+            .insert(AuditLogger::new(Arc::clone(&database_settings)));
+
+        #[cfg(feature = "plugins")]
+        {
+            match persistence::plugin_storage::load_plugin_storage(
+                &database_settings.read().unwrap(),
+            ) {
+                Ok(entries) => state
+                    .ecs()
+                    .read_resource::<PluginMgr>()
+                    .storage()
+                    .load(entries),
+                Err(e) => error!(
+                    ?e,
+                    "Failed to load the plugin storage, changes made by plugins to their storage \
+                     will not be persisted"
+                ),
+            }
+            state
+                .ecs_mut()
+                .insert(PluginStorageUpdater::new(Arc::clone(&database_settings)));
+            if let Err(e) = state.ecs().write_resource::<PluginMgr>().watch() {
+                debug!(?e, "Plugins will not be hot reloaded");
+            }
+        }
+
         // System schedulers to control execution of systems
         state
             .ecs_mut()
@@ -377,8 +439,7 @@ impl Server {
         state.ecs_mut().register::<RepositionOnChunkLoad>();
 
         // Load banned words list
-        let banned_words = settings.moderation.load_banned_words(data_dir);
-        let censor = Arc::new(Censor::Custom(banned_words.into_iter().collect()));
+        let censor = Arc::new(settings.moderation.load_censor(data_dir));
         state.ecs_mut().insert(Arc::clone(&censor));
 
         // Init automod
@@ -543,12 +604,42 @@ impl Server {
                         },
                     }
                 },
+                Protocol::EncryptedTcp {
+                    address,
+                    key_file_path,
+                    required,
+                } => match load_server_key(key_file_path) {
+                    Ok(key) => {
+                        info!(
+                            %address,
+                            server_key = %key.public_key(),
+                            "Encrypting TCP, clients can check the server key against this"
+                        );
+                        runtime.block_on(network.listen(ListenAddr::EncryptedTcp {
+                            addr: *address,
+                            key: Arc::new(key),
+                            required: *required,
+                        }))?;
+                    },
+                    Err(e) => {
+                        error!(
+                            ?e,
+                            "Failed to load the server key, running without encrypted TCP {}",
+                            *address
+                        );
+                    },
+                },
             }
         }
 
         runtime.block_on(network.listen(ListenAddr::Mpsc(14004)))?;
 
         let connection_handler = ConnectionHandler::new(network, &runtime);
+        let query_servers = settings
+            .query_addresses
+            .iter()
+            .map(|address| QueryServer::new(*address, server_status(state.ecs()), &runtime))
+            .collect();
 
         // Initiate real-time world simulation
         #[cfg(feature = "worldgen")]
@@ -559,16 +650,23 @@ impl Server {
         #[cfg(not(feature = "worldgen"))]
         rtsim::init(&mut state);
 
+        let database_backups = DatabaseBackups::new(Arc::clone(&database_settings));
+
         let this = Self {
             state,
             world,
             index,
             connection_handler,
+            query_servers,
             runtime,
 
             metrics_shutdown,
             database_settings,
+            database_backups,
             disconnect_all_clients_requested: false,
+            settings_watcher: None,
+            #[cfg(feature = "plugins")]
+            plugin_commands: Vec::new(),
         };
 
         debug!(?settings, "created veloren server with");
@@ -605,6 +703,52 @@ impl Server {
         self.state.ecs().fetch_mut::<Settings>()
     }
 
+    /// Reload the settings file whenever it changes, see
+    /// [`Server::reload_settings`]
+    pub fn watch_settings(&mut self) {
+        self.settings_watcher = Some(SettingsWatcher::new(self.data_dir().as_ref()));
+    }
+
+    /// Read the settings file again and apply the settings which can be
+    /// changed while the server is running. Returns a message listing the
+    /// changed settings which need a restart to take effect.
+    pub fn reload_settings(&mut self) -> Result<String, String> {
+        let reloaded = Settings::reload(self.data_dir().as_ref()).map_err(|e| e.to_string())?;
+        let needs_restart = self.settings_mut().apply_reloaded(reloaded);
+
+        // The automod keeps its own copy of the moderation settings
+        let moderation = self.settings().moderation.clone();
+        let censor = Arc::new(moderation.load_censor(self.data_dir().as_ref()));
+        self.state
+            .ecs()
+            .write_resource::<AutoMod>()
+            .update_settings(&moderation, Arc::clone(&censor));
+        *self.state.ecs().write_resource::<Arc<Censor>>() = censor;
+
+        Ok(if needs_restart.is_empty() {
+            "Reloaded the server settings".to_owned()
+        } else {
+            format!(
+                "Reloaded the server settings, changes to {} will only take effect after a restart",
+                needs_restart.join(", ")
+            )
+        })
+    }
+
+    /// Query the audit log of the admin and moderation actions, the most
+    /// recent entries come first
+    pub fn query_audit_log(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>, String> {
+        persistence::audit_log::query_audit_log(&self.database_settings.read().unwrap(), query)
+            .map_err(|e| format!("Failed to query the audit log: {}", e))
+    }
+
+    /// Back up the database in the background, without waiting for the next
+    /// scheduled backup
+    pub fn backup_database(&self) {
+        info!("Backing up the database due to local console command");
+        self.database_backups.backup_now();
+    }
+
     /// Get a mutable reference to the server's editable settings
     pub fn editable_settings_mut(&self) -> impl DerefMut<Target = EditableSettings> + '_ {
         self.state.ecs().fetch_mut::<EditableSettings>()
@@ -672,7 +816,26 @@ impl Server {
         // 1) Build up a list of events for this frame, to be passed to the frontend.
         let mut frontend_events = Vec::new();
 
-        // 2)
+        if self
+            .settings_watcher
+            .as_mut()
+            .map_or(false, |watcher| watcher.changed())
+        {
+            match self.reload_settings() {
+                Ok(msg) => info!("{}", msg),
+                Err(e) => error!("{}", e),
+            }
+        }
+
+        // 2) Run the plugin timers, then apply the actions emitted by plugins since the
+        //    last tick
+        #[cfg(feature = "plugins")]
+        {
+            self.reload_changed_plugins();
+            self.state.tick_plugins(dt.as_secs_f32());
+            events::handle_plugin_actions(self);
+            self.broadcast_plugin_commands();
+        }
 
         let before_new_connections = Instant::now();
 
@@ -895,6 +1058,8 @@ impl Server {
                                 pets,
                                 active_abilities,
                                 map_marker,
+                                vitals,
+                                vault,
                             } = character_data;
                             let character_data = (
                                 body,
@@ -905,6 +1070,8 @@ impl Server {
                                 pets,
                                 active_abilities,
                                 map_marker,
+                                vitals,
+                                vault,
                             );
                             ServerEvent::UpdateCharacterData {
                                 entity: query_result.entity,
@@ -938,6 +1105,23 @@ impl Server {
         drop(character_loader);
         drop(character_updater);
 
+        // Persist the changes made by plugins to their storage
+        #[cfg(feature = "plugins")]
+        {
+            let changes = self
+                .state
+                .ecs()
+                .read_resource::<PluginMgr>()
+                .storage()
+                .take_changes();
+            if !changes.is_empty() {
+                self.state
+                    .ecs()
+                    .read_resource::<PluginStorageUpdater>()
+                    .batch_update(changes);
+            }
+        }
+
         {
             // Check for new chunks; cancel and regenerate all chunks if the asset has been
             // reloaded. Note that all of these assignments are no-ops, so the
@@ -983,6 +1167,15 @@ impl Server {
         // 8) Update Metrics
         run_now::<sys::metrics::Sys>(self.state.ecs());
 
+        if !self.query_servers.is_empty()
+            && self.state.ecs().read_resource::<Tick>().0 % QUERY_STATUS_UPDATE_TICKS == 0
+        {
+            let status = server_status(self.state.ecs());
+            for query_server in &self.query_servers {
+                query_server.update(status.clone());
+            }
+        }
+
         {
             // Report timing info
             let tick_metrics = self.state.ecs().read_resource::<TickMetrics>();
@@ -1053,12 +1246,18 @@ impl Server {
             let with_persistence = disconnect_type == DisconnectType::WithPersistence;
             let clients = self.state.ecs().read_storage::<Client>();
             let entities = self.state.ecs().entities();
+            let resume_sessions = self.state.ecs().read_resource::<resume::ResumeSessions>();
 
             info!(
                 "Disconnecting all clients ({} persistence) as requested",
                 if with_persistence { "with" } else { "without" }
             );
-            for (_, entity) in (&clients, &entities).join() {
+            // Including the players waiting for their client to resume the session
+            for entity in (&clients, &entities)
+                .join()
+                .map(|(_, entity)| entity)
+                .chain(resume_sessions.detached())
+            {
                 info!("Emitting client disconnect event for entity: {:?}", entity);
                 let event = if with_persistence {
                     ServerEvent::ClientDisconnect(entity, comp::DisconnectReason::Kicked)
@@ -1145,12 +1344,36 @@ impl Server {
             #[cfg(feature = "plugins")]
             {
                 let plugin_manager = self.state.ecs().read_resource::<PluginMgr>();
+                // Commands registered by plugins are checked like the built-in ones
+                if let Some(command) = plugin_manager
+                    .commands()
+                    .into_iter()
+                    .find(|command| command.keyword == name)
+                {
+                    let check = if command.needs_role > self.entity_admin_role(entity) {
+                        Err(format!("You don't have permission to use '/{}'.", name))
+                    } else {
+                        command.verify_args(&args)
+                    };
+                    if let Err(e) = check {
+                        self.notify_client(
+                            entity,
+                            ServerGeneral::server_msg(comp::ChatType::CommandError, e),
+                        );
+                        return;
+                    }
+                }
                 let ecs_world = EcsWorld {
                     entities: &self.state.ecs().entities(),
                     health: self.state.ecs().read_component().into(),
                     uid: self.state.ecs().read_component().into(),
                     uid_allocator: &self.state.ecs().read_resource::<UidAllocator>().into(),
                     player: self.state.ecs().read_component().into(),
+                    pos: self.state.ecs().read_component().into(),
+                    inventory: self.state.ecs().read_component().into(),
+                    stats: self.state.ecs().read_component().into(),
+                    energy: self.state.ecs().read_component().into(),
+                    group: self.state.ecs().read_component().into(),
                 };
                 let uid = if let Some(uid) = ecs_world.uid.get(entity).copied() {
                     uid
@@ -1344,6 +1567,68 @@ impl Server {
         info!("Disconnecting all clients due to local console command");
         self.disconnect_all_clients_requested = true;
     }
+
+    /// Run a plugin management command, returning a message describing the
+    /// result
+    #[cfg(feature = "plugins")]
+    pub fn plugin_command(&mut self, command: PluginCommand) -> Result<String, String> {
+        let ecs = self.state.ecs();
+        let game_mode = *ecs.read_resource::<GameMode>();
+        let mut plugin_mgr = ecs.write_resource::<PluginMgr>();
+        let result = state_ext::with_ecs_world(ecs, |ecs_world| match command {
+            PluginCommand::List => {
+                let mut loaded = plugin_mgr.plugin_names().collect::<Vec<_>>();
+                let mut disabled = plugin_mgr.disabled_plugin_names().collect::<Vec<_>>();
+                loaded.sort_unstable();
+                disabled.sort_unstable();
+                Ok(format!(
+                    "Loaded plugins: {}\nDisabled plugins: {}",
+                    loaded.join(", "),
+                    disabled.join(", ")
+                ))
+            },
+            PluginCommand::Reload(None) => plugin_mgr
+                .reload_all(ecs_world, game_mode)
+                .map(|loaded| format!("Reloaded plugins: {}", loaded.join(", "))),
+            PluginCommand::Reload(Some(name)) => plugin_mgr
+                .reload(&name, ecs_world, game_mode)
+                .map(|()| format!("Reloaded plugin '{}'", name)),
+            PluginCommand::Disable(name) => plugin_mgr
+                .disable(&name)
+                .map(|()| format!("Disabled plugin '{}'", name)),
+            PluginCommand::Enable(name) => plugin_mgr
+                .enable(&name, ecs_world, game_mode)
+                .map(|()| format!("Enabled plugin '{}'", name)),
+        });
+        drop(plugin_mgr);
+        self.broadcast_plugin_commands();
+        result.map_err(|e| format!("Plugin command failed: {:?}", e))
+    }
+
+    #[cfg(feature = "plugins")]
+    fn reload_changed_plugins(&mut self) {
+        let ecs = self.state.ecs();
+        let game_mode = *ecs.read_resource::<GameMode>();
+        let mut plugin_mgr = ecs.write_resource::<PluginMgr>();
+        // The changed commands are sent by `broadcast_plugin_commands` later in the
+        // tick
+        state_ext::with_ecs_world(ecs, |ecs_world| {
+            plugin_mgr.reload_changed(ecs_world, game_mode)
+        });
+    }
+
+    /// Send the chat commands registered by the plugins to every client if they
+    /// changed since they were last sent. Plugins may register commands at any
+    /// time, not only when they are loaded.
+    #[cfg(feature = "plugins")]
+    fn broadcast_plugin_commands(&mut self) {
+        let commands = self.state.ecs().read_resource::<PluginMgr>().commands();
+        if commands != self.plugin_commands {
+            self.plugin_commands = commands.clone();
+            self.state
+                .notify_players(ServerGeneral::PluginCommands(commands));
+        }
+    }
 }
 
 impl Drop for Server {
@@ -1353,6 +1638,12 @@ impl Drop for Server {
         self.state
             .notify_players(ServerGeneral::Disconnect(DisconnectReason::Shutdown));
 
+        {
+            let ecs = self.state.ecs();
+            ecs.read_resource::<rtsim::save::RtSimPersistence>()
+                .save(&ecs.read_resource::<RtSim>(), ecs.read_resource::<Time>().0);
+        }
+
         #[cfg(feature = "persistent_world")]
         self.state
             .ecs()
@@ -1364,6 +1655,63 @@ impl Drop for Server {
     }
 }
 
+/// Load the key identifying the server for encrypted TCP, or generate it if
+/// the file doesn't exist. Only the user running the server may access it.
+fn load_server_key(path: &std::path::Path) -> Result<ServerKey, Box<dyn std::error::Error>> {
+    use std::{fs, io::Write};
+
+    if path.exists() {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(path)?.permissions().mode();
+            if mode & 0o077 != 0 {
+                return Err(format!(
+                    "other users may access the server key {} (mode {:o}), restrict it with \
+                     `chmod 600`",
+                    path.display(),
+                    mode & 0o777
+                )
+                .into());
+            }
+        }
+        return Ok(ServerKey::from_pkcs8(&fs::read(path)?)?);
+    }
+
+    info!(?path, "Generating a new server key");
+    let key = ServerKey::generate();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(key.to_pkcs8())?;
+    Ok(key)
+}
+
+/// The status answered to the queries of server lists
+fn server_status(ecs: &specs::World) -> ServerStatus {
+    let settings = ecs.fetch::<Settings>();
+    let editable_settings = ecs.fetch::<EditableSettings>();
+    let mut status = ServerStatus {
+        name: settings.server_name.clone(),
+        description: (*editable_settings.server_description).clone(),
+        players: (&ecs.read_storage::<comp::Player>()).join().count() as u32,
+        max_players: settings.max_players.into(),
+        git_hash: common::util::GIT_HASH.to_string(),
+        git_date: common::util::GIT_DATE.to_string(),
+        battle_mode: settings.gameplay.battle_mode.default_mode(),
+        battle_mode_choosable: settings.gameplay.battle_mode.allow_choosing(),
+    };
+    status.truncate();
+    status
+}
+
 #[must_use]
 pub fn handle_edit<T, S: settings::EditableSetting>(
     data: T,
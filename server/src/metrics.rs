@@ -38,6 +38,10 @@ pub struct NetworkRequestMetrics {
     pub chunks_served_lossless: IntCounter,
     pub chunks_serialisation_requests: IntCounter,
     pub chunks_distinct_serialisation_requests: IntCounter,
+    pub delta_sync_bytes: IntCounterVec,
+    pub delta_sync_bytes_saved: IntCounterVec,
+    pub delta_sync_resyncs: IntCounterVec,
+    pub client_msgs_rate_limited: IntCounterVec,
 }
 
 pub struct ChunkGenMetrics {
@@ -216,6 +220,34 @@ impl NetworkRequestMetrics {
             "chunks_distinct_serialisation_requests",
             "number of distinct chunks in requests for the sys chunk_serialisation",
         ))?;
+        let delta_sync_bytes = IntCounterVec::new(
+            Opts::new(
+                "delta_sync_bytes",
+                "bytes of delta synced components sent, either as full snapshot or as delta",
+            ),
+            &["component", "encoding"],
+        )?;
+        let delta_sync_bytes_saved = IntCounterVec::new(
+            Opts::new(
+                "delta_sync_bytes_saved",
+                "bytes saved by sending deltas instead of full snapshots of components",
+            ),
+            &["component"],
+        )?;
+        let delta_sync_resyncs = IntCounterVec::new(
+            Opts::new(
+                "delta_sync_resyncs",
+                "number of full snapshots sent because a client missed the baseline of a delta",
+            ),
+            &["component"],
+        )?;
+        let client_msgs_rate_limited = IntCounterVec::new(
+            Opts::new(
+                "client_msgs_rate_limited",
+                "number of client messages over the rate limits and the response to them",
+            ),
+            &["category", "verdict"],
+        )?;
 
         registry.register(Box::new(chunks_request_dropped.clone()))?;
         registry.register(Box::new(chunks_served_from_memory.clone()))?;
@@ -224,6 +256,10 @@ impl NetworkRequestMetrics {
         registry.register(Box::new(chunks_served_lossless.clone()))?;
         registry.register(Box::new(chunks_serialisation_requests.clone()))?;
         registry.register(Box::new(chunks_distinct_serialisation_requests.clone()))?;
+        registry.register(Box::new(delta_sync_bytes.clone()))?;
+        registry.register(Box::new(delta_sync_bytes_saved.clone()))?;
+        registry.register(Box::new(delta_sync_resyncs.clone()))?;
+        registry.register(Box::new(client_msgs_rate_limited.clone()))?;
 
         Ok(Self {
             chunks_request_dropped,
@@ -233,6 +269,10 @@ impl NetworkRequestMetrics {
             chunks_served_lossless,
             chunks_serialisation_requests,
             chunks_distinct_serialisation_requests,
+            delta_sync_bytes,
+            delta_sync_bytes_saved,
+            delta_sync_resyncs,
+            client_msgs_rate_limited,
         })
     }
 }
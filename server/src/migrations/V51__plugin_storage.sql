@@ -0,0 +1,7 @@
+-- Creates new plugin_storage table, a key/value storage scoped per plugin
+CREATE TABLE "plugin_storage" (
+      "plugin" TEXT NOT NULL,
+      "key" TEXT NOT NULL,
+      "value" BLOB NOT NULL,
+      PRIMARY KEY("plugin", "key")
+);
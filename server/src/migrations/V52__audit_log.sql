@@ -0,0 +1,15 @@
+-- Creates new audit_log table, a record of the admin and moderation actions
+CREATE TABLE "audit_log" (
+      "audit_log_id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+      "timestamp" INTEGER NOT NULL,
+      "actor" TEXT NOT NULL,
+      "actor_uuid" TEXT,
+      "target" TEXT,
+      "action" TEXT NOT NULL,
+      "args" TEXT NOT NULL,
+      "error" TEXT
+);
+
+CREATE INDEX "idx_audit_log_timestamp" ON "audit_log" ("timestamp");
+CREATE INDEX "idx_audit_log_actor" ON "audit_log" ("actor");
+CREATE INDEX "idx_audit_log_target" ON "audit_log" ("target");
@@ -0,0 +1,15 @@
+-- Creates new character_vitals table, the state of a character at logout
+CREATE TABLE "character_vitals" (
+      "entity_id" INT NOT NULL,
+      "position" TEXT,
+      "health" REAL,
+      "energy" REAL,
+      "buffs" TEXT NOT NULL,
+      PRIMARY KEY("entity_id"),
+      FOREIGN KEY("entity_id") REFERENCES "character"("character_id")
+);
+
+-- Inserts full health and energy without buffs for everyone
+INSERT INTO character_vitals
+SELECT c.character_id, NULL, NULL, NULL, '[]'
+FROM character c
@@ -0,0 +1,336 @@
+//! Persistence of the audit log, a record of the admin and moderation actions
+//! taken on the server
+
+use crate::persistence::{
+    error::PersistenceError, establish_connection, ConnectionMode, DatabaseSettings,
+    VelorenConnection,
+};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use common::uuid::Uuid;
+use rusqlite::{Connection, DropBehavior, ToSql};
+use std::sync::{Arc, RwLock};
+use tracing::{error, trace};
+
+/// The actor recorded for actions taken by the automoderator
+pub const AUTOMOD_ACTOR: &str = "automod";
+
+/// A single admin or moderation action
+#[derive(Clone, Debug)]
+pub struct AuditEntry {
+    pub time: DateTime<Utc>,
+    /// The alias of the player who took the action, or [`AUTOMOD_ACTOR`]
+    pub actor: String,
+    pub actor_uuid: Option<Uuid>,
+    /// The alias of the player the action was taken against, if any
+    pub target: Option<String>,
+    /// The command keyword, or the name of the automated action
+    pub action: String,
+    pub args: Vec<String>,
+    /// The error message if the action failed
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(
+        actor: String,
+        actor_uuid: Option<Uuid>,
+        target: Option<String>,
+        action: String,
+        args: Vec<String>,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            time: Utc::now(),
+            actor,
+            actor_uuid,
+            target,
+            action,
+            args,
+            error,
+        }
+    }
+}
+
+impl core::fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} {}: {}",
+            self.time.format("%Y-%m-%d %H:%M:%S"),
+            self.actor,
+            self.action
+        )?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        if let Some(target) = &self.target {
+            write!(f, " (target: {})", target)?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " [failed: {}]", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// The filters of an audit log query
+#[derive(Clone, Debug)]
+pub struct AuditQuery {
+    /// Only include the actions taken by or against this player alias
+    pub player: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: u32,
+}
+
+impl Default for AuditQuery {
+    fn default() -> Self {
+        Self {
+            player: None,
+            since: None,
+            until: None,
+            limit: 50,
+        }
+    }
+}
+
+impl AuditQuery {
+    /// Query the entries from `since` hours ago, up to `until` hours ago
+    pub fn hours_ago(player: Option<String>, since: f64, until: Option<f64>) -> Self {
+        // Clamped to about a century, as chrono panics on out of range durations
+        let hours_ago =
+            |hours: f64| Utc::now() - Duration::seconds((hours.clamp(0.0, 1e6) * 3600.0) as i64);
+        Self {
+            player,
+            since: Some(hours_ago(since)),
+            until: until.map(hours_ago),
+            ..Default::default()
+        }
+    }
+}
+
+/// Query the audit log, the most recent entries come first
+pub fn query_audit_log(
+    settings: &DatabaseSettings,
+    query: &AuditQuery,
+) -> Result<Vec<AuditEntry>, PersistenceError> {
+    query_entries(
+        &establish_connection(settings, ConnectionMode::ReadOnly),
+        query,
+    )
+}
+
+fn query_entries(
+    connection: &Connection,
+    query: &AuditQuery,
+) -> Result<Vec<AuditEntry>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  timestamp,
+                actor,
+                actor_uuid,
+                target,
+                action,
+                args,
+                error
+        FROM    audit_log
+        WHERE   (?1 IS NULL OR actor = ?1 OR target = ?1)
+        AND     (?2 IS NULL OR timestamp >= ?2)
+        AND     (?3 IS NULL OR timestamp <= ?3)
+        ORDER BY timestamp DESC, audit_log_id DESC
+        LIMIT   ?4",
+    )?;
+
+    let entries = stmt
+        .query_map(
+            &[
+                &query.player as &dyn ToSql,
+                &query.since.map(|t| t.timestamp()),
+                &query.until.map(|t| t.timestamp()),
+                &query.limit,
+            ],
+            |row| {
+                let timestamp: i64 = row.get(0)?;
+                let actor_uuid: Option<String> = row.get(2)?;
+                let args: String = row.get(5)?;
+                Ok(AuditEntry {
+                    time: Utc
+                        .timestamp_opt(timestamp, 0)
+                        .single()
+                        .unwrap_or_else(Utc::now),
+                    actor: row.get(1)?,
+                    actor_uuid: actor_uuid.and_then(|uuid| Uuid::parse_str(&uuid).ok()),
+                    target: row.get(3)?,
+                    action: row.get(4)?,
+                    args: serde_json::from_str(&args).unwrap_or_default(),
+                    error: row.get(6)?,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// A unidirectional messaging resource for writing the audit log in a
+/// background thread.
+pub struct AuditLogger {
+    update_tx: Option<crossbeam_channel::Sender<AuditEntry>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl AuditLogger {
+    pub fn new(settings: Arc<RwLock<DatabaseSettings>>) -> Self {
+        let (update_tx, update_rx) = crossbeam_channel::unbounded::<AuditEntry>();
+
+        let builder = std::thread::Builder::new().name("audit_logger".into());
+        let handle = builder
+            .spawn(move || {
+                let mut conn = establish_connection(
+                    &settings
+                        .read()
+                        .expect("DatabaseSettings RwLock was poisoned"),
+                    ConnectionMode::ReadWrite,
+                );
+                while let Ok(entry) = update_rx.recv() {
+                    conn.update_log_mode(&settings);
+                    // Write all the entries which are already queued in one transaction
+                    let entries = std::iter::once(entry)
+                        .chain(update_rx.try_iter())
+                        .collect::<Vec<_>>();
+                    if let Err(e) = execute_batch_insert(entries, &mut conn) {
+                        error!(?e, "Error during audit log batch insert");
+                    }
+                }
+            })
+            .unwrap();
+
+        Self {
+            update_tx: Some(update_tx),
+            handle: Some(handle),
+        }
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        if let Err(e) = self.update_tx.as_ref().unwrap().send(entry) {
+            error!(?e, "Could not send audit log entry");
+        }
+    }
+}
+
+fn execute_batch_insert(
+    entries: Vec<AuditEntry>,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    transaction.set_drop_behavior(DropBehavior::Rollback);
+    trace!("Transaction started for audit log batch insert");
+    for entry in entries {
+        let mut stmt = transaction.prepare_cached(
+            "
+            INSERT
+            INTO    audit_log (timestamp,
+                               actor,
+                               actor_uuid,
+                               target,
+                               action,
+                               args,
+                               error)
+            VALUES  (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        stmt.execute(&[
+            &entry.time.timestamp() as &dyn ToSql,
+            &entry.actor,
+            &entry.actor_uuid.map(|uuid| uuid.to_string()),
+            &entry.target,
+            &entry.action,
+            &serde_json::to_string(&entry.args)?,
+            &entry.error,
+        ])?;
+    }
+    transaction.commit()?;
+
+    trace!("Commit for audit log batch insert completed");
+    Ok(())
+}
+
+impl Drop for AuditLogger {
+    fn drop(&mut self) {
+        drop(self.update_tx.take());
+        if let Err(e) = self.handle.take().unwrap().join() {
+            error!(?e, "Error from joining audit logger thread");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::in_memory_connection;
+
+    fn actions(entries: Vec<AuditEntry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.action).collect()
+    }
+
+    #[test]
+    fn insert_and_query() {
+        let mut connection = in_memory_connection();
+        let uuid = Uuid::from_u128(1);
+        let mut kick = AuditEntry::new(
+            "alice".to_owned(),
+            Some(uuid),
+            Some("bob".to_owned()),
+            "kick".to_owned(),
+            vec!["bob".to_owned(), "spam".to_owned()],
+            None,
+        );
+        kick.time = Utc::now() - Duration::hours(5);
+        let mute = AuditEntry::new(
+            AUTOMOD_ACTOR.to_owned(),
+            None,
+            Some("carol".to_owned()),
+            "mute".to_owned(),
+            Vec::new(),
+            Some("Player not found".to_owned()),
+        );
+        execute_batch_insert(vec![kick, mute], &mut connection).unwrap();
+
+        // The most recent entries come first
+        let entries = query_entries(&connection, &AuditQuery::default()).unwrap();
+        assert_eq!(actions(entries.clone()), vec!["mute", "kick"]);
+        let kick = &entries[1];
+        assert_eq!(kick.actor, "alice");
+        assert_eq!(kick.actor_uuid, Some(uuid));
+        assert_eq!(kick.target.as_deref(), Some("bob"));
+        assert_eq!(kick.args, vec!["bob".to_owned(), "spam".to_owned()]);
+        assert_eq!(entries[0].error.as_deref(), Some("Player not found"));
+
+        // Players match both as the actor and the target
+        for (player, expected) in [("alice", vec!["kick"]), ("carol", vec!["mute"])] {
+            let query = AuditQuery {
+                player: Some(player.to_owned()),
+                ..Default::default()
+            };
+            assert_eq!(
+                actions(query_entries(&connection, &query).unwrap()),
+                expected
+            );
+        }
+
+        let query = AuditQuery::hours_ago(None, 1.0, None);
+        assert_eq!(actions(query_entries(&connection, &query).unwrap()), vec![
+            "mute"
+        ]);
+        let query = AuditQuery::hours_ago(None, 24.0, Some(1.0));
+        assert_eq!(actions(query_entries(&connection, &query).unwrap()), vec![
+            "kick"
+        ]);
+        let query = AuditQuery {
+            limit: 1,
+            ..Default::default()
+        };
+        assert_eq!(actions(query_entries(&connection, &query).unwrap()), vec![
+            "mute"
+        ]);
+    }
+}
@@ -0,0 +1,249 @@
+//! Online backups of the database, copied with the SQLite backup API while the
+//! server keeps using it
+
+use crate::persistence::{
+    error::PersistenceError, establish_connection, ConnectionMode, DatabaseSettings,
+};
+use chrono::Utc;
+use rusqlite::{backup::Backup, Connection};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use tracing::{error, info, warn};
+
+/// Directory of the backups, relative to the database directory
+const BACKUP_DIR: &str = "backups";
+const BACKUP_PREFIX: &str = "db_";
+const BACKUP_EXTENSION: &str = "sqlite";
+
+/// Copies the database to a new file in the backup directory, then deletes the
+/// oldest backups beyond [`DatabaseSettings::backup_retention`]. Returns the
+/// path of the backup.
+pub fn backup_database(settings: &DatabaseSettings) -> Result<PathBuf, PersistenceError> {
+    let backup_dir = settings.db_dir.join(BACKUP_DIR);
+    fs::create_dir_all(&backup_dir).map_err(|e| {
+        PersistenceError::OtherError(format!(
+            "Failed to create backup directory {}: {}",
+            backup_dir.display(),
+            e
+        ))
+    })?;
+    let path = new_backup_path(&backup_dir);
+
+    let connection = establish_connection(settings, ConnectionMode::ReadOnly);
+    // The whole database is copied in a single step, a stepped backup would be
+    // restarted by every write of the server in between and might never finish
+    let result = Connection::open(&path).and_then(|mut backup_connection| {
+        Backup::new(&connection, &mut backup_connection)?.run_to_completion(
+            -1,
+            Duration::ZERO,
+            None,
+        )
+    });
+    if let Err(e) = result {
+        // Don't leave an incomplete backup behind, which might be restored later
+        let _ = fs::remove_file(&path);
+        return Err(e.into());
+    }
+
+    if settings.backup_retention > 0 {
+        let mut backups = list_backups(&backup_dir);
+        backups.sort();
+        let outdated = backups.len().saturating_sub(settings.backup_retention);
+        for backup in &backups[..outdated] {
+            if let Err(e) = fs::remove_file(backup) {
+                warn!(
+                    ?e,
+                    "Failed to delete old database backup {}",
+                    backup.display()
+                );
+            }
+        }
+    }
+
+    Ok(path)
+}
+
+/// A path for a new backup named after the current time, with a counter
+/// appended if backups were taken in the same millisecond
+fn new_backup_path(backup_dir: &Path) -> PathBuf {
+    let time = Utc::now().format("%Y-%m-%d_%H-%M-%S-%3f").to_string();
+    let mut path = backup_dir.join(format!("{}{}.{}", BACKUP_PREFIX, time, BACKUP_EXTENSION));
+    let mut counter = 1;
+    while path.exists() {
+        path = backup_dir.join(format!(
+            "{}{}_{}.{}",
+            BACKUP_PREFIX, time, counter, BACKUP_EXTENSION
+        ));
+        counter += 1;
+    }
+    path
+}
+
+/// The backups in the directory, their names sort from oldest to newest
+fn list_backups(backup_dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(backup_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |ext| ext == BACKUP_EXTENSION)
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(false, |name| name.starts_with(BACKUP_PREFIX))
+        })
+        .collect()
+}
+
+/// Time until the next scheduled backup, counted from the newest backup so
+/// restarting the server doesn't postpone it
+fn next_backup_in(settings: &DatabaseSettings, interval: Duration) -> Duration {
+    let last_backup = list_backups(&settings.db_dir.join(BACKUP_DIR))
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok()?.modified().ok())
+        .max();
+    last_backup
+        .and_then(|time| SystemTime::now().duration_since(time).ok())
+        .map_or(Duration::ZERO, |elapsed| interval.saturating_sub(elapsed))
+}
+
+/// Takes the scheduled backups, and the ones requested with
+/// [`DatabaseBackups::backup_now`], in a background thread
+pub struct DatabaseBackups {
+    request_tx: Option<crossbeam_channel::Sender<()>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl DatabaseBackups {
+    pub fn new(settings: Arc<RwLock<DatabaseSettings>>) -> Self {
+        let (request_tx, request_rx) = crossbeam_channel::unbounded::<()>();
+
+        let builder = std::thread::Builder::new().name("database_backups".into());
+        let handle = builder
+            .spawn(move || loop {
+                let settings = settings
+                    .read()
+                    .expect("DatabaseSettings RwLock was poisoned")
+                    .clone();
+                // Either a backup was requested or the next scheduled one is due, unless
+                // the server is shutting down
+                let received = match settings.backup_interval {
+                    Some(interval) => request_rx
+                        .recv_timeout(next_backup_in(&settings, interval))
+                        .map_err(|e| e.is_disconnected()),
+                    None => request_rx.recv().map_err(|_| true),
+                };
+                if let Err(true) = received {
+                    break;
+                }
+                // Requests made in the meantime are covered by this backup
+                request_rx.try_iter().for_each(drop);
+
+                match backup_database(&settings) {
+                    Ok(path) => info!("Backed up the database to {}", path.display()),
+                    Err(e) => error!(?e, "Failed to back up the database"),
+                }
+            })
+            .unwrap();
+
+        Self {
+            request_tx: Some(request_tx),
+            handle: Some(handle),
+        }
+    }
+
+    /// Requests a backup, which is taken once a running backup is done
+    pub fn backup_now(&self) {
+        if let Err(e) = self.request_tx.as_ref().unwrap().send(()) {
+            error!(?e, "Could not request a database backup");
+        }
+    }
+}
+
+impl Drop for DatabaseBackups {
+    fn drop(&mut self) {
+        drop(self.request_tx.take());
+        if let Err(e) = self.handle.take().unwrap().join() {
+            error!(?e, "Error from joining database backup thread");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::{run_migrations, SqlLogMode};
+
+    fn settings(name: &str, backup_retention: usize) -> DatabaseSettings {
+        let db_dir =
+            std::env::temp_dir().join(format!("veloren-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&db_dir);
+        let settings = DatabaseSettings {
+            db_dir,
+            sql_log_mode: SqlLogMode::Disabled,
+            backup_interval: None,
+            backup_retention,
+        };
+        run_migrations(&settings);
+        settings
+    }
+
+    #[test]
+    fn backup() {
+        let settings = settings("copy", 0);
+        let path = backup_database(&settings).unwrap();
+        assert!(path.starts_with(settings.db_dir.join(BACKUP_DIR)));
+
+        // The backup is a complete copy of the migrated database
+        let backup = Connection::open(&path).unwrap();
+        let tables: i64 = backup
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'character'",
+                rusqlite::NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 1);
+
+        // Backups taken right after each other don't overwrite each other
+        let second = backup_database(&settings).unwrap();
+        assert_ne!(second, path);
+        assert_eq!(list_backups(&settings.db_dir.join(BACKUP_DIR)).len(), 2);
+
+        fs::remove_dir_all(&settings.db_dir).unwrap();
+    }
+
+    #[test]
+    fn retention() {
+        let settings = settings("retention", 2);
+        let backups = (0..4)
+            .map(|_| backup_database(&settings).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut kept = list_backups(&settings.db_dir.join(BACKUP_DIR));
+        kept.sort();
+        assert_eq!(kept, &backups[2..]);
+
+        fs::remove_dir_all(&settings.db_dir).unwrap();
+    }
+
+    #[test]
+    fn next_backup() {
+        let settings = settings("schedule", 0);
+        let interval = Duration::from_secs(3600);
+        // Without backups the first one is due right away
+        assert_eq!(next_backup_in(&settings, interval), Duration::ZERO);
+
+        backup_database(&settings).unwrap();
+        let next = next_backup_in(&settings, interval);
+        assert!(next <= interval);
+        assert!(next > interval - Duration::from_secs(60));
+
+        fs::remove_dir_all(&settings.db_dir).unwrap();
+    }
+}
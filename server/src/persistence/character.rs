@@ -16,7 +16,9 @@ use crate::{
             convert_character_from_database, convert_inventory_from_database_items,
             convert_items_to_database_items, convert_loadout_from_database_items,
             convert_skill_groups_to_database, convert_skill_set_from_database,
-            convert_stats_from_database, convert_waypoint_from_database_json,
+            convert_stats_from_database, convert_vault_from_database_items,
+            convert_vault_to_database_items, convert_vitals_from_database,
+            convert_vitals_to_database, convert_waypoint_from_database_json,
             convert_waypoint_to_database_json,
         },
         character_loader::{CharacterCreationResult, CharacterDataResult, CharacterListResult},
@@ -30,7 +32,7 @@ use common::{
     event::UpdateCharacterMetadata,
 };
 use core::ops::Range;
-use rusqlite::{types::Value, Connection, ToSql, Transaction, NO_PARAMS};
+use rusqlite::{types::Value, Connection, OptionalExtension, ToSql, Transaction, NO_PARAMS};
 use std::{num::NonZeroU64, rc::Rc};
 use tracing::{debug, error, trace, warn};
 
@@ -44,6 +46,7 @@ pub(crate) type EntityId = i64;
 const CHARACTER_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.character";
 const INVENTORY_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.inventory";
 const LOADOUT_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.loadout";
+const VAULT_PSEUDO_CONTAINER_DEF_ID: &str = "veloren.core.pseudo_containers.vault";
 const INVENTORY_PSEUDO_CONTAINER_POSITION: &str = "inventory";
 const LOADOUT_PSEUDO_CONTAINER_POSITION: &str = "loadout";
 const WORLD_PSEUDO_CONTAINER_ID: EntityId = 1;
@@ -120,6 +123,14 @@ pub fn load_character_data(
     let character_containers = get_pseudo_containers(connection, char_id)?;
     let inventory_items = load_items(connection, character_containers.inventory_container_id)?;
     let loadout_items = load_items(connection, character_containers.loadout_container_id)?;
+    // The vault is only stored once the player has used it
+    let vault = match get_vault_container_id(connection, &requesting_player_uuid)? {
+        Some(vault_container_id) => convert_vault_from_database_items(
+            vault_container_id,
+            &load_items(connection, vault_container_id)?,
+        )?,
+        None => comp::Vault::with_empty(),
+    };
 
     let mut stmt = connection.prepare_cached(
         "
@@ -257,6 +268,26 @@ pub fn load_character_data(
         })
     })?;
 
+    let mut stmt = connection.prepare_cached(
+        "
+            SELECT  position,
+                    health,
+                    energy,
+                    buffs
+            FROM    character_vitals
+            WHERE   entity_id = ?1",
+    )?;
+
+    let vitals_data = stmt.query_row(&[char_id], |row| {
+        Ok(CharacterVitals {
+            entity_id: char_id,
+            position: row.get(0)?,
+            health: row.get(1)?,
+            energy: row.get(2)?,
+            buffs: row.get(3)?,
+        })
+    })?;
+
     let (skill_set, skill_set_persistence_load_error) =
         convert_skill_set_from_database(&skill_group_data);
     Ok((
@@ -274,6 +305,8 @@ pub fn load_character_data(
             pets,
             active_abilities: convert_active_abilities_from_database(&ability_set_data),
             map_marker: char_map_marker,
+            vitals: convert_vitals_from_database(&vitals_data),
+            vault,
         },
         UpdateCharacterMetadata {
             skill_set_persistence_load_error,
@@ -281,6 +314,21 @@ pub fn load_character_data(
     ))
 }
 
+/// Loads the UUID of the player the character belongs to
+pub fn load_character_owner(
+    char_id: CharacterId,
+    connection: &Connection,
+) -> Result<String, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  player_uuid
+        FROM    character
+        WHERE   character_id = ?1",
+    )?;
+
+    Ok(stmt.query_row(&[char_id], |row| row.get(0))?)
+}
+
 /// Loads a list of characters belonging to the player. This data is a small
 /// subset of the character's data, and is used to render the character and
 /// their level in the character list.
@@ -369,9 +417,12 @@ pub fn create_character(
         skill_set,
         inventory,
         waypoint,
-        pets: _,
+        pets,
         active_abilities,
         map_marker,
+        vitals,
+        // The vault belongs to the player rather than the character
+        vault: _,
     } = persisted_components;
 
     // Fetch new entity IDs for character, inventory and loadout
@@ -500,6 +551,27 @@ pub fn create_character(
     ])?;
     drop(stmt);
 
+    let vitals = convert_vitals_to_database(character_id, &vitals);
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        INSERT INTO character_vitals (entity_id,
+                                      position,
+                                      health,
+                                      energy,
+                                      buffs)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+
+    stmt.execute(&[
+        &character_id as &dyn ToSql,
+        &vitals.position,
+        &vitals.health,
+        &vitals.energy,
+        &vitals.buffs,
+    ])?;
+    drop(stmt);
+
     // Insert default inventory and loadout item records
     let mut inserts = Vec::new();
 
@@ -535,6 +607,9 @@ pub fn create_character(
     }
     drop(stmt);
 
+    // New characters have no pets, but imported ones can
+    update_pets(character_id, pets, transaction)?;
+
     load_character_list(uuid, transaction).map(|list| (character_id, list))
 }
 
@@ -644,6 +719,17 @@ pub fn delete_character(
     stmt.execute(&[&char_id])?;
     drop(stmt);
 
+    // Delete vitals
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    character_vitals
+        WHERE   entity_id = ?1",
+    )?;
+
+    stmt.execute(&[&char_id])?;
+    drop(stmt);
+
     // Delete character
     let mut stmt = transaction.prepare_cached(
         "
@@ -828,6 +914,126 @@ fn get_pseudo_container_id(
     }
 }
 
+/// Fetches the ID of the vault pseudo-container of a player, which is stored
+/// in the world pseudo-container at the position of the player's UUID. Returns
+/// `None` if the vault hasn't been stored yet.
+fn get_vault_container_id(
+    connection: &Connection,
+    player_uuid: &str,
+) -> Result<Option<EntityId>, PersistenceError> {
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  item_id
+        FROM    item
+        WHERE   parent_container_item_id = ?1
+        AND     position = ?2
+        AND     item_definition_id = ?3",
+    )?;
+
+    Ok(stmt
+        .query_row(
+            &[
+                &WORLD_PSEUDO_CONTAINER_ID as &dyn ToSql,
+                &player_uuid,
+                &VAULT_PSEUDO_CONTAINER_DEF_ID,
+            ],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Stores the items of the vault of the player the character belongs to,
+/// creating the vault pseudo-container the first time. This must happen in the
+/// same transaction as the inventory update, since items move between them.
+fn update_vault(
+    char_id: CharacterId,
+    vault: &comp::Vault,
+    transaction: &mut Transaction,
+) -> Result<(), PersistenceError> {
+    let player_uuid = load_character_owner(char_id, transaction)?;
+    let vault_container_id = match get_vault_container_id(transaction, &player_uuid)? {
+        Some(vault_container_id) => vault_container_id,
+        None => {
+            let vault_container_id = get_new_entity_ids(transaction, |next_id| next_id + 1)?
+                .next()
+                .unwrap();
+            let mut stmt = transaction.prepare_cached(
+                "
+                INSERT INTO item (item_id,
+                                  parent_container_item_id,
+                                  item_definition_id,
+                                  stack_size,
+                                  position)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            stmt.execute(&[
+                &vault_container_id as &dyn ToSql,
+                &WORLD_PSEUDO_CONTAINER_ID,
+                &VAULT_PSEUDO_CONTAINER_DEF_ID,
+                &1,
+                &player_uuid,
+            ])?;
+            vault_container_id
+        },
+    };
+
+    let mut upserts = Vec::new();
+    get_new_entity_ids(transaction, |mut next_id| {
+        upserts = convert_vault_to_database_items(vault_container_id, vault, &mut next_id);
+        next_id
+    })?;
+
+    // Items which were taken out of the vault are either gone or were already
+    // upserted into the inventory, so they are no longer children of the vault
+    let mut existing_item_ids = vec![Value::from(vault_container_id)];
+    for it in load_items(transaction, vault_container_id)? {
+        existing_item_ids.push(Value::from(it.item_id));
+    }
+    let non_upserted_items = upserts
+        .iter()
+        .map(|item_pair| Value::from(item_pair.model.item_id))
+        .collect::<Vec<Value>>();
+
+    let mut stmt = transaction.prepare_cached(
+        "
+        DELETE
+        FROM    item
+        WHERE   parent_container_item_id
+        IN      rarray(?1)
+        AND     item_id NOT IN rarray(?2)",
+    )?;
+    let delete_count = stmt.execute(&[Rc::new(existing_item_ids), Rc::new(non_upserted_items)])?;
+    trace!("Deleted {} vault items", delete_count);
+
+    if !upserts.is_empty() {
+        // See the inventory upserts in `update` for why this is needed
+        transaction.pragma_update(None, "defer_foreign_keys", &"ON".to_string())?;
+
+        let mut stmt = transaction.prepare_cached(
+            "
+            REPLACE
+            INTO    item (item_id,
+                          parent_container_item_id,
+                          item_definition_id,
+                          stack_size,
+                          position)
+            VALUES  (?1, ?2, ?3, ?4, ?5)",
+        )?;
+
+        for item in upserts.iter().map(|model_pair| &model_pair.model) {
+            stmt.execute(&[
+                &item.item_id as &dyn ToSql,
+                &item.parent_container_item_id,
+                &item.item_definition_id,
+                &item.stack_size,
+                &item.position,
+            ])?;
+        }
+    }
+
+    Ok(())
+}
+
 /// Stores new pets in the database, and removes pets from the database that the
 /// player no longer has. Currently there are no actual updates to pet data
 /// since we don't store any updatable data about pets in the database.
@@ -961,6 +1167,8 @@ pub fn update(
     char_waypoint: Option<comp::Waypoint>,
     active_abilities: comp::ability::ActiveAbilities,
     map_marker: Option<comp::MapMarker>,
+    vitals: common::character::CharacterVitals,
+    vault: Option<comp::Vault>,
     transaction: &mut Transaction,
 ) -> Result<(), PersistenceError> {
     // Run pet persistence
@@ -1060,6 +1268,10 @@ pub fn update(
         }
     }
 
+    if let Some(vault) = vault {
+        update_vault(char_id, &vault, transaction)?;
+    }
+
     let db_skill_groups = convert_skill_groups_to_database(char_id, char_skill_set.skill_groups());
 
     let mut stmt = transaction.prepare_cached(
@@ -1126,5 +1338,35 @@ pub fn update(
         )));
     }
 
+    let vitals = convert_vitals_to_database(char_id, &vitals);
+
+    // Without a safe position now, the position is cleared so the character is
+    // loaded at its waypoint instead of where it was at an earlier update
+    let mut stmt = transaction.prepare_cached(
+        "
+        UPDATE  character_vitals
+        SET     position = ?1,
+                health = ?2,
+                energy = ?3,
+                buffs = ?4
+        WHERE   entity_id = ?5
+    ",
+    )?;
+
+    let vitals_count = stmt.execute(&[
+        &vitals.position as &dyn ToSql,
+        &vitals.health,
+        &vitals.energy,
+        &vitals.buffs,
+        &char_id,
+    ])?;
+
+    if vitals_count != 1 {
+        return Err(PersistenceError::OtherError(format!(
+            "Error updating character_vitals table for char_id {}",
+            char_id,
+        )));
+    }
+
     Ok(())
 }
@@ -1,14 +1,16 @@
 use crate::persistence::{
     character::EntityId,
-    models::{AbilitySets, Character, Item, SkillGroup},
+    models::{AbilitySets, Character, CharacterVitals, Item, SkillGroup},
 };
 
 use crate::persistence::{
     error::PersistenceError,
-    json_models::{self, CharacterPosition, DatabaseAbilitySet, GenericBody, HumanoidBody},
+    json_models::{
+        self, CharacterPosition, DatabaseAbilitySet, DatabaseBuff, GenericBody, HumanoidBody,
+    },
 };
 use common::{
-    character::CharacterId,
+    character::{CharacterId, CharacterVitals as CompVitals},
     comp::{
         inventory::{
             item::{tool::AbilityMap, Item as VelorenItem, MaterialStatManifest},
@@ -68,13 +70,42 @@ pub fn convert_items_to_database_items(
         )
     });
 
+    convert_slots_to_database_items(
+        inventory.chain(loadout),
+        &[inventory_container_id, loadout_container_id],
+        next_id,
+    )
+}
+
+/// Returns all item rows to upsert for the items of the vault, the same way as
+/// [`convert_items_to_database_items`] does for the inventory
+pub fn convert_vault_to_database_items(
+    vault_container_id: EntityId,
+    vault: &Vault,
+    next_id: &mut i64,
+) -> Vec<ItemModelPair> {
+    let slots = vault
+        .slots_with_id()
+        .map(|(pos, item)| (pos.to_string(), item.as_ref(), vault_container_id));
+
+    convert_slots_to_database_items(slots, &[vault_container_id], next_id)
+}
+
+/// Converts the items in the slots, given as their position, item and parent
+/// container, and the components of those items to item rows
+fn convert_slots_to_database_items<'a>(
+    slots: impl Iterator<Item = (String, Option<&'a VelorenItem>, EntityId)>,
+    container_ids: &[EntityId],
+    next_id: &mut i64,
+) -> Vec<ItemModelPair> {
     // Use Breadth-first search to recurse into containers/modular weapons to store
     // their parts
-    let mut bfs_queue: VecDeque<_> = inventory.chain(loadout).collect();
+    let mut bfs_queue: VecDeque<_> = slots.collect();
     let mut upserts = Vec::new();
     let mut depth = HashMap::new();
-    depth.insert(inventory_container_id, 0);
-    depth.insert(loadout_container_id, 0);
+    for container_id in container_ids {
+        depth.insert(*container_id, 0);
+    }
     while let Some((position, item, parent_container_item_id)) = bfs_queue.pop_front() {
         // Construct new items.
         if let Some(item) = item {
@@ -445,6 +476,82 @@ pub fn convert_inventory_from_database_items(
     Ok(inventory)
 }
 
+/// Loads the vault from its items, which are sorted the same way as for
+/// [`convert_inventory_from_database_items`]
+pub fn convert_vault_from_database_items(
+    vault_container_id: i64,
+    vault_items: &[Item],
+) -> Result<Vault, PersistenceError> {
+    let mut vault = Vault::with_empty();
+    let mut item_indices = HashMap::new();
+
+    for (i, db_item) in vault_items.iter().enumerate() {
+        item_indices.insert(db_item.item_id, i);
+
+        let mut item = get_item_from_asset(db_item.item_definition_id.as_str())?;
+
+        // NOTE: Since this is freshly loaded, the atomic is *unique.*
+        let comp = item.get_item_id_for_database();
+        comp.store(Some(NonZeroU64::try_from(db_item.item_id as u64).map_err(
+            |_| PersistenceError::ConversionError("Item with zero item_id".to_owned()),
+        )?));
+
+        if db_item.stack_size == 1 || item.is_stackable() {
+            item.set_amount(u32::try_from(db_item.stack_size).map_err(|_| {
+                PersistenceError::ConversionError(format!(
+                    "Invalid item stack size for stackable={}: {}",
+                    item.is_stackable(),
+                    &db_item.stack_size
+                ))
+            })?)
+            .map_err(|_| {
+                PersistenceError::ConversionError("Error setting amount for item".to_owned())
+            })?;
+        }
+
+        let slot = |s: &str| {
+            s.parse::<usize>().map_err(|_| {
+                PersistenceError::ConversionError(format!(
+                    "Failed to parse vault item position: {:?}",
+                    &db_item.position
+                ))
+            })
+        };
+
+        if db_item.parent_container_item_id == vault_container_id {
+            let slot = slot(&db_item.position)?;
+            match vault.insert_at(slot, item) {
+                Ok(None) => {},
+                Ok(Some(_)) => {
+                    return Err(PersistenceError::ConversionError(
+                        "Inserted an item into the same vault slot twice".to_string(),
+                    ));
+                },
+                Err(_) => {
+                    return Err(PersistenceError::ConversionError(format!(
+                        "Error inserting item into vault, position: {}",
+                        slot
+                    )));
+                },
+            }
+        } else if let Some(&j) = item_indices.get(&db_item.parent_container_item_id) {
+            get_mutable_item(j, vault_items, &item_indices, &mut vault, &|vault, s| {
+                vault.get_mut(slot(s).ok()?)
+            })?
+            .persistence_access_add_component(item);
+        } else {
+            return Err(PersistenceError::ConversionError(format!(
+                "Couldn't find parent item {} before item {} in vault",
+                db_item.parent_container_item_id, db_item.item_id
+            )));
+        }
+    }
+
+    vault.persistence_update_all_item_states(&ABILITY_MAP, &MATERIAL_STATS_MANIFEST);
+
+    Ok(vault)
+}
+
 pub fn convert_loadout_from_database_items(
     loadout_container_id: i64,
     database_items: &[Item],
@@ -727,3 +834,113 @@ pub fn convert_active_abilities_from_database(ability_sets: &AbilitySets) -> Act
         });
     json_models::active_abilities_from_db_model(ability_sets)
 }
+
+pub fn convert_vitals_to_database(entity_id: CharacterId, vitals: &CompVitals) -> CharacterVitals {
+    let buffs = json_models::buffs_to_db_model(&vitals.buffs);
+    CharacterVitals {
+        entity_id,
+        position: vitals
+            .position
+            .and_then(|pos| serde_json::to_string(&pos.0).ok()),
+        health: vitals.health.map(f64::from),
+        energy: vitals.energy.map(f64::from),
+        buffs: serde_json::to_string(&buffs).unwrap_or_default(),
+    }
+}
+
+pub fn convert_vitals_from_database(vitals: &CharacterVitals) -> CompVitals {
+    let position = vitals.position.as_ref().and_then(|position| {
+        serde_json::from_str(position)
+            .map_err(|err| warn!("Error de-serializing position: {} err: {}", position, err))
+            .ok()
+    });
+    let buffs = serde_json::from_str::<Vec<DatabaseBuff>>(&vitals.buffs).unwrap_or_else(|err| {
+        common_base::dev_panic!(format!(
+            "Failed to parse buffs. Error: {:#?}\nBuffs:\n{:#?}",
+            err, vitals.buffs
+        ));
+        Vec::new()
+    });
+    CompVitals {
+        position: position.map(Pos),
+        health: vitals.health.map(|health| health as f32),
+        energy: vitals.energy.map(|energy| energy as f32),
+        buffs: json_models::buffs_from_db_model(buffs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VAULT_CONTAINER_ID: EntityId = 5;
+
+    fn vault_items(vault: &Vault) -> Vec<Item> {
+        let mut next_id = 100;
+        convert_vault_to_database_items(VAULT_CONTAINER_ID, vault, &mut next_id)
+            .into_iter()
+            .map(|pair| pair.model)
+            .collect()
+    }
+
+    #[test]
+    fn vault_round_trip() {
+        let mut coins = VelorenItem::new_from_asset_expect("common.items.utility.coins");
+        coins.set_amount(42).unwrap();
+        let mut vault = Vault::with_empty();
+        vault.insert_at(3, coins).unwrap();
+        vault
+            .insert_at(
+                7,
+                VelorenItem::new_from_asset_expect("common.items.debug.admin_stick"),
+            )
+            .unwrap();
+
+        let items = vault_items(&vault);
+        assert_eq!(items.len(), 2);
+        assert!(items
+            .iter()
+            .all(|item| item.parent_container_item_id == VAULT_CONTAINER_ID));
+
+        let loaded = convert_vault_from_database_items(VAULT_CONTAINER_ID, &items).unwrap();
+        assert_eq!(loaded.capacity(), vault.capacity());
+        for ((_, slot), (_, loaded_slot)) in vault.slots_with_id().zip(loaded.slots_with_id()) {
+            assert_eq!(slot, loaded_slot);
+            assert_eq!(
+                slot.as_ref().map(VelorenItem::amount),
+                loaded_slot.as_ref().map(VelorenItem::amount)
+            );
+        }
+        // The ids of the saved items are kept when loading them
+        assert_eq!(
+            loaded.get(3).unwrap().get_item_id_for_database().load(),
+            vault.get(3).unwrap().get_item_id_for_database().load()
+        );
+    }
+
+    #[test]
+    fn invalid_vault_items() {
+        let mut vault = Vault::with_empty();
+        vault
+            .insert_at(
+                0,
+                VelorenItem::new_from_asset_expect("common.items.debug.admin_stick"),
+            )
+            .unwrap();
+        let mut twice = vault_items(&vault);
+        let duplicate = vault_items(&vault).remove(0);
+        twice.push(Item {
+            item_id: duplicate.item_id + 1,
+            ..duplicate
+        });
+        assert!(convert_vault_from_database_items(VAULT_CONTAINER_ID, &twice).is_err());
+
+        let mut outside = vault_items(&vault);
+        outside[0].position = Vault::with_empty().capacity().to_string();
+        assert!(convert_vault_from_database_items(VAULT_CONTAINER_ID, &outside).is_err());
+
+        let mut orphan = vault_items(&vault);
+        orphan[0].parent_container_item_id = VAULT_CONTAINER_ID + 1;
+        assert!(convert_vault_from_database_items(VAULT_CONTAINER_ID, &orphan).is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! Export of characters to files which can be imported into the database of
+//! another server, e.g. to merge servers or to restore a single player from a
+//! backup
+
+use crate::persistence::{
+    character, error::PersistenceError, establish_connection, ConnectionMode, DatabaseSettings,
+    PersistedComponents,
+};
+use censor::Censor;
+use common::{
+    assets::AssetExt,
+    character::CharacterId,
+    comp::{
+        self,
+        inventory::{
+            item::{tool::AbilityMap, ItemDefinitionIdOwned, MaterialStatManifest},
+            loadout_builder::LoadoutBuilder,
+            slot::InvSlotId,
+        },
+        Inventory, Item,
+    },
+};
+use rusqlite::DropBehavior;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+use tracing::info;
+
+/// Incremented whenever the format of [`CharacterExport`] changes
+pub const CHARACTER_EXPORT_VERSION: u32 = 1;
+
+/// A character as written to an export file. Waypoints, map markers and
+/// positions are left out since they belong to the world of the server the
+/// character was exported from.
+#[derive(Serialize, Deserialize)]
+pub struct CharacterExport {
+    pub version: u32,
+    pub alias: String,
+    pub body: comp::Body,
+    pub skill_set: comp::SkillSet,
+    pub active_abilities: comp::ActiveAbilities,
+    /// Equipped items by the persistence key of their loadout slot
+    pub loadout: Vec<ExportedItem<String>>,
+    /// Items in the inventory, including the slots of equipped bags
+    pub inventory: Vec<ExportedItem<InvSlotId>>,
+    pub pets: Vec<ExportedPet>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedItem<S> {
+    pub slot: S,
+    pub item: ItemDefinitionIdOwned,
+    pub amount: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportedPet {
+    pub name: String,
+    pub body: comp::Body,
+}
+
+impl<S: core::fmt::Debug> ExportedItem<S> {
+    fn new(slot: S, item: &Item) -> Self {
+        Self {
+            slot,
+            item: item.item_definition_id().to_owned(),
+            amount: item.amount(),
+        }
+    }
+
+    /// Creates the item, failing if it doesn't exist in the local assets
+    fn to_item(
+        &self,
+        ability_map: &AbilityMap,
+        msm: &MaterialStatManifest,
+    ) -> Result<Item, PersistenceError> {
+        let mut item = Item::new_from_item_definition_id(self.item.as_ref(), ability_map, msm)
+            .map_err(|err| {
+                PersistenceError::AssetError(format!(
+                    "Error loading item asset: {:?} - {}",
+                    self.item, err
+                ))
+            })?;
+        if item.is_stackable() {
+            item.set_amount(self.amount).map_err(|_| {
+                PersistenceError::ConversionError(format!(
+                    "Invalid stack size {} of item in slot {:?}",
+                    self.amount, self.slot
+                ))
+            })?;
+        }
+        Ok(item)
+    }
+}
+
+impl CharacterExport {
+    fn from_components(components: &PersistedComponents) -> Self {
+        Self {
+            version: CHARACTER_EXPORT_VERSION,
+            alias: components.stats.name.clone(),
+            body: components.body,
+            skill_set: components.skill_set.clone(),
+            active_abilities: components.active_abilities.clone(),
+            loadout: components
+                .inventory
+                .loadout_items_with_persistence_key()
+                .filter_map(|(slot, item)| Some(ExportedItem::new(slot.to_owned(), item?)))
+                .collect(),
+            inventory: components
+                .inventory
+                .slots_with_id()
+                .filter_map(|(slot, item)| Some(ExportedItem::new(slot, item.as_ref()?)))
+                .collect(),
+            pets: components
+                .pets
+                .iter()
+                .map(|(_, body, stats)| ExportedPet {
+                    name: stats.name.clone(),
+                    body: *body,
+                })
+                .collect(),
+        }
+    }
+
+    fn into_components(self, alias: String) -> Result<PersistedComponents, PersistenceError> {
+        // The inventory is only made for the loadout of humanoids
+        if !matches!(self.body, comp::Body::Humanoid(_)) {
+            return Err(PersistenceError::ConversionError(format!(
+                "Only humanoid characters can be imported, not {:?}",
+                self.body
+            )));
+        }
+
+        let ability_map = AbilityMap::load().cloned();
+        let msm = MaterialStatManifest::load().cloned();
+
+        let mut loadout = LoadoutBuilder::empty().build();
+        for exported in &self.loadout {
+            let item = exported.to_item(&ability_map, &msm)?;
+            loadout
+                .set_item_at_slot_using_persistence_key(&exported.slot, item)
+                .map_err(|_| {
+                    PersistenceError::ConversionError(format!(
+                        "Invalid persistence key: {}",
+                        exported.slot
+                    ))
+                })?;
+        }
+        // Loadout items are set first since equipped bags provide inventory slots
+        let mut inventory = Inventory::with_loadout_humanoid(loadout);
+        for exported in &self.inventory {
+            let item = exported.to_item(&ability_map, &msm)?;
+            // Fails if the slot doesn't exist or two items were exported for it
+            if !matches!(inventory.insert_at(exported.slot, item), Ok(None)) {
+                return Err(PersistenceError::ConversionError(format!(
+                    "Error inserting item into inventory, position: {:?}",
+                    exported.slot
+                )));
+            }
+        }
+
+        Ok(PersistedComponents {
+            body: self.body,
+            stats: comp::Stats::new(alias),
+            skill_set: self.skill_set,
+            inventory,
+            waypoint: None,
+            pets: self
+                .pets
+                .into_iter()
+                .map(|pet| (comp::Pet::default(), pet.body, comp::Stats::new(pet.name)))
+                .collect(),
+            active_abilities: self.active_abilities,
+            map_marker: None,
+            vitals: Default::default(),
+            vault: comp::Vault::with_empty(),
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, PersistenceError> {
+        let file = fs::File::open(path).map_err(|err| {
+            PersistenceError::OtherError(format!("Failed to open {}: {}", path.display(), err))
+        })?;
+        let export: Self = ron::de::from_reader(file).map_err(|err| {
+            PersistenceError::ConversionError(format!("Failed to read {}: {}", path.display(), err))
+        })?;
+        if export.version != CHARACTER_EXPORT_VERSION {
+            return Err(PersistenceError::ConversionError(format!(
+                "Unsupported character export version {}, expected {}",
+                export.version, CHARACTER_EXPORT_VERSION
+            )));
+        }
+        Ok(export)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), PersistenceError> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|err| PersistenceError::ConversionError(err.to_string()))?;
+        fs::write(path, ron).map_err(|err| {
+            PersistenceError::OtherError(format!("Failed to write {}: {}", path.display(), err))
+        })
+    }
+}
+
+/// Loads a character from the database to export it
+pub fn export_character(
+    settings: &DatabaseSettings,
+    character_id: CharacterId,
+) -> Result<CharacterExport, PersistenceError> {
+    let connection = establish_connection(settings, ConnectionMode::ReadOnly);
+    let player_uuid = character::load_character_owner(character_id, &connection)?;
+    let (components, _) = character::load_character_data(player_uuid, character_id, &connection)?;
+
+    Ok(CharacterExport::from_components(&components))
+}
+
+/// Imported characters are checked like the aliases set by commands, since
+/// the export files may come from anywhere
+fn validate_alias(alias: &str, censor: &Censor) -> Result<(), PersistenceError> {
+    comp::Player::alias_validate(alias).map_err(|err| {
+        PersistenceError::ConversionError(format!("Invalid alias '{}': {}", alias, err))
+    })?;
+    if censor.check(alias) {
+        return Err(PersistenceError::ConversionError(format!(
+            "Alias '{}' contains a banned word",
+            alias
+        )));
+    }
+    Ok(())
+}
+
+/// Adds an exported character to the characters of the player, as a new
+/// character. Returns the ID of the new character.
+pub fn import_character(
+    settings: &DatabaseSettings,
+    player_uuid: &str,
+    alias: Option<String>,
+    export: CharacterExport,
+    censor: &Censor,
+) -> Result<CharacterId, PersistenceError> {
+    let alias = alias.unwrap_or_else(|| export.alias.clone());
+    validate_alias(&alias, censor)?;
+    let components = export.into_components(alias.clone())?;
+
+    let mut connection = establish_connection(settings, ConnectionMode::ReadWrite);
+    let mut transaction = connection.connection.transaction()?;
+    transaction.set_drop_behavior(DropBehavior::Rollback);
+    let (character_id, _) =
+        character::create_character(player_uuid, &alias, components, &mut transaction)?;
+    transaction.commit()?;
+
+    info!(?character_id, "Imported character {}", alias);
+    Ok(character_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::comp::{humanoid, ActiveAbilities, SkillSet, Stats, Vault};
+
+    fn components() -> PersistedComponents {
+        let loadout = LoadoutBuilder::empty()
+            .defaults()
+            .active_mainhand(Some(Item::new_from_asset_expect(
+                "common.items.weapons.sword.starter",
+            )))
+            .build();
+        let mut inventory = Inventory::with_loadout_humanoid(loadout);
+        let mut potions = Item::new_from_asset_expect("common.items.consumable.potion_minor");
+        potions.set_amount(3).unwrap();
+        inventory.push(potions).unwrap();
+        inventory
+            .push(Item::new_from_asset_expect("common.items.food.cheese"))
+            .unwrap();
+        let body = comp::Body::Humanoid(humanoid::Body::random());
+
+        PersistedComponents {
+            body,
+            stats: Stats::new("Exported".to_owned()),
+            skill_set: SkillSet::default(),
+            inventory,
+            waypoint: None,
+            pets: vec![(comp::Pet::default(), body, Stats::new("Pet".to_owned()))],
+            active_abilities: ActiveAbilities::default(),
+            map_marker: None,
+            vitals: Default::default(),
+            vault: Vault::with_empty(),
+        }
+    }
+
+    fn to_ron(export: &CharacterExport) -> String {
+        ron::ser::to_string(export).expect("Failed to serialize the export")
+    }
+
+    #[test]
+    fn export_and_import() {
+        let dir =
+            std::env::temp_dir().join(format!("veloren-character-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("character.ron");
+
+        let export = CharacterExport::from_components(&components());
+        assert_eq!(export.alias, "Exported");
+        assert!(!export.loadout.is_empty());
+        assert_eq!(export.inventory.len(), 2);
+        export.write(&path).unwrap();
+
+        let read = CharacterExport::read(&path).unwrap();
+        assert_eq!(to_ron(&read), to_ron(&export));
+        let imported = read.into_components("Imported".to_owned()).unwrap();
+        assert_eq!(imported.stats.name, "Imported");
+        assert_eq!(imported.pets.len(), 1);
+        assert_eq!(imported.pets[0].2.name, "Pet");
+        // Exporting the imported character again gives the same export
+        let mut reexport = CharacterExport::from_components(&imported);
+        reexport.alias = export.alias.clone();
+        assert_eq!(to_ron(&reexport), to_ron(&export));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_imports() {
+        let mut export = CharacterExport::from_components(&components());
+        export.version += 1;
+        let dir = std::env::temp_dir().join(format!(
+            "veloren-character-export-invalid-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("character.ron");
+        export.write(&path).unwrap();
+        assert!(CharacterExport::read(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut export = CharacterExport::from_components(&components());
+        export.body = comp::Body::Object(comp::object::Body::Crate);
+        assert!(export.into_components("Imported".to_owned()).is_err());
+
+        let mut export = CharacterExport::from_components(&components());
+        // Two items in the same slot
+        let duplicate = ExportedItem {
+            slot: export.inventory[0].slot,
+            item: export.inventory[0].item.clone(),
+            amount: 1,
+        };
+        export.inventory.push(duplicate);
+        assert!(export.into_components("Imported".to_owned()).is_err());
+
+        let censor = Censor::Custom(["banned".to_owned()].into_iter().collect());
+        assert!(validate_alias("Imported", &censor).is_ok());
+        assert!(validate_alias("Imported Character", &censor).is_err());
+        assert!(validate_alias("banned", &censor).is_err());
+    }
+}
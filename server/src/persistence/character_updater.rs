@@ -1,5 +1,5 @@
 use crate::comp;
-use common::character::CharacterId;
+use common::character::{CharacterId, CharacterVitals};
 
 use crate::persistence::{
     character_loader::{CharacterLoaderResponse, CharacterLoaderResponseKind},
@@ -26,6 +26,8 @@ pub type CharacterUpdateData = (
     Option<comp::Waypoint>,
     comp::ability::ActiveAbilities,
     Option<comp::MapMarker>,
+    CharacterVitals,
+    Option<comp::Vault>,
 );
 
 pub type PetPersistenceData = (comp::Pet, comp::Body, comp::Stats);
@@ -63,7 +65,9 @@ pub struct CharacterUpdater {
     update_tx: Option<crossbeam_channel::Sender<CharacterUpdaterEvent>>,
     response_rx: crossbeam_channel::Receiver<CharacterLoaderResponse>,
     handle: Option<std::thread::JoinHandle<()>>,
-    pending_logout_updates: HashMap<CharacterId, CharacterUpdateData>,
+    /// Updates of characters which logged out, along with the UUID of their
+    /// player
+    pending_logout_updates: HashMap<CharacterId, (String, CharacterUpdateData)>,
     /// Will disconnect all characters (without persistence) on the next tick if
     /// set to true
     disconnect_all_clients_requested: Arc<AtomicBool>,
@@ -220,6 +224,7 @@ impl CharacterUpdater {
     pub fn add_pending_logout_update(
         &mut self,
         character_id: CharacterId,
+        player_uuid: String,
         update_data: CharacterUpdateData,
     ) {
         if !self
@@ -227,7 +232,7 @@ impl CharacterUpdater {
             .load(Ordering::Relaxed)
         {
             self.pending_logout_updates
-                .insert(character_id, update_data);
+                .insert(character_id, (player_uuid, update_data));
         } else {
             warn!(
                 "Ignoring request to add pending logout update for character ID {} as there is a \
@@ -237,10 +242,14 @@ impl CharacterUpdater {
         }
     }
 
-    /// Returns the character IDs of characters that have recently logged out
-    /// and are awaiting persistence in the next batch update.
-    pub fn characters_pending_logout(&self) -> impl Iterator<Item = CharacterId> + '_ {
-        self.pending_logout_updates.keys().copied()
+    /// Returns the UUIDs of players with a character that has recently logged
+    /// out and is awaiting persistence in the next batch update. Since the
+    /// vault is shared between the characters of a player, none of them may be
+    /// loaded until then.
+    pub fn players_pending_logout(&self) -> impl Iterator<Item = &str> + '_ {
+        self.pending_logout_updates
+            .values()
+            .map(|(player_uuid, _)| player_uuid.as_str())
     }
 
     /// Returns a value indicating whether there is a pending request to
@@ -334,6 +343,8 @@ impl CharacterUpdater {
                 Option<&'a comp::Waypoint>,
                 &'a comp::ability::ActiveAbilities,
                 Option<&'a comp::MapMarker>,
+                CharacterVitals,
+                Option<&'a comp::Vault>,
             ),
         >,
     ) {
@@ -347,6 +358,8 @@ impl CharacterUpdater {
                     waypoint,
                     active_abilities,
                     map_marker,
+                    vitals,
+                    vault,
                 )| {
                     (
                         character_id,
@@ -357,11 +370,17 @@ impl CharacterUpdater {
                             waypoint.cloned(),
                             active_abilities.clone(),
                             map_marker.cloned(),
+                            vitals,
+                            vault.cloned(),
                         ),
                     )
                 },
             )
-            .chain(self.pending_logout_updates.drain())
+            .chain(
+                self.pending_logout_updates
+                    .drain()
+                    .map(|(character_id, (_, update_data))| (character_id, update_data)),
+            )
             .collect::<Vec<_>>();
 
         if let Err(e) = self
@@ -399,7 +418,10 @@ fn execute_batch_update(
     transaction.set_drop_behavior(DropBehavior::Rollback);
     trace!("Transaction started for character batch update");
     updates.into_iter().try_for_each(
-        |(character_id, (stats, inventory, pets, waypoint, active_abilities, map_marker))| {
+        |(
+            character_id,
+            (stats, inventory, pets, waypoint, active_abilities, map_marker, vitals, vault),
+        )| {
             super::character::update(
                 character_id,
                 stats,
@@ -408,6 +430,8 @@ fn execute_batch_update(
                 waypoint,
                 active_abilities,
                 map_marker,
+                vitals,
+                vault,
                 &mut transaction,
             )
         },
@@ -2,7 +2,7 @@ use common::comp;
 use common_base::dev_panic;
 use hashbrown::HashMap;
 use serde::{Deserialize, Serialize};
-use std::string::ToString;
+use std::{string::ToString, time::Duration};
 use vek::{Vec2, Vec3};
 
 #[derive(Serialize, Deserialize)]
@@ -285,3 +285,130 @@ pub fn active_abilities_from_db_model(
         .collect::<HashMap<_, _>>();
     comp::ability::ActiveAbilities::new(ability_sets)
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct DatabaseBuff {
+    kind: comp::BuffKind,
+    strength: f32,
+    remaining_secs: f32,
+    categories: Vec<String>,
+}
+
+fn buff_category_to_string(category: comp::BuffCategory) -> Option<String> {
+    use common::comp::BuffCategory::*;
+    Some(String::from(match category {
+        Natural => "Natural",
+        Physical => "Physical",
+        Magical => "Magical",
+        Divine => "Divine",
+        PersistOnDeath => "PersistOnDeath",
+        // Aura buffs are applied again by the aura
+        FromAura(_) => return None,
+    }))
+}
+
+fn buff_category_from_string(category: &str) -> Option<comp::BuffCategory> {
+    use common::comp::BuffCategory::*;
+    match category {
+        "Natural" => Some(Natural),
+        "Physical" => Some(Physical),
+        "Magical" => Some(Magical),
+        "Divine" => Some(Divine),
+        "PersistOnDeath" => Some(PersistOnDeath),
+        unknown => {
+            dev_panic!(format!(
+                "Conversion from database to buff failed. Unknown buff category: {:#?}",
+                unknown
+            ));
+            None
+        },
+    }
+}
+
+pub fn buffs_to_db_model(buffs: &[comp::Buff]) -> Vec<DatabaseBuff> {
+    buffs
+        .iter()
+        .filter_map(|buff| {
+            Some(DatabaseBuff {
+                kind: buff.kind,
+                strength: buff.data.strength,
+                remaining_secs: buff.time?.as_secs_f32(),
+                categories: buff
+                    .cat_ids
+                    .iter()
+                    .filter_map(|category| buff_category_to_string(*category))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+pub fn buffs_from_db_model(buffs: Vec<DatabaseBuff>) -> Vec<comp::Buff> {
+    buffs
+        .into_iter()
+        .map(
+            |DatabaseBuff {
+                 kind,
+                 strength,
+                 remaining_secs,
+                 categories,
+             }| {
+                comp::Buff::new(
+                    kind,
+                    comp::BuffData::new(
+                        strength,
+                        Some(Duration::from_secs_f32(remaining_secs.max(0.0))),
+                        None,
+                    ),
+                    categories
+                        .iter()
+                        .filter_map(|category| buff_category_from_string(category))
+                        .collect(),
+                    comp::BuffSource::Unknown,
+                )
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffs_round_trip() {
+        let buff = |kind, strength, secs: Option<f32>, cat_ids| {
+            comp::Buff::new(
+                kind,
+                comp::BuffData::new(strength, secs.map(Duration::from_secs_f32), None),
+                cat_ids,
+                comp::BuffSource::Item,
+            )
+        };
+        let buffs = [
+            buff(comp::BuffKind::Potion, 50.0, Some(10.0), vec![
+                comp::BuffCategory::Natural,
+            ]),
+            buff(comp::BuffKind::Burning, 2.0, Some(3.5), vec![
+                comp::BuffCategory::Magical,
+                comp::BuffCategory::FromAura(true),
+            ]),
+            // Buffs without a duration aren't stored
+            buff(comp::BuffKind::Regeneration, 1.0, None, Vec::new()),
+        ];
+
+        let json = serde_json::to_string(&buffs_to_db_model(&buffs)).unwrap();
+        assert!(json.contains(r#""kind":"Potion""#));
+        let loaded = buffs_from_db_model(serde_json::from_str(&json).unwrap());
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].kind, comp::BuffKind::Potion);
+        assert_eq!(loaded[0].data.strength, 50.0);
+        assert_eq!(loaded[0].time, Some(Duration::from_secs(10)));
+        assert_eq!(loaded[0].cat_ids, vec![comp::BuffCategory::Natural]);
+        assert_eq!(loaded[1].kind, comp::BuffKind::Burning);
+        assert_eq!(loaded[1].time, Some(Duration::from_secs_f32(3.5)));
+        // Aura buffs are applied again by the aura
+        assert_eq!(loaded[1].cat_ids, vec![comp::BuffCategory::Magical]);
+    }
+}
@@ -1,15 +1,20 @@
 //! DB operations and schema migrations
 
+pub mod audit_log;
+pub mod backup;
 pub(in crate::persistence) mod character;
 pub mod character_loader;
+pub mod character_transfer;
 pub mod character_updater;
 mod diesel_to_rusqlite;
 pub mod error;
 mod json_models;
 mod models;
+#[cfg(feature = "plugins")]
+pub mod plugin_storage;
 
 use crate::persistence::character_updater::PetPersistenceData;
-use common::comp;
+use common::{character::CharacterVitals, comp};
 use refinery::Report;
 use rusqlite::{Connection, OpenFlags};
 use std::{
@@ -32,6 +37,9 @@ pub struct PersistedComponents {
     pub pets: Vec<PetPersistenceData>,
     pub active_abilities: comp::ActiveAbilities,
     pub map_marker: Option<comp::MapMarker>,
+    pub vitals: CharacterVitals,
+    /// The vault of the player the character belongs to
+    pub vault: comp::Vault,
 }
 
 pub type EditableComponents = (comp::Body,);
@@ -104,6 +112,12 @@ fn set_log_mode(connection: &mut Connection, sql_log_mode: SqlLogMode) {
 pub struct DatabaseSettings {
     pub db_dir: PathBuf,
     pub sql_log_mode: SqlLogMode,
+    /// Time between the scheduled backups of the database, `None` disables
+    /// them
+    pub backup_interval: Option<Duration>,
+    /// The number of backups to keep, older ones are deleted. `0` keeps all of
+    /// them.
+    pub backup_retention: usize,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -245,3 +259,18 @@ pub(crate) fn establish_connection(
 
     veloren_connection
 }
+
+/// A new in-memory database with all the migrations applied, for tests
+#[cfg(test)]
+pub(crate) fn in_memory_connection() -> VelorenConnection {
+    let mut connection =
+        Connection::open_in_memory().expect("Failed to open an in-memory database");
+    rusqlite::vtab::array::load_module(&connection).expect("Failed to load sqlite array module");
+    connection
+        .pragma_update(None, "foreign_keys", &"ON")
+        .expect("Failed to set foreign_keys PRAGMA");
+    embedded::migrations::runner()
+        .run(&mut connection)
+        .expect("Database migrations failed");
+    VelorenConnection::new(connection)
+}
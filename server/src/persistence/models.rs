@@ -40,3 +40,11 @@ pub struct AbilitySets {
     pub entity_id: i64,
     pub ability_sets: String,
 }
+
+pub struct CharacterVitals {
+    pub entity_id: i64,
+    pub position: Option<String>,
+    pub health: Option<f64>,
+    pub energy: Option<f64>,
+    pub buffs: String,
+}
@@ -0,0 +1,119 @@
+//! Persistence of the key/value storage of plugins
+
+use crate::persistence::{
+    error::PersistenceError, establish_connection, ConnectionMode, DatabaseSettings,
+    VelorenConnection,
+};
+use common_state::plugin::storage::StorageChange;
+use rusqlite::{DropBehavior, ToSql, NO_PARAMS};
+use std::sync::{Arc, RwLock};
+use tracing::{error, trace};
+
+/// Load every entry of the plugin storage as `(plugin, key, value)`. This is
+/// executed during server startup
+pub fn load_plugin_storage(
+    settings: &DatabaseSettings,
+) -> Result<Vec<(String, String, Vec<u8>)>, PersistenceError> {
+    let connection = establish_connection(settings, ConnectionMode::ReadOnly);
+    let mut stmt = connection.prepare_cached(
+        "
+        SELECT  plugin,
+                key,
+                value
+        FROM    plugin_storage",
+    )?;
+
+    let entries = stmt
+        .query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// A unidirectional messaging resource for saving the changes made by plugins
+/// to their storage in a background thread.
+pub struct PluginStorageUpdater {
+    update_tx: Option<crossbeam_channel::Sender<Vec<StorageChange>>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl PluginStorageUpdater {
+    pub fn new(settings: Arc<RwLock<DatabaseSettings>>) -> Self {
+        let (update_tx, update_rx) = crossbeam_channel::unbounded::<Vec<StorageChange>>();
+
+        let builder = std::thread::Builder::new().name("plugin_storage_updater".into());
+        let handle = builder
+            .spawn(move || {
+                // The database settings are only written by `Server::set_sql_log_mode`,
+                // which can't panic while holding the lock, so it can't be poisoned.
+                let mut conn =
+                    establish_connection(&settings.read().unwrap(), ConnectionMode::ReadWrite);
+                while let Ok(changes) = update_rx.recv() {
+                    conn.update_log_mode(&settings);
+                    if let Err(e) = execute_batch_update(changes, &mut conn) {
+                        error!(?e, "Error during plugin storage batch update");
+                    }
+                }
+            })
+            .unwrap();
+
+        Self {
+            update_tx: Some(update_tx),
+            handle: Some(handle),
+        }
+    }
+
+    /// Persist the changes, in the order they were made
+    pub fn batch_update(&self, changes: Vec<StorageChange>) {
+        if let Err(e) = self.update_tx.as_ref().unwrap().send(changes) {
+            error!(?e, "Could not send plugin storage updates");
+        }
+    }
+}
+
+fn execute_batch_update(
+    changes: Vec<StorageChange>,
+    connection: &mut VelorenConnection,
+) -> Result<(), PersistenceError> {
+    let mut transaction = connection.connection.transaction()?;
+    transaction.set_drop_behavior(DropBehavior::Rollback);
+    trace!("Transaction started for plugin storage batch update");
+    for change in changes {
+        match change {
+            StorageChange::Set { plugin, key, value } => {
+                let mut stmt = transaction.prepare_cached(
+                    "
+                    REPLACE
+                    INTO    plugin_storage (plugin,
+                                            key,
+                                            value)
+                    VALUES  (?1, ?2, ?3)",
+                )?;
+                stmt.execute(&[&plugin as &dyn ToSql, &key, &value])?;
+            },
+            StorageChange::Delete { plugin, key } => {
+                let mut stmt = transaction.prepare_cached(
+                    "
+                    DELETE
+                    FROM    plugin_storage
+                    WHERE   plugin = ?1
+                    AND     key = ?2",
+                )?;
+                stmt.execute(&[&plugin, &key])?;
+            },
+        }
+    }
+    transaction.commit()?;
+
+    trace!("Commit for plugin storage batch update completed");
+    Ok(())
+}
+
+impl Drop for PluginStorageUpdater {
+    fn drop(&mut self) {
+        drop(self.update_tx.take());
+        if let Err(e) = self.handle.take().unwrap().join() {
+            error!(?e, "Error from joining plugin storage update thread");
+        }
+    }
+}
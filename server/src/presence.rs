@@ -1,3 +1,4 @@
+use common::{uid::Uid, util::Dir};
 use common_net::msg::PresenceKind;
 use hashbrown::HashSet;
 use serde::{Deserialize, Serialize};
@@ -11,6 +12,8 @@ pub struct Presence {
     pub entity_view_distance: ViewDistance,
     pub kind: PresenceKind,
     pub lossy_terrain_compression: bool,
+    /// The entity followed by a spectator, see [`crate::sys::spectate`]
+    pub spectate_target: Option<SpectateTarget>,
 }
 
 impl Presence {
@@ -21,6 +24,41 @@ impl Presence {
             entity_view_distance: ViewDistance::new(view_distances.entity, now),
             kind,
             lossy_terrain_compression: false,
+            spectate_target: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SpectateTarget {
+    pub uid: Uid,
+    /// The look direction of the target last sent to the spectator
+    pub last_look_dir: Option<Dir>,
+}
+
+impl SpectateTarget {
+    /// Look directions closer than this (in radians) to the last one sent
+    /// aren't sent again
+    const LOOK_DIR_THRESHOLD: f32 = 0.01;
+
+    pub fn new(uid: Uid) -> Self {
+        Self {
+            uid,
+            last_look_dir: None,
+        }
+    }
+
+    /// Returns the look direction of the target if it has to be sent to the
+    /// spectator, because it changed enough since the last one sent.
+    pub fn update_look_dir(&mut self, look_dir: Dir) -> Option<Dir> {
+        let changed = self.last_look_dir.map_or(true, |last| {
+            last.angle_between(*look_dir) > Self::LOOK_DIR_THRESHOLD
+        });
+        if changed {
+            self.last_look_dir = Some(look_dir);
+            Some(look_dir)
+        } else {
+            None
         }
     }
 }
@@ -136,3 +174,26 @@ impl ViewDistance {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rotated by `angle` radians from the y axis around the z axis
+    fn look_dir(angle: f32) -> Dir { Dir::new(Vec3::new(angle.sin(), angle.cos(), 0.0)) }
+
+    #[test]
+    fn spectated_look_dir() {
+        let mut target = SpectateTarget::new(Uid(1));
+        assert_eq!(target.update_look_dir(look_dir(0.0)), Some(look_dir(0.0)));
+        assert_eq!(target.update_look_dir(look_dir(0.0)), None);
+        assert_eq!(target.update_look_dir(look_dir(0.006)), None);
+        // Small changes add up until they are sent
+        assert_eq!(
+            target.update_look_dir(look_dir(0.012)),
+            Some(look_dir(0.012))
+        );
+        assert_eq!(target.update_look_dir(look_dir(0.018)), None);
+        assert_eq!(target.update_look_dir(look_dir(-1.0)), Some(look_dir(-1.0)));
+    }
+}
@@ -0,0 +1,164 @@
+//! Answers the status queries of server lists, see [`common_net::msg::query`].
+
+use common_net::msg::query::{
+    decode_query, encode_query, QueryRequest, QueryResponse, ServerStatus, MAX_QUERY_SIZE,
+};
+use parking_lot::RwLock;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{net::UdpSocket, runtime::Runtime, select, sync::Notify};
+use tracing::{debug, error, info};
+
+/// Tokens stay valid for one to two of these
+const TOKEN_PERIOD: Duration = Duration::from_secs(30);
+
+/// Tokens are a keyed hash of the address of the client and the current
+/// period, so they don't have to be stored
+struct Tokens {
+    keys: RandomState,
+    start: Instant,
+}
+
+impl Tokens {
+    fn period(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.start).as_secs() / TOKEN_PERIOD.as_secs()
+    }
+
+    fn token_at(&self, ip: IpAddr, period: u64) -> u64 {
+        let mut hasher = self.keys.build_hasher();
+        ip.hash(&mut hasher);
+        period.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn token(&self, ip: IpAddr, now: Instant) -> u64 { self.token_at(ip, self.period(now)) }
+
+    /// Tokens of the previous period are still accepted, so clients which
+    /// got theirs right before the period changed aren't rejected
+    fn check(&self, token: u64, ip: IpAddr, now: Instant) -> bool {
+        let period = self.period(now);
+        token == self.token_at(ip, period) || (period > 0 && token == self.token_at(ip, period - 1))
+    }
+}
+
+/// IPv6 sockets only take IPv6, so the IPv4 address can be bound next to them
+/// like the game server protocols are
+fn bind(address: SocketAddr) -> io::Result<UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+    let domain = Domain::for_address(address);
+    let socket = Socket::new(domain, Type::DGRAM, Some(Protocol::UDP))?;
+    if domain == Domain::IPV6 {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_nonblocking(true)?; // Needed by Tokio
+    socket.bind(&address.into())?;
+    UdpSocket::from_std(socket.into())
+}
+
+/// Runs on the tokio runtime like the
+/// [`crate::connection_handler::ConnectionHandler`], and answers with the
+/// status the main thread keeps up to date with [`QueryServer::update`]
+pub(crate) struct QueryServer {
+    status: Arc<RwLock<ServerStatus>>,
+    shutdown: Arc<Notify>,
+}
+
+impl QueryServer {
+    pub fn new(address: SocketAddr, status: ServerStatus, runtime: &Runtime) -> Self {
+        let status = Arc::new(RwLock::new(status));
+        let shutdown = Arc::new(Notify::new());
+        runtime.spawn(Self::work(
+            address,
+            Arc::clone(&status),
+            Arc::clone(&shutdown),
+        ));
+        Self { status, shutdown }
+    }
+
+    pub fn update(&self, status: ServerStatus) { *self.status.write() = status; }
+
+    async fn work(address: SocketAddr, status: Arc<RwLock<ServerStatus>>, shutdown: Arc<Notify>) {
+        let socket = match bind(address) {
+            Ok(socket) => socket,
+            Err(e) => {
+                error!(
+                    ?e,
+                    %address,
+                    "Failed to bind the status query socket, running without status queries"
+                );
+                return;
+            },
+        };
+        info!(%address, "Answering status queries");
+
+        let tokens = Tokens {
+            keys: RandomState::new(),
+            start: Instant::now(),
+        };
+        let mut buf = [0; MAX_QUERY_SIZE];
+        loop {
+            let (len, addr) = select! {
+                _ = shutdown.notified() => break,
+                received = socket.recv_from(&mut buf) => match received {
+                    Ok(received) => received,
+                    Err(e) => {
+                        debug!(?e, "Failed to receive a status query");
+                        continue;
+                    },
+                },
+            };
+            let now = Instant::now();
+            // Anything else is ignored without an answer
+            let response = match decode_query(&buf[..len]) {
+                Some(QueryRequest::Token) => QueryResponse::Token {
+                    token: tokens.token(addr.ip(), now),
+                },
+                Some(QueryRequest::Status { token }) if tokens.check(token, addr.ip(), now) => {
+                    QueryResponse::Status(status.read().clone())
+                },
+                _ => continue,
+            };
+            if let Err(e) = socket.send_to(&encode_query(&response), addr).await {
+                debug!(?e, %addr, "Failed to answer a status query");
+            }
+        }
+    }
+}
+
+impl Drop for QueryServer {
+    fn drop(&mut self) { self.shutdown.notify_one(); }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn token_check() {
+        let tokens = Tokens {
+            keys: RandomState::new(),
+            start: Instant::now(),
+        };
+        let ip = IpAddr::from(Ipv4Addr::new(192, 0, 2, 1));
+        let other_ip = IpAddr::from(Ipv6Addr::LOCALHOST);
+        let at_period = |period: u32| tokens.start + TOKEN_PERIOD * period;
+
+        let token = tokens.token(ip, at_period(1));
+        assert!(tokens.check(token, ip, at_period(1)));
+        assert!(!tokens.check(token, other_ip, at_period(1)));
+        // Still valid in the next period, but not the one after
+        assert!(tokens.check(token, ip, at_period(2)));
+        assert!(!tokens.check(token, ip, at_period(3)));
+        // Tokens of later periods aren't valid yet
+        assert!(!tokens.check(tokens.token(ip, at_period(2)), ip, at_period(1)));
+        // The first period has no previous one
+        assert!(tokens.check(tokens.token(ip, at_period(0)), ip, at_period(0)));
+    }
+}
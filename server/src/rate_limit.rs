@@ -0,0 +1,254 @@
+//! Limits how many messages of each category a client may send, to protect
+//! the server from clients flooding it with expensive requests.
+
+use crate::settings::{RateLimit, RateLimitSettings};
+use common_net::msg::ClientGeneral;
+use std::time::Instant;
+
+/// Categories of client messages which are rate limited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MsgCategory {
+    TerrainChunk,
+    LodZone,
+    BlockEdit,
+    Command,
+    SiteInfo,
+}
+
+impl MsgCategory {
+    const COUNT: usize = 5;
+
+    /// Returns `None` for messages which aren't limited
+    pub fn of(msg: &ClientGeneral) -> Option<Self> {
+        match msg {
+            ClientGeneral::TerrainChunkRequest { .. } => Some(Self::TerrainChunk),
+            ClientGeneral::LodZoneRequest { .. } => Some(Self::LodZone),
+            ClientGeneral::BreakBlock(_) | ClientGeneral::PlaceBlock(_, _) => Some(Self::BlockEdit),
+            ClientGeneral::Command(_, _) => Some(Self::Command),
+            ClientGeneral::RequestSiteInfo(_) => Some(Self::SiteInfo),
+            _ => None,
+        }
+    }
+
+    /// Label of the category in the metrics
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::TerrainChunk => "terrain_chunk",
+            Self::LodZone => "lod_zone",
+            Self::BlockEdit => "block_edit",
+            Self::Command => "command",
+            Self::SiteInfo => "site_info",
+        }
+    }
+
+    fn limit(self, settings: &RateLimitSettings) -> &RateLimit {
+        match self {
+            Self::TerrainChunk => &settings.terrain_chunk,
+            Self::LodZone => &settings.lod_zone,
+            Self::BlockEdit => &settings.block_edit,
+            Self::Command => &settings.command,
+            Self::SiteInfo => &settings.site_info,
+        }
+    }
+}
+
+/// What to do with a message, the responses escalate the more messages a
+/// client sends over its limits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Drop,
+    /// Drop the message and warn the client to slow down
+    Warn,
+    /// Drop the message and kick the client
+    Kick,
+}
+
+impl Verdict {
+    /// Label of the verdict in the metrics
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Drop => "drop",
+            Self::Warn => "warn",
+            Self::Kick => "kick",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    tokens: f32,
+    last_refill: Instant,
+}
+
+/// Token buckets of one client, one per [`MsgCategory`]
+pub struct RateLimiter {
+    buckets: [Option<Bucket>; MsgCategory::COUNT],
+    /// Messages dropped since the client last kept to its limits for a while
+    dropped: u32,
+    last_drop: Option<Instant>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self {
+            buckets: [None; MsgCategory::COUNT],
+            dropped: 0,
+            last_drop: None,
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn check(
+        &mut self,
+        category: MsgCategory,
+        settings: &RateLimitSettings,
+        now: Instant,
+    ) -> Verdict {
+        if !settings.enabled {
+            return Verdict::Allow;
+        }
+        let limit = category.limit(settings);
+        // Buckets start full, so clients can send a burst right after joining
+        let bucket = self.buckets[category as usize].get_or_insert(Bucket {
+            tokens: limit.burst,
+            last_refill: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f32() * limit.per_second).min(limit.burst);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Verdict::Allow;
+        }
+
+        if self.last_drop.map_or(false, |last| {
+            now.saturating_duration_since(last) > settings.forgive_after
+        }) {
+            self.dropped = 0;
+        }
+        self.dropped += 1;
+        self.last_drop = Some(now);
+        if self.dropped >= settings.kick_after {
+            Verdict::Kick
+        } else if self.dropped == settings.warn_after {
+            Verdict::Warn
+        } else {
+            Verdict::Drop
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn settings() -> RateLimitSettings {
+        RateLimitSettings {
+            command: RateLimit {
+                per_second: 2.0,
+                burst: 4.0,
+            },
+            warn_after: 3,
+            kick_after: 5,
+            forgive_after: Duration::from_secs(10),
+            ..RateLimitSettings::default()
+        }
+    }
+
+    #[test]
+    fn burst() {
+        let settings = settings();
+        let mut limiter = RateLimiter::default();
+        let now = Instant::now();
+        for _ in 0..4 {
+            assert_eq!(
+                limiter.check(MsgCategory::Command, &settings, now),
+                Verdict::Allow
+            );
+        }
+        assert_eq!(
+            limiter.check(MsgCategory::Command, &settings, now),
+            Verdict::Drop
+        );
+        // Each category has its own bucket
+        assert_eq!(
+            limiter.check(MsgCategory::SiteInfo, &settings, now),
+            Verdict::Allow
+        );
+    }
+
+    #[test]
+    fn refill() {
+        let settings = settings();
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        let check = |limiter: &mut RateLimiter, secs: f32| {
+            limiter.check(
+                MsgCategory::Command,
+                &settings,
+                start + Duration::from_secs_f32(secs),
+            )
+        };
+        for _ in 0..4 {
+            check(&mut limiter, 0.0);
+        }
+        assert_eq!(check(&mut limiter, 0.0), Verdict::Drop);
+
+        // Two messages per second
+        assert_eq!(check(&mut limiter, 0.5), Verdict::Allow);
+        assert_eq!(check(&mut limiter, 0.5), Verdict::Drop);
+        assert_eq!(check(&mut limiter, 1.5), Verdict::Allow);
+        assert_eq!(check(&mut limiter, 1.5), Verdict::Allow);
+        assert_eq!(check(&mut limiter, 1.5), Verdict::Drop);
+
+        // The bucket doesn't fill up beyond the burst
+        for _ in 0..4 {
+            assert_eq!(check(&mut limiter, 100.0), Verdict::Allow);
+        }
+        assert_eq!(check(&mut limiter, 100.0), Verdict::Drop);
+    }
+
+    #[test]
+    fn limit() {
+        let settings = settings();
+        let mut limiter = RateLimiter::default();
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.check(MsgCategory::Command, &settings, start);
+        }
+        let verdicts = (0..5)
+            .map(|_| limiter.check(MsgCategory::Command, &settings, start))
+            .collect::<Vec<_>>();
+        assert_eq!(verdicts, [
+            Verdict::Drop,
+            Verdict::Drop,
+            Verdict::Warn,
+            Verdict::Drop,
+            Verdict::Kick
+        ]);
+
+        // Dropped messages are forgiven after a while
+        let later = start + Duration::from_secs(20);
+        for _ in 0..4 {
+            limiter.check(MsgCategory::Command, &settings, later);
+        }
+        assert_eq!(
+            limiter.check(MsgCategory::Command, &settings, later),
+            Verdict::Drop
+        );
+
+        // Nothing is limited if the limits are disabled
+        let settings = RateLimitSettings {
+            enabled: false,
+            ..settings
+        };
+        assert_eq!(
+            limiter.check(MsgCategory::Command, &settings, later),
+            Verdict::Allow
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! Sessions of registered clients, which they can resume after losing their
+//! connection, see [`ResumeToken`].
+
+use common_net::msg::ResumeToken;
+use hashbrown::HashMap;
+use specs::Entity as EcsEntity;
+use std::time::Duration;
+
+struct Session {
+    entity: EcsEntity,
+    /// When the client of the entity lost its connection
+    detached_since: Option<f64>,
+}
+
+/// The sessions of all registered clients, by their resume token
+#[derive(Default)]
+pub struct ResumeSessions {
+    sessions: HashMap<ResumeToken, Session>,
+    tokens: HashMap<EcsEntity, ResumeToken>,
+}
+
+impl ResumeSessions {
+    /// Start a session for the freshly registered client of `entity`. The
+    /// token is random, so it's unique in practice.
+    pub fn open(&mut self, entity: EcsEntity, token: ResumeToken) {
+        self.remove(entity);
+        self.sessions.insert(token, Session {
+            entity,
+            detached_since: None,
+        });
+        self.tokens.insert(entity, token);
+    }
+
+    /// Keep the session of `entity` open after it lost its client at `time`.
+    /// Returns `false` if the entity has no session, in which case it should
+    /// be removed.
+    pub fn detach(&mut self, entity: EcsEntity, time: f64) -> bool {
+        match self
+            .tokens
+            .get(&entity)
+            .and_then(|token| self.sessions.get_mut(token))
+        {
+            Some(session) => {
+                session.detached_since.get_or_insert(time);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn is_detached(&self, entity: EcsEntity) -> bool {
+        self.tokens
+            .get(&entity)
+            .and_then(|token| self.sessions.get(token))
+            .map_or(false, |session| session.detached_since.is_some())
+    }
+
+    /// All entities waiting for their client to come back
+    pub fn detached(&self) -> impl Iterator<Item = EcsEntity> + '_ {
+        self.sessions
+            .values()
+            .filter(|session| session.detached_since.is_some())
+            .map(|session| session.entity)
+    }
+
+    /// Attach a new client to the session of `token`, returns the entity the
+    /// client takes over. The session keeps its token.
+    ///
+    /// The session doesn't have to be detached, the client might notice the
+    /// lost connection before the server does.
+    pub fn resume(&mut self, token: ResumeToken) -> Option<EcsEntity> {
+        let session = self.sessions.get_mut(&token)?;
+        session.detached_since = None;
+        Some(session.entity)
+    }
+
+    /// Move the session of `from` to `to`, when the client changes its entity
+    pub fn transfer(&mut self, from: EcsEntity, to: EcsEntity) {
+        if let Some(token) = self.tokens.remove(&from) {
+            if let Some(session) = self.sessions.get_mut(&token) {
+                session.entity = to;
+            }
+            self.tokens.insert(to, token);
+        }
+    }
+
+    pub fn remove(&mut self, entity: EcsEntity) {
+        if let Some(token) = self.tokens.remove(&entity) {
+            self.sessions.remove(&token);
+        }
+    }
+
+    /// Close the sessions which have been detached for longer than `timeout`
+    /// and return their entities
+    pub fn expire(&mut self, time: f64, timeout: Duration) -> Vec<EcsEntity> {
+        let expired = self
+            .sessions
+            .values()
+            .filter(|session| {
+                session
+                    .detached_since
+                    .map_or(false, |since| time - since > timeout.as_secs_f64())
+            })
+            .map(|session| session.entity)
+            .collect::<Vec<_>>();
+        for entity in &expired {
+            self.remove(*entity);
+        }
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use specs::{Builder, World, WorldExt};
+
+    fn entities<const N: usize>() -> [EcsEntity; N] {
+        let mut world = World::new();
+        [(); N].map(|_| world.create_entity().build())
+    }
+
+    #[test]
+    fn open_and_resume() {
+        let [a, b] = entities();
+        let mut sessions = ResumeSessions::default();
+        sessions.open(a, ResumeToken(1));
+        sessions.open(b, ResumeToken(2));
+        assert_eq!(sessions.resume(ResumeToken(1)), Some(a));
+        assert_eq!(sessions.resume(ResumeToken(2)), Some(b));
+        assert_eq!(sessions.resume(ResumeToken(3)), None);
+
+        // Opening a new session for the entity replaces the old one
+        sessions.open(a, ResumeToken(4));
+        assert_eq!(sessions.resume(ResumeToken(1)), None);
+        assert_eq!(sessions.resume(ResumeToken(4)), Some(a));
+    }
+
+    #[test]
+    fn detach_and_resume() {
+        let [a, b] = entities();
+        let mut sessions = ResumeSessions::default();
+        sessions.open(a, ResumeToken(1));
+        assert!(!sessions.detach(b, 0.0));
+        assert!(!sessions.is_detached(a));
+
+        assert!(sessions.detach(a, 1.0));
+        assert!(sessions.is_detached(a));
+        assert_eq!(sessions.detached().collect::<Vec<_>>(), vec![a]);
+
+        assert_eq!(sessions.resume(ResumeToken(1)), Some(a));
+        assert!(!sessions.is_detached(a));
+        assert_eq!(sessions.detached().count(), 0);
+
+        sessions.remove(a);
+        assert!(!sessions.detach(a, 2.0));
+        assert_eq!(sessions.resume(ResumeToken(1)), None);
+    }
+
+    #[test]
+    fn transfer() {
+        let [a, b] = entities();
+        let mut sessions = ResumeSessions::default();
+        sessions.open(a, ResumeToken(1));
+        sessions.transfer(a, b);
+        assert!(!sessions.detach(a, 0.0));
+        assert!(sessions.detach(b, 0.0));
+        assert_eq!(sessions.resume(ResumeToken(1)), Some(b));
+
+        // Entities without a session don't get one
+        sessions.transfer(a, b);
+        assert_eq!(sessions.resume(ResumeToken(1)), Some(b));
+    }
+
+    #[test]
+    fn expire() {
+        let [a, b, c] = entities();
+        let timeout = Duration::from_secs(10);
+        let mut sessions = ResumeSessions::default();
+        sessions.open(a, ResumeToken(1));
+        sessions.open(b, ResumeToken(2));
+        sessions.open(c, ResumeToken(3));
+        sessions.detach(a, 0.0);
+        sessions.detach(b, 5.0);
+        // Detaching again keeps the time the connection was lost first
+        sessions.detach(b, 9.0);
+
+        assert!(sessions.expire(10.0, timeout).is_empty());
+        assert_eq!(sessions.expire(10.5, timeout), vec![a]);
+        assert_eq!(sessions.resume(ResumeToken(1)), None);
+        assert_eq!(sessions.expire(15.5, timeout), vec![b]);
+        // Attached sessions never expire
+        assert!(sessions.expire(1000.0, timeout).is_empty());
+        assert_eq!(sessions.resume(ResumeToken(3)), Some(c));
+    }
+}
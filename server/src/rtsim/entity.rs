@@ -8,6 +8,7 @@ use common::{
 };
 use enumset::*;
 use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 use tracing::warn;
 use world::{
@@ -16,17 +17,21 @@ use world::{
     IndexRef, World,
 };
 
+#[derive(Serialize, Deserialize)]
 pub struct Entity {
+    // Entities are never loaded when restored from a save
+    #[serde(skip)]
     pub is_loaded: bool,
     pub pos: Vec3<f32>,
     pub seed: u32,
     pub last_time_ticked: f64,
+    #[serde(skip)]
     pub controller: RtSimController,
     pub kind: RtSimEntityKind,
     pub brain: Brain,
 }
 
-#[derive(Clone, Copy, strum::EnumIter, PartialEq, Eq)]
+#[derive(Clone, Copy, strum::EnumIter, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RtSimEntityKind {
     Wanderer,
     Cultist,
@@ -64,6 +69,48 @@ const PERM_TRADE: u32 = 5;
 impl Entity {
     pub fn rng(&self, perm: u32) -> impl Rng { RandomPerm::new(self.seed + perm) }
 
+    /// Move all the points in time the entity keeps by `offset` seconds, used
+    /// to carry them over to the game time of a new server run
+    pub fn shift_time(&mut self, offset: f64) {
+        self.last_time_ticked += offset;
+        for memory in &mut self.brain.memories {
+            memory.time_to_forget += offset;
+        }
+        if let Travel::DirectRaid {
+            time_to_move: Some(time_to_move),
+            ..
+        } = &mut self.brain.route
+        {
+            *time_to_move += offset;
+        }
+    }
+
+    /// Whether all the sites and tracks the entity refers to exist in `world`
+    pub fn is_valid_in(&self, world: &World) -> bool {
+        let civs = world.civs();
+        let site_valid = |id: &Id<Site>| civs.sites.recreate_id(id.id()).is_some();
+        let brain = &self.brain;
+        [brain.begin, brain.tgt, brain.last_visited]
+            .iter()
+            .flatten()
+            .all(site_valid)
+            && match &brain.route {
+                Travel::Lost | Travel::Idle => true,
+                Travel::InSite { site_id } => site_valid(site_id),
+                Travel::Direct { target_id } | Travel::CustomPath { target_id, .. } => {
+                    site_valid(target_id)
+                },
+                Travel::Path {
+                    target_id,
+                    track_id,
+                    ..
+                } => site_valid(target_id) && civs.tracks.recreate_id(track_id.id()).is_some(),
+                Travel::DirectRaid {
+                    target_id, home_id, ..
+                } => site_valid(target_id) && site_valid(home_id),
+            }
+    }
+
     pub fn loadout_rng(&self) -> impl Rng { self.rng(PERM_LOADOUT) }
 
     pub fn get_body(&self) -> comp::Body {
@@ -649,7 +696,7 @@ impl Entity {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Travel {
     // The initial state all entities start in, and a fallback for when a state has stopped making
     // sense. Non humanoids will always revert to this state after reaching their goal since the
@@ -761,11 +808,13 @@ impl PersonalityBase {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Personality {
     pub personality_traits: EnumSet<PersonalityTrait>,
     pub will_ambush: bool,
 }
 
+// NOTE: Traits are saved as a bitset, only add new ones at the end
 #[derive(EnumSetType)]
 pub enum PersonalityTrait {
     Open,
@@ -825,6 +874,7 @@ impl Personality {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Brain {
     pub begin: Option<Id<Site>>,
     pub tgt: Option<Id<Site>>,
@@ -1070,4 +1120,39 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn shift_time() {
+        let site = serde_json::from_str::<Id<Site>>("0").unwrap();
+        let mut brain = Brain::raid(site, site, &mut thread_rng());
+        if let Travel::DirectRaid { time_to_move, .. } = &mut brain.route {
+            *time_to_move = Some(30.0);
+        }
+        brain.memories.push(Memory {
+            item: MemoryItem::CharacterFight {
+                name: "Bandit".to_owned(),
+            },
+            time_to_forget: 20.0,
+        });
+        let mut entity = Entity {
+            is_loaded: false,
+            pos: Vec3::zero(),
+            seed: 0,
+            last_time_ticked: 10.0,
+            controller: RtSimController::default(),
+            kind: RtSimEntityKind::Cultist,
+            brain,
+        };
+
+        entity.shift_time(100.0);
+        assert_eq!(entity.last_time_ticked, 110.0);
+        assert_eq!(entity.brain.memories[0].time_to_forget, 120.0);
+        assert!(matches!(
+            entity.brain.route,
+            Travel::DirectRaid {
+                time_to_move: Some(time_to_move),
+                ..
+            } if time_to_move == 130.0
+        ));
+    }
 }
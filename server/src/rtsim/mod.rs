@@ -3,14 +3,17 @@
 mod chunks;
 pub(crate) mod entity;
 mod load_chunks;
+pub mod save;
 mod tick;
 mod unload_chunks;
 
 use crate::rtsim::entity::{Personality, Travel};
 
-use self::chunks::Chunks;
+use self::{chunks::Chunks, save::RtSimPersistence};
+use crate::{data_dir::DataDir, sys::SysScheduler, Settings};
 use common::{
     comp,
+    resources::Time,
     rtsim::{Memory, RtSimController, RtSimEntity, RtSimId},
     terrain::TerrainChunk,
     vol::RectRasterableVol,
@@ -20,6 +23,7 @@ use common_state::State;
 use rand::prelude::*;
 use slab::Slab;
 use specs::{DispatcherBuilder, WorldExt};
+use std::time::Duration;
 use vek::*;
 
 pub use self::entity::{Brain, Entity, RtSimEntityKind};
@@ -104,6 +108,7 @@ pub fn add_server_systems(dispatch_builder: &mut DispatcherBuilder) {
         &load_chunks::Sys::sys_name(),
         &unload_chunks::Sys::sys_name(),
     ]);
+    dispatch::<save::Sys>(dispatch_builder, &[&tick::Sys::sys_name()]);
 }
 
 pub fn init(
@@ -117,10 +122,30 @@ pub fn init(
     #[cfg(not(feature = "worldgen"))]
     let mut rtsim = RtSim::new(Vec2::new(40, 40));
 
+    let persistence = RtSimPersistence::new(
+        &state.ecs().read_resource::<DataDir>().path,
+        state.ecs().read_resource::<Settings>().world_seed,
+    );
+    let restored = persistence.load(state.ecs().read_resource::<Time>().0);
+    // Sites and tracks may be different even with the same seed, if world
+    // generation changed since the entities were saved
+    #[cfg(feature = "worldgen")]
+    let restored = restored.filter(|entities| {
+        let valid = entities.iter().all(|(_, entity)| entity.is_valid_in(world));
+        if !valid {
+            tracing::info!("The world sites changed, rtsim entities will be generated again");
+        }
+        valid
+    });
+    if let Some(entities) = restored {
+        tracing::info!("Restored {} rtsim entities", entities.len());
+        rtsim.entities = entities;
+    }
+
     // TODO: Determine number of rtsim entities based on things like initial site
     // populations rather than world size
     #[cfg(feature = "worldgen")]
-    {
+    if rtsim.entities.is_empty() {
         for _ in 0..world.sim().get_size().product() / 400 {
             let pos = rtsim
                 .chunks
@@ -430,6 +455,12 @@ pub fn init(
     }
 
     state.ecs_mut().insert(rtsim);
+    state.ecs_mut().insert(persistence);
+    state
+        .ecs_mut()
+        .insert(SysScheduler::<save::Sys>::every(Duration::from_secs(
+            save::SAVE_INTERVAL_SECS,
+        )));
     state.ecs_mut().register::<RtSimEntity>();
     tracing::info!("Initiated real-time world simulation");
 }
@@ -0,0 +1,231 @@
+//! Saving the rtsim entities to disk, so the simulation carries on across
+//! server restarts rather than being generated again every time.
+
+use super::{Entity, RtSim};
+use crate::sys::SysScheduler;
+use atomicwrites::{AtomicFile, OverwriteBehavior};
+use common::{resources::Time, slowjob::SlowJobPool};
+use common_ecs::{Job, Origin, Phase, System};
+use serde::{Deserialize, Serialize};
+use slab::Slab;
+use specs::{Read, ReadExpect, Write};
+use std::{
+    fs,
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+use tracing::{error, info, warn};
+
+/// How often the rtsim state is saved, in addition to saving it on shutdown
+pub const SAVE_INTERVAL_SECS: u64 = 300;
+
+/// Where and for which world the rtsim state is saved.
+pub struct RtSimPersistence {
+    path: PathBuf,
+    world_seed: u32,
+}
+
+impl RtSimPersistence {
+    pub fn new(data_dir: &Path, world_seed: u32) -> Self {
+        Self {
+            path: data_dir.join("rtsim.dat"),
+            world_seed,
+        }
+    }
+
+    /// Load the saved entities, with their points in time carried over to the
+    /// current game `time`. Returns `None` if there is no save, or if it can't
+    /// be used for this world, in which case the entities should be
+    /// generated again.
+    pub fn load(&self, time: f64) -> Option<Slab<Entity>> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                if self.path.exists() {
+                    error!(?e, ?self.path, "Failed to read the rtsim save");
+                }
+                return None;
+            },
+        };
+        let save = match bincode::deserialize::<version::Current<Vec<Entity>>>(&bytes) {
+            Ok(save) => save,
+            Err(e) => {
+                warn!(
+                    ?e,
+                    "The rtsim save is corrupt or of an unsupported version, rtsim entities will \
+                     be generated again"
+                );
+                return None;
+            },
+        };
+        if save.world_seed != self.world_seed {
+            info!("The world seed changed, rtsim entities will be generated again");
+            return None;
+        }
+
+        let offset = time - save.time;
+        let mut entities = Slab::with_capacity(save.entities.len());
+        for mut entity in save.entities {
+            entity.shift_time(offset);
+            entities.insert(entity);
+        }
+        Some(entities)
+    }
+
+    /// Serialize the rtsim entities, as they are at the game `time`
+    pub fn serialize(&self, rtsim: &RtSim, time: f64) -> Option<Vec<u8>> {
+        let save = version::Current {
+            version: version::CURRENT,
+            world_seed: self.world_seed,
+            time,
+            entities: rtsim
+                .entities
+                .iter()
+                .map(|(_, entity)| entity)
+                .collect::<Vec<_>>(),
+        };
+        bincode::serialize(&save)
+            .map_err(|e| error!(?e, "Failed to serialize the rtsim state"))
+            .ok()
+    }
+
+    pub fn write(path: &Path, bytes: &[u8]) {
+        let atomic_file = AtomicFile::new(path, OverwriteBehavior::AllowOverwrite);
+        if let Err(e) = atomic_file.write(|file| file.write_all(bytes)) {
+            error!(?e, "Failed to write the rtsim save");
+        }
+    }
+
+    /// Save the rtsim entities, blocking until they are written
+    pub fn save(&self, rtsim: &RtSim, time: f64) {
+        if let Some(bytes) = self.serialize(rtsim, time) {
+            Self::write(&self.path, &bytes);
+            info!("Saved {} rtsim entities", rtsim.entities.len());
+        }
+    }
+}
+
+/// # Changing the save format
+///
+/// The entities are saved as they are in memory, so a change to any of the
+/// types they are made of changes the format. When that happens, increment
+/// [`version::CURRENT`]: saves of other versions are discarded and the
+/// entities are generated again.
+mod version {
+    use super::*;
+
+    pub const CURRENT: u32 = 1;
+
+    #[derive(Serialize, Deserialize)]
+    pub struct Current<E> {
+        #[serde(deserialize_with = "version")]
+        pub version: u32,
+        pub world_seed: u32,
+        /// The game time when the entities were saved
+        pub time: f64,
+        pub entities: E,
+    }
+
+    fn version<'de, D: serde::Deserializer<'de>>(de: D) -> Result<u32, D::Error> {
+        u32::deserialize(de).and_then(|x| {
+            if x == CURRENT {
+                Ok(x)
+            } else {
+                Err(serde::de::Error::invalid_value(
+                    serde::de::Unexpected::Unsigned(x as u64),
+                    &"the current rtsim save version",
+                ))
+            }
+        })
+    }
+}
+
+/// Saves the rtsim state periodically
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        ReadExpect<'a, RtSim>,
+        ReadExpect<'a, RtSimPersistence>,
+        Read<'a, Time>,
+        ReadExpect<'a, SlowJobPool>,
+        Write<'a, SysScheduler<Self>>,
+    );
+
+    const NAME: &'static str = "rtsim::save";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (rtsim, persistence, time, slow_jobs, mut scheduler): Self::SystemData,
+    ) {
+        if !scheduler.should_run() {
+            return;
+        }
+        // Write the serialized entities to disk in the background
+        if let Some(bytes) = persistence.serialize(&rtsim, time.0) {
+            let path = persistence.path.clone();
+            slow_jobs.spawn("RTSIM_SAVE", move || RtSimPersistence::write(&path, &bytes));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtsim::entity::{Brain, RtSimEntityKind};
+    use common::rtsim::{Memory, MemoryItem, RtSimController};
+    use rand::thread_rng;
+    use vek::*;
+
+    #[test]
+    fn save_and_load() {
+        let data_dir = std::env::temp_dir().join(format!(
+            "veloren-rtsim-save-and-load-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&data_dir).unwrap();
+        let persistence = RtSimPersistence::new(&data_dir, 42);
+        assert!(persistence.load(0.0).is_none());
+
+        let mut brain = Brain::idle(&mut thread_rng());
+        brain.memories.push(Memory {
+            item: MemoryItem::CharacterInteraction {
+                name: "Merchant".to_owned(),
+            },
+            time_to_forget: 150.0,
+        });
+        let mut rtsim = RtSim::new(Vec2::new(4, 4));
+        rtsim.entities.insert(Entity {
+            is_loaded: true,
+            pos: Vec3::new(1.0, 2.0, 3.0),
+            seed: 7,
+            last_time_ticked: 90.0,
+            controller: RtSimController::default(),
+            kind: RtSimEntityKind::Merchant,
+            brain,
+        });
+        persistence.save(&rtsim, 100.0);
+
+        // The points in time are carried over to the game time of the new run
+        let entities = persistence
+            .load(10.0)
+            .expect("The rtsim save was not loaded");
+        assert_eq!(entities.len(), 1);
+        let entity = entities.iter().next().unwrap().1;
+        assert!(!entity.is_loaded);
+        assert_eq!(entity.pos, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(entity.seed, 7);
+        assert!(entity.kind == RtSimEntityKind::Merchant);
+        assert_eq!(entity.last_time_ticked, 0.0);
+        assert_eq!(entity.brain.memories[0].time_to_forget, 60.0);
+
+        // Saves of other worlds or versions are not used
+        assert!(RtSimPersistence::new(&data_dir, 43).load(10.0).is_none());
+        RtSimPersistence::write(&persistence.path, &[0xff; 16]);
+        assert!(persistence.load(10.0).is_none());
+
+        fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -8,7 +8,8 @@ pub use editable::{EditableSetting, Error as SettingError};
 
 pub use admin::{AdminRecord, Admins};
 pub use banlist::{
-    Ban, BanAction, BanEntry, BanError, BanErrorKind, BanInfo, BanKind, BanRecord, Banlist,
+    Ban, BanAction, BanEntry, BanError, BanErrorKind, BanInfo, BanKind, BanRecord, Banlist, Mute,
+    MuteAction, MuteChat, MuteEntry,
 };
 pub use server_description::ServerDescription;
 pub use whitelist::{Whitelist, WhitelistInfo, WhitelistRecord};
@@ -16,15 +17,18 @@ pub use whitelist::{Whitelist, WhitelistInfo, WhitelistRecord};
 use chrono::Utc;
 use common::{
     calendar::{Calendar, CalendarEvent},
+    comp::{Admin, AdminRole},
     resources::BattleMode,
 };
+use common_net::msg::query::DEFAULT_QUERY_PORT;
 use core::time::Duration;
 use portpicker::pick_unused_port;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs,
+    fmt, fs,
     net::{Ipv4Addr, Ipv6Addr, SocketAddr},
     path::{Path, PathBuf},
+    time::{Instant, SystemTime},
 };
 use tracing::{error, warn};
 use world::sim::FileOpts;
@@ -63,7 +67,7 @@ impl ServerBattleMode {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Protocol {
     Quic {
         address: SocketAddr,
@@ -73,6 +77,17 @@ pub enum Protocol {
     Tcp {
         address: SocketAddr,
     },
+    /// Tcp which encrypts the connections of clients asking for it. The key
+    /// identifies the server, clients pin it on their first connection. It's
+    /// generated if the file doesn't exist, and only the user running the
+    /// server may access it.
+    EncryptedTcp {
+        address: SocketAddr,
+        key_file_path: PathBuf,
+        /// Reject clients which don't ask for encryption
+        #[serde(default)]
+        required: bool,
+    },
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -83,6 +98,10 @@ pub struct GameplaySettings {
     pub safe_spawn: bool,
     #[serde(default)]
     pub explosion_burn_marks: bool,
+    /// Characters log in where they last stood safely before logging out,
+    /// instead of at their waypoint
+    #[serde(default)]
+    pub restore_logout_position: bool,
 }
 
 impl Default for GameplaySettings {
@@ -91,6 +110,7 @@ impl Default for GameplaySettings {
             battle_mode: ServerBattleMode::default(),
             safe_spawn: false,
             explosion_burn_marks: true,
+            restore_logout_position: false,
         }
     }
 }
@@ -103,9 +123,17 @@ pub struct ModerationSettings {
     pub automod: bool,
     #[serde(default)]
     pub admins_exempt: bool,
+    /// Allow players without a moderator role to spectate, e.g. for events
+    #[serde(default)]
+    pub allow_spectators: bool,
 }
 
 impl ModerationSettings {
+    /// The censor of the words of [`Self::load_banned_words`]
+    pub fn load_censor(&self, data_dir: &Path) -> censor::Censor {
+        censor::Censor::Custom(self.load_banned_words(data_dir).into_iter().collect())
+    }
+
     pub fn load_banned_words(&self, data_dir: &Path) -> Vec<String> {
         let mut banned_words = Vec::new();
         for fname in self.banned_words_files.iter() {
@@ -121,6 +149,11 @@ impl ModerationSettings {
         }
         banned_words
     }
+
+    /// Whether a player with this role may join as spectator
+    pub fn may_spectate(&self, admin: Option<&Admin>) -> bool {
+        self.allow_spectators || admin.map_or(false, |admin| admin.0 >= AdminRole::Moderator)
+    }
 }
 
 impl Default for ModerationSettings {
@@ -129,6 +162,57 @@ impl Default for ModerationSettings {
             banned_words_files: Vec::new(),
             automod: false,
             admins_exempt: true,
+            allow_spectators: false,
+        }
+    }
+}
+
+/// Token bucket of a category of client messages
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Messages a client may send per second on average
+    pub per_second: f32,
+    /// Messages a client may send at once
+    pub burst: f32,
+}
+
+impl RateLimit {
+    const fn new(per_second: f32, burst: f32) -> Self { Self { per_second, burst } }
+}
+
+/// Limits of the messages clients may send, see [`crate::rate_limit`]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitSettings {
+    pub enabled: bool,
+    pub terrain_chunk: RateLimit,
+    pub lod_zone: RateLimit,
+    /// Placing and breaking blocks
+    pub block_edit: RateLimit,
+    pub command: RateLimit,
+    pub site_info: RateLimit,
+    /// Dropped messages after which the client is warned
+    pub warn_after: u32,
+    /// Dropped messages after which the client is kicked
+    pub kick_after: u32,
+    /// Dropped messages are forgiven when the client keeps to its limits for
+    /// this long
+    pub forgive_after: Duration,
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            // The client requests at most 2 chunks per tick
+            terrain_chunk: RateLimit::new(80.0, 160.0),
+            lod_zone: RateLimit::new(2.0, 10.0),
+            block_edit: RateLimit::new(30.0, 60.0),
+            command: RateLimit::new(5.0, 20.0),
+            site_info: RateLimit::new(5.0, 20.0),
+            warn_after: 20,
+            kick_after: 200,
+            forgive_after: Duration::from_secs(60),
         }
     }
 }
@@ -161,6 +245,9 @@ impl CalendarMode {
 pub struct Settings {
     pub gameserver_protocols: Vec<Protocol>,
     pub metrics_address: SocketAddr,
+    /// Where to answer the status queries of server lists over UDP, empty to
+    /// not answer them
+    pub query_addresses: Vec<SocketAddr>,
     pub auth_server_address: Option<String>,
     pub max_players: u16,
     pub world_seed: u32,
@@ -185,6 +272,8 @@ pub struct Settings {
     pub gameplay: GameplaySettings,
     #[serde(default)]
     pub moderation: ModerationSettings,
+    #[serde(default)]
+    pub rate_limits: RateLimitSettings,
 }
 
 impl Default for Settings {
@@ -199,6 +288,10 @@ impl Default for Settings {
                 },
             ],
             metrics_address: SocketAddr::from((Ipv4Addr::LOCALHOST, 14005)),
+            query_addresses: vec![
+                SocketAddr::from((Ipv4Addr::UNSPECIFIED, DEFAULT_QUERY_PORT)),
+                SocketAddr::from((Ipv6Addr::UNSPECIFIED, DEFAULT_QUERY_PORT)),
+            ],
             auth_server_address: Some("https://auth.veloren.net".into()),
             world_seed: DEFAULT_WORLD_SEED,
             server_name: "Veloren Server".into(),
@@ -214,6 +307,7 @@ impl Default for Settings {
             experimental_terrain_persistence: false,
             gameplay: GameplaySettings::default(),
             moderation: ModerationSettings::default(),
+            rate_limits: RateLimitSettings::default(),
         }
     }
 }
@@ -281,6 +375,7 @@ impl Settings {
                 Ipv4Addr::LOCALHOST,
                 pick_unused_port().expect("Failed to find unused port!"),
             )),
+            query_addresses: Vec::new(),
             auth_server_address: None,
             // If loading the default map file, make sure the seed is also default.
             world_seed: if load.map_file.is_some() {
@@ -297,6 +392,81 @@ impl Settings {
         }
     }
 
+    /// Read the settings file again to apply it with
+    /// [`Settings::apply_reloaded`]. Unlike [`Settings::load`], this doesn't
+    /// fall back to the default settings if the file is missing or invalid.
+    ///
+    /// path: Directory that contains the server config directory
+    pub fn reload(path: &Path) -> Result<Self, ReloadError> {
+        let file = fs::File::open(Self::get_settings_path(path)).map_err(ReloadError::Io)?;
+        ron::de::from_reader(file).map_err(ReloadError::Parse)
+    }
+
+    /// Apply the fields of the reloaded settings which can be changed while
+    /// the server is running. Returns the names of the changed fields which
+    /// are only read when the server starts, and so were left as they are.
+    pub fn apply_reloaded(&mut self, reloaded: Settings) -> Vec<&'static str> {
+        // Destructured so new fields have to be sorted into either category
+        let Settings {
+            gameserver_protocols,
+            metrics_address,
+            query_addresses,
+            auth_server_address,
+            max_players,
+            world_seed,
+            server_name,
+            start_time,
+            map_file,
+            max_view_distance,
+            max_player_group_size,
+            client_timeout,
+            spawn_town,
+            max_player_for_kill_broadcast,
+            calendar_mode,
+            experimental_terrain_persistence,
+            gameplay,
+            moderation,
+            rate_limits,
+        } = reloaded;
+
+        let needs_restart = [
+            (
+                "gameserver_protocols",
+                self.gameserver_protocols != gameserver_protocols,
+            ),
+            ("metrics_address", self.metrics_address != metrics_address),
+            ("query_addresses", self.query_addresses != query_addresses),
+            (
+                "auth_server_address",
+                self.auth_server_address != auth_server_address,
+            ),
+            ("world_seed", self.world_seed != world_seed),
+            ("start_time", self.start_time != start_time),
+            ("map_file", self.map_file != map_file),
+            ("spawn_town", self.spawn_town != spawn_town),
+            (
+                "experimental_terrain_persistence",
+                self.experimental_terrain_persistence != experimental_terrain_persistence,
+            ),
+        ];
+
+        self.max_players = max_players;
+        self.server_name = server_name;
+        self.max_view_distance = max_view_distance;
+        self.max_player_group_size = max_player_group_size;
+        self.client_timeout = client_timeout;
+        self.max_player_for_kill_broadcast = max_player_for_kill_broadcast;
+        self.calendar_mode = calendar_mode;
+        self.gameplay = gameplay;
+        self.moderation = moderation;
+        self.rate_limits = rate_limits;
+
+        needs_restart
+            .into_iter()
+            .filter_map(|(field, changed)| changed.then_some(field))
+            .collect()
+    }
+
     fn get_settings_path(path: &Path) -> PathBuf {
         let mut path = with_config_dir(path);
         path.push(SETTINGS_FILENAME);
@@ -304,6 +474,63 @@ impl Settings {
     }
 }
 
+/// Errors that can occur when reloading the settings file.
+#[derive(Debug)]
+pub enum ReloadError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReloadError::Io(e) => write!(f, "Failed to read the settings file: {}", e),
+            ReloadError::Parse(e) => write!(f, "Failed to parse the settings file: {}", e),
+        }
+    }
+}
+
+/// How often the modification time of the settings file is checked
+const SETTINGS_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Detects changes to the settings file by polling its modification time
+pub struct SettingsWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl SettingsWatcher {
+    /// path: Directory that contains the server config directory
+    pub fn new(path: &Path) -> Self {
+        let path = Settings::get_settings_path(path);
+        Self {
+            modified: modified_time(&path),
+            path,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Returns whether the settings file was modified since the last call
+    pub fn changed(&mut self) -> bool {
+        if self.last_poll.elapsed() < SETTINGS_POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+        let modified = modified_time(&self.path);
+        if modified != self.modified {
+            self.modified = modified;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
 pub fn with_config_dir(path: &Path) -> PathBuf {
     let mut path = PathBuf::from(path);
     path.push(CONFIG_DIR);
@@ -364,3 +591,43 @@ impl EditableSettings {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_reloaded() {
+        let mut settings = Settings::default();
+        let reloaded = Settings {
+            max_players: 10,
+            server_name: "Reloaded".into(),
+            world_seed: DEFAULT_WORLD_SEED.wrapping_add(1),
+            metrics_address: SocketAddr::from((Ipv4Addr::LOCALHOST, 14006)),
+            ..Settings::default()
+        };
+
+        let needs_restart = settings.apply_reloaded(reloaded);
+        assert_eq!(needs_restart, vec!["metrics_address", "world_seed"]);
+        assert_eq!(settings.max_players, 10);
+        assert_eq!(settings.server_name, "Reloaded");
+        // The fields only read when the server starts are left as they were
+        assert_eq!(settings.world_seed, DEFAULT_WORLD_SEED);
+        assert_eq!(
+            settings.metrics_address,
+            Settings::default().metrics_address
+        );
+    }
+
+    #[test]
+    fn may_spectate() {
+        let mut moderation = ModerationSettings::default();
+        assert!(!moderation.may_spectate(None));
+        assert!(moderation.may_spectate(Some(&Admin(AdminRole::Moderator))));
+        assert!(moderation.may_spectate(Some(&Admin(AdminRole::Admin))));
+
+        // Spectators can be allowed for events
+        moderation.allow_spectators = true;
+        assert!(moderation.may_spectate(None));
+    }
+}
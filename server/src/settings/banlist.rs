@@ -12,20 +12,21 @@ use serde::{Deserialize, Serialize};
 /// BanlistRaw, the TryFrom<BanlistRaw> for Banlist, the previously most recent
 /// module, and add a new module for the latest version!  Please respect the
 /// migration upgrade guarantee found in the parent module with any upgrade.
-pub use self::v1::*;
+pub use self::v2::*;
 
 /// Versioned settings files, one per version (v0 is only here as an example; we
 /// do not expect to see any actual v0 settings files).
 #[derive(Deserialize, Serialize)]
 pub enum BanlistRaw {
     V0(v0::Banlist),
-    V1(Banlist),
+    V1(v1::Banlist),
+    V2(Banlist),
 }
 
 impl From<Banlist> for BanlistRaw {
     fn from(value: Banlist) -> Self {
         // Replace variant with that of current latest version.
-        Self::V1(value)
+        Self::V2(value)
     }
 }
 
@@ -37,9 +38,10 @@ impl TryFrom<BanlistRaw> for (Version, Banlist) {
         Ok(match value {
             // Old versions
             V0(value) => (Version::Old, value.try_into()?),
+            V1(value) => (Version::Old, value.try_into()?),
             // Latest version (move to old section using the pattern of other old version when it
             // is no longer latest).
-            V1(mut value) => (value.validate()?, value),
+            V2(mut value) => (value.validate()?, value),
         })
     }
 }
@@ -58,6 +60,8 @@ impl EditableSetting for Banlist {
 pub enum BanKind {
     Ban,
     Unban,
+    Mute,
+    Unmute,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -69,6 +73,11 @@ pub enum BanErrorKind {
     },
     /// Cannot unban an already-unbanned user.
     AlreadyUnbanned,
+    /// A mute end date went past its start date.
+    InvalidMuteDateRange {
+        start_date: chrono::DateTime<chrono::Utc>,
+        end_date: chrono::DateTime<chrono::Utc>,
+    },
     /// Permission denied to perform requested action.
     PermissionDenied(BanKind),
 }
@@ -190,16 +199,20 @@ mod v0 {
 }
 
 mod v1 {
-    use super::{v0 as prev, BanError, BanErrorKind, BanKind, Final};
-    use crate::settings::editable::{EditableSetting, Error, Version};
+    use super::{
+        v0 as prev, v2 as next, BanError, BanErrorKind, BanKind, Final, MIGRATION_UPGRADE_GUARANTEE,
+    };
+    use crate::settings::editable::{EditableSetting, Version};
     use authc::Uuid;
     use chrono::{prelude::*, Utc};
     use common::comp::AdminRole;
-    use core::{mem, ops::Deref};
-    use hashbrown::{hash_map, HashMap};
+    use core::{
+        convert::{TryFrom, TryInto},
+        ops::Deref,
+    };
+    use hashbrown::HashMap;
     use serde::{Deserialize, Serialize};
     use tracing::warn;
-    /* use super::v2 as next; */
 
     /// Important: even if the role we are storing here appears to be identical
     /// to one used in another versioned store (like admin::Role), we *must*
@@ -304,7 +317,7 @@ mod v1 {
     impl BanRecord {
         /// Returns true if this record represents an expired ban, false
         /// otherwise.
-        fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        pub(super) fn is_expired(&self, now: DateTime<Utc>) -> bool {
             match &self.action {
                 BanAction::Ban(ban) => ban.is_expired(now),
                 BanAction::Unban(_) => true,
@@ -425,7 +438,7 @@ mod v1 {
         /// If we were invalid, returns an error.  Otherwise, returns Ok(v),
         /// where v is Latest if the hint bit was modified, Old
         /// otherwise.
-        fn validate(
+        pub(super) fn validate(
             &mut self,
             now: DateTime<Utc>,
             uuid: Uuid,
@@ -475,6 +488,207 @@ mod v1 {
         fn deref(&self) -> &Self::Target { &self.0 }
     }
 
+    impl Banlist {
+        /// One-off migration from the previous version.  This must be
+        /// guaranteed to produce a valid settings file as long as it is
+        /// called with a valid settings file from the previous version.
+        pub(super) fn migrate(prev: prev::Banlist) -> Self {
+            // The ban start date for migrations from legacy is the current one; we could
+            // record that they actually have an unknown start date, but this
+            // would just complicate the format.
+            let date = Utc::now();
+            Banlist(
+                prev.0
+                    .into_iter()
+                    .map(
+                        |(
+                            uid,
+                            prev::BanRecord {
+                                username_when_banned,
+                                reason,
+                            },
+                        )| {
+                            (uid, BanEntry {
+                                current: BanRecord {
+                                    username_when_performed: username_when_banned,
+                                    // We only recorded unbans pre-migration.
+                                    action: BanAction::Ban(Ban {
+                                        reason,
+                                        // We don't know who banned this user pre-migration.
+                                        info: None,
+                                        // All bans pre-migration are of unlimited duration.
+                                        end_date: None,
+                                    }),
+                                    date,
+                                },
+                                // Old bans never expire, so set the expiration hint to false.
+                                expired: false,
+                                // There is no known ban history yet.
+                                history: Vec::new(),
+                            })
+                        },
+                    )
+                    .collect(),
+            )
+        }
+
+        /// Perform any needed validation on this banlist that can't be done
+        /// using parsing.
+        ///
+        /// The returned version being "Old" indicates the loaded setting has
+        /// been modified during validation (this is why validate takes
+        /// `&mut self`).
+        pub(super) fn validate(&mut self) -> Result<Version, <Final as EditableSetting>::Error> {
+            let mut version = Version::Latest;
+            let now = Utc::now();
+            for (&uuid, value) in self.0.iter_mut() {
+                if matches!(value.validate(now, uuid)?, Version::Old) {
+                    // Update detected.
+                    version = Version::Old;
+                }
+            }
+            Ok(version)
+        }
+    }
+
+    /// Pretty much every TryFrom implementation except that of the very last
+    /// version should look exactly like this.
+    impl TryFrom<Banlist> for Final {
+        type Error = <Final as EditableSetting>::Error;
+
+        #[allow(clippy::useless_conversion)]
+        fn try_from(mut value: Banlist) -> Result<Final, Self::Error> {
+            value.validate()?;
+            Ok(next::Banlist::migrate(value)
+                .try_into()
+                .expect(MIGRATION_UPGRADE_GUARANTEE))
+        }
+    }
+}
+
+mod v2 {
+    use super::{v1 as prev, BanError, BanErrorKind, BanKind, Final};
+    use crate::settings::editable::{EditableSetting, Error, Version};
+    use authc::Uuid;
+    use chrono::{prelude::*, Utc};
+    use common::{cmd::MUTE_CHATS, comp::ChatType};
+    use core::{mem, ops::Deref, str::FromStr};
+    use hashbrown::{hash_map, HashMap};
+    use serde::{Deserialize, Serialize};
+    /* use super::v3 as next; */
+
+    // The ban records did not change from the previous version, and old versions
+    // are never modified, so they can be reused as is.
+    pub use prev::{Ban, BanAction, BanEntry, BanInfo, BanRecord, Role};
+
+    /// The chat a mute applies to.
+    ///
+    /// Like [`Role`], *never remove variants from this enum* without bumping
+    /// the version.
+    #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+    pub enum MuteChat {
+        /// Every chat, including tells
+        All,
+        Say,
+        Region,
+        World,
+        Faction,
+        Group,
+        Tell,
+    }
+
+    impl MuteChat {
+        /// Whether a message sent with `chat_type` is affected by a mute of
+        /// this chat.
+        pub fn applies_to<G>(&self, chat_type: &ChatType<G>) -> bool {
+            match self {
+                MuteChat::All => true,
+                MuteChat::Say => matches!(chat_type, ChatType::Say(_)),
+                MuteChat::Region => matches!(chat_type, ChatType::Region(_)),
+                MuteChat::World => matches!(chat_type, ChatType::World(_)),
+                MuteChat::Faction => matches!(chat_type, ChatType::Faction(_, _)),
+                MuteChat::Group => matches!(chat_type, ChatType::Group(_, _)),
+                MuteChat::Tell => matches!(chat_type, ChatType::Tell(_, _)),
+            }
+        }
+    }
+
+    impl FromStr for MuteChat {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "all" => Ok(Self::All),
+                "say" => Ok(Self::Say),
+                "region" => Ok(Self::Region),
+                "world" => Ok(Self::World),
+                "faction" => Ok(Self::Faction),
+                "group" => Ok(Self::Group),
+                "tell" => Ok(Self::Tell),
+                _ => Err(format!(
+                    "Unknown chat '{}', expected one of: {}",
+                    s,
+                    MUTE_CHATS.join(", ")
+                )),
+            }
+        }
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct Mute {
+        pub chat: MuteChat,
+        pub reason: String,
+        pub info: BanInfo,
+        /// Date when the user was muted.
+        pub date: DateTime<Utc>,
+        /// NOTE: Should always be higher than date, if present!
+        pub end_date: Option<DateTime<Utc>>,
+    }
+
+    impl Mute {
+        /// Returns true if the mute is expired, false otherwise.
+        pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+            self.end_date.map_or(false, |end_date| end_date <= now)
+        }
+
+        fn validate(&self) -> Result<(), BanErrorKind> {
+            match self.end_date {
+                Some(end_date) if self.date > end_date => Err(BanErrorKind::InvalidMuteDateRange {
+                    start_date: self.date,
+                    end_date,
+                }),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[derive(Clone, Deserialize, Serialize)]
+    pub struct MuteEntry {
+        /// Username of the user when they were last muted.
+        pub username_when_muted: String,
+        /// The mutes of this user, at most one per chat.  Expired mutes are
+        /// removed when the file is loaded or edited.
+        pub mutes: Vec<Mute>,
+    }
+
+    pub enum MuteAction {
+        Mute(Mute),
+        /// Lift the mute of the given chat, or all the mutes if `None`
+        Unmute(Option<MuteChat>, BanInfo),
+    }
+
+    #[derive(Clone, Deserialize, Serialize, Default)]
+    pub struct Banlist {
+        pub(super) bans: HashMap<Uuid, BanEntry>,
+        pub(super) mutes: HashMap<Uuid, MuteEntry>,
+    }
+
+    impl Deref for Banlist {
+        type Target = HashMap<Uuid, BanEntry>;
+
+        fn deref(&self) -> &Self::Target { &self.bans }
+    }
+
     impl Banlist {
         /// Attempt to perform the ban action `action` for the user with UUID
         /// `uuid` and username `username`, starting from time `now`
@@ -539,7 +753,7 @@ mod v1 {
             // Perform an atomic edit.
             Some(
                 self.edit(data_dir.as_ref(), |banlist| {
-                    match banlist.0.entry(uuid) {
+                    match banlist.bans.entry(uuid) {
                         hash_map::Entry::Vacant(v) => {
                             // If this is an unban, it will have no effect, so return early.
                             if matches!(ban_record.action, BanAction::Unban(_)) {
@@ -584,50 +798,115 @@ mod v1 {
                 .1,
             )
         }
-    }
 
-    impl Banlist {
+        pub fn mutes(&self) -> &HashMap<Uuid, MuteEntry> { &self.mutes }
+
+        /// The mute in effect for the user with UUID `uuid` for messages sent
+        /// with `chat_type`, if any.
+        pub fn mute<G>(
+            &self,
+            uuid: &Uuid,
+            chat_type: &ChatType<G>,
+            now: DateTime<Utc>,
+        ) -> Option<&Mute> {
+            self.mutes
+                .get(uuid)?
+                .mutes
+                .iter()
+                .find(|mute| !mute.is_expired(now) && mute.chat.applies_to(chat_type))
+        }
+
+        /// Attempt to perform the mute action `action` for the user with UUID
+        /// `uuid` and username `username`, with a settings file maintained
+        /// at path root `data_dir`.
+        ///
+        /// A mute replaces the mute of the same chat, if any, but only when
+        /// `overwrite` is set.  Mutes can only be replaced or lifted by users
+        /// with at least the role of the muting party.
+        ///
+        /// Returns None if the action would have no effect, otherwise the
+        /// result works like that of [`Banlist::ban_action`].
+        #[must_use]
+        pub fn mute_action(
+            &mut self,
+            data_dir: &std::path::Path,
+            now: DateTime<Utc>,
+            uuid: Uuid,
+            username: String,
+            action: MuteAction,
+            overwrite: bool,
+        ) -> Option<Result<(), Error<Final>>> {
+            let active = self
+                .mutes
+                .get(&uuid)
+                .map_or(&[][..], |entry| &entry.mutes[..])
+                .iter()
+                .filter(|mute| !mute.is_expired(now));
+            let (affected, role, kind) = match &action {
+                MuteAction::Mute(mute) => (
+                    active.filter(|m| m.chat == mute.chat).collect::<Vec<_>>(),
+                    mute.info.performed_by_role,
+                    BanKind::Mute,
+                ),
+                MuteAction::Unmute(chat, info) => (
+                    active
+                        .filter(|m| chat.map_or(true, |chat| m.chat == chat))
+                        .collect::<Vec<_>>(),
+                    info.performed_by_role,
+                    BanKind::Unmute,
+                ),
+            };
+            // Muting an already muted user or unmuting a user who isn't muted has no
+            // effect.
+            let no_effect = match action {
+                MuteAction::Mute(_) => !affected.is_empty() && !overwrite,
+                MuteAction::Unmute(..) => affected.is_empty(),
+            };
+            if no_effect {
+                return None;
+            }
+            if affected
+                .iter()
+                .any(|mute| mute.info.performed_by_role > role)
+            {
+                return Some(Err(Error::Integrity(BanError {
+                    kind: BanErrorKind::PermissionDenied(kind),
+                    uuid,
+                    username,
+                })));
+            }
+
+            Some(
+                self.edit(data_dir.as_ref(), |banlist| {
+                    let entry = banlist.mutes.entry(uuid).or_insert_with(|| MuteEntry {
+                        username_when_muted: username.clone(),
+                        mutes: Vec::new(),
+                    });
+                    match action {
+                        MuteAction::Mute(mute) => {
+                            entry.username_when_muted = username;
+                            entry.mutes.retain(|m| m.chat != mute.chat);
+                            entry.mutes.push(mute);
+                        },
+                        MuteAction::Unmute(chat, _) => entry
+                            .mutes
+                            .retain(|m| chat.map_or(false, |chat| m.chat != chat)),
+                    }
+                    Some(())
+                })?
+                .1,
+            )
+        }
+
         /// One-off migration from the previous version.  This must be
         /// guaranteed to produce a valid settings file as long as it is
         /// called with a valid settings file from the previous version.
         pub(super) fn migrate(prev: prev::Banlist) -> Self {
-            // The ban start date for migrations from legacy is the current one; we could
-            // record that they actually have an unknown start date, but this
-            // would just complicate the format.
-            let date = Utc::now();
-            Banlist(
-                prev.0
-                    .into_iter()
-                    .map(
-                        |(
-                            uid,
-                            prev::BanRecord {
-                                username_when_banned,
-                                reason,
-                            },
-                        )| {
-                            (uid, BanEntry {
-                                current: BanRecord {
-                                    username_when_performed: username_when_banned,
-                                    // We only recorded unbans pre-migration.
-                                    action: BanAction::Ban(Ban {
-                                        reason,
-                                        // We don't know who banned this user pre-migration.
-                                        info: None,
-                                        // All bans pre-migration are of unlimited duration.
-                                        end_date: None,
-                                    }),
-                                    date,
-                                },
-                                // Old bans never expire, so set the expiration hint to false.
-                                expired: false,
-                                // There is no known ban history yet.
-                                history: Vec::new(),
-                            })
-                        },
-                    )
-                    .collect(),
-            )
+            // There were no mutes in the previous version.
+            Banlist {
+                bans: prev.0,
+                mutes: HashMap::new(),
+            }
         }
 
         /// Perform any needed validation on this banlist that can't be done
@@ -639,12 +918,33 @@ mod v1 {
         pub(super) fn validate(&mut self) -> Result<Version, <Final as EditableSetting>::Error> {
             let mut version = Version::Latest;
             let now = Utc::now();
-            for (&uuid, value) in self.0.iter_mut() {
+            for (&uuid, value) in self.bans.iter_mut() {
                 if matches!(value.validate(now, uuid)?, Version::Old) {
                     // Update detected.
                     version = Version::Old;
                 }
             }
+            for (&uuid, entry) in self.mutes.iter_mut() {
+                for mute in &entry.mutes {
+                    mute.validate().map_err(|kind| BanError {
+                        kind,
+                        uuid,
+                        username: entry.username_when_muted.clone(),
+                    })?;
+                }
+                // Unlike bans, there is no history of mutes (the audit log has it), so
+                // expired mutes are just removed.
+                let len = entry.mutes.len();
+                entry.mutes.retain(|mute| !mute.is_expired(now));
+                if entry.mutes.len() != len {
+                    version = Version::Old;
+                }
+            }
+            let len = self.mutes.len();
+            self.mutes.retain(|_, entry| !entry.mutes.is_empty());
+            if self.mutes.len() != len {
+                version = Version::Old;
+            }
             Ok(version)
         }
     }
@@ -661,3 +961,121 @@ mod v1 {
         }
     } */
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use common::{comp::ChatType, uid::Uid};
+
+    fn moderator() -> BanInfo {
+        BanInfo {
+            performed_by: Uuid::from_u128(2),
+            performed_by_username: "moderator".to_owned(),
+            performed_by_role: Role::Moderator,
+        }
+    }
+
+    #[test]
+    fn migrate_v1() {
+        let raw = ron::from_str::<BanlistRaw>(
+            r#"V1({
+                "00000000-0000-0000-0000-000000000001": (
+                    current: (
+                        username_when_performed: "griefer",
+                        action: Ban((
+                            reason: "griefing",
+                            info: Some((
+                                performed_by: "00000000-0000-0000-0000-000000000002",
+                                performed_by_username: "moderator",
+                                performed_by_role: Moderator,
+                            )),
+                            end_date: None,
+                        )),
+                        date: "2022-01-01T00:00:00Z",
+                    ),
+                    history: [],
+                    expired: false,
+                ),
+            })"#,
+        )
+        .expect("Failed to parse the v1 banlist");
+
+        let (version, banlist): (Version, Banlist) = raw.try_into().unwrap();
+        assert!(matches!(version, Version::Old));
+        assert!(banlist.mutes().is_empty());
+        let entry = &banlist[&Uuid::from_u128(1)];
+        assert_eq!(entry.current.username_when_performed, "griefer");
+        assert_eq!(
+            entry.current.action.ban().map(|ban| ban.reason.as_str()),
+            Some("griefing")
+        );
+    }
+
+    #[test]
+    fn mute_expiry() {
+        let data_dir = std::env::temp_dir().join(format!(
+            "veloren-banlist-mute-expiry-{}",
+            std::process::id()
+        ));
+        let uuid = Uuid::from_u128(1);
+        let say = ChatType::<String>::Say(Uid(1));
+        let now = Utc::now();
+        let later = now + Duration::hours(2);
+        let mute = |chat| {
+            MuteAction::Mute(Mute {
+                chat,
+                reason: "spam".to_owned(),
+                info: moderator(),
+                date: now,
+                end_date: Some(now + Duration::hours(1)),
+            })
+        };
+
+        let mut banlist = Banlist::default();
+        let mute_action = |banlist: &mut Banlist, now, overwrite| {
+            banlist.mute_action(
+                &data_dir,
+                now,
+                uuid,
+                "spammer".to_owned(),
+                mute(MuteChat::Say),
+                overwrite,
+            )
+        };
+        assert!(matches!(
+            mute_action(&mut banlist, now, false),
+            Some(Ok(()))
+        ));
+        assert!(banlist.mute(&uuid, &say, now).is_some());
+        assert!(banlist
+            .mute(&uuid, &ChatType::<String>::World(Uid(1)), now)
+            .is_none());
+        // Muting again only replaces the mute when overwriting
+        assert!(mute_action(&mut banlist, now, false).is_none());
+        assert!(matches!(mute_action(&mut banlist, now, true), Some(Ok(()))));
+
+        // Once expired, the mute no longer applies and can be replaced
+        assert!(banlist.mute(&uuid, &say, later).is_none());
+        assert!(matches!(
+            mute_action(&mut banlist, later, false),
+            Some(Ok(()))
+        ));
+        let _ = std::fs::remove_dir_all(&data_dir);
+
+        // Expired mutes are removed when the banlist is validated
+        let mut banlist = Banlist::default();
+        banlist.mutes.insert(uuid, MuteEntry {
+            username_when_muted: "spammer".to_owned(),
+            mutes: vec![Mute {
+                chat: MuteChat::All,
+                reason: String::new(),
+                info: moderator(),
+                date: now - Duration::hours(2),
+                end_date: Some(now - Duration::hours(1)),
+            }],
+        });
+        assert!(matches!(banlist.validate(), Ok(Version::Old)));
+        assert!(banlist.mutes().is_empty());
+    }
+}
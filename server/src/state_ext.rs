@@ -1,14 +1,18 @@
 use crate::{
-    automod::AutoMod,
+    automod::{ActionErr, AutoMod},
     client::Client,
     events::{self, update_map_markers},
-    persistence::PersistedComponents,
+    persistence::{
+        audit_log::{AuditEntry, AuditLogger, AUTOMOD_ACTOR},
+        PersistedComponents,
+    },
     pet::restore_pet,
     presence::{Presence, RepositionOnChunkLoad},
-    settings::Settings,
+    settings::{EditableSettings, Settings},
     sys::sentinel::DeletedEntities,
     wiring, BattleModeBuffer, SpawnPoint,
 };
+use chrono::Utc;
 use common::{
     calendar::Calendar,
     character::CharacterId,
@@ -32,6 +36,8 @@ use common_net::{
     msg::{CharacterInfo, PlayerListUpdate, PresenceKind, ServerGeneral},
     sync::WorldSyncExt,
 };
+#[cfg(feature = "plugins")]
+use common_state::plugin::{memory_manager::EcsWorld, PluginMgr};
 use common_state::State;
 use rand::prelude::*;
 use specs::{
@@ -139,6 +145,17 @@ pub trait StateExt {
         &mut self,
         entity: EcsEntity,
     ) -> Result<(), specs::error::WrongGeneration>;
+    /// Execute a plugin event, returning the responses of the plugins which
+    /// handle it. If a plugin fails the error is logged and no responses are
+    /// returned.
+    ///
+    /// NOTE: The components accessible by plugins must not be borrowed
+    /// mutably while calling this.
+    #[cfg(feature = "plugins")]
+    fn execute_plugin_event<T: plugin_api::Event>(&self, event: &T) -> Vec<T::Response>;
+    /// Call `on_tick` and the timers of plugins which ran out
+    #[cfg(feature = "plugins")]
+    fn tick_plugins(&self, dt: f32);
 }
 
 impl StateExt for State {
@@ -602,6 +619,8 @@ impl StateExt for State {
             pets,
             active_abilities,
             map_marker,
+            vitals,
+            vault,
         } = components;
 
         if let Some(player_uid) = self.read_component_copied::<Uid>(entity) {
@@ -628,8 +647,25 @@ impl StateExt for State {
                     .skill_level(Skill::General(GeneralSkill::EnergyIncrease))
                     .unwrap_or(0),
             );
-            self.write_component_ignore_entity_dead(entity, comp::Health::new(body, health_level));
-            self.write_component_ignore_entity_dead(entity, comp::Energy::new(body, energy_level));
+            let mut health = comp::Health::new(body, health_level);
+            if let Some(fraction) = vitals.health {
+                // Don't let the character die from rounding
+                let amount = (fraction * health.maximum()).max(1.0) - health.maximum();
+                health.change_by(comp::HealthChange {
+                    amount,
+                    by: None,
+                    cause: None,
+                    time: *self.ecs().read_resource::<Time>(),
+                    crit: false,
+                    instance: rand::random(),
+                });
+            }
+            let mut energy = comp::Energy::new(body, energy_level);
+            if let Some(fraction) = vitals.energy {
+                energy.change_by((fraction - 1.0) * energy.maximum());
+            }
+            self.write_component_ignore_entity_dead(entity, health);
+            self.write_component_ignore_entity_dead(entity, energy);
             self.write_component_ignore_entity_dead(entity, Poise::new(body));
             self.write_component_ignore_entity_dead(entity, stats);
             self.write_component_ignore_entity_dead(entity, active_abilities);
@@ -639,8 +675,24 @@ impl StateExt for State {
                 entity,
                 comp::InventoryUpdate::new(comp::InventoryUpdateEvent::default()),
             );
+            self.write_component_ignore_entity_dead(entity, vault);
 
-            if let Some(waypoint) = waypoint {
+            let logout_pos = vitals.position.filter(|_| {
+                self.ecs()
+                    .read_resource::<Settings>()
+                    .gameplay
+                    .restore_logout_position
+            });
+            if let Some(pos) = logout_pos {
+                // The character stood on the ground there, so unlike waypoints it isn't
+                // moved up to the surface
+                if let Some(waypoint) = waypoint {
+                    self.write_component_ignore_entity_dead(entity, waypoint);
+                }
+                self.write_component_ignore_entity_dead(entity, pos);
+                self.write_component_ignore_entity_dead(entity, comp::Vel(Vec3::zero()));
+                self.write_component_ignore_entity_dead(entity, comp::ForceUpdate::forced());
+            } else if let Some(waypoint) = waypoint {
                 self.write_component_ignore_entity_dead(entity, RepositionOnChunkLoad);
                 self.write_component_ignore_entity_dead(entity, waypoint);
                 self.write_component_ignore_entity_dead(entity, comp::Pos(waypoint.get_pos()));
@@ -648,6 +700,12 @@ impl StateExt for State {
                 self.write_component_ignore_entity_dead(entity, comp::ForceUpdate::forced());
             }
 
+            if let Some(mut buffs) = self.ecs().write_storage::<comp::Buffs>().get_mut(entity) {
+                for buff in vitals.buffs {
+                    buffs.insert(buff);
+                }
+            }
+
             if let Some(map_marker) = map_marker {
                 self.write_component_ignore_entity_dead(entity, map_marker);
             }
@@ -730,6 +788,25 @@ impl StateExt for State {
         let Some(client) = client.get(entity) else { return true };
         let Some(player) = player.get(entity) else { return true };
 
+        let editable_settings = self.ecs().read_resource::<EditableSettings>();
+        if let Some(mute) = editable_settings
+            .banlist
+            .mute(&player.uuid(), chat_type, Utc::now())
+        {
+            let _ = client.send(ServerGeneral::server_msg(
+                ChatType::CommandError,
+                match mute.end_date {
+                    Some(end_date) => format!(
+                        "You are muted until {} UTC: {}",
+                        end_date.format("%Y-%m-%d %H:%M"),
+                        mute.reason
+                    ),
+                    None => format!("You are muted: {}", mute.reason),
+                },
+            ));
+            return false;
+        }
+
         match automod.validate_chat_msg(
             player.uuid(),
             self.ecs()
@@ -750,6 +827,18 @@ impl StateExt for State {
                 true
             },
             Err(err) => {
+                if let ActionErr::SpamMuteStarted(dur) = err {
+                    self.ecs()
+                        .read_resource::<AuditLogger>()
+                        .record(AuditEntry::new(
+                            AUTOMOD_ACTOR.to_owned(),
+                            None,
+                            Some(player.alias.clone()),
+                            "spam_mute".to_owned(),
+                            vec![format!("{}s", dur.as_secs())],
+                            None,
+                        ));
+                }
                 let _ = client.send(ServerGeneral::server_msg(
                     ChatType::CommandError,
                     format!("{}", err),
@@ -1087,6 +1176,46 @@ impl StateExt for State {
         }
         res
     }
+
+    #[cfg(feature = "plugins")]
+    fn execute_plugin_event<T: plugin_api::Event>(&self, event: &T) -> Vec<T::Response> {
+        let ecs = self.ecs();
+        with_ecs_world(ecs, |ecs_world| {
+            ecs.read_resource::<PluginMgr>()
+                .execute_event(ecs_world, event)
+                .unwrap_or_else(|e| {
+                    tracing::error!(?e, "Failed to execute plugin event");
+                    Vec::new()
+                })
+        })
+    }
+
+    #[cfg(feature = "plugins")]
+    fn tick_plugins(&self, dt: f32) {
+        let ecs = self.ecs();
+        let time = ecs.read_resource::<Time>().0;
+        with_ecs_world(ecs, |ecs_world| {
+            ecs.read_resource::<PluginMgr>().tick(ecs_world, time, dt)
+        })
+    }
+}
+
+/// Run `f` with the view of the ECS given to plugins
+#[cfg(feature = "plugins")]
+pub(crate) fn with_ecs_world<R>(ecs: &specs::World, f: impl FnOnce(&EcsWorld) -> R) -> R {
+    let ecs_world = EcsWorld {
+        entities: &ecs.entities(),
+        health: ecs.read_component().into(),
+        uid: ecs.read_component().into(),
+        uid_allocator: &ecs.read_resource::<UidAllocator>().into(),
+        player: ecs.read_component().into(),
+        pos: ecs.read_component().into(),
+        inventory: ecs.read_component().into(),
+        stats: ecs.read_component().into(),
+        energy: ecs.read_component().into(),
+        group: ecs.read_component().into(),
+    };
+    f(&ecs_world)
 }
 
 fn send_to_group(g: &Group, ecs: &specs::World, msg: &comp::ChatMsg) {
@@ -1,16 +1,21 @@
 use super::sentinel::{DeletedEntities, TrackedStorages, UpdateTrackers};
 use crate::{
     client::Client,
+    interest::{Interest, InterestBudgets},
+    metrics::NetworkRequestMetrics,
     presence::{Presence, RegionSubscription},
     Tick,
 };
 use common::{
     calendar::Calendar,
-    comp::{Collider, ForceUpdate, InventoryUpdate, Last, Ori, Player, Pos, Vel},
+    comp::{
+        group, Agent, Collider, ForceUpdate, InventoryUpdate, InventoryUpdateEvent, Last, Ori,
+        Player, Pos, Vel,
+    },
     event::EventBus,
     outcome::Outcome,
     region::{Event as RegionEvent, RegionMap},
-    resources::{PlayerPhysicsSettings, TimeOfDay},
+    resources::{DeltaTime, PlayerPhysicsSettings, TimeOfDay},
     terrain::TerrainChunkSize,
     uid::Uid,
     vol::RectVolSize,
@@ -30,12 +35,14 @@ impl<'a> System<'a> for Sys {
     type SystemData = (
         Entities<'a>,
         Read<'a, Tick>,
+        Read<'a, DeltaTime>,
         Read<'a, PlayerPhysicsSettings>,
         TrackedStorages<'a>,
         ReadExpect<'a, TimeOfDay>,
         ReadExpect<'a, Calendar>,
         ReadExpect<'a, RegionMap>,
         ReadExpect<'a, UpdateTrackers>,
+        ReadExpect<'a, NetworkRequestMetrics>,
         ReadStorage<'a, Pos>,
         ReadStorage<'a, Vel>,
         ReadStorage<'a, Ori>,
@@ -43,12 +50,14 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Player>,
         ReadStorage<'a, Presence>,
         ReadStorage<'a, Client>,
+        ReadStorage<'a, Agent>,
         WriteStorage<'a, Last<Pos>>,
         WriteStorage<'a, Last<Vel>>,
         WriteStorage<'a, Last<Ori>>,
         WriteStorage<'a, ForceUpdate>,
         WriteStorage<'a, InventoryUpdate>,
         Write<'a, DeletedEntities>,
+        Write<'a, InterestBudgets>,
         Read<'a, EventBus<Outcome>>,
     );
 
@@ -61,12 +70,14 @@ impl<'a> System<'a> for Sys {
         (
             entities,
             tick,
+            dt,
             player_physics_settings,
             tracked_storages,
             time_of_day,
             calendar,
             region_map,
             trackers,
+            network_metrics,
             positions,
             velocities,
             orientations,
@@ -74,12 +85,14 @@ impl<'a> System<'a> for Sys {
             players,
             presences,
             clients,
+            agents,
             mut last_pos,
             mut last_vel,
             mut last_ori,
             mut force_updates,
             mut inventory_updates,
             mut deleted_entities,
+            mut interest_budgets,
             outcomes,
         ): Self::SystemData,
     ) {
@@ -91,6 +104,7 @@ impl<'a> System<'a> for Sys {
         let colliders = &tracked_storages.collider;
         let inventories = &tracked_storages.inventory;
         let is_rider = &tracked_storages.is_rider;
+        let groups = &tracked_storages.group;
 
         // To send entity updates
         // 1. Iterate through regions
@@ -119,6 +133,16 @@ impl<'a> System<'a> for Sys {
             .map(|(key, region)| (key, region, deleted_entities.take_deleted_in_region(key)))
             .collect::<Vec<_>>();
 
+        // Fit the physics updates of the last tick into the bandwidth of each client
+        interest_budgets.update(
+            (&entities, &clients).join().map(|(entity, client)| {
+                let bandwidth = client.participant.as_ref().map_or(0.0, |p| p.bandwidth());
+                (entity, bandwidth)
+            }),
+            dt.0,
+        );
+        let interest_budgets = &*interest_budgets;
+
         use rayon::iter::{IntoParallelIterator, ParallelIterator};
         job.cpu_stats.measure(common_ecs::ParMode::Rayon);
         common_base::prof_span!(guard, "regions");
@@ -230,6 +254,11 @@ impl<'a> System<'a> for Sys {
 
                 for (client, _, client_entity, client_pos) in &mut subscribers {
                     let mut comp_sync_package = CompSyncPackage::new();
+                    let pressure = interest_budgets.pressure(*client_entity);
+                    let client_group = groups
+                        .get(*client_entity)
+                        .filter(|g| **g != group::ENEMY && **g != group::NPC);
+                    let mut updates = 0;
 
                     for (_, entity, &uid, (&pos, last_pos), vel, ori, force_update, collider) in (
                         region.entities(),
@@ -263,33 +292,26 @@ impl<'a> System<'a> for Sys {
                             // for these where we can.
                             true
                         } else {
-                            // Throttle update rates for all other entities based on distance to
-                            // client
-                            let distance_sq = client_pos.0.distance_squared(pos.0);
-                            let id_staggered_tick = tick + entity.id() as u64;
-
-                            // More entities farther away so checks start there
-                            if distance_sq > 500.0f32.powi(2) {
-                                id_staggered_tick % 32 == 0
-                            } else if distance_sq > 300.0f32.powi(2) {
-                                id_staggered_tick % 16 == 0
-                            } else if distance_sq > 200.0f32.powi(2) {
-                                id_staggered_tick % 8 == 0
-                            } else if distance_sq > 120.0f32.powi(2) {
-                                id_staggered_tick % 6 == 0
-                            } else if distance_sq > 64.0f32.powi(2) {
-                                id_staggered_tick % 3 == 0
-                            } else if distance_sq > 24.0f32.powi(2) {
-                                id_staggered_tick % 2 == 0
-                            } else {
-                                true
-                            }
+                            // Throttle update rates for all other entities based on how
+                            // interesting they are to the client
+                            let interest = Interest {
+                                distance_sq: client_pos.0.distance_squared(pos.0),
+                                speed_sq: vel.map_or(0.0, |(v, _)| v.0.magnitude_squared()),
+                                relevant: client_group.map_or(false, |client_group| {
+                                    groups.get(entity) == Some(client_group)
+                                }) || agents
+                                    .get(entity)
+                                    .and_then(|agent| agent.target.as_ref())
+                                    .map_or(false, |target| target.target == *client_entity),
+                            };
+                            (tick + entity.id() as u64) % interest.interval(pressure) == 0
                         };
 
                         if last_pos.is_none() {
                             comp_sync_package.comp_inserted(uid, pos);
                         } else if send_now {
                             comp_sync_package.comp_modified(uid, pos);
+                            updates += 1;
                         }
 
                         if let Some((v, last_vel)) = vel {
@@ -309,6 +331,7 @@ impl<'a> System<'a> for Sys {
                         }
                     }
 
+                    interest_budgets.count(*client_entity, updates);
                     client.send_fallible(ServerGeneral::CompSync(
                         comp_sync_package,
                         force_updates.get(*client_entity).map_or(0, |f| f.counter()),
@@ -357,24 +380,68 @@ impl<'a> System<'a> for Sys {
 
         // TODO: Sync clients that don't have a position?
 
-        // Sync inventories
-        for (inventory, update, client) in (inventories, &inventory_updates, &clients).join() {
-            client.send_fallible(ServerGeneral::InventoryUpdate(
-                inventory.clone(),
-                update.event(),
-            ));
+        // Sync inventories, as delta to what the client acknowledged
+        for (entity, inventory, client) in (&entities, inventories, &clients).join() {
+            let mut inventory_delta = client.inventory_delta.lock().unwrap();
+            let event = match inventory_updates.get(entity) {
+                Some(update) => update.event(),
+                None if inventory_delta.needs_resync() => {
+                    network_metrics
+                        .delta_sync_resyncs
+                        .with_label_values(&["inventory"])
+                        .inc();
+                    InventoryUpdateEvent::Init
+                },
+                None => continue,
+            };
+            let (packet, full_size) = inventory_delta.encode(inventory);
+            drop(inventory_delta);
+
+            let encoding = if packet.is_full() { "full" } else { "delta" };
+            network_metrics
+                .delta_sync_bytes
+                .with_label_values(&["inventory", encoding])
+                .inc_by(packet.size() as u64);
+            network_metrics
+                .delta_sync_bytes_saved
+                .with_label_values(&["inventory"])
+                .inc_by(full_size.saturating_sub(packet.size()) as u64);
+            client.send_fallible(ServerGeneral::InventoryUpdate(packet, event));
         }
 
-        // Sync components that are only synced for the client's own entity.
-        for (entity, client) in (&entities, &clients).join() {
-            let comp_sync_package =
-                trackers.create_sync_from_client_package(&tracked_storages, entity);
-            if !comp_sync_package.is_empty() {
-                client.send_fallible(ServerGeneral::CompSync(
-                    comp_sync_package,
-                    force_updates.get(entity).map_or(0, |f| f.counter()),
-                ));
+        // Sync components that are only synced for the client's own entity, as delta
+        // to what the client acknowledged
+        for (entity, uid, client) in (&entities, uids, &clients).join() {
+            let mut comp_sync_delta = client.comp_sync_delta.lock().unwrap();
+            let comp_sync_package = if comp_sync_delta.needs_resync() {
+                network_metrics
+                    .delta_sync_resyncs
+                    .with_label_values(&["comp_sync"])
+                    .inc();
+                // Replaces all the components the client has
+                tracked_storages.create_sync_from_client_entity_switch(*uid, *uid, entity)
+            } else {
+                trackers.create_sync_from_client_package(&tracked_storages, entity)
+            };
+            if comp_sync_package.is_empty() {
+                continue;
             }
+            let (packet, full_size) = comp_sync_delta.encode(&comp_sync_package);
+            drop(comp_sync_delta);
+
+            let encoding = if packet.is_full() { "full" } else { "delta" };
+            network_metrics
+                .delta_sync_bytes
+                .with_label_values(&["comp_sync", encoding])
+                .inc_by(packet.size() as u64);
+            network_metrics
+                .delta_sync_bytes_saved
+                .with_label_values(&["comp_sync"])
+                .inc_by(full_size.saturating_sub(packet.size()) as u64);
+            client.send_fallible(ServerGeneral::CompSyncDelta(
+                packet,
+                force_updates.get(entity).map_or(0, |f| f.counter()),
+            ));
         }
 
         // Consume/clear the current outcomes and convert them to a vec
@@ -4,10 +4,10 @@ use crate::{
     client::Client,
     persistence::{character_loader::CharacterLoader, character_updater::CharacterUpdater},
     presence::Presence,
-    EditableSettings,
+    EditableSettings, Settings,
 };
 use common::{
-    comp::{Admin, AdminRole, ChatType, Player, UnresolvedChatMsg},
+    comp::{Admin, ChatType, Player, UnresolvedChatMsg},
     event::{EventBus, ServerEvent},
     uid::Uid,
 };
@@ -29,6 +29,7 @@ impl Sys {
         admins: &ReadStorage<'_, Admin>,
         presences: &ReadStorage<'_, Presence>,
         editable_settings: &ReadExpect<'_, EditableSettings>,
+        settings: &Settings,
         censor: &ReadExpect<'_, Arc<censor::Censor>>,
         automod: &AutoMod,
         msg: ClientGeneral,
@@ -65,13 +66,16 @@ impl Sys {
         match msg {
             // Request spectator state
             ClientGeneral::Spectate(requested_view_distances) => {
-                if let Some(admin) = admins.get(entity) && admin.0 >= AdminRole::Moderator {
+                if settings.moderation.may_spectate(admins.get(entity)) {
                     send_join_messages()?;
 
                     server_emitter.emit(ServerEvent::InitSpectator(entity, requested_view_distances));
 
                 } else {
-                    debug!("dropped Spectate msg from unprivileged client")
+                    debug!("dropped Spectate msg from unprivileged client");
+                    client.send(ServerGeneral::CharacterActionError(
+                        "You are not allowed to spectate".to_string(),
+                    ))?;
                 }
             },
             ClientGeneral::Character(character_id, requested_view_distances) => {
@@ -79,8 +83,8 @@ impl Sys {
                     if presences.contains(entity) {
                         debug!("player already ingame, aborting");
                     } else if character_updater
-                        .characters_pending_logout()
-                        .any(|x| x == character_id)
+                        .players_pending_logout()
+                        .any(|uuid| uuid == player.uuid().to_string())
                     {
                         debug!("player recently logged out pending persistence, aborting");
                         client.send(ServerGeneral::CharacterDataLoadResult(Err(
@@ -226,6 +230,7 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Admin>,
         ReadStorage<'a, Presence>,
         ReadExpect<'a, EditableSettings>,
+        Read<'a, Settings>,
         ReadExpect<'a, Arc<censor::Censor>>,
         ReadExpect<'a, AutoMod>,
     );
@@ -247,6 +252,7 @@ impl<'a> System<'a> for Sys {
             admins,
             presences,
             editable_settings,
+            settings,
             censor,
             automod,
         ): Self::SystemData,
@@ -266,6 +272,7 @@ impl<'a> System<'a> for Sys {
                     &admins,
                     &presences,
                     &editable_settings,
+                    &settings,
                     &censor,
                     &automod,
                     msg,
@@ -1,4 +1,4 @@
-use crate::client::Client;
+use crate::{client::Client, metrics::NetworkRequestMetrics, Settings};
 use common::{
     comp::{ChatMode, Player},
     event::{EventBus, ServerEvent},
@@ -8,7 +8,7 @@ use common::{
 use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::ClientGeneral;
 use rayon::prelude::*;
-use specs::{Entities, Join, ParJoin, Read, ReadStorage, WriteStorage};
+use specs::{Entities, Join, ParJoin, Read, ReadExpect, ReadStorage, WriteStorage};
 use tracing::{debug, error, warn};
 
 impl Sys {
@@ -68,6 +68,8 @@ impl<'a> System<'a> for Sys {
         Entities<'a>,
         Read<'a, EventBus<ServerEvent>>,
         Read<'a, Time>,
+        Read<'a, Settings>,
+        ReadExpect<'a, NetworkRequestMetrics>,
         ReadStorage<'a, Uid>,
         ReadStorage<'a, ChatMode>,
         ReadStorage<'a, Player>,
@@ -80,7 +82,17 @@ impl<'a> System<'a> for Sys {
 
     fn run(
         _job: &mut Job<Self>,
-        (entities, server_event_bus, time, uids, chat_modes, players, mut clients): Self::SystemData,
+        (
+            entities,
+            server_event_bus,
+            time,
+            settings,
+            network_metrics,
+            uids,
+            chat_modes,
+            players,
+            mut clients,
+        ): Self::SystemData,
     ) {
         (&entities, &mut clients, players.maybe())
             .par_join()
@@ -88,6 +100,16 @@ impl<'a> System<'a> for Sys {
                 || server_event_bus.emitter(),
                 |server_emitter, (entity, client, player)| {
                     let res = super::try_recv_all(client, 3, |client, msg| {
+                        if !super::check_rate_limit(
+                            server_emitter,
+                            entity,
+                            client,
+                            &msg,
+                            &settings.rate_limits,
+                            &network_metrics,
+                        )? {
+                            return Ok(());
+                        }
                         Self::handle_general_msg(
                             server_emitter,
                             entity,
@@ -1,10 +1,13 @@
-#[cfg(feature = "persistent_world")]
-use crate::TerrainPersistence;
-use crate::{client::Client, presence::Presence, Settings};
+use crate::{
+    client::Client,
+    metrics::NetworkRequestMetrics,
+    presence::{Presence, SpectateTarget},
+    Settings,
+};
 use common::{
     comp::{
-        Admin, AdminRole, CanBuild, ControlEvent, Controller, ForceUpdate, Health, Ori, Player,
-        Pos, SkillSet, Vel,
+        Admin, CanBuild, ControlEvent, Controller, ForceUpdate, Health, Ori, Player, Pos, SkillSet,
+        Vel,
     },
     event::{EventBus, ServerEvent},
     link::Is,
@@ -16,7 +19,7 @@ use common::{
 };
 use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::{ClientGeneral, PresenceKind, ServerGeneral};
-use common_state::{BlockChange, BuildAreas};
+use common_state::BuildAreas;
 use core::mem;
 use rayon::prelude::*;
 use specs::{Entities, Join, Read, ReadExpect, ReadStorage, Write, WriteStorage};
@@ -24,24 +27,6 @@ use std::{borrow::Cow, time::Instant};
 use tracing::{debug, trace, warn};
 use vek::*;
 
-#[cfg(feature = "persistent_world")]
-pub type TerrainPersistenceData<'a> = Option<Write<'a, TerrainPersistence>>;
-#[cfg(not(feature = "persistent_world"))]
-pub type TerrainPersistenceData<'a> = core::marker::PhantomData<&'a mut ()>;
-
-// NOTE: These writes are considered "rare", meaning (currently) that they are
-// admin-gated features that players shouldn't normally access, and which we're
-// not that concerned about the performance of when two players try to use them
-// at once.
-//
-// In such cases, we're okay putting them behind a mutex and penalizing the
-// system if they're actually used concurrently by lots of users.  Please do not
-// put less rare writes here, unless you want to serialize the system!
-struct RareWrites<'a, 'b> {
-    block_changes: &'b mut BlockChange,
-    _terrain_persistence: &'b mut TerrainPersistenceData<'a>,
-}
-
 impl Sys {
     #[allow(clippy::too_many_arguments)]
     fn handle_client_in_game_msg(
@@ -55,7 +40,6 @@ impl Sys {
         force_updates: &ReadStorage<'_, ForceUpdate>,
         skill_set: &mut Option<Cow<'_, SkillSet>>,
         healths: &ReadStorage<'_, Health>,
-        rare_writes: &parking_lot::Mutex<RareWrites<'_, '_>>,
         position: Option<&mut Pos>,
         velocity: Option<&mut Vel>,
         orientation: Option<&mut Ori>,
@@ -203,56 +187,38 @@ impl Sys {
             },
             ClientGeneral::BreakBlock(pos) => {
                 if let Some(comp_can_build) = can_build.get(entity) {
-                    if comp_can_build.enabled {
-                        for area in comp_can_build.build_areas.iter() {
-                            if let Some(old_block) = build_areas
+                    if comp_can_build.enabled
+                        && comp_can_build.build_areas.iter().any(|area| {
+                            build_areas
                                 .areas()
                                 .get(*area)
                                 // TODO: Make this an exclusive check on the upper bound of the AABB
                                 // Vek defaults to inclusive which is not optimal
-                                .filter(|aabb| aabb.contains_point(pos))
-                                .and_then(|_| terrain.get(pos).ok())
-                            {
-                                let new_block = old_block.into_vacant();
-                                // Take the rare writes lock as briefly as possible.
-                                let mut guard = rare_writes.lock();
-                                let _was_set = guard.block_changes.try_set(pos, new_block).is_some();
-                                #[cfg(feature = "persistent_world")]
-                                if _was_set {
-                                    if let Some(terrain_persistence) = guard._terrain_persistence.as_mut()
-                                    {
-                                        terrain_persistence.set_block(pos, new_block);
-                                    }
-                                }
-                            }
-                        }
+                                .map_or(false, |aabb| aabb.contains_point(pos))
+                        })
+                        && terrain.get(pos).is_ok()
+                    {
+                        server_emitter.emit(ServerEvent::BreakBlock { entity, pos });
                     }
                 }
             },
             ClientGeneral::PlaceBlock(pos, new_block) => {
                 if let Some(comp_can_build) = can_build.get(entity) {
-                    if comp_can_build.enabled {
-                        for area in comp_can_build.build_areas.iter() {
-                            if build_areas
+                    if comp_can_build.enabled
+                        && comp_can_build.build_areas.iter().any(|area| {
+                            build_areas
                                 .areas()
                                 .get(*area)
                                 // TODO: Make this an exclusive check on the upper bound of the AABB
                                 // Vek defaults to inclusive which is not optimal
-                                .filter(|aabb| aabb.contains_point(pos))
-                                .is_some()
-                            {
-                                // Take the rare writes lock as briefly as possible.
-                                let mut guard = rare_writes.lock();
-                                let _was_set = guard.block_changes.try_set(pos, new_block).is_some();
-                                #[cfg(feature = "persistent_world")]
-                                if _was_set {
-                                    if let Some(terrain_persistence) = guard._terrain_persistence.as_mut()
-                                    {
-                                        terrain_persistence.set_block(pos, new_block);
-                                    }
-                                }
-                            }
-                        }
+                                .map_or(false, |aabb| aabb.contains_point(pos))
+                        })
+                    {
+                        server_emitter.emit(ServerEvent::PlaceBlock {
+                            entity,
+                            pos,
+                            block: new_block,
+                        });
                     }
                 }
             },
@@ -281,12 +247,28 @@ impl Sys {
                 server_emitter.emit(ServerEvent::UpdateMapMarker { entity, update });
             },
             ClientGeneral::SpectatePosition(pos) => {
-                if let Some(admin) = maybe_admin && admin.0 >= AdminRole::Moderator && presence.kind == PresenceKind::Spectator {
+                // Spectators following an entity are moved by the spectate system
+                if settings.moderation.may_spectate(*maybe_admin)
+                    && presence.kind == PresenceKind::Spectator
+                    && presence.spectate_target.is_none()
+                {
                     if let Some(position) = position {
                         position.0 = pos;
                     }
                 }
             },
+            ClientGeneral::SpectateEntity(target) => {
+                if settings.moderation.may_spectate(*maybe_admin)
+                    && presence.kind == PresenceKind::Spectator
+                {
+                    presence.spectate_target = target.map(SpectateTarget::new);
+                    client.send(ServerGeneral::Spectating(target))?;
+                } else {
+                    client.send(ServerGeneral::Spectating(None))?;
+                }
+            },
+            ClientGeneral::InventoryAck(ack) => client.inventory_delta.lock().unwrap().ack(ack),
+            ClientGeneral::CompSyncAck(ack) => client.comp_sync_delta.lock().unwrap().ack(ack),
             ClientGeneral::RequestCharacterList
             | ClientGeneral::CreateCharacter { .. }
             | ClientGeneral::EditCharacter { .. }
@@ -324,7 +306,6 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Is<Rider>>,
         WriteStorage<'a, SkillSet>,
         ReadStorage<'a, Health>,
-        Write<'a, BlockChange>,
         WriteStorage<'a, Pos>,
         WriteStorage<'a, Vel>,
         WriteStorage<'a, Ori>,
@@ -332,9 +313,9 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, Client>,
         WriteStorage<'a, Controller>,
         Read<'a, Settings>,
+        ReadExpect<'a, NetworkRequestMetrics>,
         Read<'a, BuildAreas>,
         Write<'a, PlayerPhysicsSettings>,
-        TerrainPersistenceData<'a>,
         ReadStorage<'a, Player>,
         ReadStorage<'a, Admin>,
     );
@@ -355,7 +336,6 @@ impl<'a> System<'a> for Sys {
             is_rider,
             mut skill_sets,
             healths,
-            mut block_changes,
             mut positions,
             mut velocities,
             mut orientations,
@@ -363,22 +343,15 @@ impl<'a> System<'a> for Sys {
             mut clients,
             mut controllers,
             settings,
+            network_metrics,
             build_areas,
             mut player_physics_settings_,
-            mut terrain_persistence,
             players,
             admins,
         ): Self::SystemData,
     ) {
         let time_for_vd_changes = Instant::now();
 
-        // NOTE: stdlib mutex is more than good enough on Linux and (probably) Windows,
-        // but not Mac.
-        let rare_writes = parking_lot::Mutex::new(RareWrites {
-            block_changes: &mut block_changes,
-            _terrain_persistence: &mut terrain_persistence,
-        });
-
         let player_physics_settings = &*player_physics_settings_;
         let mut deferred_updates = (
             &entities,
@@ -422,6 +395,16 @@ impl<'a> System<'a> for Sys {
                     let mut clearable_maybe_presence = maybe_presence.as_deref_mut();
                     let mut skill_set = skill_set.map(Cow::Borrowed);
                     let _ = super::try_recv_all(client, 2, |client, msg| {
+                        if !super::check_rate_limit(
+                            server_emitter,
+                            entity,
+                            client,
+                            &msg,
+                            &settings.rate_limits,
+                            &network_metrics,
+                        )? {
+                            return Ok(());
+                        }
                         Self::handle_client_in_game_msg(
                             server_emitter,
                             entity,
@@ -433,7 +416,6 @@ impl<'a> System<'a> for Sys {
                             &force_updates,
                             &mut skill_set,
                             &healths,
-                            &rare_writes,
                             pos.as_deref_mut(),
                             vel.as_deref_mut(),
                             ori.as_deref_mut(),
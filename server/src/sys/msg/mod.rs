@@ -7,11 +7,21 @@ pub mod terrain;
 
 use crate::{
     client::Client,
+    metrics::NetworkRequestMetrics,
+    rate_limit::{MsgCategory, Verdict},
+    settings::RateLimitSettings,
     sys::{loot, pets},
 };
+use common::{
+    comp::{self, ChatType},
+    event::{Emitter, ServerEvent},
+};
 use common_ecs::{dispatch, System};
+use common_net::msg::{ClientGeneral, DisconnectReason, ServerGeneral};
 use serde::de::DeserializeOwned;
 use specs::DispatcherBuilder;
+use std::time::Instant;
+use tracing::debug;
 
 pub fn add_server_systems(dispatch_builder: &mut DispatcherBuilder) {
     //run ping after general, as its super fast anyway. also don't get duplicate
@@ -50,3 +60,54 @@ where
         cnt += 1;
     }
 }
+
+/// Applies the rate limits of the client to `msg`, returns whether the message
+/// should be handled. Clients which keep exceeding their limits are warned and
+/// then kicked, in which case an error is returned to stop receiving.
+pub(crate) fn check_rate_limit(
+    server_emitter: &mut Emitter<'_, ServerEvent>,
+    entity: specs::Entity,
+    client: &Client,
+    msg: &ClientGeneral,
+    settings: &RateLimitSettings,
+    network_metrics: &NetworkRequestMetrics,
+) -> Result<bool, crate::error::Error> {
+    let Some(category) = MsgCategory::of(msg) else { return Ok(true) };
+    let verdict = client
+        .rate_limiter
+        .lock()
+        .unwrap()
+        .check(category, settings, Instant::now());
+    if verdict != Verdict::Allow {
+        network_metrics
+            .client_msgs_rate_limited
+            .with_label_values(&[category.name(), verdict.name()])
+            .inc();
+    }
+    match verdict {
+        Verdict::Allow => return Ok(true),
+        Verdict::Drop => {},
+        Verdict::Warn => client.send(ServerGeneral::server_msg(
+            ChatType::CommandError,
+            "You are sending too many requests, slow down or you will be kicked.",
+        ))?,
+        Verdict::Kick => {
+            debug!(
+                ?entity,
+                ?category,
+                "Kicking client for exceeding its rate limits"
+            );
+            client.send_fallible(ServerGeneral::Disconnect(DisconnectReason::Kicked(
+                "You sent too many requests.".to_owned(),
+            )));
+            server_emitter.emit(ServerEvent::ClientDisconnect(
+                entity,
+                comp::DisconnectReason::Kicked,
+            ));
+            return Err(crate::error::Error::Other(
+                "client exceeded its rate limits".to_owned(),
+            ));
+        },
+    }
+    Ok(false)
+}
@@ -1,12 +1,13 @@
-use crate::{client::Client, Settings};
+use crate::{client::Client, resume::ResumeSessions, Settings};
 use common::{
+    comp::Health,
     event::{EventBus, ServerEvent},
     resources::Time,
 };
 use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::PingMsg;
 use rayon::prelude::*;
-use specs::{Entities, ParJoin, Read, WriteStorage};
+use specs::{Entities, ParJoin, Read, ReadStorage, Write, WriteStorage};
 use tracing::{debug, info};
 
 impl Sys {
@@ -29,6 +30,8 @@ impl<'a> System<'a> for Sys {
         Read<'a, Time>,
         WriteStorage<'a, Client>,
         Read<'a, Settings>,
+        Write<'a, ResumeSessions>,
+        ReadStorage<'a, Health>,
     );
 
     const NAME: &'static str = "msg::ping";
@@ -37,7 +40,15 @@ impl<'a> System<'a> for Sys {
 
     fn run(
         _job: &mut Job<Self>,
-        (entities, server_event_bus, time, mut clients, settings): Self::SystemData,
+        (
+            entities,
+            server_event_bus,
+            time,
+            mut clients,
+            settings,
+            mut resume_sessions,
+            healths,
+        ): Self::SystemData,
     ) {
         (&entities, &mut clients).par_join().for_each_init(
             || server_event_bus.emitter(),
@@ -81,5 +92,35 @@ impl<'a> System<'a> for Sys {
                 }
             },
         );
+
+        // Characters which died or were deleted while their client was away can't
+        // be resumed, close their sessions right away
+        let mut server_emitter = server_event_bus.emitter();
+        let gone = resume_sessions
+            .detached()
+            .filter(|entity| {
+                !entities.is_alive(*entity)
+                    || healths.get(*entity).map_or(false, |health| health.is_dead)
+            })
+            .collect::<Vec<_>>();
+        for entity in gone {
+            resume_sessions.remove(entity);
+            if entities.is_alive(entity) {
+                debug!(?entity, "character died while detached, disconnecting");
+                server_emitter.emit(ServerEvent::ClientDisconnect(
+                    entity,
+                    common::comp::DisconnectReason::Timeout,
+                ));
+            }
+        }
+
+        // Clients which lost their connection didn't come back in time
+        for entity in resume_sessions.expire(time.0, settings.client_timeout) {
+            info!(?entity, "session wasn't resumed, disconnecting");
+            server_emitter.emit(ServerEvent::ClientDisconnect(
+                entity,
+                common::comp::DisconnectReason::Timeout,
+            ));
+        }
     }
 }
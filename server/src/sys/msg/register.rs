@@ -2,6 +2,7 @@ use crate::{
     client::Client,
     login_provider::{LoginProvider, PendingLogin},
     metrics::PlayerMetrics,
+    resume::ResumeSessions,
     sys::sentinel::TrackedStorages,
     EditableSettings, Settings,
 };
@@ -16,7 +17,7 @@ use common_base::prof_span;
 use common_ecs::{Job, Origin, Phase, System};
 use common_net::msg::{
     CharacterInfo, ClientRegister, DisconnectReason, PlayerInfo, PlayerListUpdate, RegisterError,
-    ServerGeneral, ServerInit, WorldMapMsg,
+    ResumeToken, ServerGeneral, ServerInit, WorldMapMsg,
 };
 use hashbrown::{hash_map, HashMap};
 use itertools::Either;
@@ -24,7 +25,7 @@ use plugin_api::Health;
 use rayon::prelude::*;
 use specs::{
     shred::ResourceId, Entities, Join, ParJoin, Read, ReadExpect, ReadStorage, SystemData, World,
-    WriteStorage,
+    Write, WriteStorage,
 };
 use tracing::{debug, info, trace, warn};
 
@@ -52,6 +53,7 @@ pub struct ReadData<'a> {
     map: ReadExpect<'a, WorldMapMsg>,
     trackers: TrackedStorages<'a>,
     _healths: ReadStorage<'a, Health>, // used by plugin feature
+    _positions: ReadStorage<'a, comp::Pos>, // used by plugin feature
     _plugin_mgr: ReadPlugin<'a>,       // used by plugin feature
     _uid_allocator: Read<'a, UidAllocator>, // used by plugin feature
 }
@@ -66,6 +68,7 @@ impl<'a> System<'a> for Sys {
         WriteStorage<'a, Client>,
         WriteStorage<'a, Player>,
         WriteStorage<'a, PendingLogin>,
+        Write<'a, ResumeSessions>,
     );
 
     const NAME: &'static str = "msg::register";
@@ -74,7 +77,14 @@ impl<'a> System<'a> for Sys {
 
     fn run(
         _job: &mut Job<Self>,
-        (event_bus, read_data, mut clients, mut players, mut pending_logins): Self::SystemData,
+        (
+            event_bus,
+            read_data,
+            mut clients,
+            mut players,
+            mut pending_logins,
+            mut resume_sessions,
+        ): Self::SystemData,
     ) {
         // Player list to send new players, and lookup from UUID to entity (so we don't
         // have to do a linear scan over all entities on each login to see if
@@ -121,14 +131,19 @@ impl<'a> System<'a> for Sys {
         // NOTE: stdlib mutex is more than good enough on Linux and (probably) Windows,
         // but not Mac.
         let new_players = parking_lot::Mutex::new((
-            HashMap::<_, (_, _, _, _)>::with_capacity(capacity),
+            HashMap::<_, (_, _, _, _, _)>::with_capacity(capacity),
             Vec::with_capacity(capacity),
             Vec::with_capacity(capacity),
         ));
 
         // defer auth lockup
+        let mut resumes = Vec::new();
         for (entity, client) in (&read_data.entities, &mut clients).join() {
             let _ = super::try_recv_all(client, 0, |_, msg: ClientRegister| {
+                if let Some(token) = msg.resume_token {
+                    resumes.push((entity, token));
+                    return Ok(());
+                }
                 trace!(?msg.token_or_username, "defer auth lockup");
                 let pending = read_data.login_provider.verify(&msg.token_or_username);
                 let _ = pending_logins.insert(entity, pending);
@@ -136,6 +151,52 @@ impl<'a> System<'a> for Sys {
             });
         }
 
+        // Resumed sessions skip the login, their clients take over the entity they
+        // left behind
+        for (entity, token) in resumes {
+            let Some(client) = clients.get(entity) else {
+                continue;
+            };
+            let resumed = match resume_sessions.resume(token) {
+                Some(resumed) => match read_data.uids.get(resumed) {
+                    Some(uid) if read_data.entities.is_alive(resumed) => Some((resumed, *uid)),
+                    // The entity is gone, its session can't be resumed anymore
+                    _ => {
+                        resume_sessions.remove(resumed);
+                        None
+                    },
+                },
+                None => None,
+            };
+            let res = match resumed {
+                Some((resumed, uid)) => {
+                    debug!(?entity, ?resumed, "Resuming session");
+                    event_bus.emit_now(ServerEvent::ClientResume { entity, resumed });
+                    client.send(Ok(())).and_then(|()| {
+                        client.send(ServerInit::Resume {
+                            entity_package: read_data
+                                .trackers
+                                .create_entity_package_with_uid(resumed, uid, None, None, None),
+                            time_of_day: *read_data.time_of_day,
+                        })?;
+                        client.send(ServerGeneral::PlayerListUpdate(PlayerListUpdate::Init(
+                            player_list.clone(),
+                        )))
+                    })
+                },
+                None => {
+                    event_bus.emit_now(ServerEvent::ClientDisconnect(
+                        entity,
+                        common::comp::DisconnectReason::Kicked,
+                    ));
+                    client.send(Err(RegisterError::SessionExpired))
+                },
+            };
+            if let Err(e) = res {
+                trace!(?e, "failed to resume session");
+            }
+        }
+
         let old_player_count = player_list.len();
         #[cfg(feature = "plugins")]
         let ecs_world = EcsWorld {
@@ -145,9 +206,16 @@ impl<'a> System<'a> for Sys {
             // NOTE: Only the old player list is provided, to avoid scalability
             // bottlenecks.
             player: (&players).into(),
+            pos: (&read_data._positions).into(),
+            inventory: (&read_data.trackers.inventory).into(),
+            stats: (&read_data.stats).into(),
+            energy: (&read_data.trackers.energy).into(),
+            group: (&read_data.trackers.group).into(),
             uid_allocator: &read_data._uid_allocator,
         };
 
+        let detached_sessions = &*resume_sessions;
+
         // NOTE: this is just default value.
         //
         // It will be overwritten in ServerExt::update_character_data.
@@ -270,6 +338,19 @@ impl<'a> System<'a> for Sys {
                             Either::Left,
                         );
                         let vacant_player = match old_player {
+                            Either::Left((old_entity, _))
+                                if detached_sessions.is_detached(old_entity) =>
+                            {
+                                // The old player lost its connection and waits to resume its
+                                // session, which we close in favour of the new login
+                                retries.push((entity, pending_login));
+                                drop(new_players_guard);
+                                server_emitter.emit(ServerEvent::ClientDisconnect(
+                                    old_entity,
+                                    common::comp::DisconnectReason::NewerLogin,
+                                ));
+                                return Ok(());
+                            },
                             Either::Left((old_entity, old_client)) => {
                                 if matches!(old_client, None | Some(Some(_))) {
                                     // We can't login the new client right now as the
@@ -326,7 +407,8 @@ impl<'a> System<'a> for Sys {
                         // adding a new player.
 
                         // Add to list to notify all clients of the new player
-                        vacant_player.insert((entity, player, admin, msg));
+                        let resume_token = ResumeToken(rand::random());
+                        vacant_player.insert((entity, player, admin, msg, resume_token));
                         drop(new_players_guard);
                         read_data.player_metrics.players_connected.inc();
 
@@ -349,6 +431,7 @@ impl<'a> System<'a> for Sys {
                             component_recipe_book: default_component_recipe_book().cloned(),
                             material_stats: (*read_data.material_stats).clone(),
                             ability_map: (*read_data.ability_map).clone(),
+                            resume_token,
                         })?;
                         debug!("Done initial sync with client.");
 
@@ -357,6 +440,12 @@ impl<'a> System<'a> for Sys {
                             player_list.clone(),
                         )))?;
 
+                        // Send the chat commands registered by plugins
+                        #[cfg(feature = "plugins")]
+                        client.send(ServerGeneral::PluginCommands(
+                            read_data._plugin_mgr.commands(),
+                        ))?;
+
                         Ok(())
                     }() {
                         trace!(?e, "failed to process register");
@@ -377,7 +466,7 @@ impl<'a> System<'a> for Sys {
         // Handle new players.
         let msgs = new_players
             .into_values()
-            .map(|(entity, player, admin, msg)| {
+            .map(|(entity, player, admin, msg, resume_token)| {
                 let username = &player.alias;
                 let uuid = player.uuid();
                 info!(?username, "New User");
@@ -393,6 +482,7 @@ impl<'a> System<'a> for Sys {
                 players
                     .insert(entity, player)
                     .expect("The entity was joined against in the same system, so it exists");
+                resume_sessions.open(entity, resume_token);
 
                 // Give the Admin component to the player if their name exists in
                 // admin list
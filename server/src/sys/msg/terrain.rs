@@ -1,6 +1,6 @@
 use crate::{
     chunk_serialize::ChunkSendEntry, client::Client, lod::Lod, metrics::NetworkRequestMetrics,
-    presence::Presence, ChunkRequest,
+    presence::Presence, ChunkRequest, Settings,
 };
 use common::{
     comp::Pos,
@@ -26,6 +26,7 @@ impl<'a> System<'a> for Sys {
         ReadExpect<'a, TerrainGrid>,
         ReadExpect<'a, Lod>,
         ReadExpect<'a, NetworkRequestMetrics>,
+        Read<'a, Settings>,
         Write<'a, Vec<ChunkRequest>>,
         ReadStorage<'a, Pos>,
         ReadStorage<'a, Presence>,
@@ -45,6 +46,7 @@ impl<'a> System<'a> for Sys {
             terrain,
             lod,
             network_metrics,
+            settings,
             mut chunk_requests,
             positions,
             presences,
@@ -61,6 +63,16 @@ impl<'a> System<'a> for Sys {
                 |(chunk_send_emitter, server_emitter), (entity, client, maybe_presence)| {
                     let mut chunk_requests = Vec::new();
                     let _ = super::try_recv_all(client, 5, |client, msg| {
+                        if !super::check_rate_limit(
+                            server_emitter,
+                            entity,
+                            client,
+                            &msg,
+                            &settings.rate_limits,
+                            &network_metrics,
+                        )? {
+                            return Ok(());
+                        }
                         let presence = match maybe_presence {
                             Some(g) => g,
                             None => {
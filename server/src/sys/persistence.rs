@@ -1,8 +1,10 @@
 use crate::{persistence::character_updater, presence::Presence, sys::SysScheduler};
 use common::{
+    character::CharacterVitals,
     comp::{
         pet::{is_tameable, Pet},
-        ActiveAbilities, Alignment, Body, Inventory, MapMarker, SkillSet, Stats, Waypoint,
+        ActiveAbilities, Alignment, Body, Buffs, Energy, Health, Inventory, MapMarker,
+        PhysicsState, Pos, SkillSet, Stats, Vault, Waypoint,
     },
     uid::Uid,
 };
@@ -26,6 +28,12 @@ impl<'a> System<'a> for Sys {
         ReadStorage<'a, Pet>,
         ReadStorage<'a, Stats>,
         ReadStorage<'a, ActiveAbilities>,
+        ReadStorage<'a, Pos>,
+        ReadStorage<'a, PhysicsState>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Energy>,
+        ReadStorage<'a, Buffs>,
+        ReadStorage<'a, Vault>,
         WriteExpect<'a, character_updater::CharacterUpdater>,
         Write<'a, SysScheduler<Self>>,
     );
@@ -48,6 +56,12 @@ impl<'a> System<'a> for Sys {
             pets,
             stats,
             active_abilities,
+            positions,
+            physics_states,
+            healths,
+            energies,
+            buffs,
+            vaults,
             mut updater,
             mut scheduler,
         ): Self::SystemData,
@@ -62,6 +76,12 @@ impl<'a> System<'a> for Sys {
                     player_waypoints.maybe(),
                     &active_abilities,
                     map_markers.maybe(),
+                    positions.maybe(),
+                    physics_states.maybe(),
+                    healths.maybe(),
+                    energies.maybe(),
+                    buffs.maybe(),
+                    vaults.maybe(),
                 )
                     .join()
                     .filter_map(
@@ -73,6 +93,12 @@ impl<'a> System<'a> for Sys {
                             waypoint,
                             active_abilities,
                             map_marker,
+                            pos,
+                            physics_state,
+                            health,
+                            energy,
+                            buffs,
+                            vault,
                         )| match presence.kind {
                             PresenceKind::Character(id) => {
                                 let pets = (&alignments, &bodies, &stats, &pets)
@@ -98,6 +124,8 @@ impl<'a> System<'a> for Sys {
                                     waypoint,
                                     active_abilities,
                                     map_marker,
+                                    CharacterVitals::new(pos, physics_state, health, energy, buffs),
+                                    vault,
                                 ))
                             },
                             PresenceKind::Spectator | PresenceKind::Possessor => None,
@@ -0,0 +1,56 @@
+use crate::{client::Client, presence::Presence};
+use common::{
+    comp::{Controller, Pos},
+    uid::UidAllocator,
+};
+use common_ecs::{Job, Origin, Phase, System};
+use common_net::msg::ServerGeneral;
+use specs::{saveload::MarkerAllocator, Entities, Join, Read, ReadStorage, WriteStorage};
+
+/// This system moves spectators along with the entity they follow, so the
+/// world around it gets synced to them, and sends them where it looks
+#[derive(Default)]
+pub struct Sys;
+impl<'a> System<'a> for Sys {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, UidAllocator>,
+        ReadStorage<'a, Client>,
+        ReadStorage<'a, Controller>,
+        WriteStorage<'a, Presence>,
+        WriteStorage<'a, Pos>,
+    );
+
+    const NAME: &'static str = "spectate";
+    const ORIGIN: Origin = Origin::Server;
+    const PHASE: Phase = Phase::Create;
+
+    fn run(
+        _job: &mut Job<Self>,
+        (entities, uid_allocator, clients, controllers, mut presences, mut positions): Self::SystemData,
+    ) {
+        for (entity, client, presence) in (&entities, &clients, &mut presences).join() {
+            let Some(target) = presence.spectate_target.as_mut() else { continue };
+            let Some((target_entity, target_pos)) = uid_allocator
+                .retrieve_entity_internal(target.uid.0)
+                .filter(|target_entity| *target_entity != entity)
+                .and_then(|e| Some((e, *positions.get(e)?)))
+            else {
+                // The followed entity is gone, the spectator stays where it was
+                presence.spectate_target = None;
+                client.send_fallible(ServerGeneral::Spectating(None));
+                continue;
+            };
+            if let Some(pos) = positions.get_mut(entity) {
+                *pos = target_pos;
+            }
+
+            if let Some(look_dir) = controllers
+                .get(target_entity)
+                .and_then(|controller| target.update_look_dir(controller.inputs.look_dir))
+            {
+                client.send_fallible(ServerGeneral::SpectatedLookDir(look_dir));
+            }
+        }
+    }
+}
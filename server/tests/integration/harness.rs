@@ -0,0 +1,286 @@
+//! Boots a [`Server`] with a tiny world and connects real [`Client`]s to it
+//! over MPSC, to test the server together with the client. Without the
+//! `worldgen` feature the world is the one of `test_world.rs`, with it a tiny
+//! map is generated. Both are ticked with a fixed delta time, only the time
+//! the network takes to deliver the messages in between varies, so tests wait
+//! for a condition with [`TestServer::tick_until`] instead of for a number of
+//! ticks.
+
+use common::{
+    character::CharacterId,
+    comp::{
+        humanoid::{self, Species},
+        invite::InviteKind,
+        Body, ControllerInputs,
+    },
+    uid::Uid,
+    ViewDistances,
+};
+use common_net::{
+    msg::{PresenceKind, ServerGeneral},
+    sync::WorldSyncExt,
+};
+use rand::{rngs::SmallRng, SeedableRng};
+use specs::{Entity, WorldExt};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::runtime::Runtime;
+use veloren_client::{addr::ConnectionArgs, Client, Event};
+use veloren_server::{
+    persistence::{DatabaseSettings, SqlLogMode},
+    presence::Presence,
+    EditableSettings, Input, Server, Settings,
+};
+use world::sim::{FileOpts, SizeOpts};
+
+pub const DT: Duration = Duration::from_millis(33);
+/// [`TestServer::tick_until`] fails after this many ticks
+const MAX_TICKS: u32 = 1000;
+/// Time the network tasks get to deliver messages after each tick
+const NETWORK_WAIT: Duration = Duration::from_millis(2);
+/// Small, so as little of the world as possible has to be generated
+pub const VIEW_DISTANCES: ViewDistances = ViewDistances {
+    terrain: 2,
+    entity: 2,
+};
+
+/// The server always listens on the same MPSC address, so only one test
+/// server can run at a time
+static SERVER_LOCK: Mutex<()> = Mutex::new(());
+static NEXT_DIR: AtomicU64 = AtomicU64::new(0);
+
+pub struct TestServer {
+    pub server: Server,
+    runtime: Arc<Runtime>,
+    data_dir: PathBuf,
+    _lock: MutexGuard<'static, ()>,
+}
+
+pub struct TestClient {
+    pub client: Client,
+    /// Events of all ticks of the client so far
+    pub events: Vec<Event>,
+    /// All messages the client received from the server after registering
+    pub received: Vec<ServerGeneral>,
+}
+
+impl TestServer {
+    pub fn new() -> Self { Self::with_settings(|_, _| {}) }
+
+    /// Start the server with settings changed by `configure`
+    pub fn with_settings(configure: impl FnOnce(&mut Settings, &mut EditableSettings)) -> Self {
+        // A failed test which held the lock doesn't affect the next one
+        let lock = SERVER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let data_dir = std::env::temp_dir().join(format!(
+            "veloren-server-test-{}-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+            NEXT_DIR.fetch_add(1, Ordering::Relaxed),
+        ));
+
+        let mut settings = Settings {
+            gameserver_protocols: Vec::new(),
+            metrics_address: ([127, 0, 0, 1], 0).into(),
+            query_addresses: Vec::new(),
+            auth_server_address: None,
+            // Only used with the `worldgen` feature
+            map_file: Some(FileOpts::Generate(SizeOpts::new(6, 6, 2.0))),
+            ..Settings::default()
+        };
+        let mut editable_settings = EditableSettings::load(&data_dir);
+        configure(&mut settings, &mut editable_settings);
+
+        let runtime = Arc::new(Runtime::new().expect("Failed to start the tokio runtime"));
+        let server = Server::new(
+            settings,
+            editable_settings,
+            DatabaseSettings {
+                db_dir: data_dir.join("saves"),
+                sql_log_mode: SqlLogMode::Disabled,
+                backup_interval: None,
+                backup_retention: 0,
+            },
+            &data_dir,
+            Arc::clone(&runtime),
+        )
+        .expect("Failed to start the server");
+
+        Self {
+            server,
+            runtime,
+            data_dir,
+            _lock: lock,
+        }
+    }
+
+    /// Connect and register a client, ticking the server until it is done
+    pub fn connect(&mut self, username: &str) -> TestClient {
+        let handle = self.runtime.spawn({
+            let runtime = Arc::clone(&self.runtime);
+            let username = username.to_owned();
+            async move {
+                Client::new(
+                    ConnectionArgs::Mpsc(14004),
+                    runtime,
+                    &mut None,
+                    &username,
+                    "",
+                    |_| true,
+                )
+                .await
+            }
+        });
+        for _ in 0..MAX_TICKS {
+            if handle.is_finished() {
+                let mut client = self
+                    .runtime
+                    .block_on(handle)
+                    .expect("The client task panicked")
+                    .expect("Failed to connect the client");
+                client.keep_received();
+                return TestClient {
+                    client,
+                    events: Vec::new(),
+                    received: Vec::new(),
+                };
+            }
+            self.tick(&mut []);
+        }
+        panic!("Connecting {} timed out", username);
+    }
+
+    /// Connect a client for each of the usernames, ticking until all of them
+    /// see each other in the player list
+    pub fn connect_all<const N: usize>(&mut self, usernames: [&str; N]) -> [TestClient; N] {
+        let mut clients = usernames.map(|username| self.connect(username));
+        self.tick_until(&mut clients.iter_mut().collect::<Vec<_>>(), |_, clients| {
+            clients
+                .iter()
+                .all(|client| client.client.player_list().len() == N)
+        });
+        clients
+    }
+
+    /// Create a character for the client and join the world with it, ticking
+    /// until the server put it in the world
+    pub fn load_character(&mut self, client: &mut TestClient, alias: &str) -> CharacterId {
+        let seen = client.events.len();
+        let body = humanoid::Body::random_with(&mut SmallRng::seed_from_u64(0), &Species::Human);
+        client.client.create_character(
+            alias.to_owned(),
+            Some("common.items.weapons.sword.starter".to_owned()),
+            None,
+            Body::Humanoid(body),
+        );
+        let mut character_id = None;
+        self.tick_until(&mut [&mut *client], |_, clients| {
+            character_id = clients[0].events[seen..]
+                .iter()
+                .find_map(|event| match event {
+                    Event::CharacterCreated(character_id) => Some(*character_id),
+                    _ => None,
+                });
+            character_id.is_some()
+        });
+        let character_id = character_id.unwrap();
+
+        client
+            .client
+            .request_character(character_id, VIEW_DISTANCES);
+        self.tick_until(&mut [&mut *client], |server, clients| {
+            server
+                .entity(&clients[0])
+                .and_then(|entity| server.presence_kind(entity))
+                == Some(PresenceKind::Character(character_id))
+        });
+        character_id
+    }
+
+    /// Let the character of `inviter` invite the one of `invitee` to trade,
+    /// ticking until both of them are trading
+    pub fn open_trade(&mut self, inviter: &mut TestClient, invitee: &mut TestClient) {
+        let invitee_uid = invitee.client.uid().expect("The invitee has no uid");
+        inviter.client.send_invite(invitee_uid, InviteKind::Trade);
+        self.tick_until(&mut [&mut *inviter, &mut *invitee], |_, clients| {
+            clients[1].client.invite().is_some()
+        });
+        invitee.client.accept_invite();
+        self.tick_until(&mut [&mut *inviter, &mut *invitee], |_, clients| {
+            clients.iter().all(|client| client.client.is_trading())
+        });
+    }
+
+    /// Tick the server, then the clients
+    pub fn tick(&mut self, clients: &mut [&mut TestClient]) {
+        self.server
+            .tick(Input::default(), DT)
+            .expect("Failed to tick the server");
+        self.server.cleanup();
+        for client in clients.iter_mut() {
+            client.tick();
+        }
+        thread::sleep(NETWORK_WAIT);
+    }
+
+    /// Tick until `done`, panics if it takes longer than [`MAX_TICKS`]
+    pub fn tick_until(
+        &mut self,
+        clients: &mut [&mut TestClient],
+        mut done: impl FnMut(&Self, &[&mut TestClient]) -> bool,
+    ) {
+        for _ in 0..MAX_TICKS {
+            if done(self, clients) {
+                return;
+            }
+            self.tick(clients);
+        }
+        panic!("The condition wasn't met after {} ticks", MAX_TICKS);
+    }
+
+    /// The entity of the client on the server
+    pub fn entity(&self, client: &TestClient) -> Option<Entity> {
+        let uid: Uid = client.client.uid()?;
+        self.server.state().ecs().entity_from_uid(uid.0)
+    }
+
+    /// Whether the entity is in the world as a character or spectator
+    pub fn presence_kind(&self, entity: Entity) -> Option<PresenceKind> {
+        self.server
+            .state()
+            .ecs()
+            .read_storage::<Presence>()
+            .get(entity)
+            .map(|presence| presence.kind)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        // Leave the data directory in place if the test failed, to look into it
+        if !thread::panicking() {
+            let _ = std::fs::remove_dir_all(&self.data_dir);
+        }
+    }
+}
+
+impl TestClient {
+    pub fn tick(&mut self) {
+        let events = self
+            .client
+            .tick(ControllerInputs::default(), DT, |_| {})
+            .expect("Failed to tick the client");
+        self.events.extend(events);
+        self.received.extend(self.client.take_received());
+        self.client.cleanup();
+    }
+}
@@ -0,0 +1,6 @@
+//! Tests of the server together with real clients, see [`harness`].
+
+mod harness;
+mod register;
+mod spectate;
+mod trade;
@@ -0,0 +1,65 @@
+use crate::harness::TestServer;
+use common::comp::{ChatMsg, ChatType, Inventory, Player, Stats};
+use common_net::msg::{PresenceKind, ServerGeneral};
+use specs::WorldExt;
+use veloren_client::Event;
+
+fn is_command_info(chat: &ChatMsg) -> bool { matches!(chat.chat_type, ChatType::CommandInfo) }
+
+#[test]
+fn clients_see_each_other() {
+    let mut server = TestServer::new();
+    let [alice, _bob] = server.connect_all(["alice", "bob"]);
+
+    assert_eq!(server.server.number_of_players(), 2);
+    let alice_entity = server.entity(&alice).expect("alice has no entity");
+    let players = server.server.state().ecs().read_storage::<Player>();
+    assert_eq!(
+        players
+            .get(alice_entity)
+            .map(|player| player.alias.as_str()),
+        Some("alice")
+    );
+}
+
+#[test]
+fn characters_are_loaded() {
+    let mut server = TestServer::new();
+    let mut alice = server.connect("alice");
+
+    let character_id = server.load_character(&mut alice, "Alice");
+
+    assert_eq!(
+        alice.client.presence(),
+        Some(PresenceKind::Character(character_id))
+    );
+    let entity = server.entity(&alice).expect("alice has no entity");
+    let ecs = server.server.state().ecs();
+    assert_eq!(
+        ecs.read_storage::<Stats>()
+            .get(entity)
+            .map(|stats| stats.name.as_str()),
+        Some("Alice")
+    );
+    assert!(ecs.read_storage::<Inventory>().get(entity).is_some());
+}
+
+#[test]
+fn commands_are_answered() {
+    let mut server = TestServer::new();
+    let mut alice = server.connect("alice");
+
+    alice.client.send_command("help".to_owned(), Vec::new());
+    server.tick_until(&mut [&mut alice], |_, clients| {
+        clients[0]
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::Chat(chat) if is_command_info(chat)))
+    });
+
+    // The answer is among the messages received from the server
+    assert!(alice
+        .received
+        .iter()
+        .any(|msg| matches!(msg, ServerGeneral::ChatMsg(chat) if is_command_info(chat))));
+}
@@ -0,0 +1,87 @@
+use crate::harness::{TestServer, VIEW_DISTANCES};
+use common::comp::Pos;
+use common_net::msg::PresenceKind;
+use specs::WorldExt;
+use veloren_client::Event;
+
+#[test]
+fn spectating_needs_a_moderator() {
+    let mut server = TestServer::new();
+    let mut alice = server.connect("alice");
+
+    alice.client.request_spectate(VIEW_DISTANCES);
+    server.tick_until(&mut [&mut alice], |_, clients| {
+        clients[0]
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::CharacterError(_)))
+    });
+
+    let entity = server.entity(&alice).expect("alice has no entity");
+    assert_eq!(server.presence_kind(entity), None);
+    assert_eq!(alice.client.presence(), None);
+    assert!(!alice
+        .events
+        .iter()
+        .any(|event| matches!(event, Event::StartSpectate(_))));
+}
+
+#[test]
+fn spectators_can_be_allowed() {
+    let mut server = TestServer::with_settings(|settings, _| {
+        settings.moderation.allow_spectators = true;
+    });
+    let mut alice = server.connect("alice");
+
+    alice.client.request_spectate(VIEW_DISTANCES);
+    server.tick_until(&mut [&mut alice], |_, clients| {
+        clients[0]
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::StartSpectate(_)))
+    });
+
+    let entity = server.entity(&alice).expect("alice has no entity");
+    assert_eq!(server.presence_kind(entity), Some(PresenceKind::Spectator));
+}
+
+#[test]
+fn spectators_follow_players() {
+    let mut server = TestServer::with_settings(|settings, _| {
+        settings.moderation.allow_spectators = true;
+    });
+    let [mut alice, mut bob] = server.connect_all(["alice", "bob"]);
+    server.load_character(&mut bob, "Bob");
+    alice.client.request_spectate(VIEW_DISTANCES);
+    server.tick_until(&mut [&mut alice, &mut bob], |server, clients| {
+        server
+            .entity(&clients[0])
+            .and_then(|entity| server.presence_kind(entity))
+            == Some(PresenceKind::Spectator)
+    });
+
+    let bob_uid = bob.client.uid().expect("bob has no uid");
+    alice.client.spectate_entity(Some(bob_uid));
+    server.tick_until(&mut [&mut alice, &mut bob], |_, clients| {
+        clients[0]
+            .events
+            .iter()
+            .any(|event| matches!(event, Event::Spectating(Some(uid)) if *uid == bob_uid))
+    });
+    // The spectator is moved along with the player on the server, at most a
+    // tick behind it
+    server.tick_until(&mut [&mut alice, &mut bob], |server, clients| {
+        let positions = server.server.state().ecs().read_storage::<Pos>();
+        let pos = |client| {
+            server
+                .entity(client)
+                .and_then(|entity| positions.get(entity))
+                .map(|pos| pos.0)
+        };
+        matches!(
+            (pos(&clients[0]), pos(&clients[1])),
+            (Some(alice_pos), Some(bob_pos)) if alice_pos.distance(bob_pos) < 2.0
+        )
+    });
+    assert_eq!(alice.client.spectating(), Some(bob_uid));
+}
@@ -0,0 +1,86 @@
+use crate::harness::{TestClient, TestServer};
+use common::{
+    comp::Inventory,
+    trade::{TradeAction, TradePhase, TradeResult},
+};
+use specs::WorldExt;
+use veloren_client::Event;
+
+/// How many items with this name are in the inventory of the client's entity
+fn item_count(server: &TestServer, client: &TestClient, name: &str) -> u32 {
+    let entity = server.entity(client).expect("The client has no entity");
+    server
+        .server
+        .state()
+        .ecs()
+        .read_storage::<Inventory>()
+        .get(entity)
+        .expect("The client has no inventory")
+        .slots()
+        .flatten()
+        .filter(|item| item.name() == name)
+        .map(|item| item.amount())
+        .sum()
+}
+
+#[test]
+fn items_are_traded() {
+    let mut server = TestServer::new();
+    let [mut alice, mut bob] = server.connect_all(["alice", "bob"]);
+    server.load_character(&mut alice, "Alice");
+    server.load_character(&mut bob, "Bob");
+    server.open_trade(&mut alice, &mut bob);
+
+    let (slot, name) = {
+        let inventories = alice.client.inventories();
+        let (slot, item) = inventories
+            .get(alice.client.entity())
+            .expect("alice has no inventory")
+            .slots_with_id()
+            .find_map(|(slot, item)| Some((slot, item.as_ref()?)))
+            .expect("alice has no items");
+        (slot, item.name().into_owned())
+    };
+    let bob_count = item_count(&server, &bob, &name);
+    alice.client.perform_trade_action(TradeAction::AddItem {
+        item: slot,
+        quantity: 1,
+        ours: true,
+    });
+    server.tick_until(&mut [&mut alice, &mut bob], |_, clients| {
+        clients
+            .iter()
+            .all(|client| match client.client.pending_trade() {
+                Some((_, trade, _)) => !trade.offers[0].is_empty(),
+                None => false,
+            })
+    });
+
+    for phase in [TradePhase::Mutate, TradePhase::Review] {
+        alice
+            .client
+            .perform_trade_action(TradeAction::Accept(phase));
+        bob.client.perform_trade_action(TradeAction::Accept(phase));
+        server.tick_until(&mut [&mut alice, &mut bob], |_, clients| {
+            clients.iter().all(|client| {
+                client
+                    .client
+                    .pending_trade()
+                    .as_ref()
+                    .map_or(true, |(_, trade, _)| trade.phase() != phase)
+            })
+        });
+    }
+    server.tick_until(&mut [&mut alice, &mut bob], |_, clients| {
+        clients.iter().all(|client| {
+            client.events.iter().any(|event| {
+                matches!(event, Event::TradeComplete {
+                    result: TradeResult::Completed,
+                    ..
+                })
+            })
+        })
+    });
+
+    assert_eq!(item_count(&server, &bob, &name), bob_count + 1);
+}
@@ -8,6 +8,7 @@
 //! Airshipper should only use arguments listed above! Since we will not try to
 //! be careful about their stability otherwise.
 use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 pub struct Args {
@@ -16,4 +17,9 @@ pub struct Args {
     /// This allows passing in server selection performed in airshipper.
     #[clap(short, long)]
     pub server: Option<String>,
+
+    /// Replay file to play back instead of connecting to a server, see the
+    /// `record_replays` networking setting.
+    #[clap(long)]
+    pub replay: Option<PathBuf>,
 }
@@ -14,6 +14,7 @@ pub enum ClientChatCommand {
     ExperimentalShader,
     Help,
     Mute,
+    Replay,
     Unmute,
 }
 
@@ -44,6 +45,22 @@ impl ClientChatCommand {
                 "Mutes chat messages from a player.",
                 None,
             ),
+            ClientChatCommand::Replay => cmd(
+                vec![
+                    Enum(
+                        "action",
+                        ["pause", "play", "seek", "speed"]
+                            .iter()
+                            .map(|action| action.to_string())
+                            .collect(),
+                        Optional,
+                    ),
+                    Float("value", 1.0, Optional),
+                ],
+                "Controls the replay being played back: pause, play, seek to a second or set the \
+                 speed. Shows the progress without an action.",
+                None,
+            ),
             ClientChatCommand::Unmute => cmd(
                 vec![PlayerName(Required)],
                 "Unmutes a player muted with the 'mute' command.",
@@ -57,6 +74,7 @@ impl ClientChatCommand {
             ClientChatCommand::ExperimentalShader => "experimental_shader",
             ClientChatCommand::Help => "help",
             ClientChatCommand::Mute => "mute",
+            ClientChatCommand::Replay => "replay",
             ClientChatCommand::Unmute => "unmute",
         }
     }
@@ -162,6 +180,10 @@ pub fn run_command(
         Ok(ChatCommandKind::Client(cmd)) => {
             Ok(Some(run_client_command(client, global_state, cmd, args)?))
         },
+        Err(()) if plugin_command(client, cmd).is_some() => {
+            client.send_command(cmd.to_string(), args);
+            Ok(None) // The server will provide a response when the command is run
+        },
         Err(()) => Err(invalid_command_message(client, cmd.to_string())),
     }
 }
@@ -176,7 +198,14 @@ fn invalid_command_message(client: &Client, user_entered_invalid_command: String
     let usable_commands = ServerChatCommand::iter()
         .filter(|cmd| cmd.needs_role() <= entity_role)
         .map(|cmd| cmd.keyword())
-        .chain(ClientChatCommand::iter().map(|cmd| cmd.keyword()));
+        .chain(ClientChatCommand::iter().map(|cmd| cmd.keyword()))
+        .chain(
+            client
+                .plugin_commands()
+                .iter()
+                .filter(|cmd| cmd.needs_role <= entity_role)
+                .map(|cmd| cmd.keyword.as_str()),
+        );
 
     let most_similar_str = usable_commands
         .clone()
@@ -205,6 +234,7 @@ fn run_client_command(
         ClientChatCommand::ExperimentalShader => handle_experimental_shader,
         ClientChatCommand::Help => handle_help,
         ClientChatCommand::Mute => handle_mute,
+        ClientChatCommand::Replay => handle_replay,
         ClientChatCommand::Unmute => handle_unmute,
     };
 
@@ -216,7 +246,11 @@ fn handle_help(
     _global_state: &mut GlobalState,
     args: Vec<String>,
 ) -> Result<String, String> {
-    if let Some(cmd) = parse_cmd_args!(args, ServerChatCommand) {
+    if let Some(cmd) = parse_cmd_args!(args.clone(), ServerChatCommand) {
+        Ok(cmd.help_string())
+    } else if let Some(cmd) =
+        parse_cmd_args!(args, String).and_then(|keyword| plugin_command(client, &keyword))
+    {
         Ok(cmd.help_string())
     } else {
         let mut message = String::new();
@@ -237,6 +271,15 @@ fn handle_help(
                 message += &cmd.help_string();
                 message += "\n";
             });
+        // And through the commands registered by the plugins of the server
+        client
+            .plugin_commands()
+            .iter()
+            .filter(|cmd| cmd.needs_role <= entity_role)
+            .for_each(|cmd| {
+                message += &cmd.help_string();
+                message += "\n";
+            });
         message += "Additionally, you can use the following shortcuts:";
         ServerChatCommand::iter()
             .filter(|cmd| cmd.needs_role() <= entity_role)
@@ -312,6 +355,49 @@ fn handle_unmute(
     }
 }
 
+fn handle_replay(
+    _client: &Client,
+    global_state: &mut GlobalState,
+    args: Vec<String>,
+) -> Result<String, String> {
+    let replay = global_state
+        .replay
+        .as_ref()
+        .ok_or_else(|| "No replay is being played back.".to_string())?;
+    match parse_cmd_args!(args, String, f64) {
+        (None, _) => {
+            let (position, duration) = replay.progress();
+            Ok(format!(
+                "{:.0}s of {:.0}s at {:.2}x speed{}.",
+                position,
+                duration,
+                replay.speed(),
+                if replay.paused() { ", paused" } else { "" }
+            ))
+        },
+        (Some(action), value) => match (action.as_str(), value) {
+            ("pause", _) => {
+                replay.set_paused(true);
+                Ok("Paused the replay.".to_string())
+            },
+            ("play", _) => {
+                replay.set_paused(false);
+                Ok("Playing the replay.".to_string())
+            },
+            ("seek", Some(position)) => {
+                replay.seek(position);
+                Ok(format!("Jumped to {:.0}s.", position))
+            },
+            ("speed", Some(speed)) => {
+                replay.set_speed(speed);
+                Ok(format!("Playing at {:.2}x speed.", replay.speed()))
+            },
+            ("seek" | "speed", None) => Err(format!("You must specify a value to {}.", action)),
+            _ => Err(format!("{} is not a replay action.", action)),
+        },
+    }
+}
+
 fn handle_experimental_shader(
     _client: &Client,
     global_state: &mut GlobalState,
@@ -366,6 +452,14 @@ fn handle_experimental_shader(
     }
 }
 
+/// Find the command registered by a plugin of the server with the given keyword
+fn plugin_command<'a>(client: &'a Client, keyword: &str) -> Option<&'a PluginChatCommand> {
+    client
+        .plugin_commands()
+        .iter()
+        .find(|cmd| cmd.keyword == keyword)
+}
+
 /// A helper function to get the Uuid of a player with a given alias
 pub fn get_player_uuid(client: &Client, alias: &String) -> Option<Uuid> {
     client
@@ -379,7 +473,7 @@ trait TabComplete {
     fn complete(&self, part: &str, client: &Client) -> Vec<String>;
 }
 
-impl TabComplete for ArgumentSpec {
+impl<L> TabComplete for ArgumentSpec<L> {
     fn complete(&self, part: &str, client: &Client) -> Vec<String> {
         match self {
             ArgumentSpec::PlayerName(_) => complete_player(part, client),
@@ -399,7 +493,11 @@ impl TabComplete for ArgumentSpec {
                 }
             },
             ArgumentSpec::Any(_, _) => vec![],
-            ArgumentSpec::Command(_) => complete_command(part, ' '),
+            ArgumentSpec::Command(_) => {
+                let mut completions = complete_command(part, ' ');
+                completions.extend(complete_plugin_command(part, ' ', client));
+                completions
+            },
             ArgumentSpec::Message(_) => complete_player(part, client),
             ArgumentSpec::SubCommand => complete_command(part, ' '),
             ArgumentSpec::Enum(_, strings, _) => strings
@@ -480,6 +578,16 @@ fn complete_command(part: &str, prefix: char) -> Vec<String> {
         .collect()
 }
 
+fn complete_plugin_command(part: &str, prefix: char, client: &Client) -> Vec<String> {
+    client
+        .plugin_commands()
+        .iter()
+        .map(|cmd| &cmd.keyword)
+        .filter(|kwd| kwd.starts_with(part))
+        .map(|kwd| format!("{}{}", prefix, kwd))
+        .collect()
+}
+
 pub fn complete(line: &str, client: &Client, cmd_prefix: char) -> Vec<String> {
     let word = if line.chars().last().map_or(true, char::is_whitespace) {
         ""
@@ -496,37 +604,17 @@ pub fn complete(line: &str, client: &Client, cmd_prefix: char) -> Vec<String> {
             // Completing chat command name. This is the start of the line so the prefix
             // will be part of it
             let word = word.strip_prefix(cmd_prefix).unwrap_or(word);
-            return complete_command(word, cmd_prefix);
+            let mut completions = complete_command(word, cmd_prefix);
+            completions.extend(complete_plugin_command(word, cmd_prefix, client));
+            return completions;
         }
 
-        let args = {
-            if let Ok(cmd) = cmd.parse::<ServerChatCommand>() {
-                Some(cmd.data().args)
-            } else if let Ok(cmd) = cmd.parse::<ClientChatCommand>() {
-                Some(cmd.data().args)
-            } else {
-                None
-            }
-        };
-
-        if let Some(args) = args {
-            if let Some(arg) = args.get(i - 1) {
-                // Complete ith argument
-                arg.complete(word, client)
-            } else {
-                // Complete past the last argument
-                match args.last() {
-                    Some(ArgumentSpec::SubCommand) => {
-                        if let Some(index) = nth_word(line, args.len()) {
-                            complete(&line[index..], client, cmd_prefix)
-                        } else {
-                            vec![]
-                        }
-                    },
-                    Some(ArgumentSpec::Message(_)) => complete_player(word, client),
-                    _ => vec![], // End of command. Nothing to complete
-                }
-            }
+        if let Ok(cmd) = cmd.parse::<ServerChatCommand>() {
+            complete_args(&cmd.data().args, i, line, word, client, cmd_prefix)
+        } else if let Ok(cmd) = cmd.parse::<ClientChatCommand>() {
+            complete_args(&cmd.data().args, i, line, word, client, cmd_prefix)
+        } else if let Some(cmd) = plugin_command(client, cmd) {
+            complete_args(&cmd.args, i, line, word, client, cmd_prefix)
         } else {
             // Completing for unknown chat command
             complete_player(word, client)
@@ -537,6 +625,34 @@ pub fn complete(line: &str, client: &Client, cmd_prefix: char) -> Vec<String> {
     }
 }
 
+/// Complete the `i`th argument of a command given the arguments it takes
+fn complete_args<L>(
+    args: &[ArgumentSpec<L>],
+    i: usize,
+    line: &str,
+    word: &str,
+    client: &Client,
+    cmd_prefix: char,
+) -> Vec<String> {
+    if let Some(arg) = args.get(i - 1) {
+        // Complete ith argument
+        arg.complete(word, client)
+    } else {
+        // Complete past the last argument
+        match args.last() {
+            Some(ArgumentSpec::SubCommand) => {
+                if let Some(index) = nth_word(line, args.len()) {
+                    complete(&line[index..], client, cmd_prefix)
+                } else {
+                    vec![]
+                }
+            },
+            Some(ArgumentSpec::Message(_)) => complete_player(word, client),
+            _ => vec![], // End of command. Nothing to complete
+        }
+    }
+}
+
 #[test]
 fn verify_cmd_list_sorted() {
     let mut list = ClientChatCommand::iter()
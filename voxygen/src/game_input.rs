@@ -152,6 +152,8 @@ pub enum GameInput {
     SpectateSpeedBoost,
     #[strum(serialize = "gameinput-spectateviewpoint")]
     SpectateViewpoint,
+    #[strum(serialize = "gameinput-spectatenextplayer")]
+    SpectateNextPlayer,
     #[strum(serialize = "gameinput-mutemaster")]
     MuteMaster,
     #[strum(serialize = "gameinput-muteinactivemaster")]
@@ -23,6 +23,7 @@ mod slots;
 mod social;
 mod trade;
 pub mod util;
+mod vault;
 
 pub use crafting::CraftingTab;
 pub use hotbar::{SlotContents as HotbarSlotContents, State as HotbarState};
@@ -51,6 +52,7 @@ use settings_window::{SettingsTab, SettingsWindow};
 use skillbar::Skillbar;
 use social::Social;
 use trade::Trade;
+use vault::Vault;
 
 use crate::{
     cmd::get_player_uuid,
@@ -307,6 +309,7 @@ widget_ids! {
         prompt_dialog,
         bag,
         trade,
+        vault,
         social,
         quest,
         diary,
@@ -872,6 +875,8 @@ pub struct Show {
     bag: bool,
     bag_inv: bool,
     trade: bool,
+    /// Position of the vault sprite the vault window was opened from
+    vault: Option<Vec3<i32>>,
     social: bool,
     diary: bool,
     group: bool,
@@ -902,12 +907,16 @@ impl Show {
             self.crafting_fields.salvage = false;
             if !open {
                 self.crafting = false;
+                self.vault = None;
             }
 
             self.want_grab = !self.any_window_requires_cursor();
         }
     }
 
+    /// Position of the vault sprite, if the vault window is open
+    pub fn vault(&self) -> Option<Vec3<i32>> { self.vault }
+
     fn trade(&mut self, open: bool) {
         if !self.esc_menu {
             self.bag = open;
@@ -917,11 +926,23 @@ impl Show {
         }
     }
 
+    pub fn open_vault(&mut self, pos: Vec3<i32>) {
+        if !self.esc_menu {
+            self.vault = Some(pos);
+            self.bag = true;
+            self.crafting = false;
+            self.crafting_fields.salvage = false;
+            self.map = false;
+            self.want_grab = !self.any_window_requires_cursor();
+        }
+    }
+
     fn map(&mut self, open: bool) {
         if !self.esc_menu {
             self.map = open;
             self.bag = false;
             self.crafting = false;
+            self.vault = None;
             self.crafting_fields.salvage = false;
             self.social = false;
             self.diary = false;
@@ -951,6 +972,7 @@ impl Show {
             self.crafting_fields.salvage = false;
             self.crafting_fields.recipe_inputs = HashMap::new();
             self.bag = open;
+            self.vault = None;
             self.map = false;
             self.want_grab = !self.any_window_requires_cursor();
         }
@@ -976,6 +998,7 @@ impl Show {
             self.crafting = false;
             self.crafting_fields.salvage = false;
             self.bag = false;
+            self.vault = None;
             self.map = false;
             self.diary_fields = diary::DiaryShow::default();
             self.diary = open;
@@ -991,6 +1014,7 @@ impl Show {
                 Windows::None
             };
             self.bag = false;
+            self.vault = None;
             self.social = false;
             self.crafting = false;
             self.crafting_fields.salvage = false;
@@ -1036,6 +1060,7 @@ impl Show {
     fn any_window_requires_cursor(&self) -> bool {
         self.bag
             || self.trade
+            || self.vault.is_some()
             || self.esc_menu
             || self.map
             || self.social
@@ -1050,6 +1075,7 @@ impl Show {
         if self.any_window_requires_cursor() {
             self.bag = false;
             self.trade = false;
+            self.vault = None;
             self.esc_menu = false;
             self.help = false;
             self.intro = false;
@@ -1348,6 +1374,7 @@ impl Hud {
                 bag: false,
                 bag_inv: false,
                 trade: false,
+                vault: None,
                 esc_menu: false,
                 open_windows: Windows::None,
                 map: false,
@@ -2037,7 +2064,7 @@ impl Hud {
                             Interaction::Collect => {
                                 vec![(GameInput::Interact, i18n.get_msg("hud-collect").to_string())]
                             },
-                            Interaction::Craft(_) => {
+                            Interaction::Craft(_) | Interaction::Vault => {
                                 vec![(GameInput::Interact, i18n.get_msg("hud-use").to_string())]
                             },
                             Interaction::Mine => {
@@ -3046,6 +3073,27 @@ impl Hud {
             }
         }
 
+        // Vault window
+        if self.show.vault.is_some() {
+            if let Some(vault::Event::Close) = Vault::new(
+                client,
+                &info,
+                &self.imgs,
+                &self.item_imgs,
+                &self.fonts,
+                &self.rot_imgs,
+                item_tooltip_manager,
+                &mut self.slot_manager,
+                i18n,
+                &msm,
+                self.pulse,
+            )
+            .set(self.ids.vault, ui_widgets)
+            {
+                self.show.vault = None;
+            }
+        }
+
         // Buffs
         if let (Some(player_buffs), Some(health), Some(energy), Some(char_state)) = (
             buffs.get(info.viewpoint_entity),
@@ -3549,6 +3597,7 @@ impl Hud {
                 Trade(_) => None,
                 Ability(_) => None,
                 Crafting(_) => None,
+                Vault(v) => Some(Slot::Vault(v.index)),
             };
             match event {
                 slot::Event::Dragged(a, b) => {
@@ -3758,6 +3807,31 @@ impl Hud {
                 slot::Event::Used(from) => {
                     // Item used (selected and then clicked again)
                     if let Some(from) = to_slot(from) {
+                        let me = info.viewpoint_entity;
+                        let vaults = ecs.read_storage::<comp::Vault>();
+                        // Using an item while the vault is open moves it in or out of the vault
+                        if let (Some(vault), Some(inventory), Some(_)) =
+                            (vaults.get(me), inventories.get(me), self.show.vault)
+                        {
+                            let target = match from {
+                                Slot::Vault(slot) => vault
+                                    .inventory_slot_for(inventory, slot)
+                                    .map(Slot::Inventory),
+                                Slot::Inventory(slot) => inventory
+                                    .get(slot)
+                                    .and_then(|item| vault.vault_slot_for(item))
+                                    .map(Slot::Vault),
+                                Slot::Equip(_) => None,
+                            };
+                            if let Some(target) = target {
+                                events.push(Event::SwapSlots {
+                                    slot_a: from,
+                                    slot_b: target,
+                                    bypass_dialog: false,
+                                });
+                                continue;
+                            }
+                        }
                         if self.show.crafting_fields.salvage
                             && matches!(
                                 self.show.crafting_fields.crafting_tab,
@@ -4495,6 +4569,16 @@ impl Hud {
                     }
             });
 
+        // Close the vault when out of range
+        self.show.vault = self.show.vault.filter(|pos| {
+            self.show.bag
+                && if let Some(player_pos) = client.position() {
+                    pos.map(|e| e as f32 + 0.5).distance(player_pos) < MAX_PICKUP_RANGE
+                } else {
+                    false
+                }
+        });
+
         // Optimization: skip maintaining UI when it's off.
         if !self.show.ui {
             return std::mem::take(&mut self.events);
@@ -4878,6 +4962,7 @@ pub fn get_sprite_desc(sprite: SpriteKind, localized_strings: &Localization) ->
         SpriteKind::SpinningWheel => "hud-crafting-spinning_wheel",
         SpriteKind::TanningRack => "hud-crafting-tanning_rack",
         SpriteKind::DismantlingBench => "hud-crafting-salvaging_station",
+        SpriteKind::Vault => "hud-vault",
         SpriteKind::ChestBuried
         | SpriteKind::Chest
         | SpriteKind::CoralChest
@@ -10,7 +10,7 @@ use common::{
         ability::{Ability, AbilityInput, AuxiliaryAbility},
         item::tool::{AbilityContext, ToolKind},
         slot::InvSlotId,
-        ActiveAbilities, Body, Combo, Energy, Inventory, Item, ItemKey, SkillSet,
+        ActiveAbilities, Body, Combo, Energy, Inventory, Item, ItemKey, SkillSet, Vault,
     },
     recipe::ComponentRecipeBook,
 };
@@ -28,6 +28,7 @@ pub enum SlotKind {
     Trade(TradeSlot),
     Ability(AbilitySlot),
     Crafting(CraftSlot),
+    Vault(VaultSlot),
     /* Spellbook(SpellbookSlot), TODO */
 }
 
@@ -115,6 +116,30 @@ impl SlotKey<Inventory, ItemImgs> for TradeSlot {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VaultSlot {
+    pub index: usize,
+}
+
+impl SlotKey<Vault, ItemImgs> for VaultSlot {
+    type ImageKey = ItemKey;
+
+    fn image_key(&self, source: &Vault) -> Option<(Self::ImageKey, Option<Color>)> {
+        source.get(self.index).map(|i| (i.into(), None))
+    }
+
+    fn amount(&self, source: &Vault) -> Option<u32> {
+        source
+            .get(self.index)
+            .map(|item| item.amount())
+            .filter(|amount| *amount > 1)
+    }
+
+    fn image_ids(key: &Self::ImageKey, source: &ItemImgs) -> Vec<image::Id> {
+        source.img_ids_or_not_found_img(key.clone())
+    }
+}
+
 #[derive(Clone, PartialEq, Eq)]
 pub enum HotbarImage {
     Item(ItemKey),
@@ -324,6 +349,10 @@ impl From<CraftSlot> for SlotKind {
     fn from(craft: CraftSlot) -> Self { Self::Crafting(craft) }
 }
 
+impl From<VaultSlot> for SlotKind {
+    fn from(vault: VaultSlot) -> Self { Self::Vault(vault) }
+}
+
 impl SumSlot for SlotKind {
     fn drag_size(&self) -> Option<[f64; 2]> {
         Some(match self {
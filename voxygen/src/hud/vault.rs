@@ -0,0 +1,263 @@
+use conrod_core::{
+    color,
+    widget::{self, Button, Image, Rectangle, Text},
+    widget_ids, Color, Colorable, Positionable, Sizeable, Widget, WidgetCommon,
+};
+use vek::*;
+
+use client::Client;
+use common::comp::{
+    self,
+    inventory::item::{ItemDesc, MaterialStatManifest, Quality},
+};
+use i18n::Localization;
+
+use crate::ui::{
+    fonts::Fonts,
+    slot::{ContentSize, SlotMaker},
+    ImageFrame, ItemTooltip, ItemTooltipManager, ItemTooltipable,
+};
+
+use super::{
+    img_ids::{Imgs, ImgsRot},
+    item_imgs::ItemImgs,
+    slots::{SlotManager, VaultSlot},
+    HudInfo, TEXT_COLOR, UI_HIGHLIGHT_0, UI_MAIN,
+};
+
+pub enum Event {
+    Close,
+}
+
+widget_ids! {
+    pub struct Ids {
+        vault_close,
+        bg,
+        bg_frame,
+        vault_title_bg,
+        vault_title,
+        slots_align,
+        slots[],
+    }
+}
+
+#[derive(WidgetCommon)]
+pub struct Vault<'a> {
+    client: &'a Client,
+    info: &'a HudInfo,
+    imgs: &'a Imgs,
+    item_imgs: &'a ItemImgs,
+    fonts: &'a Fonts,
+    rot_imgs: &'a ImgsRot,
+    item_tooltip_manager: &'a mut ItemTooltipManager,
+    #[conrod(common_builder)]
+    common: widget::CommonBuilder,
+    slot_manager: &'a mut SlotManager,
+    localized_strings: &'a Localization,
+    msm: &'a MaterialStatManifest,
+    pulse: f32,
+}
+
+impl<'a> Vault<'a> {
+    pub fn new(
+        client: &'a Client,
+        info: &'a HudInfo,
+        imgs: &'a Imgs,
+        item_imgs: &'a ItemImgs,
+        fonts: &'a Fonts,
+        rot_imgs: &'a ImgsRot,
+        item_tooltip_manager: &'a mut ItemTooltipManager,
+        slot_manager: &'a mut SlotManager,
+        localized_strings: &'a Localization,
+        msm: &'a MaterialStatManifest,
+        pulse: f32,
+    ) -> Self {
+        Self {
+            client,
+            info,
+            imgs,
+            item_imgs,
+            fonts,
+            rot_imgs,
+            item_tooltip_manager,
+            common: widget::CommonBuilder::default(),
+            slot_manager,
+            localized_strings,
+            msm,
+            pulse,
+        }
+    }
+}
+
+const SLOTS_PER_ROW: usize = 6;
+const SLOT_SIZE: f64 = 40.0;
+
+pub struct State {
+    ids: Ids,
+}
+
+impl<'a> Widget for Vault<'a> {
+    type Event = Option<Event>;
+    type State = State;
+    type Style = ();
+
+    fn init_state(&self, id_gen: widget::id::Generator) -> Self::State {
+        State {
+            ids: Ids::new(id_gen),
+        }
+    }
+
+    fn style(&self) -> Self::Style {}
+
+    fn update(mut self, args: widget::UpdateArgs<Self>) -> Self::Event {
+        common_base::prof_span!("Vault::update");
+        let widget::UpdateArgs { state, ui, .. } = args;
+
+        let vaults = self.client.state().read_storage::<comp::Vault>();
+        let vault = match vaults.get(self.info.viewpoint_entity) {
+            Some(vault) => vault,
+            None => return Some(Event::Close),
+        };
+
+        let rows = (vault.capacity() + SLOTS_PER_ROW - 1) / SLOTS_PER_ROW;
+        let grid_size = Vec2::new(SLOTS_PER_ROW as f64, rows as f64) * SLOT_SIZE;
+
+        // Background
+        Image::new(self.imgs.inv_middle_bg_bag)
+            .w_h(grid_size.x + 60.0, grid_size.y + 90.0)
+            .color(Some(UI_MAIN))
+            .mid_bottom_with_margin_on(ui.window, 295.0)
+            .set(state.ids.bg, ui);
+        Image::new(self.imgs.inv_middle_frame)
+            .w_h(grid_size.x + 60.0, grid_size.y + 90.0)
+            .middle_of(state.ids.bg)
+            .color(Some(UI_HIGHLIGHT_0))
+            .set(state.ids.bg_frame, ui);
+
+        // Title
+        Text::new(&self.localized_strings.get_msg("hud-vault"))
+            .mid_top_with_margin_on(state.ids.bg_frame, 9.0)
+            .font_id(self.fonts.cyri.conrod_id)
+            .font_size(self.fonts.cyri.scale(20))
+            .color(Color::Rgba(0.0, 0.0, 0.0, 1.0))
+            .set(state.ids.vault_title_bg, ui);
+        Text::new(&self.localized_strings.get_msg("hud-vault"))
+            .top_left_with_margins_on(state.ids.vault_title_bg, 2.0, 2.0)
+            .font_id(self.fonts.cyri.conrod_id)
+            .font_size(self.fonts.cyri.scale(20))
+            .color(TEXT_COLOR)
+            .set(state.ids.vault_title, ui);
+
+        // Tooltips
+        let item_tooltip = ItemTooltip::new(
+            {
+                // Edge images [t, b, r, l]
+                // Corner images [tr, tl, br, bl]
+                let edge = &self.rot_imgs.tt_side;
+                let corner = &self.rot_imgs.tt_corner;
+                ImageFrame::new(
+                    [edge.cw180, edge.none, edge.cw270, edge.cw90],
+                    [corner.none, corner.cw270, corner.cw90, corner.cw180],
+                    Color::Rgba(0.08, 0.07, 0.04, 1.0),
+                    5.0,
+                )
+            },
+            self.client,
+            self.info,
+            self.imgs,
+            self.item_imgs,
+            self.pulse,
+            self.msm,
+            self.localized_strings,
+        )
+        .title_font_size(self.fonts.cyri.scale(20))
+        .parent(ui.window)
+        .desc_font_size(self.fonts.cyri.scale(12))
+        .font_id(self.fonts.cyri.conrod_id)
+        .desc_text_color(TEXT_COLOR);
+
+        // Slots
+        Rectangle::fill_with(grid_size.into_array(), color::TRANSPARENT)
+            .mid_top_with_margin_on(state.ids.bg, 60.0)
+            .set(state.ids.slots_align, ui);
+
+        if state.ids.slots.len() < vault.capacity() {
+            state.update(|s| {
+                s.ids
+                    .slots
+                    .resize(vault.capacity(), &mut ui.widget_id_generator());
+            });
+        }
+
+        let mut slot_maker = SlotMaker {
+            empty_slot: self.imgs.inv_slot,
+            filled_slot: self.imgs.inv_slot,
+            selected_slot: self.imgs.inv_slot_sel,
+            background_color: Some(UI_MAIN),
+            content_size: ContentSize {
+                width_height_ratio: 1.0,
+                max_fraction: 0.75,
+            },
+            selected_content_scale: 1.067,
+            amount_font: self.fonts.cyri.conrod_id,
+            amount_margins: Vec2::new(-4.0, 0.0),
+            amount_font_size: self.fonts.cyri.scale(12),
+            amount_text_color: TEXT_COLOR,
+            content_source: vault,
+            image_source: self.item_imgs,
+            slot_manager: Some(self.slot_manager),
+            pulse: self.pulse,
+        };
+
+        for (i, item) in vault.slots_with_id() {
+            let x = i % SLOTS_PER_ROW;
+            let y = i / SLOTS_PER_ROW;
+
+            let slot_widget = slot_maker
+                .fabricate(VaultSlot { index: i }, [SLOT_SIZE; 2])
+                .top_left_with_margins_on(
+                    state.ids.slots_align,
+                    y as f64 * SLOT_SIZE,
+                    x as f64 * SLOT_SIZE,
+                );
+            if let Some(item) = item {
+                let quality_col_img = match item.quality() {
+                    Quality::Low => self.imgs.inv_slot_grey,
+                    Quality::Common => self.imgs.inv_slot_common,
+                    Quality::Moderate => self.imgs.inv_slot_green,
+                    Quality::High => self.imgs.inv_slot_blue,
+                    Quality::Epic => self.imgs.inv_slot_purple,
+                    Quality::Legendary => self.imgs.inv_slot_gold,
+                    Quality::Artifact => self.imgs.inv_slot_orange,
+                    _ => self.imgs.inv_slot_red,
+                };
+
+                slot_widget
+                    .filled_slot(quality_col_img)
+                    .with_item_tooltip(
+                        self.item_tooltip_manager,
+                        core::iter::once(item as &dyn ItemDesc),
+                        &None,
+                        &item_tooltip,
+                    )
+                    .set(state.ids.slots[i], ui);
+            } else {
+                slot_widget.set(state.ids.slots[i], ui);
+            }
+        }
+
+        // Close button
+        if Button::image(self.imgs.close_btn)
+            .w_h(24.0, 25.0)
+            .hover_image(self.imgs.close_btn_hover)
+            .press_image(self.imgs.close_btn_press)
+            .top_right_with_margins_on(state.ids.bg, 0.0, 0.0)
+            .set(state.ids.vault_close, ui)
+            .was_clicked()
+        {
+            Some(Event::Close)
+        } else {
+            None
+        }
+    }
+}
@@ -51,6 +51,7 @@ use crate::{
     settings::Settings,
     window::{Event, Window},
 };
+use client::replay::ReplayServer;
 use common::clock::Clock;
 use common_base::span;
 use i18n::LocalizationHandle;
@@ -75,6 +76,8 @@ pub struct GlobalState {
     pub clock: Clock,
     #[cfg(feature = "singleplayer")]
     pub singleplayer: Option<Singleplayer>,
+    /// The replay being played back, see [`client::replay`]
+    pub replay: Option<ReplayServer>,
     // TODO: redo this so that the watcher doesn't have to exist for reloading to occur
     pub i18n: LocalizationHandle,
     pub clipboard: iced_winit::Clipboard,
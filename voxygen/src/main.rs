@@ -221,6 +221,7 @@ fn main() {
         info_message: None,
         #[cfg(feature = "singleplayer")]
         singleplayer: None,
+        replay: None,
         i18n,
         clipboard,
         clear_shadows_next_frame: false,
@@ -228,5 +229,5 @@ fn main() {
         discord,
     };
 
-    run::run(global_state, event_loop, args.server);
+    run::run(global_state, event_loop, args.server, args.replay);
 }
@@ -1,10 +1,14 @@
 use client::{
     addr::ConnectionArgs,
-    error::{Error as ClientError, NetworkConnectError, NetworkError},
+    error::{
+        EncryptionError, Error as ClientError, InitProtocolError, NetworkConnectError,
+        NetworkError, PublicKey,
+    },
     Client, ServerInfo,
 };
 use crossbeam_channel::{unbounded, Receiver, Sender, TryRecvError};
 use std::{
+    path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -12,7 +16,7 @@ use std::{
     time::Duration,
 };
 use tokio::runtime;
-use tracing::{trace, warn};
+use tracing::{info, trace, warn};
 
 #[derive(Debug)]
 #[allow(clippy::enum_variant_names)] //TODO: evaluate ClientError ends with Enum name
@@ -24,33 +28,48 @@ pub enum Error {
     },
     ClientCrashed,
     ServerNotFound,
+    /// The user didn't trust the key of a server connected to for the first
+    /// time
+    ServerKeyNotTrusted,
 }
 
 #[allow(clippy::large_enum_variant)] // TODO: Pending review in #587
 pub enum Msg {
     IsAuthTrusted(String),
+    /// The key of a server we connected to for the first time, the user has
+    /// to confirm it before it's pinned
+    IsServerKeyTrusted {
+        hostname: String,
+        key: PublicKey,
+    },
     Done(Result<Client, Error>),
 }
 
 pub struct AuthTrust(String, bool);
 
+pub struct ServerKeyTrust(PublicKey, bool);
+
 // Used to asynchronously parse the server address, resolve host names,
 // and create the client (which involves establishing a connection to the
 // server).
 pub struct ClientInit {
     rx: Receiver<Msg>,
     trust_tx: Sender<AuthTrust>,
+    key_trust_tx: Sender<ServerKeyTrust>,
     cancel: Arc<AtomicBool>,
 }
 impl ClientInit {
+    /// The session is recorded into a replay at `replay_path` if it is set
     pub fn new(
-        connection_args: ConnectionArgs,
+        mut connection_args: ConnectionArgs,
         username: String,
         password: String,
         runtime: Arc<runtime::Runtime>,
+        replay_path: Option<PathBuf>,
     ) -> Self {
         let (tx, rx) = unbounded();
         let (trust_tx, trust_rx) = unbounded();
+        let (key_trust_tx, key_trust_rx) = unbounded();
         let cancel = Arc::new(AtomicBool::new(false));
         let cancel2 = Arc::clone(&cancel);
 
@@ -73,16 +92,32 @@ impl ClientInit {
                     break;
                 }
                 let mut mismatched_server_info = None;
-                match Client::new(
-                    connection_args.clone(),
-                    Arc::clone(&runtime2),
-                    &mut mismatched_server_info,
-                    &username,
-                    &password,
-                    trust_fn,
-                )
-                .await
-                {
+                let client = match &replay_path {
+                    Some(replay_path) => {
+                        Client::new_recording(
+                            connection_args.clone(),
+                            Arc::clone(&runtime2),
+                            &mut mismatched_server_info,
+                            &username,
+                            &password,
+                            trust_fn,
+                            replay_path,
+                        )
+                        .await
+                    },
+                    None => {
+                        Client::new(
+                            connection_args.clone(),
+                            Arc::clone(&runtime2),
+                            &mut mismatched_server_info,
+                            &username,
+                            &password,
+                            trust_fn,
+                        )
+                        .await
+                    },
+                };
+                match client {
                     Ok(client) => {
                         let _ = tx.send(Msg::Done(Ok(client)));
                         tokio::task::block_in_place(move || drop(runtime2));
@@ -93,6 +128,35 @@ impl ClientInit {
                     ))) => {
                         warn!(?e, "Failed to connect to the server. Retrying...");
                     },
+                    Err(ClientError::NetworkErr(NetworkError::ConnectFailed(
+                        NetworkConnectError::Handshake(InitProtocolError::Encryption(
+                            EncryptionError::UnknownServerKey(key),
+                        )),
+                    ))) => {
+                        // Only connecting without a pinned key fails like this, pin the key
+                        // on the first connection like SSH does, if the user trusts it
+                        if let ConnectionArgs::EncryptedTcp {
+                            hostname, pinned, ..
+                        } = &mut connection_args
+                        {
+                            let _ = tx.send(Msg::IsServerKeyTrusted {
+                                hostname: hostname.clone(),
+                                key,
+                            });
+                            let trusted = key_trust_rx
+                                .recv()
+                                .map_or(false, |ServerKeyTrust(trusted_key, trust)| {
+                                    trust && trusted_key == key
+                                });
+                            if !trusted {
+                                last_err = Some(Error::ServerKeyNotTrusted);
+                                break 'tries;
+                            }
+                            info!(%key, %hostname, "Pinning the key of a new server");
+                            *pinned = Some(key);
+                            continue;
+                        }
+                    },
                     Err(e) => {
                         trace!(?e, "Aborting server connection attempt");
                         last_err = Some(Error::ClientError {
@@ -117,6 +181,7 @@ impl ClientInit {
         ClientInit {
             rx,
             trust_tx,
+            key_trust_tx,
             cancel,
         }
     }
@@ -137,6 +202,11 @@ impl ClientInit {
         let _ = self.trust_tx.send(AuthTrust(auth_server, trusted));
     }
 
+    /// Report whether the user trusts the key of a new server
+    pub fn server_key_trust(&self, key: PublicKey, trusted: bool) {
+        let _ = self.key_trust_tx.send(ServerKeyTrust(key, trusted));
+    }
+
     pub fn cancel(&mut self) { self.cancel.store(true, Ordering::Relaxed); }
 }
 
@@ -7,23 +7,33 @@ use super::char_selection::CharSelectionState;
 use crate::singleplayer::Singleplayer;
 use crate::{
     render::{Drawer, GlobalsBindGroup},
+    session::SessionState,
     settings::Settings,
     window::Event,
     Direction, GlobalState, PlayState, PlayStateResult,
 };
 use client::{
     addr::ConnectionArgs,
-    error::{InitProtocolError, NetworkConnectError, NetworkError},
+    error::{EncryptionError, InitProtocolError, NetworkConnectError, NetworkError},
+    query::{query_status, ServerStatus},
+    replay::ReplayServer,
     Client, ServerInfo,
 };
 use client_init::{ClientInit, Error as InitError, Msg as InitMsg};
-use common::comp;
+use common::{comp, event::UpdateCharacterMetadata};
 use common_base::span;
+use crossbeam_channel::{unbounded, Receiver};
 use i18n::LocalizationHandle;
 use scene::Scene;
-use std::sync::Arc;
+use std::{
+    cell::RefCell,
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 use tokio::runtime;
-use tracing::error;
+use tracing::{debug, error};
 use ui::{Event as MainMenuEvent, MainMenuUi};
 
 // TODO: show status messages for waiting on server creation, client init, and
@@ -46,19 +56,31 @@ impl InitState {
     }
 }
 
+type StatusAnswer = (String, Option<(ServerStatus, Duration)>);
+
 pub struct MainMenuState {
     main_menu_ui: MainMenuUi,
     init: InitState,
     scene: Scene,
+    /// Answers to the status queries of the servers in the list
+    status_answers: Option<Receiver<StatusAnswer>>,
+    /// Replay to play back on the first tick
+    replay_path: Option<PathBuf>,
 }
 
 impl MainMenuState {
     /// Create a new `MainMenuState`.
-    pub fn new(global_state: &mut GlobalState, server: Option<String>) -> Self {
+    pub fn new(
+        global_state: &mut GlobalState,
+        server: Option<String>,
+        replay_path: Option<PathBuf>,
+    ) -> Self {
         Self {
             main_menu_ui: MainMenuUi::new(global_state, server),
             init: InitState::None,
             scene: Scene::new(global_state.window.renderer_mut()),
+            status_answers: None,
+            replay_path,
         }
     }
 }
@@ -75,6 +97,7 @@ impl PlayState for MainMenuState {
         {
             global_state.singleplayer = None;
         }
+        global_state.replay = None;
 
         // Updated localization in case the selected language was changed
         self.main_menu_ui
@@ -109,6 +132,7 @@ impl PlayState for MainMenuState {
                             &mut self.init,
                             &global_state.tokio_runtime,
                             &global_state.i18n,
+                            None,
                         );
                     },
                     Ok(Err(e)) => {
@@ -169,6 +193,34 @@ impl PlayState for MainMenuState {
                 }
             }
         }
+        if let Some(path) = self.replay_path.take() {
+            match ReplayServer::start(&path, &global_state.tokio_runtime) {
+                Ok(replay) => {
+                    attempt_login(
+                        &mut global_state.info_message,
+                        "replay".to_owned(),
+                        "".to_owned(),
+                        replay.connection_args(),
+                        &mut self.init,
+                        &global_state.tokio_runtime,
+                        &global_state.i18n,
+                        None,
+                    );
+                    global_state.replay = Some(replay);
+                },
+                Err(e) => {
+                    error!(?e, ?path, "Could not play back the replay");
+                    global_state.info_message = Some(
+                        localized_strings
+                            .get_msg_ctx("main-replay-failed", &i18n::fluent_args! {
+                                "reason" => e.to_string()
+                            })
+                            .into_owned(),
+                    );
+                },
+            }
+        }
+
         // Handle window events.
         for event in events {
             // Pass all events to the ui first.
@@ -204,6 +256,10 @@ impl PlayState for MainMenuState {
                         .into_owned(),
                 );
             },
+            Some(InitMsg::IsServerKeyTrusted { hostname, key }) => {
+                // Show the key of the new server and prompt for approval
+                self.main_menu_ui.server_key_prompt(hostname, key);
+            },
             Some(InitMsg::IsAuthTrusted(auth_server)) => {
                 if global_state
                     .settings
@@ -270,14 +326,34 @@ impl PlayState for MainMenuState {
                     core::mem::replace(&mut self.init, InitState::None)
                 {
                     self.main_menu_ui.connected();
+                    let client = Rc::new(RefCell::new(*client));
+                    // Replays have no characters to select, they are watched as spectator
+                    if global_state.replay.is_some() {
+                        let graphics = &global_state.settings.graphics;
+                        client.borrow_mut().request_spectate(common::ViewDistances {
+                            terrain: graphics.terrain_view_distance,
+                            entity: graphics.entity_view_distance,
+                        });
+                        return PlayStateResult::Push(Box::new(SessionState::new(
+                            global_state,
+                            UpdateCharacterMetadata::default(),
+                            client,
+                        )));
+                    }
                     return PlayStateResult::Push(Box::new(CharSelectionState::new(
                         global_state,
-                        std::rc::Rc::new(std::cell::RefCell::new(*client)),
+                        client,
                     )));
                 }
             }
         }
 
+        if let Some(status_answers) = &self.status_answers {
+            for (server, status) in status_answers.try_iter() {
+                self.main_menu_ui.server_status(server, status);
+            }
+        }
+
         // Maintain the UI.
         for event in self
             .main_menu_ui
@@ -290,7 +366,26 @@ impl PlayState for MainMenuState {
                     server_address,
                 } => {
                     let mut net_settings = &mut global_state.settings.networking;
+                    let replay_path = net_settings.record_replays.then(|| {
+                        global_state
+                            .userdata_dir
+                            .join("voxygen")
+                            .join("replays")
+                            .join(format!(
+                                "replay_{}.vreplay",
+                                SystemTime::now()
+                                    .duration_since(SystemTime::UNIX_EPOCH)
+                                    .map(|d| d.as_millis())
+                                    .unwrap_or(0)
+                            ))
+                    });
                     let use_quic = net_settings.use_quic;
+                    let use_encryption = net_settings.use_encryption;
+                    let prefer_ipv6 = net_settings.prefer_ipv6;
+                    let pinned = net_settings
+                        .server_keys
+                        .get(&server_address)
+                        .and_then(|key| key.parse().ok());
                     net_settings.username = username.clone();
                     net_settings.default_server = server_address.clone();
                     if !net_settings.servers.contains(&server_address) {
@@ -303,12 +398,18 @@ impl PlayState for MainMenuState {
                     let connection_args = if use_quic {
                         ConnectionArgs::Quic {
                             hostname: server_address,
-                            prefer_ipv6: false,
+                            prefer_ipv6,
+                        }
+                    } else if use_encryption {
+                        ConnectionArgs::EncryptedTcp {
+                            hostname: server_address,
+                            prefer_ipv6,
+                            pinned,
                         }
                     } else {
                         ConnectionArgs::Tcp {
                             hostname: server_address,
-                            prefer_ipv6: false,
+                            prefer_ipv6,
                         }
                     };
                     attempt_login(
@@ -319,6 +420,7 @@ impl PlayState for MainMenuState {
                         &mut self.init,
                         &global_state.tokio_runtime,
                         &global_state.i18n,
+                        replay_path,
                     );
                 },
                 MainMenuEvent::CancelLoginAttempt => {
@@ -329,6 +431,7 @@ impl PlayState for MainMenuState {
                     {
                         global_state.singleplayer = None;
                     }
+                    global_state.replay = None;
                     self.init = InitState::None;
                     self.main_menu_ui.cancel_connection();
                 },
@@ -370,6 +473,21 @@ impl PlayState for MainMenuState {
                         .client()
                         .map(|init| init.auth_trust(auth_server, trust));
                 },
+                MainMenuEvent::ServerKeyTrust(hostname, key, trust) => {
+                    if trust {
+                        global_state
+                            .settings
+                            .networking
+                            .server_keys
+                            .insert(hostname, key.to_string());
+                        global_state
+                            .settings
+                            .save_to_file_warn(&global_state.config_dir);
+                    }
+                    self.init
+                        .client()
+                        .map(|init| init.server_key_trust(key, trust));
+                },
                 MainMenuEvent::DeleteServer { server_index } => {
                     let net_settings = &mut global_state.settings.networking;
                     net_settings.servers.remove(server_index);
@@ -378,6 +496,24 @@ impl PlayState for MainMenuState {
                         .settings
                         .save_to_file_warn(&global_state.config_dir);
                 },
+                MainMenuEvent::QueryServers => {
+                    let (sender, receiver) = unbounded();
+                    let net_settings = &global_state.settings.networking;
+                    let query_port = net_settings.query_port;
+                    let prefer_ipv6 = net_settings.prefer_ipv6;
+                    for server in net_settings.servers.iter().cloned() {
+                        let sender = sender.clone();
+                        global_state.tokio_runtime.spawn(async move {
+                            let status = query_status(&server, query_port, prefer_ipv6)
+                                .await
+                                .map_err(|e| debug!(?e, ?server, "Status query failed"))
+                                .ok();
+                            let _ = sender.send((server, status));
+                        });
+                    }
+                    // Answers to earlier queries aren't needed anymore
+                    self.status_answers = Some(receiver);
+                },
             }
         }
 
@@ -475,6 +611,17 @@ fn get_client_msg_error(
                     .into_owned(),
                 mismatched_server_info,
             ),
+            Error::NetworkErr(NetworkError::ConnectFailed(NetworkConnectError::Handshake(
+                InitProtocolError::Encryption(EncryptionError::ServerKeyMismatch {
+                    expected,
+                    actual,
+                }),
+            ))) => localization
+                .get_msg_ctx("main-login-server_key_mismatch", &i18n::fluent_args! {
+                    "expected" => expected.to_string(),
+                    "actual" => actual.to_string(),
+                })
+                .into_owned(),
             Error::NetworkErr(e) => net_error(e.to_string(), mismatched_server_info),
             Error::ParticipantErr(e) => net_error(e.to_string(), mismatched_server_info),
             Error::StreamErr(e) => net_error(e.to_string(), mismatched_server_info),
@@ -515,6 +662,9 @@ fn get_client_msg_error(
         },
         InitError::ClientCrashed => localization.get_msg("main-login-client_crashed").into(),
         InitError::ServerNotFound => localization.get_msg("main-login-server_not_found").into(),
+        InitError::ServerKeyNotTrusted => localization
+            .get_msg("main-login-server_key_not_trusted")
+            .into(),
     }
 }
 
@@ -526,6 +676,7 @@ fn attempt_login(
     init: &mut InitState,
     runtime: &Arc<runtime::Runtime>,
     localized_strings: &LocalizationHandle,
+    replay_path: Option<PathBuf>,
 ) {
     let localization = localized_strings.read();
     if let Err(err) = comp::Player::alias_validate(&username) {
@@ -557,6 +708,7 @@ fn attempt_login(
             username,
             password,
             Arc::clone(runtime),
+            replay_path,
         ));
     }
 }
@@ -188,7 +188,8 @@ impl Screen {
                     bottom_bar.into(),
                 ]
             },
-            ConnectionState::AuthTrustPrompt { msg, .. } => {
+            ConnectionState::AuthTrustPrompt { msg, .. }
+            | ConnectionState::ServerKeyPrompt { msg, .. } => {
                 let text = Text::new(msg).size(fonts.cyri.scale(25));
 
                 let cancel = neat_button(
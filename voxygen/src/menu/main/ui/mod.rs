@@ -22,9 +22,11 @@ use iced::{text_input, Column, Container, HorizontalAlignment, Length, Row, Spac
 use keyboard_keynames::key_layout::KeyLayout;
 //ImageFrame, Tooltip,
 use crate::settings::Settings;
+use client::{error::PublicKey, query::ServerStatus};
 use common::assets::{self, AssetExt};
 use rand::{seq::SliceRandom, thread_rng};
-use std::time::Duration;
+use servers::ServerQuery;
+use std::{collections::HashMap, time::Duration};
 use tracing::warn;
 
 // TODO: what is this? (showed up in rebase)
@@ -91,9 +93,14 @@ pub enum Event {
     // Note: Keeping in case we re-add the disclaimer
     //DisclaimerAccepted,
     AuthServerTrust(String, bool),
+    /// Whether the user trusts the key of a server connected to for the first
+    /// time
+    ServerKeyTrust(String, PublicKey, bool),
     DeleteServer {
         server_index: usize,
     },
+    /// Query the status of the servers in the list
+    QueryServers,
 }
 
 pub struct LoginInfo {
@@ -104,7 +111,15 @@ pub struct LoginInfo {
 
 enum ConnectionState {
     InProgress,
-    AuthTrustPrompt { auth_server: String, msg: String },
+    AuthTrustPrompt {
+        auth_server: String,
+        msg: String,
+    },
+    ServerKeyPrompt {
+        hostname: String,
+        key: PublicKey,
+        msg: String,
+    },
 }
 
 enum Screen {
@@ -145,6 +160,7 @@ struct Controls {
     // field).
     server_field_locked: bool,
     selected_server_index: Option<usize>,
+    server_statuses: HashMap<String, ServerQuery>,
     login_info: LoginInfo,
 
     is_selecting_language: bool,
@@ -235,6 +251,7 @@ impl Controls {
 
             server_field_locked,
             selected_server_index,
+            server_statuses: HashMap::new(),
             login_info,
 
             is_selecting_language: false,
@@ -316,6 +333,7 @@ impl Controls {
                 &self.fonts,
                 &self.imgs,
                 &settings.networking.servers,
+                &self.server_statuses,
                 self.selected_server_index,
                 &self.i18n.read(),
                 button_style,
@@ -371,6 +389,11 @@ impl Controls {
                     self.screen = Screen::Servers {
                         screen: servers::Screen::new(),
                     };
+                    self.server_statuses = servers
+                        .iter()
+                        .map(|server| (server.clone(), ServerQuery::Pending))
+                        .collect();
+                    events.push(Event::QueryServers);
                 }
             },
             Message::ShowCredits => {
@@ -427,12 +450,21 @@ impl Controls {
                     connection_state, ..
                 } = &mut self.screen
                 {
-                    if let ConnectionState::AuthTrustPrompt { auth_server, .. } = connection_state {
-                        let auth_server = std::mem::take(auth_server);
-                        let added = matches!(msg, Message::TrustPromptAdd);
-
-                        *connection_state = ConnectionState::InProgress;
-                        events.push(Event::AuthServerTrust(auth_server, added));
+                    let added = matches!(msg, Message::TrustPromptAdd);
+                    match connection_state {
+                        ConnectionState::AuthTrustPrompt { auth_server, .. } => {
+                            let auth_server = std::mem::take(auth_server);
+
+                            *connection_state = ConnectionState::InProgress;
+                            events.push(Event::AuthServerTrust(auth_server, added));
+                        },
+                        ConnectionState::ServerKeyPrompt { hostname, key, .. } => {
+                            let (hostname, key) = (std::mem::take(hostname), *key);
+
+                            *connection_state = ConnectionState::InProgress;
+                            events.push(Event::ServerKeyTrust(hostname, key, added));
+                        },
+                        ConnectionState::InProgress => {},
                     }
                 }
             },
@@ -486,6 +518,24 @@ impl Controls {
         }
     }
 
+    fn server_key_prompt(&mut self, hostname: String, key: PublicKey) {
+        if let Screen::Connecting {
+            connection_state, ..
+        } = &mut self.screen
+        {
+            let msg = format!(
+                "This is your first encrypted connection to:\n\n{}\n\nThe server identifies \
+                 itself with the key:\n\n{}\n\nIf the server announced its key somewhere else, \
+                 check that they match. The key is saved and the server has to show it again on \
+                 every connection.",
+                &hostname,
+                key.fingerprint()
+            );
+
+            *connection_state = ConnectionState::ServerKeyPrompt { hostname, key, msg };
+        }
+    }
+
     fn connection_error(&mut self, error: String) {
         if matches!(&self.screen, Screen::Connecting { .. })
             || matches!(&self.screen, Screen::Login { .. })
@@ -582,8 +632,22 @@ impl MainMenuUi {
         self.controls.auth_trust_prompt(auth_server);
     }
 
+    pub fn server_key_prompt(&mut self, hostname: String, key: PublicKey) {
+        self.controls.server_key_prompt(hostname, key);
+    }
+
     pub fn show_info(&mut self, msg: String) { self.controls.connection_error(msg); }
 
+    /// Show the answer to the status query of a server in the list, `None` if
+    /// it didn't answer
+    pub fn server_status(&mut self, server: String, status: Option<(ServerStatus, Duration)>) {
+        let query = match status {
+            Some((status, ping)) => ServerQuery::Answered { status, ping },
+            None => ServerQuery::Failed,
+        };
+        self.controls.server_statuses.insert(server, query);
+    }
+
     pub fn connected(&mut self) { self.controls.exit_connect_screen(); }
 
     pub fn cancel_connection(&mut self) { self.controls.exit_connect_screen(); }
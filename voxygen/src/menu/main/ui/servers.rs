@@ -3,10 +3,23 @@ use crate::ui::{
     fonts::IcedFonts as Fonts,
     ice::{component::neat_button, style, Element},
 };
+use client::query::ServerStatus;
+use common::resources::BattleMode;
 use i18n::Localization;
 use iced::{
     button, scrollable, Align, Button, Column, Container, Length, Row, Scrollable, Space, Text,
 };
+use std::{collections::HashMap, time::Duration};
+
+/// Status of a server in the list, queried whenever the list is opened
+pub enum ServerQuery {
+    Pending,
+    Answered {
+        status: ServerStatus,
+        ping: Duration,
+    },
+    Failed,
+}
 
 pub struct Screen {
     back_button: button::State,
@@ -30,6 +43,7 @@ impl Screen {
         fonts: &Fonts,
         imgs: &Imgs,
         servers: &[impl AsRef<str>],
+        statuses: &HashMap<String, ServerQuery>,
         selected_server_index: Option<usize>,
         i18n: &Localization,
         button_style: style::button::Style,
@@ -87,16 +101,42 @@ impl Screen {
                     } else {
                         (97, 97, 25)
                     };
+                    let (status_text, motd) = match statuses.get(server.as_ref()) {
+                        Some(ServerQuery::Answered { status, ping }) => (
+                            status_line(status, *ping, i18n),
+                            // Only the first line fits into the list
+                            status
+                                .description
+                                .lines()
+                                .next()
+                                .unwrap_or_default()
+                                .to_owned(),
+                        ),
+                        Some(ServerQuery::Failed) => (
+                            i18n.get_msg("main-servers-unreachable").into_owned(),
+                            String::new(),
+                        ),
+                        Some(ServerQuery::Pending) | None => (
+                            i18n.get_msg("main-servers-querying").into_owned(),
+                            String::new(),
+                        ),
+                    };
                     let button = Button::new(
                         state,
                         Row::with_children(vec![
                             Space::new(Length::FillPortion(5), Length::Units(0)).into(),
-                            Text::new(server.as_ref())
-                                .size(fonts.cyri.scale(30))
-                                .width(Length::FillPortion(95))
-                                .vertical_alignment(iced::VerticalAlignment::Center)
-                                .into(),
-                        ]),
+                            Column::with_children(vec![
+                                Text::new(server.as_ref()).size(fonts.cyri.scale(30)).into(),
+                                Text::new(status_text)
+                                    .size(fonts.cyri.scale(18))
+                                    .color(iced::Color::from_rgb8(0xc5, 0xc5, 0xc5))
+                                    .into(),
+                                Text::new(motd).size(fonts.cyri.scale(18)).into(),
+                            ])
+                            .width(Length::FillPortion(95))
+                            .into(),
+                        ])
+                        .align_items(Align::Center),
                     )
                     .style(
                         style::button::Style::new(imgs.selection)
@@ -146,3 +186,32 @@ impl Screen {
         .into()
     }
 }
+
+/// Players, ping and battle mode of a server, and whether it runs a
+/// different version
+fn status_line(status: &ServerStatus, ping: Duration, i18n: &Localization) -> String {
+    let battle_mode = match status.battle_mode {
+        BattleMode::PvP => i18n.get_msg("main-servers-pvp"),
+        BattleMode::PvE => i18n.get_msg("main-servers-pve"),
+    };
+    let battle_mode = if status.battle_mode_choosable {
+        i18n.get_msg_ctx("main-servers-battle_mode_choosable", &i18n::fluent_args! {
+            "default" => battle_mode.into_owned(),
+        })
+    } else {
+        battle_mode
+    };
+    let mut line = i18n
+        .get_msg_ctx("main-servers-status", &i18n::fluent_args! {
+            "players" => status.players,
+            "max_players" => status.max_players,
+            "ping" => ping.as_millis() as u64,
+            "battle_mode" => battle_mode.into_owned(),
+        })
+        .into_owned();
+    if status.git_hash != *common::util::GIT_HASH {
+        line.push_str(" · ");
+        line.push_str(&i18n.get_msg("main-servers-different_version"));
+    }
+    line
+}
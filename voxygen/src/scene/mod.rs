@@ -38,7 +38,7 @@ use common::{
     vol::ReadVol,
 };
 use common_base::{prof_span, span};
-use common_net::msg::PresenceKind;
+use common_net::{msg::PresenceKind, sync::WorldSyncExt};
 use common_state::State;
 use comp::item::Reagent;
 use hashbrown::HashMap;
@@ -544,6 +544,16 @@ impl Scene {
                     .rotate_by(Vec3::from([self.camera_input_state.x, 0.0, 0.0]));
                 self.camera
                     .rotate_by(Vec3::from([0.0, self.camera_input_state.y, 0.0]));
+            } else if let Some(look_dir) = client.spectated_look_dir()
+                && client
+                    .spectating()
+                    .and_then(|uid| ecs.entity_from_uid(uid.0))
+                    == Some(scene_data.viewpoint_entity)
+            {
+                // Look where the player followed as spectator looks
+                let yaw = look_dir.x.atan2(look_dir.y);
+                let pitch = (-look_dir.z).asin();
+                self.camera.set_orientation(Vec3::new(yaw, pitch, 0.0));
             } else {
                 // Otherwise set the cameras rotation to the viewpoints
                 let q = viewpoint_ori;
@@ -10,6 +10,7 @@ pub enum Interaction {
     Collect,
     Craft(CraftingTab),
     Mine,
+    Vault,
 }
 
 pub enum FireplaceType {
@@ -164,6 +165,7 @@ impl BlocksOfInterest {
                         fires.push(pos);
                         interactables.push((pos, Interaction::Craft(CraftingTab::Dismantle)))
                     },
+                    Some(SpriteKind::Vault) => interactables.push((pos, Interaction::Vault)),
                     _ => {},
                 },
             }
@@ -28,6 +28,7 @@ use common::{
     recipe,
     terrain::{Block, BlockKind},
     trade::TradeResult,
+    uid::Uid,
     util::{Dir, Plane},
     vol::ReadVol,
 };
@@ -398,6 +399,12 @@ impl SessionState {
                 client::Event::SpectatePosition(pos) => {
                     self.scene.camera_mut().force_focus_pos(pos);
                 },
+                client::Event::Spectating(target) => {
+                    if target.is_none() && self.viewpoint_entity.is_some() {
+                        self.viewpoint_entity = None;
+                        self.scene.camera_mut().set_mode(CameraMode::Freefly);
+                    }
+                },
             }
         }
 
@@ -860,6 +867,9 @@ impl PlayState for SessionState {
                                                         )
                                                     },
                                                     Interaction::Mine => {},
+                                                    Interaction::Vault => {
+                                                        self.hud.show.open_vault(pos)
+                                                    },
                                                 }
                                             },
                                             Interactable::Entity(entity) => {
@@ -992,9 +1002,15 @@ impl PlayState for SessionState {
                                 }
                             },
                             GameInput::SpectateViewpoint if state => {
+                                let mut client = self.client.borrow_mut();
+                                let is_spectator =
+                                    client.presence() == Some(PresenceKind::Spectator);
                                 if self.viewpoint_entity.is_some() {
                                     self.viewpoint_entity = None;
                                     self.scene.camera_mut().set_mode(CameraMode::Freefly);
+                                    if client.spectating().is_some() {
+                                        client.spectate_entity(None);
+                                    }
                                 } else if let Some(interactable) = self.interactable {
                                     if self.scene.camera().get_mode() == CameraMode::Freefly {
                                         match interactable {
@@ -1004,11 +1020,42 @@ impl PlayState for SessionState {
                                                 self.scene
                                                     .camera_mut()
                                                     .set_mode(CameraMode::FirstPerson);
+                                                // Spectators follow it on the server too, so
+                                                // the world around it stays loaded
+                                                let uid = client
+                                                    .state()
+                                                    .read_component_copied::<Uid>(entity);
+                                                if is_spectator && uid.is_some() {
+                                                    client.spectate_entity(uid);
+                                                }
                                             },
                                         }
                                     }
                                 }
                             },
+                            GameInput::SpectateNextPlayer if state => {
+                                let mut client = self.client.borrow_mut();
+                                if client.presence() == Some(PresenceKind::Spectator) {
+                                    let mut players = client
+                                        .player_list()
+                                        .iter()
+                                        .filter(|(_, info)| info.character.is_some())
+                                        .map(|(uid, _)| *uid)
+                                        .collect::<Vec<_>>();
+                                    players.sort_by_key(|uid| uid.0);
+                                    // The player after the followed one, wrapping around
+                                    let next =
+                                        client.spectating().map_or(players.first(), |current| {
+                                            players
+                                                .iter()
+                                                .find(|uid| uid.0 > current.0)
+                                                .or_else(|| players.first())
+                                        });
+                                    if let Some(next) = next.copied() {
+                                        client.spectate_entity(Some(next));
+                                    }
+                                }
+                            },
                             _ => {},
                         }
                     },
@@ -1038,6 +1085,18 @@ impl PlayState for SessionState {
                 }
             }
 
+            // View the entity followed as spectator once it got synced
+            let spectated_entity = {
+                let client = self.client.borrow();
+                client
+                    .spectating()
+                    .and_then(|uid| client.state().ecs().entity_from_uid(uid.0))
+            };
+            if spectated_entity.is_some() && spectated_entity != self.viewpoint_entity {
+                self.viewpoint_entity = spectated_entity;
+                self.scene.camera_mut().set_mode(CameraMode::FirstPerson);
+            }
+
             if self.viewpoint_entity.map_or(false, |entity| {
                 !self
                     .client
@@ -1418,6 +1477,7 @@ impl PlayState for SessionState {
                                             move_allowed = false;
                                         }
                                     },
+                                    Slot::Vault(_) => {},
                                 }
                             };
                         }
@@ -1477,7 +1537,14 @@ impl PlayState for SessionState {
                             }
                         }
                         if move_allowed {
-                            self.client.borrow_mut().swap_slots(slot_a, slot_b);
+                            let mut client = self.client.borrow_mut();
+                            match (self.hud.show.vault(), slot_a, slot_b) {
+                                (Some(vault_pos), Slot::Vault(_), _)
+                                | (Some(vault_pos), _, Slot::Vault(_)) => {
+                                    client.swap_vault_slots(slot_a, slot_b, vault_pos)
+                                },
+                                _ => client.swap_slots(slot_a, slot_b),
+                            }
                         }
                     },
                     HudEvent::SelectExpBar(skillgroup) => {
@@ -1531,7 +1598,14 @@ impl PlayState for SessionState {
                             }
                         };
                         if move_allowed {
-                            self.client.borrow_mut().split_swap_slots(slot_a, slot_b);
+                            let mut client = self.client.borrow_mut();
+                            match (self.hud.show.vault(), slot_a, slot_b) {
+                                (Some(vault_pos), Slot::Vault(_), _)
+                                | (Some(vault_pos), _, Slot::Vault(_)) => {
+                                    client.split_swap_vault_slots(slot_a, slot_b, vault_pos)
+                                },
+                                _ => client.split_swap_slots(slot_a, slot_b),
+                            }
                         }
                     },
                     HudEvent::DropSlot(x) => {
@@ -191,6 +191,7 @@ impl ControlSettings {
             GameInput::MapSetMarker => Some(KeyMouse::Mouse(MouseButton::Middle)),
             GameInput::SpectateSpeedBoost => Some(KeyMouse::Key(VirtualKeyCode::LControl)),
             GameInput::SpectateViewpoint => Some(KeyMouse::Mouse(MouseButton::Middle)),
+            GameInput::SpectateNextPlayer => Some(KeyMouse::Key(VirtualKeyCode::V)),
             GameInput::MuteMaster => Some(KeyMouse::Key(VirtualKeyCode::Mute)),
             GameInput::MuteInactiveMaster => None,
             GameInput::MuteMusic => Some(KeyMouse::Key(VirtualKeyCode::F8)),
@@ -1,4 +1,5 @@
-use hashbrown::HashSet;
+use client::query::DEFAULT_QUERY_PORT;
+use hashbrown::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 
 /// `NetworkingSettings` stores server and networking settings.
@@ -10,9 +11,20 @@ pub struct NetworkingSettings {
     pub default_server: String,
     pub trusted_auth_servers: HashSet<String>,
     pub use_quic: bool,
+    pub use_encryption: bool,
+    /// Resolve the server addresses to IPv6 addresses first
+    pub prefer_ipv6: bool,
+    /// UDP port the status of the servers in the server list is queried on
+    pub query_port: u16,
+    /// Keys of the servers connected to with encryption by their address, as
+    /// hex. They are pinned on the first connection, like SSH known hosts.
+    pub server_keys: HashMap<String, String>,
     pub player_physics_behavior: bool,
     pub lossy_terrain_compression: bool,
     pub enable_discord_integration: bool,
+    /// Record the sessions on servers into replay files in the userdata
+    /// directory
+    pub record_replays: bool,
 }
 
 impl Default for NetworkingSettings {
@@ -26,9 +38,14 @@ impl Default for NetworkingSettings {
                 .map(|s| s.to_string())
                 .collect(),
             use_quic: false,
+            use_encryption: false,
+            prefer_ipv6: false,
+            query_port: DEFAULT_QUERY_PORT,
+            server_keys: HashMap::new(),
             player_physics_behavior: false,
             lossy_terrain_compression: false,
             enable_discord_integration: true,
+            record_replays: false,
         }
     }
 }
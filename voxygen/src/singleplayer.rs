@@ -92,6 +92,8 @@ impl Singleplayer {
                                                  * so SQL logging can't be enabled for
                                                  * singleplayer without changing this line
                                                  * manually */
+            backup_interval: None,
+            backup_retention: 0,
         };
 
         let paused = Arc::new(AtomicBool::new(false));
@@ -135,7 +135,7 @@ pub(crate) struct GenCtx {
     pub uplift_nz: Worley,
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(default)]
 pub struct SizeOpts {
     x_lg: u32,
@@ -157,7 +157,7 @@ impl Default for SizeOpts {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub enum FileOpts {
     /// If set, generate the world map and do not try to save to or load from
     /// file (default).
@@ -141,6 +141,9 @@ impl Structure for Workshop {
                 painter.sprite(position.with_z(base), cr_station);
             }
         }
+        // The vault goes on the outermost ring, which the stations above don't fill,
+        // so adding it doesn't move any of them
+        painter.sprite((center + CARDINALS[2] * 7).with_z(base), SpriteKind::Vault);
 
         painter.spawn(
             EntityInfo::at(self.bounds.center().with_z(base).map(|e| e as f32 + 0.5))